@@ -0,0 +1,38 @@
+// generates the grpc service stubs for the hand-written messages in src/grpc.rs
+fn main() {
+    #[cfg(feature = "grpc")]
+    {
+        use tonic_build::manual::{Builder, Method, Service};
+
+        let method = |name: &str, route: &str, input: &str, output: &str| {
+            Method::builder()
+                .name(name)
+                .route_name(route)
+                .input_type(format!("crate::grpc::{}", input))
+                .output_type(format!("crate::grpc::{}", output))
+                .codec_path("tonic::codec::ProstCodec")
+        };
+        let service = Service::builder()
+            .name("RokuRemote")
+            .package("roku_remote")
+            .method(
+                method(
+                    "list_devices",
+                    "ListDevices",
+                    "ListDevicesRequest",
+                    "ListDevicesResponse",
+                )
+                .build(),
+            )
+            .method(method("get_state", "GetState", "DeviceRequest", "DeviceState").build())
+            .method(method("keypress", "Keypress", "KeypressRequest", "CommandReply").build())
+            .method(method("launch", "Launch", "LaunchRequest", "CommandReply").build())
+            .method(
+                method("watch_state", "WatchState", "DeviceRequest", "DeviceState")
+                    .server_streaming()
+                    .build(),
+            )
+            .build();
+        Builder::new().build_client(false).compile(&[service]);
+    }
+}
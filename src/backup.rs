@@ -0,0 +1,247 @@
+use crate::config::Config;
+use crate::continue_watching::{ContinueWatching, Resume};
+use crate::gallery::{Gallery, Shot};
+use crate::history::{Entry, History};
+use crate::icons::IconCache;
+use crate::schedule::ScheduleState;
+use crate::session::Session;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use directories::UserDirs;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// bump when the layout of Backup changes in a way older builds can't read
+pub const BACKUP_FORMAT: u32 = 2;
+
+// everything needed to move the remote to another machine, in one json file: the config, the
+// state kept next to it, the command history, and the screenshots and channel icons with
+// their images in base64
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Backup {
+    pub format: u32,
+    pub app_version: String,
+    pub created: u64,
+    pub config: Config,
+    // None in backups made before the state files went in, which leaves the current ones be
+    #[serde(default)]
+    pub session: Option<Session>,
+    #[serde(default)]
+    pub continue_watching: Option<Vec<Resume>>,
+    #[serde(default)]
+    pub schedule_state: Option<ScheduleState>,
+    #[serde(default)]
+    pub history: Option<Vec<Entry>>,
+    #[serde(default)]
+    pub screenshots: Option<Vec<SavedShot>>,
+    #[serde(default)]
+    pub icons: Option<Vec<SavedIcon>>,
+}
+
+// a screenshot and its image, if the backup carries it
+pub type ShotImage = (Shot, Option<Vec<u8>>);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedShot {
+    #[serde(flatten)]
+    pub shot: Shot,
+    // empty in format 1 backups, which only had the index
+    #[serde(default)]
+    pub image: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedIcon {
+    pub device: String,
+    pub app_id: String,
+    pub image: String,
+}
+
+impl Backup {
+    pub fn new(
+        config: &Config,
+        session: &Session,
+        continue_watching: &ContinueWatching,
+        gallery: &Gallery,
+        history: &History,
+        icons: &IconCache,
+    ) -> Backup {
+        Backup {
+            format: BACKUP_FORMAT,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            created: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            config: config.clone(),
+            session: Some(session.clone()),
+            continue_watching: Some(continue_watching.entries.clone()),
+            // the daemon's, read from disk since the GUI doesn't keep it
+            schedule_state: Some(ScheduleState::load()),
+            history: Some(history.entries.iter().cloned().collect()),
+            // a shot whose file has gone missing has nothing to restore
+            screenshots: Some(
+                gallery
+                    .shots
+                    .iter()
+                    .filter_map(|shot| {
+                        Some(SavedShot {
+                            shot: shot.clone(),
+                            image: STANDARD.encode(gallery.read(shot).ok()?),
+                        })
+                    })
+                    .collect(),
+            ),
+            icons: Some(
+                icons
+                    .files()
+                    .into_iter()
+                    .map(|(device, app_id, bytes)| SavedIcon {
+                        device,
+                        app_id,
+                        image: STANDARD.encode(bytes),
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    // the screenshots with their images, None for one saved without it
+    pub fn shot_images(&self) -> Result<Vec<ShotImage>, String> {
+        let shots = self.screenshots.as_deref().unwrap_or_default();
+        shots
+            .iter()
+            .map(|saved| {
+                let file = &saved.shot.file;
+                // the name is joined onto the screenshots folder, so it has to stay in it
+                if file.is_empty() || file.starts_with('.') || file.contains(['/', '\\']) {
+                    return Err(format!("screenshot with a bad file name: {:?}", file));
+                }
+                if saved.image.is_empty() {
+                    return Ok((saved.shot.clone(), None));
+                }
+                let image = STANDARD
+                    .decode(&saved.image)
+                    .map_err(|err| format!("screenshot {}: {}", file, err))?;
+                Ok((saved.shot.clone(), Some(image)))
+            })
+            .collect()
+    }
+
+    pub fn icon_images(&self) -> Result<Vec<(String, String, Vec<u8>)>, String> {
+        let icons = self.icons.as_deref().unwrap_or_default();
+        icons
+            .iter()
+            .map(|icon| {
+                let image = STANDARD
+                    .decode(&icon.image)
+                    .map_err(|err| format!("icon for {}: {}", icon.app_id, err))?;
+                Ok((icon.device.clone(), icon.app_id.clone(), image))
+            })
+            .collect()
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "backup from version {} with {} device(s), {} group(s)",
+            self.app_version,
+            self.config.devices.len(),
+            self.config.groups.len()
+        )
+    }
+}
+
+// documents folder if there is one, otherwise home
+pub fn default_backup_dir() -> Option<PathBuf> {
+    let dirs = UserDirs::new()?;
+    Some(
+        dirs.document_dir()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| dirs.home_dir().to_path_buf()),
+    )
+}
+
+// writes a timestamped backup into dir and returns its path
+pub fn write_backup(dir: &Path, backup: &Backup) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("roku-remote-backup-{}.json", backup.created));
+    let text = serde_json::to_string_pretty(backup).map_err(io::Error::other)?;
+    fs::write(&path, text)?;
+    Ok(path)
+}
+
+// reads a backup, refusing files that aren't backups, come from a newer format or have any
+// part that doesn't read, so a restore is all of the backup or nothing
+pub fn read_backup(path: &Path) -> io::Result<Backup> {
+    let text = fs::read_to_string(path)?;
+    let backup: Backup = serde_json::from_str(&text).map_err(|err| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("not a backup: {}", err))
+    })?;
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+    if backup.format == 0 {
+        return Err(invalid("not a backup: format 0".into()));
+    }
+    if backup.format > BACKUP_FORMAT {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "backup was made by version {} (format {}), this build reads up to format {}",
+                backup.app_version, backup.format, BACKUP_FORMAT
+            ),
+        ));
+    }
+    backup
+        .shot_images()
+        .and(backup.icon_images())
+        .map_err(|err| invalid(format!("damaged backup: {}", err)))?;
+    Ok(backup)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::read_backup;
+    use std::fs;
+
+    fn read(name: &str, text: &str) -> Result<(), String> {
+        let path = std::env::temp_dir().join(format!(
+            "roku-remote-backup-{}-{}.json",
+            name,
+            std::process::id()
+        ));
+        fs::write(&path, text).unwrap();
+        let result = read_backup(&path)
+            .map(|_| ())
+            .map_err(|err| err.to_string());
+        fs::remove_file(&path).ok();
+        result
+    }
+
+    #[test]
+    fn a_backup_is_read_whole_or_not_at_all() {
+        let head = r#""format": 2, "app_version": "0.1.0", "created": 1, "config": {}"#;
+        let shot = |file: &str, image: &str| {
+            format!(
+                r#"{{{}, "screenshots": [{{"file": "{}", "device": "d", "taken": 1, "image": "{}"}}]}}"#,
+                head, file, image
+            )
+        };
+        assert_eq!(read("ok", &shot("1-d.png", "iVBORw0K")), Ok(()));
+        // format 1 kept only the index
+        assert_eq!(read("index", &shot("1-d.png", "")), Ok(()));
+        assert!(read("name", &shot("../1-d.png", "iVBORw0K"))
+            .unwrap_err()
+            .contains("bad file name"));
+        assert!(read("image", &shot("1-d.png", "not base64!"))
+            .unwrap_err()
+            .starts_with("damaged backup"));
+        let bad_session = format!(r#"{{{}, "session": {{"devices": 3}}}}"#, head);
+        assert!(read("session", &bad_session)
+            .unwrap_err()
+            .starts_with("not a backup"));
+        let newer = r#"{"format": 3, "app_version": "9.0.0", "created": 1, "config": {}}"#;
+        assert!(read("newer", newer).unwrap_err().contains("format 3"));
+    }
+}
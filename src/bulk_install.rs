@@ -0,0 +1,345 @@
+// installing a list of channels one after another, for setting up a new Roku. ECP only opens
+// a channel's store page; the install itself takes a Select on "Add channel" and is done once
+// the channel shows up in query/apps, when another Select dismisses the "channel added" dialog.
+// exports of a device's channels are lists this reads back
+
+use crate::ecp::{self, AppEntry, Ecp};
+use roku_remote::transport::{Auto, RokuTransport};
+use roku_remote::RokuError;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// how long each part of an install is given; the tests take less
+struct Timing {
+    // the store page takes a moment to load before Select lands on "Add channel"
+    store_load: Duration,
+    // downloads of big channels take a while on slow connections
+    install_timeout: Duration,
+    poll: Duration,
+    // for the "channel added" dialog to come up once the channel is in
+    dialog: Duration,
+}
+
+const TIMING: Timing = Timing {
+    store_load: Duration::from_secs(5),
+    install_timeout: Duration::from_secs(90),
+    poll: Duration::from_secs(2),
+    dialog: Duration::from_secs(3),
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Status {
+    Pending,
+    Installing,
+    Installed,
+    AlreadyInstalled,
+    Failed(String),
+}
+
+impl Status {
+    pub fn label(&self) -> String {
+        match self {
+            Status::Pending => "waiting".into(),
+            Status::Installing => "installing…".into(),
+            Status::Installed => "installed".into(),
+            Status::AlreadyInstalled => "already installed".into(),
+            Status::Failed(err) => err.clone(),
+        }
+    }
+}
+
+// channel ids from a list file: JSON (an array of ids, or of objects with an "id") or plain
+// text with one id per line, anything after it being a name for the reader; # starts a comment
+pub fn parse_list(text: &str) -> Result<Vec<String>, String> {
+    let text = text.trim();
+    if text.starts_with('[') {
+        let list: Vec<Value> = serde_json::from_str(text).map_err(|err| err.to_string())?;
+        return list
+            .iter()
+            .map(|item| match item {
+                Value::String(id) => Ok(id.trim().to_string()),
+                Value::Number(id) => Ok(id.to_string()),
+                Value::Object(channel) => match &channel.get("id") {
+                    Some(Value::String(id)) => Ok(id.trim().to_string()),
+                    Some(Value::Number(id)) => Ok(id.to_string()),
+                    _ => Err(format!("channel without an id: {}", item)),
+                },
+                _ => Err(format!("not a channel id: {}", item)),
+            })
+            .collect();
+    }
+    // a CSV export reads the same, its header aside
+    Ok(text
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter_map(|line| line.split([',', ' ', '\t']).next())
+        .map(|id| id.trim_matches('"'))
+        .filter(|id| !id.is_empty() && !id.eq_ignore_ascii_case("id"))
+        .map(str::to_string)
+        .collect())
+}
+
+#[derive(Serialize)]
+struct Exported<'a> {
+    id: &'a str,
+    name: &'a str,
+    version: &'a str,
+}
+
+// the installed channels as a JSON array of {id, name, version}
+pub fn export_json(apps: &[AppEntry]) -> String {
+    let channels: Vec<Exported> = apps
+        .iter()
+        .map(|app| Exported {
+            id: &app.id,
+            name: &app.name,
+            version: &app.version,
+        })
+        .collect();
+    serde_json::to_string_pretty(&channels).unwrap_or_default()
+}
+
+pub fn export_csv(apps: &[AppEntry]) -> String {
+    let field = |value: &str| {
+        if value.contains([',', '"', '\n']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    };
+    let mut csv = "id,name,version\n".to_string();
+    for app in apps {
+        csv += &format!(
+            "{},{},{}\n",
+            field(&app.id),
+            field(&app.name),
+            field(&app.version)
+        );
+    }
+    csv
+}
+
+// how two devices' channel lineups differ; inputs and the like are left out, there is no
+// installing those
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LineupDiff {
+    // on the source device only: what installing brings the target up to match
+    pub missing: Vec<AppEntry>,
+    // on the target device only
+    pub extra: Vec<AppEntry>,
+}
+
+pub fn diff(source: &[AppEntry], target: &[AppEntry]) -> LineupDiff {
+    let channels = |apps: &[AppEntry]| -> Vec<AppEntry> {
+        apps.iter()
+            .filter(|app| app.kind == "appl")
+            .cloned()
+            .collect()
+    };
+    let (source, target) = (channels(source), channels(target));
+    let on = |apps: &[AppEntry], app: &AppEntry| apps.iter().any(|other| other.id == app.id);
+    LineupDiff {
+        missing: source
+            .iter()
+            .filter(|app| !on(&target, app))
+            .cloned()
+            .collect(),
+        extra: target
+            .iter()
+            .filter(|app| !on(&source, app))
+            .cloned()
+            .collect(),
+    }
+}
+
+// the export for a file name: CSV for .csv, JSON otherwise
+pub fn export_for(path: &std::path::Path, apps: &[AppEntry]) -> String {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("csv") => export_csv(apps),
+        _ => export_json(apps),
+    }
+}
+
+fn installed<T: RokuTransport + ?Sized>(
+    ecp: Ecp<'_, T>,
+    ip: &str,
+    app_id: &str,
+) -> Result<bool, RokuError> {
+    Ok(ecp.get_apps(ip)?.iter().any(|app| app.id == app_id))
+}
+
+// installs one channel, walking the store page
+pub fn install(ip: &str, app_id: &str) -> Status {
+    install_via(ecp::via(&Auto), ip, app_id, &TIMING)
+}
+
+fn install_via<T: RokuTransport + ?Sized>(
+    ecp: Ecp<'_, T>,
+    ip: &str,
+    app_id: &str,
+    timing: &Timing,
+) -> Status {
+    match installed(ecp, ip, app_id) {
+        Ok(true) => return Status::AlreadyInstalled,
+        Ok(false) => {}
+        Err(err) => return Status::Failed(err.to_string()),
+    }
+    let add = || -> Result<(), RokuError> {
+        ecp.open_store_page(ip, app_id)?;
+        thread::sleep(timing.store_load);
+        ecp.send_command(ip, "Select")
+    };
+    if let Err(err) = add() {
+        return Status::Failed(err.to_string());
+    }
+    // a paid channel, or one behind a PIN, asks for it here instead and never turns up. the
+    // second Select waits for the channel, since on those screens it could confirm a purchase
+    let started = Instant::now();
+    loop {
+        // a device busy installing can miss a query; only the timeout gives up
+        if installed(ecp, ip, app_id) == Ok(true) {
+            thread::sleep(timing.dialog);
+            let _ = ecp.send_command(ip, "Select");
+            return Status::Installed;
+        }
+        if started.elapsed() >= timing.install_timeout {
+            return Status::Failed("needs a PIN or a purchase, or didn't install in time".into());
+        }
+        thread::sleep(timing.poll);
+    }
+}
+
+// every channel on the list, one at a time; on_status hears each change
+pub fn install_all(ip: &str, app_ids: &[String], mut on_status: impl FnMut(usize, Status)) {
+    for (i, app_id) in app_ids.iter().enumerate() {
+        on_status(i, Status::Installing);
+        on_status(i, install(ip, app_id));
+    }
+    // leave the device on the home screen rather than a store page
+    let _ = ecp::send_command(ip, "Home");
+}
+
+// a bulk install running on its own thread, for the GUI
+pub struct BulkInstall {
+    pub device: String,
+    pub channels: Vec<String>,
+    pub status: Vec<Status>,
+    rx: Receiver<(usize, Status)>,
+}
+
+impl BulkInstall {
+    pub fn start(device: String, channels: Vec<String>) -> BulkInstall {
+        let (tx, rx) = mpsc::channel();
+        let run = BulkInstall {
+            status: vec![Status::Pending; channels.len()],
+            device: device.clone(),
+            channels: channels.clone(),
+            rx,
+        };
+        thread::spawn(move || {
+            install_all(&device, &channels, |i, status| {
+                tx.send((i, status)).ok();
+            });
+        });
+        run
+    }
+
+    // applies status reported since the last frame; false once every channel is through
+    pub fn poll(&mut self) -> bool {
+        while let Ok((i, status)) = self.rx.try_recv() {
+            self.status[i] = status;
+        }
+        self.status
+            .iter()
+            .any(|s| matches!(s, Status::Pending | Status::Installing))
+    }
+
+    pub fn failures(&self) -> usize {
+        self.status
+            .iter()
+            .filter(|s| matches!(s, Status::Failed(_)))
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{install_via, Status, Timing};
+    use crate::ecp;
+    use roku_remote::transport::Mock;
+    use std::thread;
+    use std::time::Duration;
+
+    const DEVICE: &str = "192.0.2.7:8060";
+    const FAST: Timing = Timing {
+        store_load: Duration::ZERO,
+        install_timeout: Duration::from_millis(300),
+        poll: Duration::from_millis(20),
+        dialog: Duration::ZERO,
+    };
+
+    fn with_apps(ids: &[&str]) -> Mock {
+        let mock = Mock::new();
+        mock.reply("query/apps", apps(ids));
+        mock
+    }
+
+    fn apps(ids: &[&str]) -> String {
+        let apps: String = ids
+            .iter()
+            .map(|id| {
+                format!(
+                    "<app id=\"{}\" type=\"appl\" version=\"1\">{}</app>",
+                    id, id
+                )
+            })
+            .collect();
+        format!("<apps>{}</apps>", apps)
+    }
+
+    #[test]
+    fn an_installed_channel_is_left_alone() {
+        let mock = with_apps(&["12", "13"]);
+        let status = install_via(ecp::via(&mock), DEVICE, "12", &FAST);
+        assert_eq!(status, Status::AlreadyInstalled);
+        assert!(mock.commands().is_empty());
+    }
+
+    #[test]
+    fn a_channel_that_never_turns_up_gets_one_select() {
+        // a PIN prompt or a purchase screen: pressing on could buy something
+        let mock = with_apps(&["13"]);
+        let status = install_via(ecp::via(&mock), DEVICE, "12", &FAST);
+        assert!(matches!(status, Status::Failed(ref why) if why.contains("PIN")));
+        assert_eq!(mock.commands(), ["install/12", "keypress/Select"]);
+    }
+
+    #[test]
+    fn the_dialog_is_dismissed_once_the_channel_is_in() {
+        let mock = with_apps(&["13"]);
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                thread::sleep(Duration::from_millis(100));
+                mock.reply("query/apps", apps(&["12", "13"]));
+            });
+            let status = install_via(ecp::via(&mock), DEVICE, "12", &FAST);
+            assert_eq!(status, Status::Installed);
+        });
+        assert_eq!(
+            mock.commands(),
+            ["install/12", "keypress/Select", "keypress/Select"]
+        );
+    }
+
+    #[test]
+    fn an_unreadable_app_list_is_a_failure() {
+        let mock = Mock::new();
+        mock.reply_with("query/apps", 503, "");
+        let status = install_via(ecp::via(&mock), DEVICE, "12", &FAST);
+        assert!(matches!(status, Status::Failed(_)));
+        assert!(mock.commands().is_empty());
+    }
+}
@@ -0,0 +1,253 @@
+// Play on Roku: a local video, song or photo served over http from this machine, and the Roku
+// Media Player launched with a deep link to it, the way the mobile app flings media. the file
+// is served under a random path for as long as its MediaServer is kept, with range requests
+// so the player can seek
+
+use crate::ecp;
+use roku_remote::http::dry_run;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+use tiny_http::{Header, Method, Request, Response, Server, StatusCode};
+
+pub const DEFAULT_PORT: u16 = 8063;
+// the Roku Media Player, on every device
+pub const MEDIA_PLAYER: &str = "2213";
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MediaKind {
+    Video,
+    Audio,
+    Photo,
+}
+
+impl MediaKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            MediaKind::Video => "video",
+            MediaKind::Audio => "song",
+            MediaKind::Photo => "photo",
+        }
+    }
+}
+
+// what the player can take, by extension: the kind, the content type it's served with and
+// the format named in the deep link
+pub fn media_type(path: &Path) -> Option<(MediaKind, &'static str, &'static str)> {
+    let ext = path.extension()?.to_str()?.to_lowercase();
+    Some(match ext.as_str() {
+        "mp4" | "m4v" => (MediaKind::Video, "video/mp4", "mp4"),
+        "mov" => (MediaKind::Video, "video/quicktime", "mov"),
+        "mkv" => (MediaKind::Video, "video/x-matroska", "mkv"),
+        "ts" => (MediaKind::Video, "video/mp2t", "ts"),
+        "mp3" => (MediaKind::Audio, "audio/mpeg", "mp3"),
+        "m4a" | "aac" => (MediaKind::Audio, "audio/mp4", "m4a"),
+        "flac" => (MediaKind::Audio, "audio/flac", "flac"),
+        "wav" => (MediaKind::Audio, "audio/wav", "wav"),
+        "jpg" | "jpeg" => (MediaKind::Photo, "image/jpeg", "jpg"),
+        "png" => (MediaKind::Photo, "image/png", "png"),
+        "gif" => (MediaKind::Photo, "image/gif", "gif"),
+        _ => return None,
+    })
+}
+
+// a file being served to a device; dropping it stops serving
+pub struct MediaServer {
+    pub path: PathBuf,
+    pub device: String,
+    pub kind: MediaKind,
+    // what the device was handed to play
+    pub url: String,
+    // None in a dry run, which serves nothing
+    server: Option<Arc<Server>>,
+}
+
+impl Drop for MediaServer {
+    fn drop(&mut self) {
+        if let Some(server) = &self.server {
+            server.unblock();
+        }
+    }
+}
+
+// serves the file on the port, a free one if it's taken, and has the device play it
+pub fn play_on_roku(device: &str, path: &Path, port: u16) -> Result<MediaServer, String> {
+    let (kind, content_type, format) = media_type(path).ok_or_else(|| {
+        format!(
+            "{}: not a video, song or photo the Media Player plays",
+            path.display()
+        )
+    })?;
+    let len = std::fs::metadata(path)
+        .map_err(|err| format!("{}: {}", path.display(), err))?
+        .len();
+    if dry_run::enabled() {
+        let device = ecp::device_addr(device);
+        dry_run::record(&format!("CAST {} to {}", path.display(), device));
+        return Ok(MediaServer {
+            path: path.to_path_buf(),
+            device,
+            kind,
+            url: String::new(),
+            server: None,
+        });
+    }
+    let host = local_addr_towards(device)
+        .map_err(|err| format!("no local address {} can reach: {}", device, err))?;
+    let server = Server::http(("0.0.0.0", port))
+        .or_else(|_| Server::http(("0.0.0.0", 0)))
+        .map_err(|err| format!("serving the file: {}", err))?;
+    let port = server
+        .server_addr()
+        .to_ip()
+        .map(|addr| addr.port())
+        .unwrap_or(port);
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    // the random part keeps anything else on the network from reading the machine's files
+    let token: [u8; 8] = rand::random();
+    let route = format!(
+        "/{}/{}",
+        token
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>(),
+        url::form_urlencoded::byte_serialize(name.as_bytes()).collect::<String>()
+    );
+    let url = format!("http://{}{}", SocketAddr::new(host, port), route);
+
+    let server = Arc::new(server);
+    {
+        let (server, path) = (Arc::clone(&server), path.to_path_buf());
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                if request.url() != route {
+                    request.respond(Response::empty(404)).ok();
+                    continue;
+                }
+                // the player reads ahead while seeking, so one request mustn't hold up the next
+                let path = path.clone();
+                thread::spawn(move || serve(request, &path, len, content_type));
+            }
+        });
+    }
+
+    let device = ecp::device_addr(device);
+    let title = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    // the parameters of Play on Roku's deep link, which the Media Player takes too
+    let params: Vec<(&str, &str)> = match kind {
+        MediaKind::Video => vec![
+            ("t", "v"),
+            ("u", &url),
+            ("videoName", &title),
+            ("videoFormat", format),
+        ],
+        MediaKind::Audio => vec![
+            ("t", "a"),
+            ("u", &url),
+            ("songName", &title),
+            ("songFormat", format),
+        ],
+        MediaKind::Photo => vec![("t", "p"), ("u", &url)],
+    };
+    ecp::launch_with_params(&device, MEDIA_PLAYER, &params)
+        .map_err(|err| format!("launching the Media Player on {}: {}", device, err))?;
+    Ok(MediaServer {
+        path: path.to_path_buf(),
+        device,
+        kind,
+        url,
+        server: Some(server),
+    })
+}
+
+fn serve(request: Request, path: &Path, len: u64, content_type: &str) {
+    if !matches!(request.method(), Method::Get | Method::Head) {
+        request.respond(Response::empty(405)).ok();
+        return;
+    }
+    let range = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Range"))
+        .map(|header| header.value.as_str().to_string());
+    let mut headers = vec![
+        Header::from_bytes("Content-Type", content_type).unwrap(),
+        Header::from_bytes("Accept-Ranges", "bytes").unwrap(),
+    ];
+    let (status, start, end) = match range.as_deref().map(|range| byte_range(range, len)) {
+        None => (200, 0, len),
+        Some(Some((start, end))) => {
+            let value = format!("bytes {}-{}/{}", start, end - 1, len);
+            headers.push(Header::from_bytes("Content-Range", value).unwrap());
+            (206, start, end)
+        }
+        Some(None) => {
+            let value = format!("bytes */{}", len);
+            let header = Header::from_bytes("Content-Range", value).unwrap();
+            request
+                .respond(Response::empty(416).with_header(header))
+                .ok();
+            return;
+        }
+    };
+    let body = File::open(path).and_then(|mut file| {
+        file.seek(SeekFrom::Start(start))?;
+        Ok(file.take(end - start))
+    });
+    let resp = match body {
+        Ok(body) => Response::new(
+            StatusCode(status),
+            headers,
+            body,
+            Some((end - start) as usize),
+            None,
+        ),
+        Err(_) => {
+            request.respond(Response::empty(404)).ok();
+            return;
+        }
+    };
+    // the player hanging up partway is how it seeks
+    request.respond(resp).ok();
+}
+
+// the half-open byte range of a Range header, e.g. "bytes=100-" or "bytes=-500"; None for one
+// outside the file. only the first range of several is served
+fn byte_range(header: &str, len: u64) -> Option<(u64, u64)> {
+    let spec = header
+        .trim()
+        .strip_prefix("bytes=")?
+        .split(',')
+        .next()?
+        .trim();
+    let (first, last) = spec.split_once('-')?;
+    let (start, end) = match (first.trim(), last.trim()) {
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().ok()?;
+            (len.saturating_sub(suffix), len)
+        }
+        (first, "") => (first.parse().ok()?, len),
+        (first, last) => {
+            let last: u64 = last.parse().ok()?;
+            (first.parse().ok()?, (last + 1).min(len))
+        }
+    };
+    (start < end).then_some((start, end))
+}
+
+// the address of this machine on the network the device is on
+fn local_addr_towards(device: &str) -> io::Result<IpAddr> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    // nothing is sent: connecting a udp socket only picks the route
+    socket.connect(ecp::device_addr(device))?;
+    Ok(socket.local_addr()?.ip())
+}
@@ -0,0 +1,789 @@
+// command-line frontend; with no subcommand the GUI starts as before
+
+use crate::bulk_install;
+use crate::cast;
+use crate::config::{Config, ConfigStore};
+use crate::dev_installer;
+use crate::ecp;
+use crate::macros;
+use crate::scripting;
+use crate::server;
+use crate::session::Session;
+use crate::tui;
+use clap::{Parser, Subcommand, ValueEnum};
+use roku_remote::http::{self, dry_run};
+use roku_remote::{wol, RokuKey};
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Parser)]
+#[command(name = "roku-remote", version, about = "Control Roku devices over ECP")]
+pub struct Cli {
+    /// Run the control server instead of the GUI
+    #[arg(long, value_name = "ADDR", num_args = 0..=1, default_missing_value = server::DEFAULT_ADDR)]
+    pub serve: Option<String>,
+    /// Also serve gRPC on this address (needs the grpc feature)
+    #[arg(long, value_name = "ADDR", requires = "serve")]
+    pub grpc: Option<String>,
+    /// Print the commands that would be sent instead of sending them; queries still go out
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Find Roku devices on the local network and print their addresses
+    Discover {
+        /// Also try every address on the local /24s, for networks that block multicast
+        #[arg(long)]
+        deep: bool,
+        /// json for scripts and other programs
+        #[arg(long, value_enum, default_value_t = Format::Plain)]
+        format: Format,
+    },
+    /// Press one or more remote keys, e.g. Home, Select, VolumeUp
+    Keypress {
+        /// Device address, with or without the :8060 port
+        #[arg(long)]
+        ip: String,
+        /// Key names as ECP takes them, case and underscores aside; Lit_a types an a
+        #[arg(required = true)]
+        keys: Vec<RokuKey>,
+    },
+    /// Launch a channel by its id, as listed by the apps command
+    Launch {
+        /// Device address, with or without the :8060 port
+        #[arg(long)]
+        ip: String,
+        app_id: String,
+        /// Go straight to this title in the channel
+        #[arg(long)]
+        content_id: Option<String>,
+        /// The title's media type, e.g. movie or episode; some channels need it with --content-id
+        #[arg(long, requires = "content_id")]
+        media_type: Option<String>,
+    },
+    /// Type text into whatever text field is on screen
+    Text {
+        /// Device address, with or without the :8060 port
+        #[arg(long)]
+        ip: String,
+        /// The text, or - to read it from standard input, e.g. a password manager's output
+        text: String,
+        /// Milliseconds between characters, for devices that drop some; the GUI's setting if
+        /// left out
+        #[arg(long, value_name = "MS")]
+        delay: Option<u64>,
+    },
+    /// List a Roku TV's antenna and cable channels
+    TvChannels {
+        /// Device address, with or without the :8060 port
+        #[arg(long)]
+        ip: String,
+        /// json for scripts and other programs
+        #[arg(long, value_enum, default_value_t = Format::Plain)]
+        format: Format,
+    },
+    /// Switch a Roku TV's tuner to a channel, e.g. 7.1
+    Tune {
+        /// Device address, with or without the :8060 port
+        #[arg(long)]
+        ip: String,
+        channel: String,
+    },
+    /// Wake a TV in deep standby with a Wake-on-LAN packet
+    Wake {
+        /// Device address, its MAC taken from what the GUI last learned of it
+        #[arg(long, required_unless_present = "mac")]
+        ip: Option<String>,
+        /// The MAC to wake, for a device the GUI hasn't seen
+        #[arg(long)]
+        mac: Option<String>,
+    },
+    /// Search on the device itself, as its search screen would
+    Search {
+        /// Device address, with or without the :8060 port
+        #[arg(long)]
+        ip: String,
+        keyword: String,
+        /// Match the keyword as an exact title
+        #[arg(long)]
+        exact: bool,
+        /// movie, tv-show, person, channel or game
+        #[arg(long = "type", value_name = "TYPE")]
+        kind: Option<String>,
+        /// Channel ids to prefer, comma-separated
+        #[arg(long, value_delimiter = ',')]
+        provider: Vec<String>,
+        /// Start playing the match in the first --provider that has it
+        #[arg(long, requires = "provider")]
+        launch: bool,
+    },
+    /// List installed channels
+    Apps {
+        /// Device address, with or without the :8060 port
+        #[arg(long)]
+        ip: String,
+        /// Download each channel's icon into this directory
+        #[arg(long, value_name = "DIR")]
+        icons: Option<PathBuf>,
+        /// Also write the list to this file, as CSV for a .csv name and JSON otherwise
+        #[arg(long, value_name = "FILE")]
+        export: Option<PathBuf>,
+        /// json for scripts and other programs
+        #[arg(long, value_enum, default_value_t = Format::Plain)]
+        format: Format,
+    },
+    /// Print fields from the device's device-info
+    #[command(alias = "device-info")]
+    Info {
+        /// Device address, with or without the :8060 port
+        #[arg(long)]
+        ip: String,
+        /// Comma-separated field names, e.g. power-mode,software-version; all fields if left out
+        #[arg(long, value_delimiter = ',')]
+        fields: Vec<String>,
+        /// json for scripts and other programs
+        #[arg(long, value_enum, default_value_t = Format::Plain)]
+        format: Format,
+    },
+    /// Install every channel listed in a file, one at a time
+    Install {
+        /// Device addresses, with or without the :8060 port; comma-separated or repeated to
+        /// install on several devices at once
+        #[arg(long, required = true, value_delimiter = ',')]
+        ip: Vec<String>,
+        /// One channel id per line, or a JSON array of ids
+        #[arg(required_unless_present = "channel")]
+        file: Option<PathBuf>,
+        /// Channel-store ids to install, comma-separated, instead of a file
+        #[arg(long, value_delimiter = ',', conflicts_with = "file")]
+        channel: Vec<String>,
+    },
+    /// Compare two devices' channels, optionally installing what the second one lacks
+    Lineup {
+        /// The device whose channels to match
+        #[arg(long)]
+        from: String,
+        /// The device to bring up to match it
+        #[arg(long)]
+        to: String,
+        /// Install the missing channels on --to
+        #[arg(long)]
+        install: bool,
+    },
+    /// Run macros saved from the GUI
+    Macro {
+        #[command(subcommand)]
+        action: MacroAction,
+    },
+    /// Play a local video, song or photo in the Roku Media Player, served from this machine
+    Cast {
+        /// Device address, with or without the :8060 port
+        #[arg(long)]
+        ip: String,
+        file: PathBuf,
+        /// Port to serve the file on; a free one if it's taken
+        #[arg(long, default_value_t = cast::DEFAULT_PORT)]
+        port: u16,
+    },
+    /// Install a channel .zip as the dev channel on a device in developer mode, or delete it
+    Sideload {
+        /// Device address, with or without the :8060 port
+        #[arg(long)]
+        ip: String,
+        /// The developer installer's password; the one saved in the GUI if left out
+        #[arg(long)]
+        password: Option<String>,
+        #[arg(required_unless_present = "delete")]
+        zip: Option<PathBuf>,
+        /// Install even if the zip is identical to the installed one
+        #[arg(long, conflicts_with = "delete")]
+        replace: bool,
+        /// Delete the dev channel instead
+        #[arg(long, conflicts_with = "zip")]
+        delete: bool,
+    },
+    /// Screenshot the dev channel on a device in developer mode
+    Screenshot {
+        /// Device address, with or without the :8060 port
+        #[arg(long)]
+        ip: String,
+        /// The developer installer's password; the one saved in the GUI if left out
+        #[arg(long)]
+        password: Option<String>,
+        /// Where to write the image; roku-screenshot.jpg (or .png) if left out
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// A remote in the terminal, for controlling devices over ssh (needs the tui feature)
+    #[command(after_help = tui::TUI_HELP)]
+    Tui {
+        /// Device to start on; the one the GUI last used if left out
+        #[arg(long)]
+        ip: Option<String>,
+    },
+    /// Run Rhai automation scripts
+    Script {
+        #[command(subcommand)]
+        action: ScriptAction,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum Format {
+    Plain,
+    Json,
+}
+
+#[derive(Subcommand)]
+pub enum MacroAction {
+    /// List saved macros
+    List,
+    /// Run a saved macro
+    Run {
+        name: String,
+        /// Device address, room name or group; defaults to the only device on the network
+        #[arg(long)]
+        device: Option<String>,
+        /// Check each keypress was taken, and showed on the device where ECP can see it, before going on
+        #[arg(long)]
+        verify: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ScriptAction {
+    /// List the scripts saved in the GUI
+    List,
+    /// Run a script, printing what it prints
+    #[command(after_help = scripting::SCRIPT_HELP)]
+    Run {
+        /// A .rhai file, or the name of a script saved in the GUI
+        script: String,
+    },
+}
+
+// piped text, without the newline echo and most password managers end it with
+fn read_stdin() -> Result<String, String> {
+    let mut text = String::new();
+    io::stdin()
+        .read_to_string(&mut text)
+        .map_err(|err| format!("reading standard input: {}", err))?;
+    let text = text.strip_suffix('\n').unwrap_or(&text);
+    let text = text.strip_suffix('\r').unwrap_or(text);
+    if text.is_empty() {
+        return Err("nothing to type on standard input".into());
+    }
+    Ok(text.to_string())
+}
+
+pub fn run(command: Command) -> Result<(), String> {
+    match command {
+        Command::Discover { deep, format } => {
+            let (_, mut config) = ConfigStore::open();
+            config.ssdp.deep_scan |= deep;
+            let found = ecp::discover_or_scan(&config.ssdp);
+            if found.is_empty() {
+                return Err("no Roku devices found".into());
+            }
+            match format {
+                Format::Json => print_json(&found),
+                Format::Plain => {
+                    for device in found {
+                        println!("{}", device.address);
+                    }
+                }
+            }
+            Ok(())
+        }
+        Command::Keypress { ip, keys } => {
+            let ip = ecp::device_addr(&ip);
+            for key in keys {
+                ecp::press(&ip, key).map_err(|err| format!("{} on {}: {}", key, ip, err))?;
+            }
+            Ok(())
+        }
+        Command::Launch {
+            ip,
+            app_id,
+            content_id,
+            media_type,
+        } => {
+            let ip = ecp::device_addr(&ip);
+            match &content_id {
+                Some(content_id) => {
+                    ecp::launch_content(&ip, &app_id, content_id, media_type.as_deref())
+                }
+                None => ecp::launch_app(&ip, &app_id),
+            }
+            .map_err(|err| format!("launching {} on {}: {}", app_id, ip, err))
+        }
+        Command::Text { ip, text, delay } => {
+            let ip = ecp::device_addr(&ip);
+            let delay = delay.map_or_else(http::typing_delay, Duration::from_millis);
+            let text = if text == "-" { read_stdin()? } else { text };
+            ecp::send_text(&ip, &text, delay, |_| true)
+                .map_err(|err| format!("typing on {}: {}", ip, err))
+        }
+        Command::TvChannels { ip, format } => {
+            let ip = ecp::device_addr(&ip);
+            let channels = ecp::get_tv_channels(&ip)
+                .map_err(|err| format!("listing channels on {}: {}", ip, err))?;
+            let tuned = ecp::get_tv_active_channel(&ip).map(|now| now.channel.number);
+            if format == Format::Json {
+                let channels: Vec<serde_json::Value> = channels
+                    .into_iter()
+                    .map(|channel| {
+                        let now = tuned.as_ref() == Some(&channel.number);
+                        let mut value = serde_json::to_value(channel).unwrap_or_default();
+                        value["tuned"] = now.into();
+                        value
+                    })
+                    .collect();
+                print_json(&channels);
+                return Ok(());
+            }
+            for channel in channels {
+                let mark = if tuned.as_ref() == Some(&channel.number) {
+                    "*"
+                } else {
+                    " "
+                };
+                let hidden = if channel.hidden { "\thidden" } else { "" };
+                println!(
+                    "{}{}\t{}\t{}{}",
+                    mark, channel.number, channel.name, channel.kind, hidden
+                );
+            }
+            Ok(())
+        }
+        Command::Tune { ip, channel } => {
+            let ip = ecp::device_addr(&ip);
+            ecp::tune_tv_channel(&ip, &channel)
+                .map_err(|err| format!("tuning {} to {}: {}", ip, channel, err))
+        }
+        Command::Wake { ip, mac } => {
+            let ip = ip.map(|ip| ecp::device_addr(&ip));
+            let mac = match (mac, &ip) {
+                (Some(mac), _) => mac,
+                (None, Some(ip)) => Session::load()
+                    .macs
+                    .remove(ip)
+                    .ok_or_else(|| format!("no MAC known for {}; pass --mac", ip))?,
+                (None, None) => unreachable!("clap requires one of them"),
+            };
+            wol::wake(&mac).map_err(|err| format!("waking {}: {}", mac, err))
+        }
+        Command::Search {
+            ip,
+            keyword,
+            exact,
+            kind,
+            provider,
+            launch,
+        } => {
+            let ip = ecp::device_addr(&ip);
+            let (keyword, title) = if exact {
+                (String::new(), keyword)
+            } else {
+                (keyword, String::new())
+            };
+            let query = ecp::SearchQuery {
+                keyword,
+                title,
+                kind: kind.unwrap_or_default(),
+                provider_ids: provider,
+                launch,
+            };
+            ecp::search_browse(&ip, &query).map_err(|err| format!("searching on {}: {}", ip, err))
+        }
+        Command::Apps {
+            ip,
+            icons,
+            export,
+            format,
+        } => apps(&ecp::device_addr(&ip), icons, export, format),
+        Command::Info { ip, fields, format } => info(&ecp::device_addr(&ip), &fields, format),
+        Command::Install { ip, file, channel } => {
+            let devices: Vec<String> = ip.iter().map(|ip| ecp::device_addr(ip)).collect();
+            let channels = match file {
+                Some(file) => read_list(&file)?,
+                None => channel,
+            };
+            install_everywhere(&devices, &channels)
+        }
+        Command::Lineup { from, to, install } => {
+            lineup(&ecp::device_addr(&from), &ecp::device_addr(&to), install)
+        }
+        Command::Macro { action } => {
+            let (_, config) = ConfigStore::open();
+            match action {
+                MacroAction::List => {
+                    for (name, script) in &config.macros {
+                        let steps = macros::parse(script).map(|s| s.len()).unwrap_or_default();
+                        println!("{}\t{} step(s)", name, steps);
+                    }
+                    Ok(())
+                }
+                MacroAction::Run {
+                    name,
+                    device,
+                    verify,
+                } => run_macro(&config, &name, device.as_deref(), verify),
+            }
+        }
+        Command::Cast { ip, file, port } => {
+            let served = cast::play_on_roku(&ip, &file, port)?;
+            if dry_run::enabled() {
+                return Ok(());
+            }
+            println!(
+                "Serving {} to {} at {} while the Media Player is open; Ctrl+C to stop",
+                served.kind.label(),
+                served.device,
+                served.url
+            );
+            wait_for_media_player(&served.device);
+            Ok(())
+        }
+        Command::Sideload {
+            ip,
+            password,
+            zip,
+            replace,
+            delete,
+        } => {
+            let ip = ecp::device_addr(&ip);
+            let password = match password {
+                Some(password) => password,
+                None => dev_password(&ip)?,
+            };
+            let message = match zip.filter(|_| !delete) {
+                Some(zip) => {
+                    let bytes =
+                        fs::read(&zip).map_err(|err| format!("{}: {}", zip.display(), err))?;
+                    let name = zip.file_name().unwrap_or_default().to_string_lossy();
+                    dev_installer::install(&ip, &password, &name, &bytes, replace)
+                }
+                None => dev_installer::delete(&ip, &password),
+            }
+            .map_err(|err| format!("{}: {}", ip, err))?;
+            println!("{}", message);
+            Ok(())
+        }
+        Command::Screenshot {
+            ip,
+            password,
+            output,
+        } => {
+            let ip = ecp::device_addr(&ip);
+            let password = match password {
+                Some(password) => password,
+                None => dev_password(&ip)?,
+            };
+            let (bytes, ext) = dev_installer::screenshot(&ip, &password)
+                .map_err(|err| format!("screenshot of {}: {}", ip, err))?;
+            let output =
+                output.unwrap_or_else(|| PathBuf::from(format!("roku-screenshot.{}", ext)));
+            fs::write(&output, bytes).map_err(|err| format!("{}: {}", output.display(), err))?;
+            println!("{}", output.display());
+            Ok(())
+        }
+        Command::Tui { ip } => tui::run(ip),
+        Command::Script { action } => {
+            let (_, config) = ConfigStore::open();
+            match action {
+                ScriptAction::List => {
+                    for (name, path) in &config.scripts {
+                        println!("{}\t{}", name, path);
+                    }
+                    Ok(())
+                }
+                ScriptAction::Run { script } => run_script(&config, &script),
+            }
+        }
+    }
+}
+
+fn info(ip: &str, fields: &[String], format: Format) -> Result<(), String> {
+    let all = ecp::get_device_info_fields(ip).map_err(|err| format!("{}: {}", ip, err))?;
+    let selected: Vec<(String, String)> = if fields.is_empty() {
+        all
+    } else {
+        fields
+            .iter()
+            .map(|field| {
+                all.iter()
+                    .find(|(name, _)| name == field)
+                    .cloned()
+                    .ok_or_else(|| format!("{} has no field {}", ip, field))
+            })
+            .collect::<Result<_, _>>()?
+    };
+
+    match format {
+        Format::Json => {
+            let object: serde_json::Map<String, serde_json::Value> = selected
+                .into_iter()
+                .map(|(name, value)| (name, value.into()))
+                .collect();
+            println!("{}", serde_json::Value::Object(object));
+        }
+        // a single field prints bare, so it can go straight into a shell test
+        Format::Plain if fields.len() == 1 => println!("{}", selected[0].1),
+        Format::Plain => {
+            for (name, value) in selected {
+                println!("{}: {}", name, value);
+            }
+        }
+    }
+    Ok(())
+}
+
+// one line of json, so a pipeline can read the output with jq or the like
+fn print_json(value: &impl serde::Serialize) {
+    match serde_json::to_string(value) {
+        Ok(json) => println!("{}", json),
+        Err(err) => eprintln!("error: {}", err),
+    }
+}
+
+fn read_list(file: &Path) -> Result<Vec<String>, String> {
+    let text = fs::read_to_string(file).map_err(|err| format!("{}: {}", file.display(), err))?;
+    bulk_install::parse_list(&text)
+}
+
+// one device installs like install_channels; several work side by side, each line naming its
+// device
+fn install_everywhere(devices: &[String], channels: &[String]) -> Result<(), String> {
+    if let [device] = devices {
+        return install_channels(device, channels);
+    }
+    let failed: usize = std::thread::scope(|scope| {
+        let runs: Vec<_> = devices
+            .iter()
+            .map(|device| {
+                scope.spawn(move || {
+                    let mut failed = 0;
+                    bulk_install::install_all(device, channels, |i, status| {
+                        if status == bulk_install::Status::Installing {
+                            return;
+                        }
+                        failed += matches!(status, bulk_install::Status::Failed(_)) as usize;
+                        println!("{}\t{}\t{}", device, channels[i], status.label());
+                    });
+                    failed
+                })
+            })
+            .collect();
+        runs.into_iter()
+            .map(|run| run.join().unwrap_or_default())
+            .sum()
+    });
+    match failed {
+        0 => Ok(()),
+        n => Err(format!(
+            "{} of {} install(s) failed",
+            n,
+            channels.len() * devices.len()
+        )),
+    }
+}
+
+fn install_channels(ip: &str, channels: &[String]) -> Result<(), String> {
+    let mut failed = 0;
+    bulk_install::install_all(ip, channels, |i, status| {
+        if status == bulk_install::Status::Installing {
+            return;
+        }
+        failed += matches!(status, bulk_install::Status::Failed(_)) as usize;
+        println!("{}\t{}", channels[i], status.label());
+    });
+    match failed {
+        0 => Ok(()),
+        n => Err(format!("{} of {} channel(s) failed", n, channels.len())),
+    }
+}
+
+fn lineup(from: &str, to: &str, install_missing: bool) -> Result<(), String> {
+    let source = ecp::get_apps(from).map_err(|err| format!("{}: {}", from, err))?;
+    let target = ecp::get_apps(to).map_err(|err| format!("{}: {}", to, err))?;
+    if source.is_empty() {
+        return Err(format!("no channels reported by {}", from));
+    }
+    let diff = bulk_install::diff(&source, &target);
+    println!("missing on {}:", to);
+    for app in &diff.missing {
+        println!("  {:<10} {}", app.id, app.name);
+    }
+    println!("only on {}:", to);
+    for app in &diff.extra {
+        println!("  {:<10} {}", app.id, app.name);
+    }
+    if install_missing && !diff.missing.is_empty() {
+        let ids: Vec<String> = diff.missing.iter().map(|app| app.id.clone()).collect();
+        return install_channels(to, &ids);
+    }
+    Ok(())
+}
+
+fn run_macro(
+    config: &Config,
+    name: &str,
+    device: Option<&str>,
+    verify: bool,
+) -> Result<(), String> {
+    let script = config
+        .macros
+        .get(name)
+        .ok_or_else(|| format!("no macro named \"{}\"", name))?;
+    let steps = macros::parse(script).map_err(|err| format!("macro {}: {}", name, err))?;
+    let targets = resolve_devices(config, device)?;
+
+    let mut failed = 0;
+    for ip in &targets {
+        let verify = verify || config.macro_settings.verify;
+        let result = macros::run(ip, &steps, verify, |i| {
+            eprintln!("{}: {}/{} {}", ip, i + 1, steps.len(), steps[i].label());
+        });
+        if let Err(err) = result {
+            eprintln!("{}: {}", ip, err);
+            failed += 1;
+        }
+    }
+    match failed {
+        0 => Ok(()),
+        n => Err(format!(
+            "macro failed on {} of {} device(s)",
+            n,
+            targets.len()
+        )),
+    }
+}
+
+// the password saved for the device in the GUI
+fn dev_password(ip: &str) -> Result<String, String> {
+    let (_, config) = ConfigStore::open();
+    config
+        .devices
+        .get(ip)
+        .map(|meta| meta.dev_password.clone())
+        .filter(|password| !password.is_empty())
+        .ok_or_else(|| format!("no developer password saved for {}; pass --password", ip))
+}
+
+// until the Media Player has come up and been left again, or hasn't come up in a while
+fn wait_for_media_player(ip: &str) {
+    let on_screen = || ecp::get_active_app(ip).is_some_and(|app| app.id == cast::MEDIA_PLAYER);
+    let deadline = Instant::now() + Duration::from_secs(15);
+    while !on_screen() {
+        if Instant::now() > deadline {
+            return;
+        }
+        thread::sleep(Duration::from_millis(500));
+    }
+    while on_screen() {
+        thread::sleep(Duration::from_secs(2));
+    }
+}
+
+fn run_script(config: &Config, script: &str) -> Result<(), String> {
+    let path = match config.scripts.get(script) {
+        Some(path) if !Path::new(script).exists() => PathBuf::from(path),
+        _ => PathBuf::from(script),
+    };
+    let source = fs::read_to_string(&path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    // nothing stops it but the end of the script or Ctrl+C
+    let stop = std::sync::Arc::default();
+    scripting::run(&source, &config.ssdp, stop, |line| println!("{}", line))
+        .map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+// see macros::target_devices; without a --device, the only device around. when discovery
+// finds nothing, saved devices that still answer at their last address count instead
+fn resolve_devices(config: &Config, device: Option<&str>) -> Result<Vec<String>, String> {
+    let Some(alias) = device else {
+        let mut found = ecp::discover_with(&config.ssdp);
+        if found.is_empty() {
+            let saved: Vec<String> = config.devices.keys().cloned().collect();
+            found = ecp::reachable(&saved);
+        }
+        return match found.as_slice() {
+            [only] => Ok(vec![only.clone()]),
+            [] => Err("no Roku devices found".into()),
+            _ => Err(format!(
+                "found {} devices, pick one with --device",
+                found.len()
+            )),
+        };
+    };
+    Ok(macros::target_devices(config, alias))
+}
+
+// an app id as the device sent it, made safe to name a file by: anything but letters, digits,
+// _ and - becomes _, so "tvinput.hdmi1" is tvinput_hdmi1 and no id reaches outside the folder
+fn file_stem(app_id: &str) -> String {
+    app_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '-') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn apps(
+    ip: &str,
+    icons: Option<PathBuf>,
+    export: Option<PathBuf>,
+    format: Format,
+) -> Result<(), String> {
+    let apps = ecp::get_apps(ip).map_err(|err| format!("{}: {}", ip, err))?;
+    if apps.is_empty() {
+        return Err(format!("no channels reported by {}", ip));
+    }
+    if let Some(path) = &export {
+        fs::write(path, bulk_install::export_for(path, &apps))
+            .map_err(|err| format!("{}: {}", path.display(), err))?;
+    }
+    if let Some(dir) = &icons {
+        fs::create_dir_all(dir).map_err(|err| format!("{}: {}", dir.display(), err))?;
+    }
+
+    if format == Format::Json {
+        print_json(&apps);
+    }
+    for app in &apps {
+        if format == Format::Plain {
+            println!(
+                "{:<10} {:<6} {:<10} {}",
+                app.id, app.kind, app.version, app.name
+            );
+        }
+        let Some(dir) = &icons else {
+            continue;
+        };
+        let Some((bytes, content_type)) = ecp::get_app_icon(ip, &app.id) else {
+            eprintln!("no icon for {} ({})", app.name, app.id);
+            continue;
+        };
+        let ext = match content_type.as_str() {
+            "image/jpeg" => "jpg",
+            "image/gif" => "gif",
+            "image/webp" => "webp",
+            _ => "png",
+        };
+        let path = dir.join(format!("{}.{}", file_stem(&app.id), ext));
+        fs::write(&path, bytes).map_err(|err| format!("{}: {}", path.display(), err))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,173 @@
+// watching devices without writing a polling loop: one background thread discovers and
+// polls, and every subscriber gets the change events
+
+use crate::ecp::{self, SsdpSettings};
+use crate::events::{self, DeviceEvent, StateCache};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Watches Roku devices and hands their state changes to subscribers.
+///
+/// Polling starts with the first subscription and stops when the client is dropped.
+pub struct Client {
+    shared: Arc<Shared>,
+    poll_interval: Duration,
+    rediscover_interval: Option<Duration>,
+    ssdp: SsdpSettings,
+}
+
+struct Shared {
+    devices: Mutex<Vec<String>>,
+    subscribers: Mutex<Vec<Sender<DeviceEvent>>>,
+    started: AtomicBool,
+    stop: AtomicBool,
+}
+
+/// An `on_event` callback; dropping it unsubscribes.
+pub struct Subscription {
+    stop: Arc<AtomicBool>,
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Client {
+    /// Finds devices with SSDP, again every minute.
+    pub fn new() -> Client {
+        Client {
+            shared: Arc::new(Shared {
+                devices: Mutex::new(Vec::new()),
+                subscribers: Mutex::new(Vec::new()),
+                started: AtomicBool::new(false),
+                stop: AtomicBool::new(false),
+            }),
+            poll_interval: Duration::from_secs(5),
+            rediscover_interval: Some(Duration::from_secs(60)),
+            ssdp: SsdpSettings::default(),
+        }
+    }
+
+    /// Watches just these "ip:port" devices, without discovery.
+    pub fn with_devices(devices: impl IntoIterator<Item = String>) -> Client {
+        let client = Client::new().rediscover_interval(None);
+        client.add_devices(devices);
+        client
+    }
+
+    pub fn poll_interval(mut self, interval: Duration) -> Client {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// How often to look for new devices; `None` turns discovery off.
+    pub fn rediscover_interval(mut self, interval: Option<Duration>) -> Client {
+        self.rediscover_interval = interval;
+        self
+    }
+
+    /// How discovery searches, for networks where the default search misses devices.
+    pub fn ssdp(mut self, settings: SsdpSettings) -> Client {
+        self.ssdp = settings;
+        self
+    }
+
+    pub fn add_devices(&self, devices: impl IntoIterator<Item = String>) {
+        let mut known = self.shared.devices.lock().unwrap();
+        for device in devices {
+            if !known.contains(&device) {
+                known.push(device);
+            }
+        }
+    }
+
+    pub fn devices(&self) -> Vec<String> {
+        self.shared.devices.lock().unwrap().clone()
+    }
+
+    /// Every event from now on, as a channel.
+    pub fn events(&self) -> Receiver<DeviceEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.shared.subscribers.lock().unwrap().push(tx);
+        self.start();
+        rx
+    }
+
+    /// Calls `f` on its own thread for every event until the subscription is dropped.
+    pub fn on_event(&self, f: impl Fn(&DeviceEvent) + Send + 'static) -> Subscription {
+        let rx = self.events();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::clone(&stop);
+        thread::spawn(move || {
+            for event in rx {
+                if stopped.load(Ordering::Relaxed) {
+                    break;
+                }
+                f(&event);
+            }
+        });
+        Subscription { stop }
+    }
+
+    fn start(&self) {
+        if self.shared.started.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        let shared = Arc::clone(&self.shared);
+        let (poll_interval, rediscover_interval) = (self.poll_interval, self.rediscover_interval);
+        let ssdp = self.ssdp.clone();
+        thread::spawn(move || {
+            let mut cache = StateCache::default();
+            let mut seen = 0;
+            let mut last_discovery: Option<Instant> = None;
+            while !shared.stop.load(Ordering::Relaxed) {
+                if let Some(interval) = rediscover_interval {
+                    if last_discovery.is_none_or(|at| at.elapsed() >= interval) {
+                        last_discovery = Some(Instant::now());
+                        let found = ecp::discover_with(&ssdp);
+                        let mut known = shared.devices.lock().unwrap();
+                        for device in found {
+                            if !known.contains(&device) {
+                                cache.discovered(&device);
+                                known.push(device);
+                            }
+                        }
+                    }
+                }
+                let devices = shared.devices.lock().unwrap().clone();
+                for device in devices {
+                    cache.update(events::poll(&device));
+                }
+
+                let fresh: Vec<DeviceEvent> = cache.events_since(seen).cloned().collect();
+                if let Some(last) = fresh.last() {
+                    seen = last.seq;
+                }
+                // subscribers whose receiver is gone drop out here
+                shared
+                    .subscribers
+                    .lock()
+                    .unwrap()
+                    .retain(|tx| fresh.iter().all(|event| tx.send(event.clone()).is_ok()));
+                thread::sleep(poll_interval);
+            }
+        });
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        self.shared.stop.store(true, Ordering::Relaxed);
+    }
+}
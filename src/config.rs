@@ -0,0 +1,858 @@
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use utoipa::ToSchema;
+
+use crate::console::ConsoleSettings;
+use crate::ecp::SsdpSettings;
+use crate::global_hotkeys::GlobalHotkeySettings;
+use crate::idle_off::AutoPowerOff;
+use crate::key_bindings::KeyBindings;
+use crate::layout::RemoteLayout;
+use crate::macros::MacroSettings;
+use crate::mouse_buttons::MouseButtonSettings;
+use crate::mqtt::MqttSettings;
+use crate::reachability::BackgroundChecks;
+use crate::schedule::{Schedule, ScheduleSettings};
+use crate::scroll::ScrollSettings;
+use crate::theme::Theme;
+use crate::tmdb::TmdbSettings;
+use crate::volume::VolumeSettings;
+use crate::watchlist::DeepLink;
+use roku_remote::http::RequestSettings;
+
+// everything the remote remembers between runs, stored as json in the platform config dir
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    // keyed by the device address ("ip:port")
+    pub devices: BTreeMap<String, DeviceMeta>,
+    pub theme: Theme,
+    // the remote's buttons as arranged in the layout editor
+    pub layout: RemoteLayout,
+    // group name -> member device addresses
+    pub groups: BTreeMap<String, Vec<String>>,
+    // tokens accepted by the control server, keyed by a name for whoever holds them
+    pub api_tokens: BTreeMap<String, ApiToken>,
+    pub scroll: ScrollSettings,
+    pub mouse_buttons: MouseButtonSettings,
+    pub keyboard: KeyBindings,
+    pub global_hotkeys: GlobalHotkeySettings,
+    pub volume: VolumeSettings,
+    // macro name -> steps in the text form macros::parse reads
+    pub macros: BTreeMap<String, String>,
+    pub macro_settings: MacroSettings,
+    // script name -> path of its .rhai file
+    pub scripts: BTreeMap<String, String>,
+    // run by the --serve daemon, keyed by name
+    pub schedules: BTreeMap<String, Schedule>,
+    pub schedule_settings: ScheduleSettings,
+    pub auto_power_off: AutoPowerOff,
+    pub mqtt: MqttSettings,
+    // saved deep links, keyed by name
+    pub watchlist: BTreeMap<String, DeepLink>,
+    pub tmdb: TmdbSettings,
+    pub console: ConsoleSettings,
+    pub ssdp: SsdpSettings,
+    pub background: BackgroundChecks,
+    pub requests: RequestSettings,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub token: String,
+    #[serde(default)]
+    pub role: Role,
+    // devices this token may see and control; empty means all of them
+    #[serde(default)]
+    pub devices: Vec<String>,
+}
+
+// what a control server client may do, each role including the ones below it:
+// viewers read device state, control also sends commands, admin also manages tokens
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Viewer,
+    #[default]
+    Control,
+    Admin,
+}
+
+impl Role {
+    pub const ALL: [Role; 3] = [Role::Viewer, Role::Control, Role::Admin];
+
+    pub fn can_control(self) -> bool {
+        self >= Role::Control
+    }
+
+    pub fn is_admin(self) -> bool {
+        self == Role::Admin
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Control => "control",
+            Role::Admin => "admin",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Role> {
+        Role::ALL.into_iter().find(|role| role.label() == label)
+    }
+}
+
+impl ApiToken {
+    pub fn generate(role: Role, devices: Vec<String>) -> ApiToken {
+        let bytes: [u8; 16] = rand::random();
+        ApiToken {
+            token: bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+            role,
+            devices,
+        }
+    }
+
+    pub fn allows(&self, device: &str) -> bool {
+        self.devices.is_empty() || self.devices.iter().any(|d| d == device)
+    }
+
+    // "control, all devices" / "viewer, 2 devices"
+    pub fn scope_label(&self) -> String {
+        match self.devices.len() {
+            0 => format!("{}, all devices", self.role.label()),
+            1 => format!("{}, {}", self.role.label(), self.devices[0]),
+            n => format!("{}, {} devices", self.role.label(), n),
+        }
+    }
+}
+
+// free-form notes and tags attached to a device, and the channels pinned for it
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeviceMeta {
+    pub notes: String,
+    pub room: String,
+    pub owner: String,
+    pub hdmi_port: String,
+    pub tags: Vec<String>,
+    // app ids, in the order they were pinned
+    pub favorites: Vec<String>,
+    // the developer installer's password, chosen when developer mode was turned on
+    pub dev_password: String,
+}
+
+impl DeviceMeta {
+    pub fn is_empty(&self) -> bool {
+        *self == DeviceMeta::default()
+    }
+
+    // one-line summary for the device panel, e.g. "Living room · Alex · HDMI 2 · #kids"
+    pub fn summary(&self) -> String {
+        let mut parts: Vec<String> = Vec::new();
+        if !self.room.is_empty() {
+            parts.push(self.room.clone());
+        }
+        if !self.owner.is_empty() {
+            parts.push(self.owner.clone());
+        }
+        if !self.hdmi_port.is_empty() {
+            parts.push(format!("HDMI {}", self.hdmi_port));
+        }
+        parts.extend(self.tags.iter().map(|tag| format!("#{}", tag)));
+        parts.join(" · ")
+    }
+}
+
+pub fn config_path() -> Option<PathBuf> {
+    ProjectDirs::from("", "", "roku-remote").map(|dirs| dirs.config_dir().join("config.json"))
+}
+
+// where the config lives; kept next to the local config so it never syncs itself
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncSettings {
+    pub folder: Option<PathBuf>,
+}
+
+const SYNCED_FILE: &str = "roku-remote.json";
+
+fn sync_settings_path() -> Option<PathBuf> {
+    config_path().map(|path| path.with_file_name("sync.json"))
+}
+
+fn secrets_path() -> Option<PathBuf> {
+    config_path().map(|path| path.with_file_name("secrets.json"))
+}
+
+// what stays out of a synced folder, where every machine and the sync service would see it:
+// the control server's tokens and the passwords. while syncing they're kept in secrets.json
+// beside the local config and put back into the config as it's read, so tokens belong to
+// the machine that made them
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct Secrets {
+    api_tokens: BTreeMap<String, ApiToken>,
+    // device address -> developer installer password
+    dev_passwords: BTreeMap<String, String>,
+    mqtt_password: String,
+    tmdb_api_key: String,
+}
+
+impl Secrets {
+    // moves them out of the config, leaving it fit to share
+    fn take(config: &mut Config) -> Secrets {
+        Secrets {
+            api_tokens: std::mem::take(&mut config.api_tokens),
+            dev_passwords: config
+                .devices
+                .iter_mut()
+                .filter(|(_, meta)| !meta.dev_password.is_empty())
+                .map(|(ip, meta)| (ip.clone(), std::mem::take(&mut meta.dev_password)))
+                .collect(),
+            mqtt_password: std::mem::take(&mut config.mqtt.password),
+            tmdb_api_key: std::mem::take(&mut config.tmdb.api_key),
+        }
+    }
+
+    // a synced file from before they were kept apart may still hold some; the local ones win
+    fn put(self, config: &mut Config) {
+        config.api_tokens.extend(self.api_tokens);
+        for (ip, password) in self.dev_passwords {
+            config.devices.entry(ip).or_default().dev_password = password;
+        }
+        if !self.mqtt_password.is_empty() {
+            config.mqtt.password = self.mqtt_password;
+        }
+        if !self.tmdb_api_key.is_empty() {
+            config.tmdb.api_key = self.tmdb_api_key;
+        }
+    }
+
+    fn load(path: &Path) -> Secrets {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, path: &Path) -> io::Result<()> {
+        write_json(path, self)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        }
+        Ok(())
+    }
+}
+
+impl SyncSettings {
+    pub fn load() -> SyncSettings {
+        sync_settings_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = sync_settings_path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+        write_json(&path, self)
+    }
+}
+
+// loads and saves the config, merging in edits made by other machines through a synced folder
+#[derive(Debug, Default)]
+pub struct ConfigStore {
+    pub sync: SyncSettings,
+    // the config as last read from or written to disk, the common ancestor for merges
+    base: Config,
+    modified: Option<SystemTime>,
+    // why the config file couldn't be read at startup; it isn't saved over while it stays so
+    pub load_error: Option<String>,
+    // where the secrets go while the config is synced
+    secrets: Option<PathBuf>,
+}
+
+impl ConfigStore {
+    // a missing config just means starting fresh. one that doesn't parse, corrupt or only
+    // half synced, starts from defaults too, but is left alone rather than saved over
+    pub fn open() -> (ConfigStore, Config) {
+        let mut store = ConfigStore {
+            sync: SyncSettings::load(),
+            secrets: secrets_path(),
+            ..Default::default()
+        };
+        let mut config = match store.read() {
+            Ok(config) => config.unwrap_or_default(),
+            Err(err) => {
+                store.load_error = Some(err.to_string());
+                Config::default()
+            }
+        };
+        store.base = config.clone();
+        // a conflict copy's common ancestor with the file isn't known, and with the file as
+        // base everything the copy lacks would count as deleted there: an empty base keeps
+        // what either side has
+        if store.merge_conflict_copies(&Config::default(), &mut config) > 0 {
+            store.save(&mut config).ok();
+        }
+        (store, config)
+    }
+
+    pub fn path(&self) -> Option<PathBuf> {
+        match &self.sync.folder {
+            Some(folder) => Some(folder.join(SYNCED_FILE)),
+            None => config_path(),
+        }
+    }
+
+    // writes the config, first folding in anything another machine saved since we last looked
+    // returns how many entries were edited on both sides (the local edit is kept)
+    pub fn save(&mut self, config: &mut Config) -> io::Result<usize> {
+        let path = self
+            .path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+
+        let mut conflicts = 0;
+        if let Some(theirs) = self.read()? {
+            if theirs != self.base {
+                let (merged, n) = Config::merge(&self.base, config, &theirs);
+                *config = merged;
+                conflicts = n;
+            }
+        }
+        // copies the sync tool made since we last looked, before they're cleared away
+        conflicts += self.merge_conflict_copies(&Config::default(), config);
+
+        config.devices.retain(|_, meta| !meta.is_empty());
+        match self.secrets_file() {
+            Some(secrets_path) => {
+                let mut shared = config.clone();
+                Secrets::take(&mut shared).save(secrets_path)?;
+                shared.devices.retain(|_, meta| !meta.is_empty());
+                write_json(&path, &shared)?;
+            }
+            None => write_json(&path, config)?,
+        }
+        self.base = config.clone();
+        self.modified = modified_time(&path);
+        self.load_error = None;
+        self.remove_conflict_copies();
+        Ok(conflicts)
+    }
+
+    // picks up a save made elsewhere while we were running; true if the config changed
+    pub fn poll_external(&mut self, config: &mut Config) -> bool {
+        let Some(path) = self.path() else {
+            return false;
+        };
+        let modified = modified_time(&path);
+        if modified.is_none() || modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+
+        let Ok(Some(theirs)) = self.read() else {
+            return false;
+        };
+        if theirs == self.base {
+            return false;
+        }
+        let (merged, _) = Config::merge(&self.base, config, &theirs);
+        self.base = theirs;
+        let changed = merged != *config;
+        *config = merged;
+        changed
+    }
+
+    // switches to (or away from) a synced folder, combining what is already there with ours
+    pub fn set_sync_folder(
+        &mut self,
+        folder: Option<PathBuf>,
+        config: &mut Config,
+    ) -> io::Result<usize> {
+        if let Some(dir) = &folder {
+            if !dir.is_dir() {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "sync folder does not exist",
+                ));
+            }
+        }
+        self.sync.folder = folder;
+        self.sync.save()?;
+        // an empty base means every entry unique to either side survives
+        self.base = Config::default();
+        self.modified = None;
+        let conflicts = self.merge_conflict_copies(&Config::default(), config);
+        Ok(self.save(config)? + conflicts)
+    }
+
+    // the secrets file, when the config is synced and so has to go without them
+    fn secrets_file(&self) -> Option<&Path> {
+        self.sync.folder.as_ref().and(self.secrets.as_deref())
+    }
+
+    // a config as read from the synced folder, with this machine's secrets put back
+    fn with_secrets(&self, mut config: Config) -> Config {
+        if let Some(path) = self.secrets_file() {
+            Secrets::load(path).put(&mut config);
+        }
+        config
+    }
+
+    // None for no config file yet; an error for one that couldn't be read or doesn't parse
+    fn read(&self) -> io::Result<Option<Config>> {
+        let Some(path) = self.path() else {
+            return Ok(None);
+        };
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let config = serde_json::from_str(&text).map_err(|err| {
+            let message = format!(
+                "{} doesn't parse, not saving over it: {}",
+                path.display(),
+                err
+            );
+            io::Error::new(io::ErrorKind::InvalidData, message)
+        })?;
+        Ok(Some(self.with_secrets(config)))
+    }
+
+    // dropbox writes "roku-remote (host's conflicted copy).json", syncthing "roku-remote.sync-conflict-….json"
+    fn conflict_copies(&self) -> Vec<PathBuf> {
+        let Some(folder) = &self.sync.folder else {
+            return Vec::new();
+        };
+        let Ok(entries) = fs::read_dir(folder) else {
+            return Vec::new();
+        };
+        entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                let name = path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .unwrap_or_default();
+                name != SYNCED_FILE
+                    && name.starts_with("roku-remote")
+                    && name.ends_with(".json")
+                    && name.contains("conflict")
+            })
+            .collect()
+    }
+
+    fn merge_conflict_copies(&self, base: &Config, config: &mut Config) -> usize {
+        let mut conflicts = 0;
+        for path in self.conflict_copies() {
+            if let Some(theirs) = read_conflict_copy(&path) {
+                let theirs = self.with_secrets(theirs);
+                let (merged, n) = Config::merge(base, config, &theirs);
+                *config = merged;
+                conflicts += n.max(1);
+            }
+        }
+        conflicts
+    }
+
+    // only the copies that were merged in; one still syncing is left for the next save
+    fn remove_conflict_copies(&self) {
+        for path in self.conflict_copies() {
+            if read_conflict_copy(&path).is_some() {
+                fs::remove_file(path).ok();
+            }
+        }
+    }
+}
+
+impl Config {
+    // three-way merge per entry: whichever side changed an entry relative to base wins,
+    // and when both did the local (ours) version is kept and counted as a conflict
+    pub fn merge(base: &Config, ours: &Config, theirs: &Config) -> (Config, usize) {
+        let mut conflicts = 0;
+        let merged = Config {
+            devices: merge_map(
+                &base.devices,
+                &ours.devices,
+                &theirs.devices,
+                &mut conflicts,
+            ),
+            theme: merge_value(&base.theme, &ours.theme, &theirs.theme, &mut conflicts),
+            layout: merge_value(&base.layout, &ours.layout, &theirs.layout, &mut conflicts),
+            groups: merge_map(&base.groups, &ours.groups, &theirs.groups, &mut conflicts),
+            api_tokens: merge_map(
+                &base.api_tokens,
+                &ours.api_tokens,
+                &theirs.api_tokens,
+                &mut conflicts,
+            ),
+            scroll: merge_value(&base.scroll, &ours.scroll, &theirs.scroll, &mut conflicts),
+            mouse_buttons: merge_value(
+                &base.mouse_buttons,
+                &ours.mouse_buttons,
+                &theirs.mouse_buttons,
+                &mut conflicts,
+            ),
+            keyboard: merge_value(
+                &base.keyboard,
+                &ours.keyboard,
+                &theirs.keyboard,
+                &mut conflicts,
+            ),
+            global_hotkeys: merge_value(
+                &base.global_hotkeys,
+                &ours.global_hotkeys,
+                &theirs.global_hotkeys,
+                &mut conflicts,
+            ),
+            volume: merge_value(&base.volume, &ours.volume, &theirs.volume, &mut conflicts),
+            macros: merge_map(&base.macros, &ours.macros, &theirs.macros, &mut conflicts),
+            macro_settings: merge_value(
+                &base.macro_settings,
+                &ours.macro_settings,
+                &theirs.macro_settings,
+                &mut conflicts,
+            ),
+            scripts: merge_map(
+                &base.scripts,
+                &ours.scripts,
+                &theirs.scripts,
+                &mut conflicts,
+            ),
+            schedules: merge_map(
+                &base.schedules,
+                &ours.schedules,
+                &theirs.schedules,
+                &mut conflicts,
+            ),
+            schedule_settings: merge_value(
+                &base.schedule_settings,
+                &ours.schedule_settings,
+                &theirs.schedule_settings,
+                &mut conflicts,
+            ),
+            auto_power_off: merge_value(
+                &base.auto_power_off,
+                &ours.auto_power_off,
+                &theirs.auto_power_off,
+                &mut conflicts,
+            ),
+            mqtt: merge_value(&base.mqtt, &ours.mqtt, &theirs.mqtt, &mut conflicts),
+            watchlist: merge_map(
+                &base.watchlist,
+                &ours.watchlist,
+                &theirs.watchlist,
+                &mut conflicts,
+            ),
+            tmdb: merge_value(&base.tmdb, &ours.tmdb, &theirs.tmdb, &mut conflicts),
+            console: merge_value(
+                &base.console,
+                &ours.console,
+                &theirs.console,
+                &mut conflicts,
+            ),
+            ssdp: merge_value(&base.ssdp, &ours.ssdp, &theirs.ssdp, &mut conflicts),
+            background: merge_value(
+                &base.background,
+                &ours.background,
+                &theirs.background,
+                &mut conflicts,
+            ),
+            requests: merge_value(
+                &base.requests,
+                &ours.requests,
+                &theirs.requests,
+                &mut conflicts,
+            ),
+        };
+        (merged, conflicts)
+    }
+
+    // a device that came back at another address: its notes, groups, tokens and schedules
+    // follow it there. notes already kept for the new address win over the old ones
+    pub fn rename_device(&mut self, old: &str, new: &str) {
+        if let Some(meta) = self.devices.remove(old) {
+            self.devices.entry(new.to_string()).or_insert(meta);
+        }
+        let lists = self
+            .groups
+            .values_mut()
+            .chain(self.api_tokens.values_mut().map(|token| &mut token.devices));
+        for devices in lists {
+            for device in devices.iter_mut().filter(|device| *device == old) {
+                *device = new.to_string();
+            }
+            let mut seen = BTreeSet::new();
+            devices.retain(|device| seen.insert(device.clone()));
+        }
+        for schedule in self.schedules.values_mut() {
+            if schedule.target == old {
+                schedule.target = new.to_string();
+            }
+        }
+    }
+
+    pub fn group_members(&self, group: &str) -> &[String] {
+        self.groups
+            .get(group)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    pub fn set_group_membership(&mut self, group: &str, device: &str, member: bool) {
+        let members = self.groups.entry(group.to_string()).or_default();
+        members.retain(|d| d != device);
+        if member {
+            members.push(device.to_string());
+        }
+    }
+}
+
+fn merge_value<T: Clone + PartialEq>(base: &T, ours: &T, theirs: &T, conflicts: &mut usize) -> T {
+    if ours == base {
+        theirs.clone()
+    } else {
+        if theirs != base && theirs != ours {
+            *conflicts += 1;
+        }
+        ours.clone()
+    }
+}
+
+fn merge_map<T: Clone + PartialEq>(
+    base: &BTreeMap<String, T>,
+    ours: &BTreeMap<String, T>,
+    theirs: &BTreeMap<String, T>,
+    conflicts: &mut usize,
+) -> BTreeMap<String, T> {
+    let keys: BTreeSet<&String> = base
+        .keys()
+        .chain(ours.keys())
+        .chain(theirs.keys())
+        .collect();
+    let mut merged = BTreeMap::new();
+    for key in keys {
+        let (b, o, t) = (base.get(key), ours.get(key), theirs.get(key));
+        let pick = if o == b {
+            t
+        } else if t == b || t == o {
+            o
+        } else {
+            *conflicts += 1;
+            o
+        };
+        if let Some(value) = pick {
+            merged.insert(key.clone(), value.clone());
+        }
+    }
+    merged
+}
+
+fn read_conflict_copy(path: &Path) -> Option<Config> {
+    let text = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&text).ok()
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+pub fn write_json<T: Serialize>(path: &Path, value: &T) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let text = serde_json::to_string_pretty(value).map_err(io::Error::other)?;
+    // written beside it and renamed over it, so a crash or a full disk never leaves half a file
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = path.with_file_name(format!(".{}.tmp", name));
+    fs::write(&temp, text)?;
+    fs::rename(&temp, path).inspect_err(|_| {
+        fs::remove_file(&temp).ok();
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ApiToken, Config, ConfigStore, DeviceMeta, Role, SyncSettings, SYNCED_FILE};
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn device(room: &str) -> DeviceMeta {
+        DeviceMeta {
+            room: room.into(),
+            ..Default::default()
+        }
+    }
+
+    fn with_devices(devices: &[(&str, &str)]) -> Config {
+        Config {
+            devices: devices
+                .iter()
+                .map(|(ip, room)| (ip.to_string(), device(room)))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn rooms(config: &Config) -> Vec<(&str, &str)> {
+        config
+            .devices
+            .iter()
+            .map(|(ip, meta)| (ip.as_str(), meta.room.as_str()))
+            .collect()
+    }
+
+    // a sync folder of its own for each test
+    fn sync_folder(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("roku-remote-{}-{}", name, std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // secrets go in a folder of their own, beside the sync folder as the local config dir is
+    fn store_in(dir: &std::path::Path, base: Config) -> ConfigStore {
+        let local = dir.with_extension("local");
+        ConfigStore {
+            sync: SyncSettings {
+                folder: Some(dir.to_path_buf()),
+            },
+            base,
+            secrets: Some(local.join("secrets.json")),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn a_deletion_on_either_side_sticks() {
+        let base = with_devices(&[("a", "Den"), ("b", "Kitchen")]);
+        let ours = with_devices(&[("a", "Den")]);
+        let theirs = with_devices(&[("b", "Kitchen")]);
+        let (merged, conflicts) = Config::merge(&base, &ours, &theirs);
+        assert!(merged.devices.is_empty());
+        assert_eq!(conflicts, 0);
+    }
+
+    #[test]
+    fn an_entry_deleted_on_one_side_and_edited_on_the_other_is_kept() {
+        let base = with_devices(&[("a", "Den")]);
+        let ours = with_devices(&[("a", "Office")]);
+        let theirs = Config::default();
+        let (merged, conflicts) = Config::merge(&base, &ours, &theirs);
+        assert_eq!(rooms(&merged), [("a", "Office")]);
+        assert_eq!(conflicts, 1);
+    }
+
+    #[test]
+    fn edits_on_both_sides_keep_ours_and_count_a_conflict() {
+        let base = with_devices(&[("a", "Den"), ("b", "Kitchen")]);
+        let ours = with_devices(&[("a", "Office"), ("b", "Kitchen")]);
+        let theirs = with_devices(&[("a", "Attic"), ("b", "Garage")]);
+        let (merged, conflicts) = Config::merge(&base, &ours, &theirs);
+        assert_eq!(rooms(&merged), [("a", "Office"), ("b", "Garage")]);
+        assert_eq!(conflicts, 1);
+    }
+
+    #[test]
+    fn the_same_edit_on_both_sides_is_no_conflict() {
+        let base = with_devices(&[("a", "Den")]);
+        let both = with_devices(&[("a", "Office")]);
+        let (merged, conflicts) = Config::merge(&base, &both, &both);
+        assert_eq!(rooms(&merged), [("a", "Office")]);
+        assert_eq!(conflicts, 0);
+    }
+
+    #[test]
+    fn a_conflict_copy_adds_its_newer_entries_without_dropping_ours() {
+        let dir = sync_folder("conflict-copy");
+        let ours = with_devices(&[("a", "Den"), ("b", "Kitchen")]);
+        // the copy never saw b, and has a device added on the other machine
+        let copy = with_devices(&[("a", "Den"), ("c", "Attic")]);
+        let copy_path = dir.join("roku-remote (laptop's conflicted copy).json");
+        fs::write(&copy_path, serde_json::to_string(&copy).unwrap()).unwrap();
+
+        let mut config = ours.clone();
+        let store = store_in(&dir, ours);
+        store.merge_conflict_copies(&Config::default(), &mut config);
+        assert_eq!(
+            rooms(&config),
+            [("a", "Den"), ("b", "Kitchen"), ("c", "Attic")]
+        );
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn a_config_that_does_not_parse_is_not_saved_over() {
+        let dir = sync_folder("corrupt");
+        let path = dir.join(SYNCED_FILE);
+        fs::write(&path, "{\"devices\": {\"a\": ").unwrap();
+
+        let mut store = store_in(&dir, Config::default());
+        assert!(store.read().is_err());
+        let mut config = with_devices(&[("b", "Kitchen")]);
+        assert!(store.save(&mut config).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{\"devices\": {\"a\": ");
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn saving_replaces_the_file_whole() {
+        let dir = sync_folder("save");
+        let mut store = store_in(&dir, Config::default());
+        let mut config = with_devices(&[("a", "Den")]);
+        store.save(&mut config).unwrap();
+        assert_eq!(store.read().unwrap(), Some(config));
+        // nothing of the temporary file is left behind
+        let names: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(names, [SYNCED_FILE]);
+        fs::remove_dir_all(&dir).ok();
+        fs::remove_dir_all(dir.with_extension("local")).ok();
+    }
+
+    #[test]
+    fn secrets_stay_out_of_the_synced_file() {
+        let dir = sync_folder("secrets");
+        let mut store = store_in(&dir, Config::default());
+        let mut config = with_devices(&[("a", "Den")]);
+        config.devices.get_mut("a").unwrap().dev_password = "hunter2".into();
+        config.devices.insert(
+            "b".into(),
+            DeviceMeta {
+                dev_password: "swordfish".into(),
+                ..Default::default()
+            },
+        );
+        config
+            .api_tokens
+            .insert("phone".into(), ApiToken::generate(Role::Admin, Vec::new()));
+        config.mqtt.password = "mqtt-secret".into();
+        config.tmdb.api_key = "tmdb-secret".into();
+        store.save(&mut config).unwrap();
+
+        let shared = fs::read_to_string(dir.join(SYNCED_FILE)).unwrap();
+        let token = &config.api_tokens["phone"].token;
+        for secret in ["hunter2", "swordfish", "mqtt-secret", "tmdb-secret", token] {
+            assert!(!shared.contains(secret), "{} in {}", secret, shared);
+        }
+        // a device known only for its password isn't left as an empty entry either
+        assert!(!shared.contains("\"b\""));
+        assert_eq!(store.read().unwrap(), Some(config));
+        fs::remove_dir_all(&dir).ok();
+        fs::remove_dir_all(dir.with_extension("local")).ok();
+    }
+}
@@ -0,0 +1,720 @@
+// the BrightScript debug console a dev channel prints to, telnet on port 8085, in a panel next
+// to the remote. long log sessions get regex and level filters, colour rules and search
+
+mod crash;
+mod telnet;
+
+use chrono::Local;
+use crash::{Crash, CrashParser};
+use eframe::egui::{self, Color32, RichText};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use telnet::Telnet;
+
+pub const CONSOLE_PORT: u16 = 8085;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+// how long a read waits before checking whether the console was closed
+const READ_TIMEOUT: Duration = Duration::from_millis(500);
+// lines kept; older ones are dropped
+const MAX_LINES: usize = 5000;
+// waits between reconnect attempts, doubling while the device stays away
+const RECONNECT_MIN: Duration = Duration::from_secs(1);
+const RECONNECT_MAX: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Highlight {
+    // regex, matched against each line
+    pub pattern: String,
+    pub color: [u8; 3],
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConsoleSettings {
+    // the first rule that matches colours the line
+    pub highlights: Vec<Highlight>,
+    // open the console again when the session drops, e.g. on a redeploy
+    pub auto_reconnect: bool,
+}
+
+impl Default for ConsoleSettings {
+    fn default() -> Self {
+        Self {
+            highlights: vec![
+                Highlight {
+                    pattern: r"(?i)\berror\b|crash|runtime error".to_string(),
+                    color: [0xe0, 0x4a, 0x4a],
+                },
+                Highlight {
+                    pattern: r"(?i)\bwarn(ing)?\b".to_string(),
+                    color: [0xe0, 0xb0, 0x30],
+                },
+            ],
+            auto_reconnect: true,
+        }
+    }
+}
+
+// BrightScript has no log levels, so they are guessed from the words in the line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Debug,
+    Info,
+    Warning,
+    Error,
+}
+
+impl Level {
+    const ALL: [Level; 4] = [Level::Debug, Level::Info, Level::Warning, Level::Error];
+
+    fn of(line: &str) -> Level {
+        let lower = line.to_lowercase();
+        if lower.contains("error") || lower.contains("crash") || lower.contains("backtrace") {
+            Level::Error
+        } else if lower.contains("warn") {
+            Level::Warning
+        } else if lower.contains("debug") || lower.starts_with("brightscript debugger") {
+            Level::Debug
+        } else {
+            Level::Info
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Level::Debug => "Everything",
+            Level::Info => "Info and up",
+            Level::Warning => "Warnings and errors",
+            Level::Error => "Errors only",
+        }
+    }
+}
+
+enum Event {
+    Connected,
+    Line(String),
+    // dropped, trying again
+    Lost(String),
+    Closed(String),
+}
+
+struct LogLine {
+    text: String,
+    level: Level,
+    // "reconnected" and the like, shown whatever the filters say
+    marker: bool,
+}
+
+// one telnet session, read on its own thread. redeploying a channel restarts the console, so
+// with reconnect on a dropped session is opened again until it comes back
+struct Connection {
+    rx: Receiver<Event>,
+    stop: Arc<AtomicBool>,
+    reconnect: Arc<AtomicBool>,
+}
+
+impl Connection {
+    fn open(device: &str, reconnect: bool) -> Connection {
+        let host = device.rsplit_once(':').map_or(device, |(host, _)| host);
+        let addr = format!("{}:{}", host, CONSOLE_PORT);
+        let (tx, rx) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let reconnect = Arc::new(AtomicBool::new(reconnect));
+        let (stopped, retry) = (Arc::clone(&stop), Arc::clone(&reconnect));
+        thread::spawn(move || {
+            let mut backoff = RECONNECT_MIN;
+            loop {
+                let mut connected = false;
+                let reason = match read_console(&addr, &tx, &stopped, &mut connected) {
+                    Ok(()) => return,
+                    Err(reason) => reason,
+                };
+                if !retry.load(Ordering::Relaxed) {
+                    let _ = tx.send(Event::Closed(reason));
+                    return;
+                }
+                if tx.send(Event::Lost(reason)).is_err() {
+                    return;
+                }
+                backoff = if connected {
+                    RECONNECT_MIN
+                } else {
+                    (backoff * 2).min(RECONNECT_MAX)
+                };
+                let until = Instant::now() + backoff;
+                while Instant::now() < until {
+                    if stopped.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    thread::sleep(READ_TIMEOUT);
+                }
+            }
+        });
+        Connection {
+            rx,
+            stop,
+            reconnect,
+        }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+// Ok when the console was closed from this end
+fn read_console(
+    addr: &str,
+    tx: &Sender<Event>,
+    stop: &AtomicBool,
+    connected: &mut bool,
+) -> Result<(), String> {
+    let addr = addr
+        .to_socket_addrs()
+        .map_err(|err| err.to_string())?
+        .next()
+        .ok_or("no address for the device")?;
+    let mut stream =
+        TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT).map_err(|err| err.to_string())?;
+    stream
+        .set_read_timeout(Some(READ_TIMEOUT))
+        .map_err(|err| err.to_string())?;
+    *connected = true;
+    if tx.send(Event::Connected).is_err() {
+        return Ok(());
+    }
+    let mut telnet = Telnet::default();
+    let mut pending = Vec::new();
+    let mut buf = [0u8; 4096];
+    while !stop.load(Ordering::Relaxed) {
+        let read = match stream.read(&mut buf) {
+            Ok(0) => return Err("the device closed the console".to_string()),
+            Ok(read) => read,
+            Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                // the debugger prompt never gets a newline, so it goes out when the device pauses
+                if pending.ends_with(b"> ") {
+                    let text = String::from_utf8_lossy(&pending).trim_end().to_string();
+                    pending.clear();
+                    if tx.send(Event::Line(text)).is_err() {
+                        return Ok(());
+                    }
+                }
+                continue;
+            }
+            Err(err) => return Err(err.to_string()),
+        };
+        let (text, reply) = telnet.filter(&buf[..read]);
+        if !reply.is_empty() {
+            stream.write_all(&reply).map_err(|err| err.to_string())?;
+        }
+        pending.extend_from_slice(&text);
+        while let Some(end) = pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = pending.drain(..=end).collect();
+            let text = String::from_utf8_lossy(&line);
+            if tx.send(Event::Line(text.trim_end().to_string())).is_err() {
+                return Ok(());
+            }
+        }
+    }
+    Ok(())
+}
+
+// a regex typed into the panel, compiled again only when the text changes
+#[derive(Default)]
+struct Pattern {
+    text: String,
+    compiled: Option<Result<Regex, String>>,
+}
+
+impl Pattern {
+    fn get(&mut self) -> Option<&Result<Regex, String>> {
+        if self.text.trim().is_empty() {
+            return None;
+        }
+        if self.compiled.is_none() {
+            self.compiled = Some(Regex::new(&self.text).map_err(|err| err.to_string()));
+        }
+        self.compiled.as_ref()
+    }
+
+    fn edit(&mut self, ui: &mut egui::Ui, hint: &str) -> egui::Response {
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut self.text)
+                .hint_text(hint)
+                .desired_width(180.0),
+        );
+        if response.changed() {
+            self.compiled = None;
+        }
+        response
+    }
+}
+
+pub struct ConsoleView {
+    pub open: bool,
+    device: Option<String>,
+    connection: Option<Connection>,
+    status: String,
+    lines: VecDeque<LogLine>,
+    // lines that came in while paused, shown on resume
+    held: Vec<LogLine>,
+    paused: bool,
+    auto_scroll: bool,
+    min_level: Level,
+    filter: Pattern,
+    search: Pattern,
+    // which of the search hits is scrolled to
+    search_hit: usize,
+    scroll_to_hit: bool,
+    highlights: Vec<(String, Option<Regex>)>,
+    edit_highlights: bool,
+    crash_parser: CrashParser,
+    // newest last
+    crashes: Vec<Crash>,
+    // connected right now, and whether a lost connection was marked in the log
+    live: bool,
+    dropped: bool,
+}
+
+impl Default for ConsoleView {
+    fn default() -> Self {
+        Self {
+            open: false,
+            device: None,
+            connection: None,
+            status: String::new(),
+            lines: VecDeque::new(),
+            held: Vec::new(),
+            paused: false,
+            auto_scroll: true,
+            min_level: Level::Debug,
+            filter: Pattern::default(),
+            search: Pattern::default(),
+            search_hit: 0,
+            scroll_to_hit: false,
+            highlights: Vec::new(),
+            edit_highlights: false,
+            crash_parser: CrashParser::default(),
+            crashes: Vec::new(),
+            live: false,
+            dropped: false,
+        }
+    }
+}
+
+impl ConsoleView {
+    pub fn connect(&mut self, device: &str, reconnect: bool) {
+        self.connection = Some(Connection::open(device, reconnect));
+        self.live = false;
+        self.dropped = false;
+        self.device = Some(device.to_string());
+        self.status = format!("Connecting to {}…", device);
+    }
+
+    pub fn disconnect(&mut self) {
+        self.connection = None;
+        self.live = false;
+        self.dropped = false;
+        self.status = "Disconnected".to_string();
+    }
+
+    // takes in whatever the session has read since the last frame
+    pub fn poll(&mut self, ctx: &egui::Context) {
+        let Some(connection) = &self.connection else {
+            return;
+        };
+        let mut events = Vec::new();
+        loop {
+            match connection.rx.try_recv() {
+                Ok(event) => events.push(event),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    events.push(Event::Closed(String::new()));
+                    break;
+                }
+            }
+        }
+        for event in events {
+            match event {
+                Event::Connected => {
+                    if self.dropped {
+                        let time = Local::now().format("%H:%M:%S");
+                        self.add_marker(format!("──── reconnected at {} ────", time));
+                        self.dropped = false;
+                    }
+                    self.live = true;
+                    self.status = "Connected".to_string();
+                }
+                Event::Line(text) => {
+                    if let Some(crash) = self.crash_parser.feed(&text) {
+                        self.crashes.push(crash);
+                    }
+                    self.add(LogLine {
+                        level: Level::of(&text),
+                        text,
+                        marker: false,
+                    });
+                }
+                Event::Lost(reason) => {
+                    self.crashes.extend(self.crash_parser.finish());
+                    // one marker per drop, not one per failed attempt while the device is away
+                    if self.live {
+                        self.add_marker(format!("──── connection lost: {} ────", reason));
+                        self.dropped = true;
+                    }
+                    self.live = false;
+                    self.status = format!("Reconnecting… ({})", reason);
+                }
+                Event::Closed(reason) => {
+                    self.crashes.extend(self.crash_parser.finish());
+                    self.live = false;
+                    self.connection = None;
+                    self.status = if reason.is_empty() {
+                        "Disconnected".to_string()
+                    } else {
+                        format!("Disconnected: {}", reason)
+                    };
+                }
+            }
+        }
+        ctx.request_repaint_after(Duration::from_millis(250));
+    }
+
+    fn add(&mut self, line: LogLine) {
+        if self.paused {
+            self.held.push(line);
+        } else {
+            self.push(line);
+        }
+    }
+
+    fn add_marker(&mut self, text: String) {
+        self.add(LogLine {
+            text,
+            level: Level::Info,
+            marker: true,
+        });
+    }
+
+    fn push(&mut self, line: LogLine) {
+        self.lines.push_back(line);
+        while self.lines.len() > MAX_LINES {
+            self.lines.pop_front();
+        }
+    }
+
+    fn resume(&mut self) {
+        self.paused = false;
+        for line in std::mem::take(&mut self.held) {
+            self.push(line);
+        }
+    }
+
+    fn line_color(&mut self, settings: &ConsoleSettings, text: &str) -> Option<Color32> {
+        if self.highlights.len() != settings.highlights.len()
+            || self
+                .highlights
+                .iter()
+                .zip(&settings.highlights)
+                .any(|((pattern, _), rule)| *pattern != rule.pattern)
+        {
+            self.highlights = settings
+                .highlights
+                .iter()
+                .map(|rule| (rule.pattern.clone(), Regex::new(&rule.pattern).ok()))
+                .collect();
+        }
+        self.highlights
+            .iter()
+            .zip(&settings.highlights)
+            .find(|((_, re), _)| re.as_ref().is_some_and(|re| re.is_match(text)))
+            .map(|(_, rule)| Color32::from_rgb(rule.color[0], rule.color[1], rule.color[2]))
+    }
+
+    // the panel, drawn before the central panel; true when the highlight rules were edited
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        device: Option<&str>,
+        settings: &mut ConsoleSettings,
+    ) -> bool {
+        if !self.open {
+            return false;
+        }
+        let mut changed = false;
+        egui::SidePanel::right("debug_console")
+            .resizable(true)
+            .default_width(520.0)
+            .show(ctx, |ui| {
+                changed = self.toolbar(ui, device, settings);
+                ui.separator();
+                changed |= self.filters(ui, settings);
+                ui.separator();
+                if !self.crashes.is_empty() {
+                    self.crashes_ui(ui);
+                    ui.separator();
+                }
+                self.log(ui, settings);
+            });
+        changed
+    }
+
+    fn toolbar(
+        &mut self,
+        ui: &mut egui::Ui,
+        device: Option<&str>,
+        settings: &mut ConsoleSettings,
+    ) -> bool {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            ui.heading("Debug Console");
+            if self.connection.is_some() {
+                if ui.button("Disconnect").clicked() {
+                    self.disconnect();
+                }
+            } else if let Some(device) = device {
+                if ui.button(format!("Connect to {}", device)).clicked() {
+                    self.connect(device, settings.auto_reconnect);
+                }
+            } else {
+                ui.weak("Select a device to connect");
+            }
+            if ui.button("Clear").clicked() {
+                self.lines.clear();
+                self.held.clear();
+                self.crashes.clear();
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label(&self.status);
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                ui.checkbox(&mut self.auto_scroll, "Auto-scroll");
+                let reconnect = ui.checkbox(&mut settings.auto_reconnect, "Reconnect");
+                if reconnect.changed() {
+                    changed = true;
+                    if let Some(connection) = &self.connection {
+                        connection
+                            .reconnect
+                            .store(settings.auto_reconnect, Ordering::Relaxed);
+                    }
+                }
+                let pause = if self.paused {
+                    format!("Resume ({} new)", self.held.len())
+                } else {
+                    "Pause".to_string()
+                };
+                if ui.button(pause).clicked() {
+                    if self.paused {
+                        self.resume();
+                    } else {
+                        self.paused = true;
+                    }
+                }
+            });
+        });
+        changed
+    }
+
+    fn filters(&mut self, ui: &mut egui::Ui, settings: &mut ConsoleSettings) -> bool {
+        ui.horizontal(|ui| {
+            ui.label("Filter:");
+            self.filter.edit(ui, "regex");
+            if let Some(Err(err)) = self.filter.get() {
+                ui.colored_label(Color32::RED, "✖")
+                    .on_hover_text(err.as_str());
+            }
+            egui::ComboBox::from_id_source("console_level")
+                .selected_text(self.min_level.label())
+                .show_ui(ui, |ui| {
+                    for level in Level::ALL {
+                        ui.selectable_value(&mut self.min_level, level, level.label());
+                    }
+                });
+        });
+        ui.horizontal(|ui| {
+            ui.label("Search:");
+            let edit = self.search.edit(ui, "regex");
+            if edit.changed() {
+                self.search_hit = 0;
+                self.scroll_to_hit = true;
+            }
+            let enter = edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            let hits = self.hits().len();
+            if let Some(Err(err)) = self.search.get() {
+                ui.colored_label(Color32::RED, "✖")
+                    .on_hover_text(err.as_str());
+            } else if !self.search.text.trim().is_empty() {
+                ui.label(if hits == 0 {
+                    "no matches".to_string()
+                } else {
+                    format!("{} of {}", self.search_hit.min(hits - 1) + 1, hits)
+                });
+            }
+            if hits > 0 {
+                if ui.small_button("▲").clicked() {
+                    self.search_hit = (self.search_hit + hits - 1) % hits;
+                    self.scroll_to_hit = true;
+                }
+                if ui.small_button("▼").clicked() || enter {
+                    self.search_hit = (self.search_hit + 1) % hits;
+                    self.scroll_to_hit = true;
+                }
+            }
+            ui.toggle_value(&mut self.edit_highlights, "🎨 Colours");
+        });
+
+        if !self.edit_highlights {
+            return false;
+        }
+        let mut changed = false;
+        let mut remove = None;
+        for (index, rule) in settings.highlights.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                changed |= ui.color_edit_button_srgb(&mut rule.color).changed();
+                changed |= ui
+                    .add(egui::TextEdit::singleline(&mut rule.pattern).desired_width(220.0))
+                    .changed();
+                if Regex::new(&rule.pattern).is_err() {
+                    ui.colored_label(Color32::RED, "✖");
+                }
+                if ui.small_button("Remove").clicked() {
+                    remove = Some(index);
+                }
+            });
+        }
+        if let Some(index) = remove {
+            settings.highlights.remove(index);
+            changed = true;
+        }
+        if ui.button("Add rule").clicked() {
+            settings.highlights.push(Highlight {
+                pattern: String::new(),
+                color: [0x4a, 0x9a, 0xe0],
+            });
+            changed = true;
+        }
+        changed
+    }
+
+    fn crashes_ui(&mut self, ui: &mut egui::Ui) {
+        let mut dismiss = None;
+        egui::CollapsingHeader::new(
+            RichText::new(format!("⚠ Crashes ({})", self.crashes.len())).color(Color32::RED),
+        )
+        .default_open(true)
+        .show(ui, |ui| {
+            egui::ScrollArea::vertical()
+                .id_source("console_crashes")
+                .max_height(220.0)
+                .show(ui, |ui| {
+                    for (index, crash) in self.crashes.iter().enumerate().rev() {
+                        ui.strong(&crash.message);
+                        ui.label(format!("at {}", crash.location()));
+                        egui::Grid::new(("crash_frames", index))
+                            .num_columns(2)
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for frame in &crash.frames {
+                                    ui.monospace(&frame.function);
+                                    ui.monospace(format!("{}({})", frame.file, frame.line));
+                                    ui.end_row();
+                                }
+                            });
+                        ui.horizontal(|ui| {
+                            if ui.button("Copy report").clicked() {
+                                ui.output_mut(|o| o.copied_text = crash.report());
+                            }
+                            if ui.button("Dismiss").clicked() {
+                                dismiss = Some(index);
+                            }
+                        });
+                        ui.separator();
+                    }
+                });
+        });
+        if let Some(index) = dismiss {
+            self.crashes.remove(index);
+        }
+    }
+
+    // indices into lines that pass the level and regex filters
+    fn visible(&mut self) -> Vec<usize> {
+        let min_level = self.min_level;
+        let filter = match self.filter.get() {
+            Some(Ok(re)) => Some(re.clone()),
+            _ => None,
+        };
+        self.lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| {
+                line.marker
+                    || (line.level >= min_level
+                        && filter.as_ref().is_none_or(|re| re.is_match(&line.text)))
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    // rows of the visible lines that match the search
+    fn hits(&mut self) -> Vec<usize> {
+        let Some(Ok(search)) = self.search.get().cloned() else {
+            return Vec::new();
+        };
+        let visible = self.visible();
+        visible
+            .iter()
+            .enumerate()
+            .filter(|(_, &index)| search.is_match(&self.lines[index].text))
+            .map(|(row, _)| row)
+            .collect()
+    }
+
+    fn log(&mut self, ui: &mut egui::Ui, settings: &ConsoleSettings) {
+        let visible = self.visible();
+        let hits = self.hits();
+        let current_hit = hits.get(self.search_hit.min(hits.len().saturating_sub(1)));
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+
+        let mut scroll = egui::ScrollArea::both()
+            .auto_shrink([false, false])
+            .stick_to_bottom(self.auto_scroll && !self.paused);
+        if let (true, Some(&row)) = (self.scroll_to_hit, current_hit) {
+            let spacing = ui.spacing().item_spacing.y;
+            scroll = scroll
+                .vertical_scroll_offset((row as f32 * (row_height + spacing) - 40.0).max(0.0));
+            self.scroll_to_hit = false;
+        }
+        scroll.show_rows(ui, row_height, visible.len(), |ui, rows| {
+            for row in rows {
+                let line = &self.lines[visible[row]];
+                let (text, marker) = (line.text.clone(), line.marker);
+                let mut rich = RichText::new(&text).monospace();
+                if marker {
+                    rich = rich.strong().color(ui.visuals().warn_fg_color);
+                } else if let Some(color) = self.line_color(settings, &text) {
+                    rich = rich.color(color);
+                }
+                if hits.binary_search(&row).is_ok() {
+                    let background = if current_hit == Some(&row) {
+                        Color32::from_rgb(0x80, 0x60, 0x00)
+                    } else {
+                        Color32::from_rgb(0x40, 0x38, 0x10)
+                    };
+                    rich = rich.background_color(background);
+                }
+                ui.add(egui::Label::new(rich).wrap(false));
+            }
+        });
+    }
+}
@@ -0,0 +1,169 @@
+// BrightScript crash dumps out of the console stream. a crash drops the channel into the micro
+// debugger, which prints the error, a backtrace and the locals before its prompt
+
+use regex::Regex;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub function: String,
+    pub file: String,
+    pub line: u32,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Crash {
+    pub message: String,
+    // the runtime error code, "&h18"
+    pub code: Option<String>,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    // innermost call first
+    pub frames: Vec<Frame>,
+    pub locals: Vec<String>,
+    // everything the debugger printed, for the report
+    pub raw: Vec<String>,
+}
+
+impl Crash {
+    pub fn location(&self) -> String {
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => format!("{}({})", file, line),
+            (Some(file), None) => file.clone(),
+            _ => "unknown location".to_string(),
+        }
+    }
+
+    // plain text for pasting into an issue
+    pub fn report(&self) -> String {
+        let mut report = format!("BrightScript crash: {}\n", self.message);
+        if let Some(code) = &self.code {
+            report += &format!("Runtime error {}\n", code);
+        }
+        report += &format!("At {}\n", self.location());
+        if !self.frames.is_empty() {
+            report += "\nBacktrace:\n";
+            for frame in &self.frames {
+                report += &format!("  {}  {}({})\n", frame.function, frame.file, frame.line);
+            }
+        }
+        if !self.locals.is_empty() {
+            report += "\nLocal variables:\n";
+            for local in &self.locals {
+                report += &format!("  {}\n", local);
+            }
+        }
+        report += "\nConsole output:\n";
+        for line in &self.raw {
+            report += line;
+            report += "\n";
+        }
+        report
+    }
+}
+
+#[derive(PartialEq)]
+enum Section {
+    Header,
+    Backtrace,
+    Locals,
+    Rest,
+}
+
+// fed the console line by line; hands back each crash once the debugger prompt shows
+pub struct CrashParser {
+    current: Option<(Crash, Section)>,
+    start: Regex,
+    error: Regex,
+    function: Regex,
+    file_line: Regex,
+    prompt: Regex,
+}
+
+impl Default for CrashParser {
+    fn default() -> Self {
+        Self {
+            current: None,
+            start: Regex::new(r"(?i)brightscript micro debugger").unwrap(),
+            error: Regex::new(
+                r"^(.*?)\s*\(runtime error (&h[0-9a-fA-F]+)\)\s*in\s*(\S+?)\((\d+)\)",
+            )
+            .unwrap(),
+            function: Regex::new(r"^#\d+\s+(?:Function|Sub)\s+(.+?)\s*$").unwrap(),
+            file_line: Regex::new(r"file/line:\s*(\S+?)\((\d+)\)").unwrap(),
+            prompt: Regex::new(r"(?i)brightscript debugger>").unwrap(),
+        }
+    }
+}
+
+impl CrashParser {
+    pub fn feed(&mut self, line: &str) -> Option<Crash> {
+        if self.start.is_match(line) {
+            // a new dump before the last one's prompt: keep what was gathered
+            let finished = self.finish();
+            self.current = Some((Crash::default(), Section::Header));
+            self.current.as_mut()?.0.raw.push(line.to_string());
+            return finished;
+        }
+        if self.current.is_none() {
+            // some firmware skips the banner and starts with the error itself
+            if !self.error.is_match(line) {
+                return None;
+            }
+            self.current = Some((Crash::default(), Section::Header));
+        }
+        if self.prompt.is_match(line) {
+            return self.finish();
+        }
+
+        let (crash, section) = self.current.as_mut()?;
+        crash.raw.push(line.to_string());
+        let trimmed = line.trim();
+        if let Some(cap) = self.error.captures(trimmed) {
+            crash.message = cap[1].to_string();
+            crash.code = Some(cap[2].to_string());
+            crash.file = Some(cap[3].to_string());
+            crash.line = cap[4].parse().ok();
+            return None;
+        }
+        match trimmed {
+            "Backtrace:" => *section = Section::Backtrace,
+            "Local Variables:" => *section = Section::Locals,
+            "Threads:" => *section = Section::Rest,
+            _ => match section {
+                Section::Backtrace => {
+                    if let Some(cap) = self.function.captures(trimmed) {
+                        crash.frames.push(Frame {
+                            function: cap[1].to_string(),
+                            file: String::new(),
+                            line: 0,
+                        });
+                    } else if let Some(cap) = self.file_line.captures(trimmed) {
+                        if let Some(frame) = crash.frames.last_mut() {
+                            frame.file = cap[1].to_string();
+                            frame.line = cap[2].parse().unwrap_or_default();
+                        }
+                    }
+                }
+                Section::Locals if !trimmed.is_empty() => crash.locals.push(trimmed.to_string()),
+                _ => {}
+            },
+        }
+        None
+    }
+
+    // the crash being read, if any, as far as it got; for when the console closes mid-dump
+    pub fn finish(&mut self) -> Option<Crash> {
+        let (mut crash, _) = self.current.take()?;
+        if crash.message.is_empty() {
+            crash.message = "channel stopped in the debugger".to_string();
+        }
+        // older firmware gives no location with the error, only in the backtrace
+        if crash.file.is_none() {
+            if let Some(frame) = crash.frames.first() {
+                crash.file = Some(frame.file.clone());
+                crash.line = Some(frame.line);
+            }
+        }
+        Some(crash)
+    }
+}
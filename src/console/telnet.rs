@@ -0,0 +1,68 @@
+// the telnet side of the console: some firmware opens the session with option negotiation,
+// which would otherwise show up in the log as stray bytes. the commands are taken out of the
+// stream, and every option the device offers or asks for is turned down, leaving plain text
+
+const IAC: u8 = 255;
+const DONT: u8 = 254;
+const DO: u8 = 253;
+const WONT: u8 = 252;
+const WILL: u8 = 251;
+const SB: u8 = 250;
+const SE: u8 = 240;
+
+#[derive(Default, Clone, Copy, PartialEq)]
+enum State {
+    #[default]
+    Data,
+    // after an IAC
+    Command,
+    // after WILL, WONT, DO or DONT, waiting for the option
+    Option(u8),
+    // inside a subnegotiation, up to IAC SE
+    Sub,
+    SubCommand,
+}
+
+// carries a command split across reads over to the next one
+#[derive(Default)]
+pub struct Telnet {
+    state: State,
+}
+
+impl Telnet {
+    // the text in what was read, and what to answer the device
+    pub fn filter(&mut self, bytes: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let (mut text, mut reply) = (Vec::with_capacity(bytes.len()), Vec::new());
+        for &byte in bytes {
+            self.state = match (self.state, byte) {
+                (State::Data, IAC) => State::Command,
+                (State::Data, byte) => {
+                    text.push(byte);
+                    State::Data
+                }
+                // a doubled IAC is a data byte of 255
+                (State::Command, IAC) => {
+                    text.push(IAC);
+                    State::Data
+                }
+                (State::Command, WILL | WONT | DO | DONT) => State::Option(byte),
+                (State::Command, SB) => State::Sub,
+                (State::Command, _) => State::Data,
+                (State::Option(verb), option) => {
+                    match verb {
+                        WILL => reply.extend([IAC, DONT, option]),
+                        DO => reply.extend([IAC, WONT, option]),
+                        // a WONT or DONT is already what we want
+                        _ => {}
+                    }
+                    State::Data
+                }
+                (State::Sub, IAC) => State::SubCommand,
+                (State::Sub, _) => State::Sub,
+                (State::SubCommand, SE) => State::Data,
+                (State::SubCommand, _) => State::Sub,
+            };
+        }
+        (text, reply)
+    }
+}
@@ -0,0 +1,131 @@
+// what was playing recently, so it can be picked up again with one click. plain ECP only says
+// which channel is playing; the content id that resumes the exact title comes from ECP-2 media
+// events, and without it resuming just reopens the channel
+
+use crate::config::{config_path, write_json};
+use crate::now_playing::NowPlaying;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const MAX_ENTRIES: usize = 20;
+// position updates are written out at most this often; new titles straight away
+const SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Resume {
+    pub app_id: String,
+    pub app_name: String,
+    #[serde(default)]
+    pub content_id: Option<String>,
+    #[serde(default)]
+    pub media_type: Option<String>,
+    // last position seen, in milliseconds
+    #[serde(default)]
+    pub position_ms: Option<u64>,
+    // unix time it was last seen playing
+    pub watched: u64,
+}
+
+impl Resume {
+    pub fn position(&self) -> Option<Duration> {
+        self.position_ms.map(Duration::from_millis)
+    }
+}
+
+// most recently watched first; local to this machine like the schedule state
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContinueWatching {
+    pub entries: Vec<Resume>,
+    #[serde(skip)]
+    unsaved: bool,
+    #[serde(skip)]
+    saved_at: Option<Instant>,
+}
+
+fn list_path() -> Option<PathBuf> {
+    config_path().map(|path| path.with_file_name("continue-watching.json"))
+}
+
+impl ContinueWatching {
+    pub fn load() -> ContinueWatching {
+        list_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&mut self) -> io::Result<()> {
+        let path = list_path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+        write_json(&path, self)?;
+        self.unsaved = false;
+        self.saved_at = Some(Instant::now());
+        Ok(())
+    }
+
+    // notes whatever is playing right now; the home screen and paused players are left alone
+    pub fn record(&mut self, now: &NowPlaying) {
+        let (Some(app), Some(playback)) = (&now.app, &now.playback) else {
+            return;
+        };
+        if app.id.is_empty() || playback.state != "play" {
+            return;
+        }
+        let watched = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since| since.as_secs())
+            .unwrap_or_default();
+        let position_ms = playback.position.map(|p| p.as_millis() as u64);
+
+        // without a content id all that's known is the channel, so one entry per channel
+        let existing = self
+            .entries
+            .iter()
+            .position(|entry| entry.app_id == app.id && entry.content_id.is_none());
+        let new_title = existing != Some(0);
+        let mut entry = match existing {
+            Some(index) => self.entries.remove(index),
+            None => Resume {
+                app_id: app.id.clone(),
+                app_name: app.name.clone(),
+                content_id: None,
+                media_type: None,
+                position_ms: None,
+                watched,
+            },
+        };
+        entry.position_ms = position_ms;
+        entry.watched = watched;
+        self.entries.insert(0, entry);
+        self.entries.truncate(MAX_ENTRIES);
+        self.unsaved = true;
+        if new_title {
+            self.save().ok();
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index < self.entries.len() {
+            self.entries.remove(index);
+            self.save().ok();
+        }
+    }
+
+    // called every frame; writes out position updates now and then
+    // the list from a backup, written out on the next save_if_due
+    pub fn replace(&mut self, entries: Vec<Resume>) {
+        self.entries = entries;
+        self.unsaved = true;
+        self.saved_at = None;
+    }
+
+    pub fn save_if_due(&mut self) {
+        if self.unsaved && self.saved_at.is_none_or(|at| at.elapsed() >= SAVE_INTERVAL) {
+            self.save().ok();
+        }
+    }
+}
@@ -0,0 +1,297 @@
+// a pretend Roku served over ECP on localhost, so the remote can be tried out, screenshotted
+// and tested without a device. it keeps just enough state to look alive: power, the channel in
+// front, a player that counts along while playing and the text typed into a search box
+
+use image::{ImageOutputFormat, Rgb, RgbImage};
+use std::io::{self, Cursor};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+// id, name, version and icon colour
+const APPS: &[(&str, &str, &str, [u8; 3])] = &[
+    ("12", "Netflix", "5.2.105", [229, 9, 20]),
+    ("837", "YouTube", "2.21.10", [255, 0, 0]),
+    ("13", "Prime Video", "14.3.2", [0, 168, 225]),
+    ("291097", "Disney Plus", "1.38.2", [17, 60, 207]),
+    ("2285", "Hulu", "5.40.1", [28, 231, 131]),
+    ("151908", "The Roku Channel", "4.6.14", [102, 45, 145]),
+    ("13535", "Plex - Free Movies & TV", "7.4.0", [229, 160, 13]),
+];
+
+// every channel plays the same hour-long programme
+const PROGRAMME: Duration = Duration::from_secs(60 * 60);
+
+static DEMO: OnceLock<String> = OnceLock::new();
+
+#[derive(PartialEq)]
+enum Player {
+    Stopped,
+    Playing { since: Instant, from: Duration },
+    Paused(Duration),
+}
+
+impl Player {
+    fn position(&self) -> Duration {
+        match self {
+            Player::Stopped => Duration::ZERO,
+            Player::Playing { since, from } => (*from + since.elapsed()).min(PROGRAMME),
+            Player::Paused(at) => *at,
+        }
+    }
+}
+
+struct Demo {
+    powered: bool,
+    // index into APPS; None on the home screen
+    app: Option<usize>,
+    player: Player,
+    typed: String,
+}
+
+// the demo device's "ip:port", starting it the first time
+pub fn start() -> io::Result<String> {
+    if let Some(addr) = DEMO.get() {
+        return Ok(addr.clone());
+    }
+    let server = Server::http("127.0.0.1:0").map_err(io::Error::other)?;
+    let addr = server
+        .server_addr()
+        .to_ip()
+        .ok_or_else(|| io::Error::other("demo device has no address"))?
+        .to_string();
+    thread::spawn(move || {
+        let mut demo = Demo {
+            powered: true,
+            app: None,
+            player: Player::Stopped,
+            typed: String::new(),
+        };
+        for request in server.incoming_requests() {
+            let (status, content_type, body) = demo.handle(&request);
+            let response = Response::from_data(body)
+                .with_status_code(status)
+                .with_header(Header::from_bytes("Content-Type", content_type).unwrap());
+            let _ = request.respond(response);
+        }
+    });
+    Ok(DEMO.get_or_init(|| addr).clone())
+}
+
+pub fn is_demo(device: &str) -> bool {
+    DEMO.get().is_some_and(|addr| addr == device)
+}
+
+fn xml(body: String) -> (u16, &'static str, Vec<u8>) {
+    let doc = format!("<?xml version=\"1.0\" encoding=\"UTF-8\" ?>\n{}\n", body);
+    (200, "text/xml; charset=\"utf-8\"", doc.into_bytes())
+}
+
+fn empty(status: u16) -> (u16, &'static str, Vec<u8>) {
+    (status, "text/plain", Vec::new())
+}
+
+fn app_xml(i: usize) -> String {
+    let (id, name, version, _) = APPS[i];
+    format!(
+        "<app id=\"{}\" type=\"appl\" version=\"{}\">{}</app>",
+        id,
+        version,
+        html_escape::encode_text(name)
+    )
+}
+
+impl Demo {
+    fn handle(&mut self, request: &Request) -> (u16, &'static str, Vec<u8>) {
+        let url = request.url().trim_start_matches('/');
+        let path = url.split('?').next().unwrap_or_default();
+        match (request.method(), path) {
+            (Method::Get, "query/device-info") => xml(self.device_info()),
+            (Method::Get, "query/apps") => {
+                let apps: String = (0..APPS.len()).map(app_xml).collect();
+                xml(format!("<apps>{}</apps>", apps))
+            }
+            (Method::Get, "query/active-app") => xml(match self.app {
+                Some(i) => format!("<active-app>{}</active-app>", app_xml(i)),
+                None => "<active-app><app>Roku</app></active-app>".to_string(),
+            }),
+            (Method::Get, "query/media-player") => xml(self.media_player()),
+            (Method::Get, "query/textedit-state") => xml(format!(
+                "<textedit-state><textedit id=\"search\" text=\"{}\"/></textedit-state>",
+                html_escape::encode_double_quoted_attribute(&self.typed)
+            )),
+            (Method::Get, path) if path.starts_with("query/icon/") => {
+                match APPS
+                    .iter()
+                    .find(|app| app.0 == &path["query/icon/".len()..])
+                {
+                    Some(app) => (200, "image/png", icon(app.3)),
+                    None => empty(404),
+                }
+            }
+            (Method::Post, path) if path.starts_with("keypress/") => {
+                self.press(&path["keypress/".len()..]);
+                empty(200)
+            }
+            // held keys do what a press would, once
+            (Method::Post, path) if path.starts_with("keydown/") => {
+                self.press(&path["keydown/".len()..]);
+                empty(200)
+            }
+            (Method::Post, path) if path.starts_with("keyup/") => empty(200),
+            (Method::Post, path) if path.starts_with("launch/") => {
+                match APPS
+                    .iter()
+                    .position(|app| app.0 == &path["launch/".len()..])
+                {
+                    Some(i) => {
+                        self.launch(i);
+                        empty(200)
+                    }
+                    None => empty(404),
+                }
+            }
+            // every channel is installed already
+            (Method::Post, path) if path.starts_with("install/") => empty(200),
+            (Method::Post, "search/browse") => empty(200),
+            _ => empty(404),
+        }
+    }
+
+    fn device_info(&self) -> String {
+        let power = if self.powered {
+            "PowerOn"
+        } else {
+            "DisplayOff"
+        };
+        format!(
+            "<device-info>\
+             <udn>00000000-0000-0000-0000-00000000demo</udn>\
+             <serial-number>DEMO00000001</serial-number>\
+             <network-type>wifi</network-type>\
+             <wifi-mac>02:00:00:00:de:30</wifi-mac>\
+             <vendor-name>Roku</vendor-name>\
+             <model-name>Demo Roku</model-name>\
+             <model-number>0000X</model-number>\
+             <friendly-device-name>Demo Roku</friendly-device-name>\
+             <user-device-name>Demo Roku</user-device-name>\
+             <software-version>12.5.0</software-version>\
+             <software-build>4178</software-build>\
+             <is-tv>false</is-tv>\
+             <supports-find-remote>true</supports-find-remote>\
+             <developer-enabled>false</developer-enabled>\
+             <power-mode>{}</power-mode>\
+             </device-info>",
+            power
+        )
+    }
+
+    fn media_player(&self) -> String {
+        let state = match (&self.player, self.app) {
+            (_, None) | (Player::Stopped, _) => {
+                return "<player error=\"false\" state=\"close\"/>".to_string()
+            }
+            (Player::Playing { .. }, _) => "play",
+            (Player::Paused(_), _) => "pause",
+        };
+        format!(
+            "<player error=\"false\" state=\"{}\">\
+             <buffering current=\"1000\" max=\"1000\" target=\"0\"/>\
+             <position>{} ms</position><duration>{} ms</duration>\
+             </player>",
+            state,
+            self.player.position().as_millis(),
+            PROGRAMME.as_millis()
+        )
+    }
+
+    fn launch(&mut self, i: usize) {
+        self.powered = true;
+        self.app = Some(i);
+        self.typed.clear();
+        self.player = Player::Playing {
+            since: Instant::now(),
+            from: Duration::ZERO,
+        };
+    }
+
+    fn press(&mut self, key: &str) {
+        if let Some(c) = key.strip_prefix("Lit_") {
+            // percent-decoded; a plus is a plus here, not a space
+            let query = format!("c={}", c.replace('+', "%2B"));
+            let decoded = url::form_urlencoded::parse(query.as_bytes())
+                .next()
+                .map(|(_, value)| value.into_owned())
+                .unwrap_or_default();
+            self.typed += &decoded;
+            return;
+        }
+        let at = self.player.position();
+        match key.to_ascii_lowercase().as_str() {
+            "poweroff" => self.powered = false,
+            "poweron" => self.powered = true,
+            "power" => self.powered = !self.powered,
+            // a press wakes the display the way it does on a real device
+            _ if !self.powered => self.powered = true,
+            "home" => {
+                self.app = None;
+                self.player = Player::Stopped;
+                self.typed.clear();
+            }
+            "back" if self.player != Player::Stopped => self.player = Player::Stopped,
+            "back" => self.app = None,
+            "play" => {
+                self.player = match self.player {
+                    Player::Playing { .. } => Player::Paused(at),
+                    _ if self.app.is_some() => Player::Playing {
+                        since: Instant::now(),
+                        from: at,
+                    },
+                    _ => Player::Stopped,
+                }
+            }
+            "select" if self.app.is_some() && self.player == Player::Stopped => {
+                self.player = Player::Playing {
+                    since: Instant::now(),
+                    from: Duration::ZERO,
+                };
+            }
+            "fwd" | "rev" => {
+                let skip = Duration::from_secs(30);
+                let to = if key.eq_ignore_ascii_case("fwd") {
+                    (at + skip).min(PROGRAMME)
+                } else {
+                    at.saturating_sub(skip)
+                };
+                self.player = match self.player {
+                    Player::Playing { .. } => Player::Playing {
+                        since: Instant::now(),
+                        from: to,
+                    },
+                    Player::Paused(_) => Player::Paused(to),
+                    Player::Stopped => Player::Stopped,
+                };
+            }
+            "backspace" => {
+                self.typed.pop();
+            }
+            _ => {}
+        }
+    }
+}
+
+// a plain square in the channel's colour with a lighter band, close enough to a channel tile
+fn icon(color: [u8; 3]) -> Vec<u8> {
+    let light = color.map(|c| c / 2 + 128);
+    let image = RgbImage::from_fn(96, 72, |_, y| {
+        if (48..60).contains(&y) {
+            Rgb(light)
+        } else {
+            Rgb(color)
+        }
+    });
+    let mut png = Cursor::new(Vec::new());
+    let _ = image.write_to(&mut png, ImageOutputFormat::Png);
+    png.into_inner()
+}
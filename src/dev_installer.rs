@@ -0,0 +1,370 @@
+// the developer installer a Roku in developer mode serves on port 80, behind http digest auth
+// as rokudev with the password set when developer mode was turned on. it installs sideloaded
+// channels and screenshots the dev channel: the same forms its web page posts. the http client
+// here is a small one of its own, since the library's only sends bodiless ECP requests
+
+use md5::{Digest, Md5};
+use roku_remote::http::{self, dry_run};
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+
+pub const USER: &str = "rokudev";
+const PORT: u16 = 80;
+const BOUNDARY: &str = "----roku-remote-form-boundary";
+
+struct Response {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+// a form field: text, or a file with its name
+enum Field<'a> {
+    Text(&'a str, &'a str),
+    File(&'a str, &'a str, &'a [u8]),
+}
+
+// installs a channel .zip as the dev channel. without replace the installer turns down a zip
+// identical to the one installed; with it the channel goes in regardless. the installer's
+// message comes back, e.g. "Install Success."
+pub fn install(
+    device: &str,
+    password: &str,
+    name: &str,
+    zip: &[u8],
+    replace: bool,
+) -> Result<String, String> {
+    let submit = if replace { "Replace" } else { "Install" };
+    let form = [
+        Field::Text("mysubmit", submit),
+        Field::File("archive", name, zip),
+    ];
+    let page = send_form(&host_of(device), password, "/plugin_install", &form)?;
+    outcome(&String::from_utf8_lossy(&page))
+}
+
+// takes the dev channel off the device
+pub fn delete(device: &str, password: &str) -> Result<String, String> {
+    let form = [
+        Field::Text("mysubmit", "Delete"),
+        Field::Text("archive", ""),
+    ];
+    let page = send_form(&host_of(device), password, "/plugin_install", &form)?;
+    outcome(&String::from_utf8_lossy(&page))
+}
+
+// the installer answers 200 either way; its message says whether it worked
+fn outcome(page: &str) -> Result<String, String> {
+    let message = installer_message(page).unwrap_or_else(|| "no word from the installer".into());
+    if message.to_lowercase().contains("fail") || message.to_lowercase().contains("error") {
+        Err(message)
+    } else {
+        Ok(message)
+    }
+}
+
+// the dev channel's screen as an image, and its extension: jpg on most devices, png on some
+pub fn screenshot(device: &str, password: &str) -> Result<(Vec<u8>, String), String> {
+    let host = host_of(device);
+    let form = [
+        Field::Text("mysubmit", "Screenshot"),
+        Field::Text("archive", ""),
+    ];
+    let page = send_form(&host, password, "/plugin_inspect", &form)?;
+    let page = String::from_utf8_lossy(&page);
+    // the page shows the capture as <img src="pkgs/dev.jpg?time=...">
+    let Some(start) = page.find("pkgs/dev.") else {
+        return Err(installer_message(&page)
+            .unwrap_or_else(|| "no screenshot: is the dev channel running?".into()));
+    };
+    let src: String = page[start..]
+        .chars()
+        .take_while(|c| !matches!(c, '"' | '\'' | ' ' | '>'))
+        .collect();
+    let ext = src
+        .split('?')
+        .next()
+        .and_then(|file| file.rsplit('.').next())
+        .unwrap_or("jpg")
+        .to_string();
+    let resp = request_authorized(&host, password, "GET", &format!("/{}", src), None, &[])?;
+    if resp.status != 200 {
+        return Err(format!("fetching the screenshot: HTTP {}", resp.status));
+    }
+    Ok((resp.body, ext))
+}
+
+// posts the form, answering the digest challenge, and hands back the page the installer shows.
+// in a dry run nothing is sent and the page is one saying so
+fn send_form(host: &str, password: &str, path: &str, fields: &[Field]) -> Result<Vec<u8>, String> {
+    if dry_run::enabled() {
+        let submit = fields.iter().find_map(|field| match field {
+            Field::Text("mysubmit", value) => Some(*value),
+            _ => None,
+        });
+        dry_run::record(&format!(
+            "POST http://{}{} ({})",
+            host,
+            path,
+            submit.unwrap_or("form")
+        ));
+        return Ok(b"<font color=\"red\">Dry run: nothing sent</font>".to_vec());
+    }
+    let mut body = Vec::new();
+    for field in fields {
+        body.extend(format!("--{}\r\n", BOUNDARY).bytes());
+        match field {
+            Field::Text(name, value) => {
+                body.extend(
+                    format!("Content-Disposition: form-data; name=\"{}\"\r\n\r\n", name).bytes(),
+                );
+                body.extend(value.bytes());
+            }
+            Field::File(name, file, bytes) => {
+                let head = format!(
+                    "Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\n\
+                     Content-Type: application/zip\r\n\r\n",
+                    name, file
+                );
+                body.extend(head.bytes());
+                body.extend_from_slice(bytes);
+            }
+        }
+        body.extend(b"\r\n");
+    }
+    body.extend(format!("--{}--\r\n", BOUNDARY).bytes());
+    let content_type = format!("multipart/form-data; boundary={}", BOUNDARY);
+    let resp = request_authorized(host, password, "POST", path, Some(&content_type), &body)?;
+    if resp.status != 200 {
+        return Err(format!("the installer answered HTTP {}", resp.status));
+    }
+    Ok(resp.body)
+}
+
+// the request with credentials for the installer's challenge. the challenge comes from a bare
+// GET first, so a channel's zip is only sent the once
+fn request_authorized(
+    host: &str,
+    password: &str,
+    method: &str,
+    path: &str,
+    content_type: Option<&str>,
+    body: &[u8],
+) -> Result<Response, String> {
+    let unreachable = |err: io::Error| format!("developer installer on {}: {}", host, err);
+    let resp = request(host, "GET", path, None, None, &[]).map_err(unreachable)?;
+    if resp.status != 401 {
+        if method == "GET" {
+            return Ok(resp);
+        }
+        return request(host, method, path, content_type, None, body).map_err(unreachable);
+    }
+    let challenge = resp
+        .header("WWW-Authenticate")
+        .and_then(Challenge::parse)
+        .ok_or("the installer asked for credentials it doesn't take")?;
+    let auth = challenge.authorization(method, path, password);
+    let resp = request(host, method, path, content_type, Some(&auth), body).map_err(unreachable)?;
+    if resp.status == 401 {
+        return Err("the developer password was turned down".into());
+    }
+    Ok(resp)
+}
+
+fn request(
+    host: &str,
+    method: &str,
+    path: &str,
+    content_type: Option<&str>,
+    authorization: Option<&str>,
+    body: &[u8],
+) -> io::Result<Response> {
+    let addr = (host, PORT)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no address"))?;
+    let mut stream = TcpStream::connect_timeout(&addr, http::timeout())?;
+    // installing a channel takes the device a while
+    stream.set_read_timeout(Some(http::timeout() * 10))?;
+    let mut head = format!(
+        "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Length: {}\r\n",
+        method,
+        path,
+        host,
+        body.len()
+    );
+    if let Some(content_type) = content_type {
+        head += &format!("Content-Type: {}\r\n", content_type);
+    }
+    if let Some(authorization) = authorization {
+        head += &format!("Authorization: {}\r\n", authorization);
+    }
+    head += "\r\n";
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(body)?;
+
+    let mut raw = Vec::new();
+    stream.read_to_end(&mut raw)?;
+    parse_response(&raw)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not an http response"))
+}
+
+fn parse_response(raw: &[u8]) -> Option<Response> {
+    let split = raw.windows(4).position(|window| window == b"\r\n\r\n")?;
+    let head = String::from_utf8_lossy(&raw[..split]);
+    let mut lines = head.lines();
+    let status = lines.next()?.split_whitespace().nth(1)?.parse().ok()?;
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect();
+    let mut resp = Response {
+        status,
+        headers,
+        body: raw[split + 4..].to_vec(),
+    };
+    if resp
+        .header("Transfer-Encoding")
+        .is_some_and(|encoding| encoding.eq_ignore_ascii_case("chunked"))
+    {
+        resp.body = unchunk(&resp.body)?;
+    }
+    Some(resp)
+}
+
+fn unchunk(mut body: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    loop {
+        let line_end = body.windows(2).position(|window| window == b"\r\n")?;
+        let size = String::from_utf8_lossy(&body[..line_end]);
+        let size = usize::from_str_radix(size.split(';').next()?.trim(), 16).ok()?;
+        body = &body[line_end + 2..];
+        if size == 0 {
+            return Some(out);
+        }
+        out.extend_from_slice(body.get(..size)?);
+        body = body.get(size + 2..)?;
+    }
+}
+
+// what the installer's page says happened, e.g. "Install Failure: Compilation Failed". older
+// firmware puts it in red on the page; newer pages carry it in their script's messages
+fn installer_message(page: &str) -> Option<String> {
+    let message = if let Some(start) = page.find("<font color=\"red\">") {
+        let start = start + "<font color=\"red\">".len();
+        let end = page[start..].find('<')? + start;
+        page[start..end].trim().to_string()
+    } else {
+        let messages = &page[page.find("messages")?..];
+        let start = messages.find("\"text\":\"")? + "\"text\":\"".len();
+        let end = messages[start..].find('"')? + start;
+        messages[start..end].trim().to_string()
+    };
+    (!message.is_empty()).then_some(message)
+}
+
+// the installer listens on the device's own address, not ECP's port
+fn host_of(device: &str) -> String {
+    match device.rsplit_once(':') {
+        Some((host, port)) if port.chars().all(|c| c.is_ascii_digit()) => host.to_string(),
+        _ => device.to_string(),
+    }
+}
+
+// an RFC 2617 digest challenge, as the installer sends it
+struct Challenge {
+    realm: String,
+    nonce: String,
+    opaque: Option<String>,
+    qop_auth: bool,
+}
+
+impl Challenge {
+    fn parse(header: &str) -> Option<Challenge> {
+        let params = header.trim().strip_prefix("Digest")?;
+        let mut challenge = Challenge {
+            realm: String::new(),
+            nonce: String::new(),
+            opaque: None,
+            qop_auth: false,
+        };
+        for param in split_params(params) {
+            let Some((key, value)) = param.split_once('=') else {
+                continue;
+            };
+            let value = value.trim().trim_matches('"').to_string();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "realm" => challenge.realm = value,
+                "nonce" => challenge.nonce = value,
+                "opaque" => challenge.opaque = Some(value),
+                "qop" => challenge.qop_auth = value.split(',').any(|qop| qop.trim() == "auth"),
+                _ => {}
+            }
+        }
+        (!challenge.nonce.is_empty()).then_some(challenge)
+    }
+
+    fn authorization(&self, method: &str, uri: &str, password: &str) -> String {
+        let ha1 = md5_hex(&format!("{}:{}:{}", USER, self.realm, password));
+        let ha2 = md5_hex(&format!("{}:{}", method, uri));
+        let mut header = format!(
+            "Digest username=\"{}\", realm=\"{}\", nonce=\"{}\", uri=\"{}\"",
+            USER, self.realm, self.nonce, uri
+        );
+        if self.qop_auth {
+            let cnonce: String = rand::random::<[u8; 8]>()
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect();
+            let response = md5_hex(&format!(
+                "{}:{}:00000001:{}:auth:{}",
+                ha1, self.nonce, cnonce, ha2
+            ));
+            header += &format!(
+                ", qop=auth, nc=00000001, cnonce=\"{}\", response=\"{}\"",
+                cnonce, response
+            );
+        } else {
+            let response = md5_hex(&format!("{}:{}:{}", ha1, self.nonce, ha2));
+            header += &format!(", response=\"{}\"", response);
+        }
+        if let Some(opaque) = &self.opaque {
+            header += &format!(", opaque=\"{}\"", opaque);
+        }
+        header
+    }
+}
+
+// the comma-separated params of a challenge, leaving commas inside quotes alone
+fn split_params(params: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut start, mut quoted) = (0, false);
+    for (i, c) in params.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                parts.push(&params[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&params[start..]);
+    parts
+}
+
+fn md5_hex(text: &str) -> String {
+    Md5::digest(text.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
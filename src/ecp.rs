@@ -0,0 +1,525 @@
+// Roku's External Control Protocol. the types and xml parsing are shared; the requests
+// themselves are blocking everywhere but wasm32, which gets async fetch versions in web
+
+use crate::error::RokuError;
+use crate::key::RokuKey;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod blocking;
+#[cfg(target_arch = "wasm32")]
+pub mod web;
+mod xml;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use blocking::*;
+
+// how discovery searches with SSDP
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SsdpSettings {
+    // the ST of the M-SEARCH; Rokus answer "roku:ecp"
+    pub search_target: String,
+    // seconds a device may wait before answering, so replies don't all land at once
+    pub mx: u32,
+    // multicast hops; above 1 the search crosses routers that forward multicast
+    pub ttl: u32,
+    // also send an ssdp:all search and keep the answers whose SERVER header names Roku,
+    // for devices that stay quiet on the roku:ecp search
+    pub search_all: bool,
+    // when the search finds nothing, try every address on the local /24s instead; for
+    // routers that drop multicast and access points that keep clients apart
+    pub deep_scan: bool,
+}
+
+impl Default for SsdpSettings {
+    fn default() -> Self {
+        Self {
+            search_target: "roku:ecp".to_string(),
+            mx: 3,
+            ttl: 4,
+            search_all: false,
+            deep_scan: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[cfg_attr(feature = "app", derive(utoipa::ToSchema))]
+pub struct AppEntry {
+    pub id: String,
+    pub name: String,
+    // "appl" for channels, "tvin" for inputs, "menu" for the home screen
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub version: String,
+}
+
+impl From<xml::App> for AppEntry {
+    fn from(app: xml::App) -> AppEntry {
+        AppEntry {
+            id: app.id,
+            name: app.name.trim().to_string(),
+            kind: app.kind,
+            version: app.version,
+        }
+    }
+}
+
+// the content types the device's search understands, for the type parameter
+pub const SEARCH_TYPES: &[&str] = &["movie", "tv-show", "person", "channel", "game"];
+
+// a search/browse request. with a keyword the device opens its search results; with an exact
+// title, a provider and launch it goes straight to playing the title in that channel
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchQuery {
+    pub keyword: String,
+    // an exact title, matched instead of the keyword when set
+    pub title: String,
+    // one of SEARCH_TYPES; any type when empty
+    pub kind: String,
+    // channel ids to prefer, in order
+    pub provider_ids: Vec<String>,
+    // start playing the first match in the first listed provider that has it
+    pub launch: bool,
+}
+
+impl SearchQuery {
+    pub fn query_string(&self) -> String {
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        let fields = [
+            ("keyword", &self.keyword),
+            ("title", &self.title),
+            ("type", &self.kind),
+        ];
+        for (name, value) in fields {
+            if !value.trim().is_empty() {
+                query.append_pair(name, value.trim());
+            }
+        }
+        if !self.provider_ids.is_empty() {
+            query.append_pair("provider-id", &self.provider_ids.join(","));
+        }
+        if self.launch {
+            query.append_pair("launch", "true");
+        }
+        query.finish()
+    }
+}
+
+// how a remote key goes out: a single press, or the two ends of holding it down
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAction {
+    Press,
+    Down,
+    Up,
+}
+
+impl KeyAction {
+    // the ECP endpoint, e.g. keydown in /keydown/Fwd
+    pub fn endpoint(self) -> &'static str {
+        match self {
+            KeyAction::Press => "keypress",
+            KeyAction::Down => "keydown",
+            KeyAction::Up => "keyup",
+        }
+    }
+}
+
+// a device discovery found, with the serial number that tells it apart from the same Roku
+// seen earlier at another address once DHCP has moved it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Discovered {
+    pub address: String,
+    pub serial: Option<String>,
+}
+
+// the serial number in an SSDP USN, "uuid:roku:ecp:P0A070000007"
+pub fn parse_usn_serial(usn: &str) -> Option<String> {
+    let serial = usn.trim().rsplit(':').next()?.trim();
+    (!serial.is_empty() && usn.contains("roku:ecp")).then(|| serial.to_string())
+}
+
+// one entry per device: an address seen twice, or a serial answering at two addresses, as
+// a device on both Wi-Fi and Ethernet does, keeps the first
+pub fn dedupe_discovered(found: impl IntoIterator<Item = Discovered>) -> Vec<Discovered> {
+    let mut unique: Vec<Discovered> = Vec::new();
+    for device in found {
+        let seen = unique.iter().any(|other| {
+            other.address == device.address
+                || (device.serial.is_some() && other.serial == device.serial)
+        });
+        if !seen {
+            unique.push(device);
+        }
+    }
+    unique
+}
+
+// devices are "ip:port"; a bare ip gets the standard ECP port
+pub fn device_addr(ip: &str) -> String {
+    if ip.contains(':') {
+        ip.to_string()
+    } else {
+        format!("{}:8060", ip)
+    }
+}
+
+// a device typed in by hand: an ip or hostname, optionally with a port and even pasted as a
+// url, checked and turned into "host:port" with the port defaulting to 8060
+pub fn parse_device_addr(input: &str) -> Result<String, String> {
+    let input = input.trim().trim_end_matches('/');
+    if input.is_empty() {
+        return Err("enter an ip address or hostname".into());
+    }
+    let with_scheme = if input.contains("://") {
+        input.to_string()
+    } else {
+        format!("http://{}", input)
+    };
+    let url = url::Url::parse(&with_scheme).map_err(|err| format!("{}: {}", input, err))?;
+    if url.scheme() != "http" || url.path() != "/" || !url.username().is_empty() {
+        return Err(format!("{} isn't an address", input));
+    }
+    let host = match url.host() {
+        Some(url::Host::Domain(domain)) if hostname(domain) => domain.to_string(),
+        Some(url::Host::Ipv4(ip)) => ip.to_string(),
+        Some(url::Host::Ipv6(ip)) => format!("[{}]", ip),
+        _ => return Err(format!("{} isn't an ip address or hostname", input)),
+    };
+    Ok(format!("{}:{}", host, url.port().unwrap_or(8060)))
+}
+
+// letters, digits and hyphens between dots; url lets much more through as a domain
+fn hostname(domain: &str) -> bool {
+    domain.split('.').all(|label| {
+        !label.is_empty()
+            && !label.starts_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    })
+}
+
+// query/apps, skipping entries without an id
+pub fn parse_apps(text: &str) -> Result<Vec<AppEntry>, RokuError> {
+    Ok(xml::parse::<xml::Apps>(text)?
+        .apps
+        .into_iter()
+        .map(AppEntry::from)
+        .filter(|app| !app.id.is_empty())
+        .collect())
+}
+
+// query/active-app; the home screen comes back as an app without an id
+pub fn parse_active_app(text: &str) -> Option<AppEntry> {
+    xml::parse::<xml::ActiveApp>(text)
+        .ok()?
+        .app
+        .map(AppEntry::from)
+}
+
+// <power-mode> from a query/device-info reply
+pub fn parse_power_mode(text: &str) -> Option<String> {
+    parse_device_info_fields(text)
+        .into_iter()
+        .find(|(name, _)| name == "power-mode")
+        .map(|(_, mode)| mode)
+}
+
+// the state attribute of <player> in a query/media-player reply
+pub fn parse_playback_state(text: &str) -> Option<String> {
+    let state = xml::parse::<xml::Player>(text).ok()?.state;
+    (!state.is_empty()).then_some(state)
+}
+
+// the player as query/media-player reports it. position and duration are missing while
+// nothing is loaded, and duration is missing for live streams too
+#[derive(Debug, Clone, PartialEq)]
+pub struct Playback {
+    pub state: String,
+    pub position: Option<Duration>,
+    pub duration: Option<Duration>,
+    // how full the buffer is, 0 to 1; only some channels report it
+    pub buffering: Option<f32>,
+}
+
+pub fn parse_playback(text: &str) -> Option<Playback> {
+    let player = xml::parse::<xml::Player>(text).ok()?;
+    if player.state.is_empty() {
+        return None;
+    }
+    // "12345 ms"
+    let millis = |value: Option<String>| {
+        let value = value?;
+        let millis = value.trim().trim_end_matches("ms").trim().parse().ok()?;
+        Some(Duration::from_millis(millis))
+    };
+    let buffering = player.buffering.and_then(|buffering| {
+        let (current, max) = (buffering.current?, buffering.max?);
+        (max > 0.0).then(|| (current / max).clamp(0.0, 1.0))
+    });
+    Some(Playback {
+        state: player.state,
+        position: millis(player.position),
+        duration: millis(player.duration),
+        buffering,
+    })
+}
+
+// the simple <field>value</field> elements of a query/device-info reply, in order
+pub fn parse_device_info_fields(text: &str) -> Vec<(String, String)> {
+    xml::leaf_fields(text)
+}
+
+// what query/device-info says about a device, the parts worth showing a person
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DeviceInfo {
+    // the name given in the device's settings, e.g. "Living Room"
+    pub name: String,
+    pub model_name: String,
+    pub model_number: String,
+    pub software_version: String,
+    pub serial_number: String,
+    pub is_tv: bool,
+    pub is_stick: bool,
+    // flashes and beeps the remote; on devices whose remote has a speaker
+    pub supports_find_remote: bool,
+    // the device's sound played through the mobile app's headphones
+    pub supports_private_listening: bool,
+    // the developer installer is up, for sideloading and screenshots
+    pub developer_enabled: bool,
+    // private listening is on: the sound goes to headphones on the remote or the mobile app,
+    // and the TV's speakers are quiet
+    pub headphones_connected: bool,
+    // of the interface the device is on, for waking it; empty when it doesn't say
+    pub mac: String,
+}
+
+impl DeviceInfo {
+    pub fn from_fields(fields: &[(String, String)]) -> DeviceInfo {
+        let field = |name: &str| {
+            fields
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.trim().to_string())
+                .unwrap_or_default()
+        };
+        // older firmware only has the friendly name, and unnamed devices leave both empty
+        let name = [field("user-device-name"), field("friendly-device-name")]
+            .into_iter()
+            .find(|name| !name.is_empty())
+            .unwrap_or_else(|| field("model-name"));
+        let build = field("software-build");
+        let mut software_version = field("software-version");
+        if !build.is_empty() {
+            software_version += &format!(" build {}", build);
+        }
+        // devices list the MAC of each interface they have, wired or not
+        let macs = match field("network-type").as_str() {
+            "ethernet" => ["ethernet-mac", "wifi-mac"],
+            _ => ["wifi-mac", "ethernet-mac"],
+        };
+        let mac = macs
+            .into_iter()
+            .map(field)
+            .find(|mac| !mac.is_empty())
+            .unwrap_or_default();
+        DeviceInfo {
+            name,
+            model_name: field("model-name"),
+            model_number: field("model-number"),
+            software_version,
+            serial_number: field("serial-number"),
+            is_tv: field("is-tv") == "true",
+            is_stick: field("is-stick") == "true",
+            supports_find_remote: field("supports-find-remote") == "true",
+            supports_private_listening: field("supports-private-listening") == "true",
+            headphones_connected: field("headphones-connected") == "true",
+            developer_enabled: field("developer-enabled") == "true",
+            mac,
+        }
+    }
+
+    // whether the key does anything on this device. TVs have volume, power and inputs of their
+    // own; players only pass volume and power on to the TV over HDMI-CEC, which sticks and the
+    // players new enough to find their remote do, but older boxes don't
+    pub fn supports(&self, key: RokuKey) -> bool {
+        let drives_tv = self.is_tv || self.is_stick || self.supports_find_remote;
+        match key {
+            RokuKey::FindRemote => self.supports_find_remote,
+            RokuKey::InputTuner
+            | RokuKey::InputHDMI1
+            | RokuKey::InputHDMI2
+            | RokuKey::InputHDMI3
+            | RokuKey::InputHDMI4
+            | RokuKey::InputAV1 => self.is_tv,
+            RokuKey::VolumeUp
+            | RokuKey::VolumeDown
+            | RokuKey::VolumeMute
+            | RokuKey::Power
+            | RokuKey::PowerOn
+            | RokuKey::PowerOff => drives_tv,
+            _ => true,
+        }
+    }
+}
+
+pub fn parse_device_info(xml: &str) -> DeviceInfo {
+    DeviceInfo::from_fields(&parse_device_info_fields(xml))
+}
+
+// an antenna or cable channel a Roku TV's tuner has found
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TvChannel {
+    // "7.1" for a digital subchannel, "12" for an analog one
+    pub number: String,
+    pub name: String,
+    // "air-digital", "air-analog", "cable-digital" and so on
+    #[serde(rename = "type")]
+    pub kind: String,
+    // taken out of the guide in the TV's settings
+    pub hidden: bool,
+}
+
+impl From<&xml::Channel> for TvChannel {
+    fn from(channel: &xml::Channel) -> TvChannel {
+        TvChannel {
+            number: channel.number.trim().to_string(),
+            name: channel.name.trim().to_string(),
+            kind: channel.kind.trim().to_string(),
+            hidden: channel.user_hidden.trim() == "true",
+        }
+    }
+}
+
+// the channel the tuner is on, with what query/tv-active-channel says about it
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TvActiveChannel {
+    pub channel: TvChannel,
+    // whether the tuner is the input on screen at all
+    pub active: bool,
+    pub program_title: String,
+    // 0 to 100
+    pub signal_quality: Option<u8>,
+}
+
+// query/tv-channels, in the order the TV lists them
+pub fn parse_tv_channels(text: &str) -> Result<Vec<TvChannel>, RokuError> {
+    Ok(xml::parse::<xml::Channels>(text)?
+        .channels
+        .iter()
+        .map(TvChannel::from)
+        .filter(|channel| !channel.number.is_empty())
+        .collect())
+}
+
+// query/tv-active-channel; None when the tuner hasn't been on a channel yet
+pub fn parse_tv_active_channel(text: &str) -> Option<TvActiveChannel> {
+    let channels = xml::parse::<xml::Channels>(text).ok()?;
+    let active = channels.channels.first()?;
+    let channel = TvChannel::from(active);
+    if channel.number.is_empty() {
+        return None;
+    }
+    Some(TvActiveChannel {
+        active: active.active_input.trim() == "true",
+        program_title: active.program_title.trim().to_string(),
+        signal_quality: active.signal_quality.trim().parse().ok(),
+        channel,
+    })
+}
+
+// keypress path for one character of text; the literal function only takes one at a time.
+// everything but letters, digits and -._~ is percent-encoded as UTF-8, since in a path & # ?
+// and / would end the key early and the device reads a raw + as a space
+pub fn literal_key(c: char) -> String {
+    let mut key = "Lit_".to_string();
+    for byte in c.to_string().bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            key.push(byte as char);
+        } else {
+            key += &format!("%{:02X}", byte);
+        }
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{literal_key, parse_apps, parse_tv_channels, RokuError};
+
+    #[test]
+    fn literal_key_leaves_letters_and_digits() {
+        assert_eq!(literal_key('a'), "Lit_a");
+        assert_eq!(literal_key('Z'), "Lit_Z");
+        assert_eq!(literal_key('7'), "Lit_7");
+        assert_eq!(literal_key('-'), "Lit_-");
+        assert_eq!(literal_key('.'), "Lit_.");
+    }
+
+    #[test]
+    fn literal_key_encodes_punctuation() {
+        assert_eq!(literal_key(' '), "Lit_%20");
+        assert_eq!(literal_key('&'), "Lit_%26");
+        assert_eq!(literal_key('#'), "Lit_%23");
+        assert_eq!(literal_key('?'), "Lit_%3F");
+        assert_eq!(literal_key('+'), "Lit_%2B");
+        assert_eq!(literal_key('/'), "Lit_%2F");
+        assert_eq!(literal_key('%'), "Lit_%25");
+        assert_eq!(literal_key('='), "Lit_%3D");
+        assert_eq!(literal_key('"'), "Lit_%22");
+    }
+
+    #[test]
+    fn literal_key_encodes_unicode_as_utf8() {
+        assert_eq!(literal_key('é'), "Lit_%C3%A9");
+        assert_eq!(literal_key('€'), "Lit_%E2%82%AC");
+        assert_eq!(literal_key('日'), "Lit_%E6%97%A5");
+        assert_eq!(literal_key('😀'), "Lit_%F0%9F%98%80");
+    }
+
+    #[test]
+    fn literal_key_round_trips() {
+        for c in "Hello, wörld! 1+1=2 & #tag? 100% / ~ 😀".chars() {
+            let key = literal_key(c);
+            let encoded = key.strip_prefix("Lit_").unwrap();
+            assert!(encoded.bytes().all(|b| b.is_ascii_graphic()), "{}", key);
+            let decoded = url::form_urlencoded::parse(format!("c={}", encoded).as_bytes())
+                .next()
+                .map(|(_, value)| value.into_owned());
+            assert_eq!(decoded.as_deref(), Some(c.to_string().as_str()));
+        }
+    }
+
+    #[test]
+    fn app_lists_parse() {
+        let xml = r#"<apps><app id="12" type="appl" version="5.1.2">Netflix</app><app id="tvinput.hdmi1" type="tvin" version="1.0.0"/><app type="appl"></app></apps>"#;
+        let apps = parse_apps(xml).unwrap();
+        assert_eq!(apps.len(), 2);
+        assert_eq!(
+            (apps[0].id.as_str(), apps[0].name.as_str()),
+            ("12", "Netflix")
+        );
+        assert_eq!(apps[1].id, "tvinput.hdmi1");
+        assert_eq!(parse_apps("<apps/>").unwrap().len(), 0);
+    }
+
+    #[test]
+    fn malformed_replies_are_parse_errors() {
+        for garbage in [
+            "<apps><app id=\"12\">Netflix</apps>",
+            "<apps",
+            "<apps>&bogus;</apps>",
+        ] {
+            assert!(
+                matches!(parse_apps(garbage), Err(RokuError::Parse(_))),
+                "{}",
+                garbage
+            );
+        }
+        assert!(matches!(
+            parse_tv_channels("<tv-channels><channel></tv-channels>"),
+            Err(RokuError::Parse(_))
+        ));
+    }
+}
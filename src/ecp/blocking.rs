@@ -0,0 +1,598 @@
+// the blocking ECP requests, for everything but wasm32
+
+use super::*;
+use crate::error::RokuError;
+use crate::http;
+use crate::key::RokuKey;
+use crate::transport::{Auto, RokuTransport};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::net::{IpAddr, Ipv4Addr, SocketAddrV4, TcpStream, UdpSocket};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use url::Url;
+
+// discover roku devices on the network using SSDP(simple service discovery protocol)
+pub fn discover_roku_devices() -> Vec<String> {
+    discover_with(&SsdpSettings::default())
+}
+
+// the same, searching the way the settings say
+pub fn discover_with(settings: &SsdpSettings) -> Vec<String> {
+    discover_devices(settings)
+        .into_iter()
+        .map(|device| device.address)
+        .collect()
+}
+
+// the devices with their serial numbers. the search goes out of every IPv4 interface, since
+// a multicast sent from an unbound socket only leaves by the default route and misses devices
+// on the other networks of a machine with Wi-Fi, Ethernet and a VPN
+pub fn discover_devices(settings: &SsdpSettings) -> Vec<Discovered> {
+    let interfaces = ipv4_interfaces();
+    let answers: Vec<Vec<Discovered>> = std::thread::scope(|scope| {
+        let searches: Vec<_> = interfaces
+            .iter()
+            .map(|interface| scope.spawn(move || search_from(*interface, settings)))
+            .collect();
+        searches
+            .into_iter()
+            .map(|search| search.join().unwrap_or_default())
+            .collect()
+    });
+    // a device reachable over two interfaces answers on both
+    dedupe_discovered(answers.into_iter().flatten())
+}
+
+// SSDP discovery first; a deep scan only when that finds nothing and the settings allow it
+pub fn discover_or_scan(settings: &SsdpSettings) -> Vec<Discovered> {
+    let found = discover_devices(settings);
+    if found.is_empty() && settings.deep_scan {
+        return scan_subnets();
+    }
+    found
+}
+
+// a Roku on the same network answers well inside this; nothing answering means no one's there
+const SCAN_CONNECT_TIMEOUT: Duration = Duration::from_millis(300);
+// enough connects at once that a /24 takes a few seconds
+const SCAN_THREADS: usize = 64;
+
+// every host on the /24 of each interface that takes a connection on the ECP port and then
+// answers device-info, for networks where multicast never arrives
+pub fn scan_subnets() -> Vec<Discovered> {
+    let mut hosts: Vec<Ipv4Addr> = ipv4_interfaces()
+        .into_iter()
+        .filter(|addr| !addr.is_unspecified())
+        .flat_map(|addr| {
+            let [a, b, c, _] = addr.octets();
+            (1..=254).map(move |d| Ipv4Addr::new(a, b, c, d))
+        })
+        .collect();
+    hosts.sort();
+    hosts.dedup();
+    let next = AtomicUsize::new(0);
+    let found = Mutex::new(Vec::new());
+    std::thread::scope(|scope| {
+        for _ in 0..SCAN_THREADS.min(hosts.len()) {
+            scope.spawn(|| {
+                while let Some(host) = hosts.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let addr = SocketAddrV4::new(*host, 8060);
+                    if TcpStream::connect_timeout(&addr.into(), SCAN_CONNECT_TIMEOUT).is_err() {
+                        continue;
+                    }
+                    // anything can listen on 8060; a Roku also answers device-info
+                    let url = format!("http://{}/query/device-info", addr);
+                    let Ok(resp) = http::get_within(&url, PROBE_TIMEOUT) else {
+                        continue;
+                    };
+                    let fields = parse_device_info_fields(&resp.text());
+                    if resp.is_success() && !fields.is_empty() {
+                        let serial = DeviceInfo::from_fields(&fields).serial_number;
+                        let device = Discovered {
+                            address: addr.to_string(),
+                            serial: (!serial.is_empty()).then_some(serial),
+                        };
+                        found.lock().unwrap().push((*host, device));
+                    }
+                }
+            });
+        }
+    });
+    let mut found = found.into_inner().unwrap();
+    found.sort_by_key(|(host, _)| *host);
+    dedupe_discovered(found.into_iter().map(|(_, device)| device))
+}
+
+// the addresses of the interfaces that are up, loopback aside; when they can't be listed the
+// search goes out the default way
+fn ipv4_interfaces() -> Vec<Ipv4Addr> {
+    let mut addrs: Vec<Ipv4Addr> = if_addrs::get_if_addrs()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|interface| !interface.is_loopback())
+        .filter_map(|interface| match interface.ip() {
+            IpAddr::V4(addr) => Some(addr),
+            IpAddr::V6(_) => None,
+        })
+        .collect();
+    addrs.sort();
+    addrs.dedup();
+    if addrs.is_empty() {
+        addrs.push(Ipv4Addr::UNSPECIFIED);
+    }
+    addrs
+}
+
+// a socket bound to the interface, whose multicasts leave by it
+fn ssdp_socket(interface: Ipv4Addr, ttl: u32) -> std::io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    if !interface.is_unspecified() {
+        socket.set_multicast_if_v4(&interface)?;
+    }
+    socket.set_multicast_loop_v4(true)?;
+    socket.set_multicast_ttl_v4(ttl)?;
+    socket.bind(&SocketAddrV4::new(interface, 0).into())?;
+    Ok(socket.into())
+}
+
+// one interface's M-SEARCH and the devices that answered it
+fn search_from(interface: Ipv4Addr, settings: &SsdpSettings) -> Vec<Discovered> {
+    const SSDP_ADDR: &str = "239.255.255.250";
+    const SSDP_PORT: u16 = 1900;
+    const TIMEOUT_SECS: u64 = 2;
+
+    let dest = SocketAddrV4::new(Ipv4Addr::new(239, 255, 255, 250), SSDP_PORT);
+    let target = match settings.search_target.trim() {
+        "" => "roku:ecp",
+        target => target,
+    };
+    let mut targets = vec![target];
+    if settings.search_all {
+        targets.push("ssdp:all");
+    }
+    let msgs: Vec<String> = targets
+        .iter()
+        .map(|st| {
+            format!(
+                "M-SEARCH * HTTP/1.1\r\n\
+                 HOST: {SSDP_ADDR}:{SSDP_PORT}\r\n\
+                 MAN: \"ssdp:discover\"\r\n\
+                 ST: {st}\r\n\
+                 MX: {}\r\n\r\n",
+                settings.mx
+            )
+        })
+        .collect();
+
+    // an interface that went away or can't multicast just finds nothing
+    let Ok(sock) = ssdp_socket(interface, settings.ttl) else {
+        return Vec::new();
+    };
+    sock.set_read_timeout(Some(Duration::from_secs(TIMEOUT_SECS)))
+        .ok();
+    for msg in &msgs {
+        sock.send_to(msg.as_bytes(), dest).ok();
+    }
+
+    let mut found = Vec::new();
+    let mut buf = [0u8; 2048];
+    while let Ok((amt, _)) = sock.recv_from(&mut buf) {
+        let data = String::from_utf8_lossy(&buf[..amt]);
+        // everything answers ssdp:all: routers, printers, other TVs
+        if settings.search_all {
+            let answers_target =
+                ssdp_header(&data, "st").is_some_and(|st| st.eq_ignore_ascii_case(target));
+            let roku = ssdp_header(&data, "server")
+                .is_some_and(|server| server.to_ascii_lowercase().contains("roku"));
+            if !answers_target && !roku {
+                continue;
+            }
+        }
+        if let Some(location) = ssdp_header(&data, "location") {
+            if let Ok(url) = Url::parse(location) {
+                if let (Some(host), Some(port)) = (url.host_str(), url.port()) {
+                    found.push(Discovered {
+                        address: format!("{}:{}", host, port),
+                        serial: ssdp_header(&data, "usn").and_then(parse_usn_serial),
+                    });
+                }
+            }
+        }
+    }
+
+    dedupe_discovered(found)
+}
+
+// a header of an SSDP answer by its lowercase name
+fn ssdp_header<'a>(data: &'a str, name: &str) -> Option<&'a str> {
+    data.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then_some(value.trim())
+    })
+}
+// the requests to one device, over a transport of the caller's choosing. the free functions
+// further down are the same requests over Auto, plain http with the ECP-2 fallback
+
+/// The ECP requests over a transport of your own, e.g. a `transport::Mock` that records
+/// what would have been sent: `ecp::via(&mock).send_command(ip, "Home")`.
+pub fn via<T: RokuTransport + ?Sized>(transport: &T) -> Ecp<'_, T> {
+    Ecp { transport }
+}
+
+/// The ECP requests made through one transport; see `via`.
+pub struct Ecp<'a, T: ?Sized> {
+    transport: &'a T,
+}
+
+impl<T: ?Sized> Clone for Ecp<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized> Copy for Ecp<'_, T> {}
+
+impl<T: RokuTransport + ?Sized> Ecp<'_, T> {
+    // query available apps to create a list and launch apps directly
+    pub fn get_apps(self, ip: &str) -> Result<Vec<AppEntry>, RokuError> {
+        let resp = self.transport.get(ip, "query/apps")?;
+        if !resp.is_success() {
+            return Err(RokuError::Status(resp.status));
+        }
+        parse_apps(&resp.text())
+    }
+    // which channel is in the foreground; the home screen comes back as an app without an id
+    pub fn get_active_app(self, ip: &str) -> Option<AppEntry> {
+        parse_active_app(&self.transport.get(ip, "query/active-app").ok()?.text())
+    }
+
+    // the channel's icon and its content type, e.g. "image/png"
+    pub fn get_app_icon(self, ip: &str, app_id: &str) -> Option<(Vec<u8>, String)> {
+        let resp = self
+            .transport
+            .get(ip, &format!("query/icon/{}", app_id))
+            .ok()?;
+        if !resp.is_success() {
+            return None;
+        }
+        let content_type = resp.content_type.unwrap_or_else(|| "image/png".into());
+        Some((resp.body, content_type))
+    }
+
+    // power-mode from device-info, e.g. "PowerOn", "DisplayOff" or "Headless"
+    pub fn get_power_mode(self, ip: &str) -> Option<String> {
+        parse_power_mode(&self.transport.get(ip, "query/device-info").ok()?.text())
+    }
+
+    // whether private listening is on; None when the device doesn't answer or doesn't have it
+    pub fn get_private_listening(self, ip: &str) -> Option<bool> {
+        let info = self.get_device_info(ip).ok()?;
+        info.supports_private_listening
+            .then_some(info.headphones_connected)
+    }
+
+    // the media player's state attribute: "play", "pause", "buffer", "stop", "close", ...
+    pub fn get_playback_state(self, ip: &str) -> Option<String> {
+        parse_playback_state(&self.transport.get(ip, "query/media-player").ok()?.text())
+    }
+
+    // the state along with the position in whatever is playing
+    pub fn get_playback(self, ip: &str) -> Option<Playback> {
+        parse_playback(&self.transport.get(ip, "query/media-player").ok()?.text())
+    }
+
+    // every field of device-info in document order, e.g. ("power-mode", "PowerOn")
+    pub fn get_device_info_fields(self, ip: &str) -> Result<Vec<(String, String)>, RokuError> {
+        let resp = self.transport.get(ip, "query/device-info")?;
+        if !resp.is_success() {
+            return Err(RokuError::Status(resp.status));
+        }
+        let fields = parse_device_info_fields(&resp.text());
+        if fields.is_empty() {
+            return Err(RokuError::Parse("no fields in device-info".into()));
+        }
+        Ok(fields)
+    }
+
+    pub fn get_device_info(self, ip: &str) -> Result<DeviceInfo, RokuError> {
+        self.get_device_info_fields(ip)
+            .map(|fields| DeviceInfo::from_fields(&fields))
+    }
+
+    // the antenna and cable channels of a Roku TV; other devices turn the request down
+    pub fn get_tv_channels(self, ip: &str) -> Result<Vec<TvChannel>, RokuError> {
+        let resp = self.transport.get(ip, "query/tv-channels")?;
+        if !resp.is_success() {
+            return Err(RokuError::Status(resp.status));
+        }
+        parse_tv_channels(&resp.text())
+    }
+
+    pub fn get_tv_active_channel(self, ip: &str) -> Option<TvActiveChannel> {
+        let resp = self.transport.get(ip, "query/tv-active-channel").ok()?;
+        if !resp.is_success() {
+            return None;
+        }
+        parse_tv_active_channel(&resp.text())
+    }
+
+    // the dev channel's screen as a node tree, which moves with the focus; needs developer mode
+    pub fn get_app_ui(self, ip: &str) -> Option<String> {
+        let resp = self.transport.get(ip, "query/app-ui").ok()?;
+        resp.is_success().then(|| resp.text())
+    }
+
+    // a snapshot of what the channel is showing, to tell whether keypresses had any effect
+    // textedit-state exists on newer firmware, app-ui needs developer mode; None if neither answers
+    pub fn get_ui_snapshot(self, ip: &str) -> Option<String> {
+        ["query/textedit-state", "query/app-ui"]
+            .iter()
+            .find_map(|path| {
+                let resp = self.transport.get(ip, path).ok()?;
+                resp.is_success().then(|| resp.text())
+            })
+    }
+
+    // every command is a bodyless POST that the device answers with an empty 200
+    fn post_command(self, ip: &str, path: &str) -> Result<(), RokuError> {
+        let resp = self.transport.post(ip, path)?;
+        if resp.is_success() {
+            Ok(())
+        } else {
+            Err(RokuError::Status(resp.status))
+        }
+    }
+
+    // form commands and send over the network using http
+    pub fn send_command(self, ip: &str, command: &str) -> Result<(), RokuError> {
+        self.send_key_action(ip, command, KeyAction::Press)
+    }
+    // send_command for a key that can't be misspelt
+    pub fn press(self, ip: &str, key: RokuKey) -> Result<(), RokuError> {
+        self.send_command(ip, &key.to_string())
+    }
+    // a keydown starts holding a key, fast-forwarding or changing the volume until its keyup
+    pub fn send_key_action(self, ip: &str, key: &str, action: KeyAction) -> Result<(), RokuError> {
+        self.post_command(ip, &format!("{}/{}", action.endpoint(), key))
+    }
+    // launch specific apps without having to manually navigate to them
+    pub fn launch_app(self, ip: &str, app_id: &str) -> Result<(), RokuError> {
+        self.post_command(ip, &format!("launch/{}", app_id))
+    }
+    // straight into one title. content ids come from the channel, e.g. out of an ECP-2 media event
+    pub fn launch_content(
+        self,
+        ip: &str,
+        app_id: &str,
+        content_id: &str,
+        media_type: Option<&str>,
+    ) -> Result<(), RokuError> {
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        query.append_pair("contentId", content_id);
+        if let Some(media_type) = media_type {
+            query.append_pair("mediaType", media_type);
+        }
+        self.post_command(ip, &format!("launch/{}?{}", app_id, query.finish()))
+    }
+    // a channel's own deep-link parameters, e.g. the Media Player's t and u for a file to play
+    pub fn launch_with_params(
+        self,
+        ip: &str,
+        app_id: &str,
+        params: &[(&str, &str)],
+    ) -> Result<(), RokuError> {
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        query.extend_pairs(params);
+        self.post_command(ip, &format!("launch/{}?{}", app_id, query.finish()))
+    }
+    // the device's own search, showing its results on screen
+    pub fn search_browse(self, ip: &str, query: &SearchQuery) -> Result<(), RokuError> {
+        self.post_command(ip, &format!("search/browse?{}", query.query_string()))
+    }
+    // the device's own search, handed to one channel: with launch it starts playing the title there
+    pub fn search_and_launch(
+        self,
+        ip: &str,
+        title: &str,
+        content_type: &str,
+        provider_id: &str,
+    ) -> Result<(), RokuError> {
+        let query = SearchQuery {
+            title: title.to_string(),
+            kind: content_type.to_string(),
+            provider_ids: vec![provider_id.to_string()],
+            launch: true,
+            ..Default::default()
+        };
+        self.search_browse(ip, &query)
+    }
+    // switches a Roku TV to its tuner on a channel, e.g. "7.1"
+    pub fn tune_tv_channel(self, ip: &str, number: &str) -> Result<(), RokuError> {
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        query.append_pair("ch", number.trim());
+        self.post_command(ip, &format!("launch/tvinput.dtv?{}", query.finish()))
+    }
+    // the store page is as far as ECP goes; installing still takes a Select on the device
+    pub fn open_store_page(self, ip: &str, app_id: &str) -> Result<(), RokuError> {
+        self.post_command(ip, &format!("install/{}", app_id))
+    }
+
+    // launches an app and waits until it is actually the active app; false if it never came
+    // up, an error if the device couldn't be reached or turned the launch down
+    pub fn launch_and_wait(
+        self,
+        ip: &str,
+        app_id: &str,
+        timeout: Duration,
+    ) -> Result<bool, RokuError> {
+        self.launch_app(ip, app_id)?;
+        let deadline = std::time::Instant::now() + timeout;
+        loop {
+            if self.get_active_app(ip).is_some_and(|app| app.id == app_id) {
+                return Ok(true);
+            }
+            if std::time::Instant::now() >= deadline {
+                return Ok(false);
+            }
+            std::thread::sleep(LAUNCH_POLL);
+        }
+    }
+
+    // send strings to roku device
+    // the literal function only sends one character at a time
+    // so for loop
+    // stops at the first character the device didn't take
+    pub fn send_key(self, ip: &str, key: &str) -> Result<(), RokuError> {
+        self.send_text(ip, key, http::typing_delay(), |_| true)
+    }
+    // send_key with a wait between characters, since devices drop ones that come in back to
+    // back. after each one progress hears how many have gone out; false from it stops there
+    pub fn send_text(
+        self,
+        ip: &str,
+        text: &str,
+        delay: Duration,
+        mut progress: impl FnMut(usize) -> bool,
+    ) -> Result<(), RokuError> {
+        for (i, c) in text.chars().enumerate() {
+            if i > 0 {
+                std::thread::sleep(delay);
+            }
+            self.send_command(ip, &literal_key(c))?;
+            if !progress(i + 1) {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+// how often launch_and_wait checks which channel is in front
+const LAUNCH_POLL: Duration = Duration::from_millis(500);
+
+// how long a probe waits for device-info before calling the device unreachable
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+// how long device-info takes to come back; None if the device doesn't answer
+pub fn probe_latency(ip: &str) -> Option<Duration> {
+    let started = std::time::Instant::now();
+    let resp = http::get_within(&format!("http://{}/query/device-info", ip), PROBE_TIMEOUT).ok()?;
+    resp.is_success().then(|| started.elapsed())
+}
+
+// the devices that answer a probe, all probed at once; for trying saved addresses when
+// discovery comes back empty, since multicast gets lost more often than devices move
+pub fn reachable(devices: &[String]) -> Vec<String> {
+    std::thread::scope(|scope| {
+        let probes: Vec<_> = devices
+            .iter()
+            .map(|device| scope.spawn(move || probe_latency(device).map(|_| device.clone())))
+            .collect();
+        probes
+            .into_iter()
+            .filter_map(|probe| probe.join().ok().flatten())
+            .collect()
+    })
+}
+
+// the requests of Ecp over Auto, which is what the remote itself uses
+pub fn get_apps(ip: &str) -> Result<Vec<AppEntry>, RokuError> {
+    via(&Auto).get_apps(ip)
+}
+pub fn get_active_app(ip: &str) -> Option<AppEntry> {
+    via(&Auto).get_active_app(ip)
+}
+pub fn get_app_icon(ip: &str, app_id: &str) -> Option<(Vec<u8>, String)> {
+    via(&Auto).get_app_icon(ip, app_id)
+}
+pub fn get_power_mode(ip: &str) -> Option<String> {
+    via(&Auto).get_power_mode(ip)
+}
+pub fn get_private_listening(ip: &str) -> Option<bool> {
+    via(&Auto).get_private_listening(ip)
+}
+pub fn get_playback_state(ip: &str) -> Option<String> {
+    via(&Auto).get_playback_state(ip)
+}
+pub fn get_playback(ip: &str) -> Option<Playback> {
+    via(&Auto).get_playback(ip)
+}
+pub fn get_device_info_fields(ip: &str) -> Result<Vec<(String, String)>, RokuError> {
+    via(&Auto).get_device_info_fields(ip)
+}
+pub fn get_device_info(ip: &str) -> Result<DeviceInfo, RokuError> {
+    via(&Auto).get_device_info(ip)
+}
+pub fn get_tv_channels(ip: &str) -> Result<Vec<TvChannel>, RokuError> {
+    via(&Auto).get_tv_channels(ip)
+}
+pub fn get_tv_active_channel(ip: &str) -> Option<TvActiveChannel> {
+    via(&Auto).get_tv_active_channel(ip)
+}
+pub fn get_app_ui(ip: &str) -> Option<String> {
+    via(&Auto).get_app_ui(ip)
+}
+pub fn get_ui_snapshot(ip: &str) -> Option<String> {
+    via(&Auto).get_ui_snapshot(ip)
+}
+pub fn send_command(ip: &str, command: &str) -> Result<(), RokuError> {
+    via(&Auto).send_command(ip, command)
+}
+pub fn press(ip: &str, key: RokuKey) -> Result<(), RokuError> {
+    via(&Auto).press(ip, key)
+}
+pub fn send_key_action(ip: &str, key: &str, action: KeyAction) -> Result<(), RokuError> {
+    via(&Auto).send_key_action(ip, key, action)
+}
+pub fn launch_app(ip: &str, app_id: &str) -> Result<(), RokuError> {
+    via(&Auto).launch_app(ip, app_id)
+}
+pub fn launch_content(
+    ip: &str,
+    app_id: &str,
+    content_id: &str,
+    media_type: Option<&str>,
+) -> Result<(), RokuError> {
+    via(&Auto).launch_content(ip, app_id, content_id, media_type)
+}
+pub fn launch_with_params(
+    ip: &str,
+    app_id: &str,
+    params: &[(&str, &str)],
+) -> Result<(), RokuError> {
+    via(&Auto).launch_with_params(ip, app_id, params)
+}
+pub fn search_browse(ip: &str, query: &SearchQuery) -> Result<(), RokuError> {
+    via(&Auto).search_browse(ip, query)
+}
+pub fn search_and_launch(
+    ip: &str,
+    title: &str,
+    content_type: &str,
+    provider_id: &str,
+) -> Result<(), RokuError> {
+    via(&Auto).search_and_launch(ip, title, content_type, provider_id)
+}
+pub fn tune_tv_channel(ip: &str, number: &str) -> Result<(), RokuError> {
+    via(&Auto).tune_tv_channel(ip, number)
+}
+pub fn open_store_page(ip: &str, app_id: &str) -> Result<(), RokuError> {
+    via(&Auto).open_store_page(ip, app_id)
+}
+pub fn launch_and_wait(ip: &str, app_id: &str, timeout: Duration) -> Result<bool, RokuError> {
+    via(&Auto).launch_and_wait(ip, app_id, timeout)
+}
+pub fn send_key(ip: &str, key: &str) -> Result<(), RokuError> {
+    via(&Auto).send_key(ip, key)
+}
+pub fn send_text(
+    ip: &str,
+    text: &str,
+    delay: Duration,
+    progress: impl FnMut(usize) -> bool,
+) -> Result<(), RokuError> {
+    via(&Auto).send_text(ip, text, delay, progress)
+}
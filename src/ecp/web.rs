@@ -0,0 +1,102 @@
+// ECP from a browser or extension, over fetch. Rokus send no CORS headers, so commands go out
+// as no-cors requests that can't see the reply; queries only work where the page is allowed
+// to read the device, e.g. an extension with host permissions. there is no SSDP in a browser,
+// so the device address has to come from the user or from the daemon's /devices
+
+use super::*;
+use crate::http::fetch;
+
+pub async fn get_apps(ip: &str) -> Vec<AppEntry> {
+    let url = format!("http://{}/query/apps", ip);
+    fetch::get(&url)
+        .await
+        .ok()
+        .and_then(|resp| parse_apps(&resp.text()).ok())
+        .unwrap_or_default()
+}
+
+pub async fn get_active_app(ip: &str) -> Option<AppEntry> {
+    let url = format!("http://{}/query/active-app", ip);
+    parse_active_app(&fetch::get(&url).await.ok()?.text())
+}
+
+// the icon bytes and content type, for showing channels in a launcher
+pub async fn get_app_icon(ip: &str, app_id: &str) -> Option<(Vec<u8>, String)> {
+    let url = format!("http://{}/query/icon/{}", ip, app_id);
+    let resp = fetch::get(&url).await.ok()?;
+    if !resp.is_success() {
+        return None;
+    }
+    let content_type = resp.content_type.unwrap_or_else(|| "image/png".into());
+    Some((resp.body, content_type))
+}
+
+pub async fn get_power_mode(ip: &str) -> Option<String> {
+    let url = format!("http://{}/query/device-info", ip);
+    parse_power_mode(&fetch::get(&url).await.ok()?.text())
+}
+
+pub async fn get_playback_state(ip: &str) -> Option<String> {
+    let url = format!("http://{}/query/media-player", ip);
+    parse_playback_state(&fetch::get(&url).await.ok()?.text())
+}
+
+pub async fn get_playback(ip: &str) -> Option<Playback> {
+    let url = format!("http://{}/query/media-player", ip);
+    parse_playback(&fetch::get(&url).await.ok()?.text())
+}
+
+pub async fn get_device_info_fields(ip: &str) -> Option<Vec<(String, String)>> {
+    let url = format!("http://{}/query/device-info", ip);
+    Some(parse_device_info_fields(
+        &fetch::get(&url).await.ok()?.text(),
+    ))
+}
+
+pub async fn get_device_info(ip: &str) -> Option<DeviceInfo> {
+    let url = format!("http://{}/query/device-info", ip);
+    Some(parse_device_info(&fetch::get(&url).await.ok()?.text()))
+}
+
+pub async fn get_tv_channels(ip: &str) -> Vec<TvChannel> {
+    let url = format!("http://{}/query/tv-channels", ip);
+    fetch::get(&url)
+        .await
+        .ok()
+        .and_then(|resp| parse_tv_channels(&resp.text()).ok())
+        .unwrap_or_default()
+}
+
+pub async fn get_tv_active_channel(ip: &str) -> Option<TvActiveChannel> {
+    let url = format!("http://{}/query/tv-active-channel", ip);
+    parse_tv_active_channel(&fetch::get(&url).await.ok()?.text())
+}
+
+pub async fn send_command(ip: &str, command: &str) {
+    let url = format!("http://{}/keypress/{}", ip, command);
+    let _ = fetch::post(&url).await;
+}
+
+pub async fn launch_app(ip: &str, app_id: &str) {
+    let url = format!("http://{}/launch/{}", ip, app_id);
+    let _ = fetch::post(&url).await;
+}
+
+pub async fn tune_tv_channel(ip: &str, number: &str) {
+    let mut query = url::form_urlencoded::Serializer::new(String::new());
+    query.append_pair("ch", number.trim());
+    let url = format!("http://{}/launch/tvinput.dtv?{}", ip, query.finish());
+    let _ = fetch::post(&url).await;
+}
+
+pub async fn search_browse(ip: &str, query: &SearchQuery) {
+    let url = format!("http://{}/search/browse?{}", ip, query.query_string());
+    let _ = fetch::post(&url).await;
+}
+
+pub async fn send_key(ip: &str, key: &str) {
+    for c in key.chars() {
+        let url = format!("http://{}/keypress/{}", ip, literal_key(c));
+        let _ = fetch::post(&url).await;
+    }
+}
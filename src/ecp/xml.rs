@@ -0,0 +1,125 @@
+// the shapes of the devices' xml replies, for quick-xml's serde support. attributes are the
+// @-prefixed fields; anything a reply has beyond these is ignored, and anything missing is
+// left at its default, since firmware versions differ in what they send. a reply that isn't
+// xml at all is a RokuError::Parse
+
+use crate::error::RokuError;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde::Deserialize;
+
+// <apps><app id="12" type="appl" version="5.1.2">Netflix</app>...</apps>
+#[derive(Debug, Default, Deserialize)]
+pub struct Apps {
+    #[serde(rename = "app", default)]
+    pub apps: Vec<App>,
+}
+
+// also self-closing, <app id="tvinput.hdmi1" type="tvin" version="1.0.0"/>, with no name
+#[derive(Debug, Default, Deserialize)]
+pub struct App {
+    #[serde(rename = "@id", default)]
+    pub id: String,
+    #[serde(rename = "@type", default)]
+    pub kind: String,
+    #[serde(rename = "@version", default)]
+    pub version: String,
+    #[serde(rename = "$text", default)]
+    pub name: String,
+}
+
+// <active-app><app>Roku</app><screensaver .../></active-app>
+#[derive(Debug, Default, Deserialize)]
+pub struct ActiveApp {
+    pub app: Option<App>,
+}
+
+// <player error="false" state="play">, its children as far as playback goes
+#[derive(Debug, Default, Deserialize)]
+pub struct Player {
+    #[serde(rename = "@state", default)]
+    pub state: String,
+    // "12345 ms"
+    pub position: Option<String>,
+    pub duration: Option<String>,
+    pub buffering: Option<Buffering>,
+}
+
+// <buffering current="250" max="1000" target="0"/>
+#[derive(Debug, Default, Deserialize)]
+pub struct Buffering {
+    #[serde(rename = "@current")]
+    pub current: Option<f32>,
+    #[serde(rename = "@max")]
+    pub max: Option<f32>,
+}
+
+// <tv-channels><channel>...</channel>...</tv-channels>, and <tv-channel> with just the one
+#[derive(Debug, Default, Deserialize)]
+pub struct Channels {
+    #[serde(rename = "channel", default)]
+    pub channels: Vec<Channel>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct Channel {
+    pub number: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub user_hidden: String,
+    // only in tv-active-channel
+    pub active_input: String,
+    pub program_title: String,
+    pub signal_quality: String,
+}
+
+// the elements right under the root that hold only text, in document order, e.g. the
+// ("power-mode", "PowerOn") of device-info. empty if the reply isn't xml
+pub fn leaf_fields(xml: &str) -> Vec<(String, String)> {
+    let mut reader = Reader::from_str(xml);
+    let mut fields = Vec::new();
+    let mut depth = 0;
+    // the child of the root being read, and whether something other than text turned up in it
+    let mut field: Option<(String, String)> = None;
+    let mut nested = false;
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(start)) => {
+                depth += 1;
+                if depth == 2 {
+                    let name = String::from_utf8_lossy(start.name().as_ref()).to_string();
+                    field = Some((name, String::new()));
+                    nested = false;
+                } else if depth > 2 {
+                    nested = true;
+                }
+            }
+            Ok(Event::Text(text)) if depth == 2 => {
+                if let (Some((_, value)), Ok(text)) = (&mut field, text.unescape()) {
+                    value.push_str(&text);
+                }
+            }
+            Ok(Event::CData(text)) if depth == 2 => {
+                if let Some((_, value)) = &mut field {
+                    value.push_str(&String::from_utf8_lossy(&text));
+                }
+            }
+            Ok(Event::End(_)) => {
+                if depth == 2 {
+                    if let Some(field) = field.take().filter(|_| !nested) {
+                        fields.push(field);
+                    }
+                }
+                depth -= 1;
+            }
+            Ok(Event::Eof) | Err(_) => return fields,
+            _ => {}
+        }
+    }
+}
+
+pub fn parse<'de, T: Deserialize<'de>>(xml: &'de str) -> Result<T, RokuError> {
+    quick_xml::de::from_str(xml).map_err(|err| RokuError::Parse(err.to_string()))
+}
@@ -0,0 +1,17 @@
+// what can go wrong talking to a device, so callers can tell a refused connection from a
+// device that's just slow or one that turned the request down
+
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum RokuError {
+    // nothing listening, no route, or the name didn't resolve
+    #[error("couldn't reach the device: {0}")]
+    Connection(String),
+    #[error("the device is not responding")]
+    Timeout,
+    #[error("the device answered {0}")]
+    Status(u16),
+    #[error("couldn't make sense of the device's reply: {0}")]
+    Parse(String),
+}
@@ -0,0 +1,150 @@
+// polled device state and the change events computed from it; shared by the daemon and
+// by library users watching devices through Client
+
+use crate::ecp::AppEntry;
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// events kept for clients catching up with ?since=
+const EVENT_HISTORY: usize = 256;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[cfg_attr(feature = "app", derive(utoipa::ToSchema))]
+pub struct DeviceState {
+    pub device: String,
+    pub online: bool,
+    // "PowerOn", "DisplayOff", "Headless", ...
+    pub power_mode: Option<String>,
+    pub active_app: Option<AppEntry>,
+    // media-player state: "play", "pause", "buffer", "stop", "close", ...
+    pub playback: Option<String>,
+    // unix time of the poll this came from
+    pub updated: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "app", derive(utoipa::ToSchema))]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventKind {
+    // found by discovery for the first time since the daemon started
+    Discovered,
+    Online,
+    Offline,
+    PowerChanged { power_mode: Option<String> },
+    AppChanged { active_app: Option<AppEntry> },
+    PlaybackChanged { playback: Option<String> },
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "app", derive(utoipa::ToSchema))]
+pub struct DeviceEvent {
+    // increases by one per event; pass the last one seen as ?since= to get only newer events
+    pub seq: u64,
+    pub device: String,
+    pub time: u64,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+#[derive(Default)]
+pub struct StateCache {
+    states: BTreeMap<String, DeviceState>,
+    events: VecDeque<DeviceEvent>,
+    next_seq: u64,
+}
+
+impl StateCache {
+    pub fn get(&self, device: &str) -> Option<&DeviceState> {
+        self.states.get(device)
+    }
+
+    // stores a fresh poll, recording an event for everything that changed since the last one
+    pub fn update(&mut self, state: DeviceState) {
+        let old = self.states.get(&state.device);
+        let mut kinds = Vec::new();
+        if old.map(|o| o.online) != Some(state.online) {
+            kinds.push(if state.online {
+                EventKind::Online
+            } else {
+                EventKind::Offline
+            });
+        }
+        // an offline device keeps its last known state rather than reporting it all as gone
+        if state.online {
+            if old.map(|o| &o.power_mode) != Some(&state.power_mode) {
+                kinds.push(EventKind::PowerChanged {
+                    power_mode: state.power_mode.clone(),
+                });
+            }
+            if old.map(|o| &o.active_app) != Some(&state.active_app) {
+                kinds.push(EventKind::AppChanged {
+                    active_app: state.active_app.clone(),
+                });
+            }
+            if old.map(|o| &o.playback) != Some(&state.playback) {
+                kinds.push(EventKind::PlaybackChanged {
+                    playback: state.playback.clone(),
+                });
+            }
+        }
+
+        for kind in kinds {
+            self.push_event(&state.device, kind);
+        }
+        let state = match (state.online, self.states.get(&state.device)) {
+            (false, Some(old)) => DeviceState {
+                online: false,
+                updated: state.updated,
+                ..old.clone()
+            },
+            _ => state,
+        };
+        self.states.insert(state.device.clone(), state);
+    }
+
+    pub fn discovered(&mut self, device: &str) {
+        self.push_event(device, EventKind::Discovered);
+    }
+
+    fn push_event(&mut self, device: &str, kind: EventKind) {
+        self.next_seq += 1;
+        self.events.push_back(DeviceEvent {
+            seq: self.next_seq,
+            device: device.to_string(),
+            time: now(),
+            kind,
+        });
+        while self.events.len() > EVENT_HISTORY {
+            self.events.pop_front();
+        }
+    }
+
+    pub fn events_since(&self, seq: u64) -> impl Iterator<Item = &DeviceEvent> {
+        self.events.iter().filter(move |event| event.seq > seq)
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+// queries one device; a device that doesn't answer device-info counts as offline
+#[cfg(not(target_arch = "wasm32"))]
+pub fn poll(device: &str) -> DeviceState {
+    let power_mode = crate::ecp::get_power_mode(device);
+    let online = power_mode.is_some();
+    DeviceState {
+        device: device.to_string(),
+        online,
+        power_mode,
+        active_app: online.then(|| crate::ecp::get_active_app(device)).flatten(),
+        playback: online
+            .then(|| crate::ecp::get_playback_state(device))
+            .flatten(),
+        updated: now(),
+    }
+}
@@ -0,0 +1,66 @@
+// fleet setup: one macro run over a list of devices, one device at a time
+
+use crate::macros::{self, Step};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Progress {
+    Pending,
+    // index of the step being run
+    Running(usize),
+    Done,
+    Failed(String),
+}
+
+pub struct FleetRun {
+    pub devices: Vec<String>,
+    pub progress: Vec<Progress>,
+    pub steps: usize,
+    rx: Receiver<(usize, Progress)>,
+}
+
+impl FleetRun {
+    pub fn start(devices: Vec<String>, steps: Vec<Step>, verify: bool) -> FleetRun {
+        let (tx, rx) = mpsc::channel();
+        let run = FleetRun {
+            progress: vec![Progress::Pending; devices.len()],
+            steps: steps.len(),
+            devices: devices.clone(),
+            rx,
+        };
+        thread::spawn(move || {
+            for (i, ip) in devices.iter().enumerate() {
+                let result = macros::run(ip, &steps, verify, |step| {
+                    tx.send((i, Progress::Running(step))).ok();
+                });
+                let progress = match result {
+                    Ok(()) => Progress::Done,
+                    Err(err) => Progress::Failed(err),
+                };
+                if tx.send((i, progress)).is_err() {
+                    // the run was dropped, nobody is watching any more
+                    return;
+                }
+            }
+        });
+        run
+    }
+
+    // applies progress reported since the last frame; false once every device has finished
+    pub fn poll(&mut self) -> bool {
+        while let Ok((i, progress)) = self.rx.try_recv() {
+            self.progress[i] = progress;
+        }
+        self.progress
+            .iter()
+            .any(|p| matches!(p, Progress::Pending | Progress::Running(_)))
+    }
+
+    pub fn failures(&self) -> usize {
+        self.progress
+            .iter()
+            .filter(|p| matches!(p, Progress::Failed(_)))
+            .count()
+    }
+}
@@ -0,0 +1,186 @@
+// every screenshot taken of a dev channel, kept with when and where it was taken so earlier
+// captures stay around to compare against. the images sit in a folder next to the config
+
+use crate::config::{config_path, write_json};
+use chrono::{Local, TimeZone};
+use directories::UserDirs;
+use eframe::egui::{self, ColorImage, TextureHandle, TextureOptions};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Shot {
+    // file name inside the screenshots folder
+    pub file: String,
+    pub device: String,
+    // unix time in milliseconds
+    pub taken: u64,
+}
+
+impl Shot {
+    pub fn label(&self) -> String {
+        let taken = Local
+            .timestamp_millis_opt(self.taken as i64)
+            .single()
+            .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default();
+        format!("{} — {}", self.device, taken)
+    }
+}
+
+// newest first
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Gallery {
+    pub shots: Vec<Shot>,
+    #[serde(skip)]
+    thumbnails: HashMap<String, Option<TextureHandle>>,
+}
+
+fn gallery_dir() -> Option<PathBuf> {
+    config_path().map(|path| path.with_file_name("screenshots"))
+}
+
+fn index_path() -> Option<PathBuf> {
+    gallery_dir().map(|dir| dir.join("index.json"))
+}
+
+fn no_dir() -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, "no config directory")
+}
+
+impl Gallery {
+    pub fn load() -> Gallery {
+        index_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> io::Result<()> {
+        write_json(&index_path().ok_or_else(no_dir)?, self)
+    }
+
+    pub fn path(&self, shot: &Shot) -> Option<PathBuf> {
+        gallery_dir().map(|dir| dir.join(&shot.file))
+    }
+
+    // files an image from device; ext is the image type, "jpg" or "png"
+    pub fn add(&mut self, device: &str, bytes: &[u8], ext: &str) -> io::Result<PathBuf> {
+        let dir = gallery_dir().ok_or_else(no_dir)?;
+        fs::create_dir_all(&dir)?;
+        let taken = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since| since.as_millis() as u64)
+            .unwrap_or_default();
+        let name: String = device
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '-' })
+            .collect();
+        let file = format!("{}-{}.{}", taken, name, ext);
+        let path = dir.join(&file);
+        fs::write(&path, bytes)?;
+        self.shots.insert(
+            0,
+            Shot {
+                file,
+                device: device.to_string(),
+                taken,
+            },
+        );
+        self.save()?;
+        Ok(path)
+    }
+
+    pub fn delete(&mut self, index: usize) -> io::Result<()> {
+        if index >= self.shots.len() {
+            return Ok(());
+        }
+        let shot = self.shots.remove(index);
+        self.thumbnails.remove(&shot.file);
+        if let Some(path) = self.path(&shot) {
+            match fs::remove_file(path) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+                _ => {}
+            }
+        }
+        self.save()
+    }
+
+    // the image of a shot, for backing it up
+    pub fn read(&self, shot: &Shot) -> io::Result<Vec<u8>> {
+        fs::read(self.path(shot).ok_or_else(no_dir)?)
+    }
+
+    // takes in shots from a backup, writing out the images it carries. a shot without its
+    // image, as older backups have them, only comes back if its file is still here
+    pub fn restore(&mut self, shots: Vec<(Shot, Option<Vec<u8>>)>) -> io::Result<()> {
+        let dir = gallery_dir().ok_or_else(no_dir)?;
+        fs::create_dir_all(&dir)?;
+        for (shot, image) in shots {
+            if self.shots.iter().any(|known| known.file == shot.file) {
+                continue;
+            }
+            let path = dir.join(&shot.file);
+            if let (Some(image), false) = (image, path.is_file()) {
+                fs::write(&path, image)?;
+            }
+            if path.is_file() {
+                self.shots.push(shot);
+            }
+        }
+        self.shots.sort_by_key(|shot| std::cmp::Reverse(shot.taken));
+        self.save()
+    }
+
+    // a copy in the pictures folder (or home), for attaching to bug reports
+    pub fn export(&self, shot: &Shot) -> io::Result<PathBuf> {
+        let dirs = UserDirs::new().ok_or_else(|| io::Error::other("no home directory"))?;
+        let dir = dirs
+            .picture_dir()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| dirs.home_dir().to_path_buf());
+        let target = dir.join(format!("roku-{}", shot.file));
+        fs::copy(self.path(shot).ok_or_else(no_dir)?, &target)?;
+        Ok(target)
+    }
+
+    pub fn copy_to_clipboard(&self, shot: &Shot) -> Result<(), String> {
+        let path = self.path(shot).ok_or("no config directory")?;
+        let image = image::open(path)
+            .map_err(|err| err.to_string())?
+            .into_rgba8();
+        let (width, height) = image.dimensions();
+        arboard::Clipboard::new()
+            .and_then(|mut clipboard| {
+                clipboard.set_image(arboard::ImageData {
+                    width: width as usize,
+                    height: height as usize,
+                    bytes: image.into_raw().into(),
+                })
+            })
+            .map_err(|err| err.to_string())
+    }
+
+    // decoded once and kept; None for files that are gone or aren't images
+    pub fn thumbnail(&mut self, ctx: &egui::Context, shot: &Shot) -> Option<TextureHandle> {
+        if let Some(texture) = self.thumbnails.get(&shot.file) {
+            return texture.clone();
+        }
+        let texture = self
+            .path(shot)
+            .and_then(|path| image::open(path).ok())
+            .map(|image| {
+                let image = image.thumbnail(320, 320).into_rgba8();
+                let size = [image.width() as usize, image.height() as usize];
+                let pixels = ColorImage::from_rgba_unmultiplied(size, &image.into_raw());
+                ctx.load_texture(&shot.file, pixels, TextureOptions::LINEAR)
+            });
+        self.thumbnails.insert(shot.file.clone(), texture.clone());
+        texture
+    }
+}
@@ -0,0 +1,204 @@
+// system-wide shortcuts for the remote, such as Ctrl+Alt+M for mute or the keyboard's media keys,
+// that keep working while another window has focus or the remote is minimized. built with the
+// hotkeys feature; without it the bindings are kept but nothing is registered. on Linux they go
+// through X11, so under Wayland they only fire while an X11 window has focus
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::SystemTime;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GlobalHotkeySettings {
+    // off until asked for, since the media keys are shared with every other player
+    pub enabled: bool,
+    // shortcut, "Ctrl+Alt+M" or "MediaPlayPause", -> remote key
+    pub keys: BTreeMap<String, String>,
+}
+
+impl Default for GlobalHotkeySettings {
+    fn default() -> Self {
+        let keys = [
+            ("Ctrl+Alt+M", "VolumeMute"),
+            ("Ctrl+Alt+ArrowUp", "VolumeUp"),
+            ("Ctrl+Alt+ArrowDown", "VolumeDown"),
+            ("MediaPlayPause", "Play"),
+            ("MediaTrackNext", "Fwd"),
+            ("MediaTrackPrevious", "Rev"),
+        ];
+        Self {
+            enabled: false,
+            keys: keys
+                .into_iter()
+                .map(|(shortcut, cmd)| (shortcut.to_string(), cmd.to_string()))
+                .collect(),
+        }
+    }
+}
+
+// a remote key a shortcut sent, to which device, when and how it went, for the history
+pub type Sent = (String, String, SystemTime, Result<(), String>);
+
+#[cfg(feature = "hotkeys")]
+mod imp {
+    use super::{GlobalHotkeySettings, Sent};
+    use crate::ecp;
+    use eframe::egui;
+    use global_hotkey::hotkey::HotKey;
+    use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::SystemTime;
+
+    // what the event handler needs; it runs off the GUI thread, and with the window minimized
+    // there is no update() to hand the press to
+    #[derive(Default)]
+    struct Shared {
+        // hotkey id -> remote key
+        bound: HashMap<u32, String>,
+        targets: Vec<String>,
+        sent: Vec<Sent>,
+    }
+
+    #[derive(Default)]
+    pub struct GlobalHotkeys {
+        // created on the first update, once the event loop is running (macOS needs that)
+        manager: Option<GlobalHotKeyManager>,
+        // the settings last registered, so they're only registered again once they change
+        applied: Option<GlobalHotkeySettings>,
+        registered: Vec<HotKey>,
+        shared: Arc<Mutex<Shared>>,
+        errors: Vec<String>,
+    }
+
+    impl GlobalHotkeys {
+        pub fn update(
+            &mut self,
+            ctx: &egui::Context,
+            settings: &GlobalHotkeySettings,
+            targets: Vec<String>,
+        ) {
+            self.shared.lock().unwrap().targets = targets;
+            if self.applied.as_ref() == Some(settings) {
+                return;
+            }
+            self.applied = Some(settings.clone());
+            if self.manager.is_none() && settings.enabled {
+                match GlobalHotKeyManager::new() {
+                    Ok(manager) => {
+                        self.set_handler(ctx);
+                        self.manager = Some(manager);
+                    }
+                    Err(err) => {
+                        self.errors = vec![format!("System-wide shortcuts unavailable: {}", err)];
+                        return;
+                    }
+                }
+            }
+            let Some(manager) = &self.manager else {
+                return;
+            };
+            let _ = manager.unregister_all(&self.registered);
+            self.registered.clear();
+            self.errors.clear();
+            let mut bound = HashMap::new();
+            for (shortcut, cmd) in settings.keys.iter().filter(|_| settings.enabled) {
+                let registered = shortcut
+                    .parse::<HotKey>()
+                    .map_err(|err| err.to_string())
+                    .and_then(|hotkey| {
+                        manager.register(hotkey).map_err(|err| err.to_string())?;
+                        Ok(hotkey)
+                    });
+                match registered {
+                    Ok(hotkey) => {
+                        bound.insert(hotkey.id(), cmd.clone());
+                        self.registered.push(hotkey);
+                    }
+                    Err(err) => self.errors.push(format!("{}: {}", shortcut, err)),
+                }
+            }
+            self.shared.lock().unwrap().bound = bound;
+        }
+
+        fn set_handler(&self, ctx: &egui::Context) {
+            let (shared, ctx) = (Arc::clone(&self.shared), ctx.clone());
+            GlobalHotKeyEvent::set_event_handler(Some(move |event: GlobalHotKeyEvent| {
+                if event.state != HotKeyState::Pressed {
+                    return;
+                }
+                let (cmd, targets) = {
+                    let shared = shared.lock().unwrap();
+                    let Some(cmd) = shared.bound.get(&event.id).cloned() else {
+                        return;
+                    };
+                    (cmd, shared.targets.clone())
+                };
+                for device in targets {
+                    let (shared, cmd, ctx) = (Arc::clone(&shared), cmd.clone(), ctx.clone());
+                    thread::spawn(move || {
+                        let at = SystemTime::now();
+                        let result =
+                            ecp::send_command(&device, &cmd).map_err(|err| err.to_string());
+                        shared.lock().unwrap().sent.push((device, cmd, at, result));
+                        ctx.request_repaint();
+                    });
+                }
+            }));
+        }
+
+        // shortcuts that couldn't be registered, e.g. because another program has them
+        pub fn errors(&self) -> &[String] {
+            &self.errors
+        }
+
+        // since the last call
+        pub fn sent(&self) -> Vec<Sent> {
+            std::mem::take(&mut self.shared.lock().unwrap().sent)
+        }
+    }
+
+    pub fn check(shortcut: &str) -> Result<(), String> {
+        shortcut
+            .parse::<HotKey>()
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+    }
+}
+
+#[cfg(not(feature = "hotkeys"))]
+mod imp {
+    use super::{GlobalHotkeySettings, Sent};
+    use eframe::egui;
+
+    #[derive(Default)]
+    pub struct GlobalHotkeys;
+
+    impl GlobalHotkeys {
+        pub fn update(
+            &mut self,
+            _ctx: &egui::Context,
+            _settings: &GlobalHotkeySettings,
+            _targets: Vec<String>,
+        ) {
+        }
+
+        pub fn errors(&self) -> &[String] {
+            &[]
+        }
+
+        pub fn sent(&self) -> Vec<Sent> {
+            Vec::new()
+        }
+    }
+
+    pub fn check(_shortcut: &str) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+pub use imp::{check, GlobalHotkeys};
+
+// whether this build registers the shortcuts at all, for the settings
+pub const AVAILABLE: bool = cfg!(feature = "hotkeys");
@@ -0,0 +1,264 @@
+// tonic::Status is large, but it is what every service method has to return
+#![allow(clippy::result_large_err)]
+
+use crate::config::ApiToken;
+use crate::ecp;
+use crate::server::{cached_state, token_for, ServerState};
+use roku_remote::{RokuKey, UnknownKey};
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+mod generated {
+    include!(concat!(env!("OUT_DIR"), "/roku_remote.RokuRemote.rs"));
+}
+
+use generated::roku_remote_server::{RokuRemote, RokuRemoteServer};
+
+// how often WatchState looks at the state cache for changes
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+// messages mirror proto/roku_remote.proto
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ListDevicesRequest {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ListDevicesResponse {
+    #[prost(string, repeated, tag = "1")]
+    pub devices: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct DeviceRequest {
+    #[prost(string, tag = "1")]
+    pub device: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct KeypressRequest {
+    #[prost(string, tag = "1")]
+    pub device: String,
+    #[prost(string, tag = "2")]
+    pub key: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct LaunchRequest {
+    #[prost(string, tag = "1")]
+    pub device: String,
+    #[prost(string, tag = "2")]
+    pub app_id: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CommandReply {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct DeviceState {
+    #[prost(string, tag = "1")]
+    pub device: String,
+    #[prost(string, tag = "2")]
+    pub power_mode: String,
+    #[prost(string, tag = "3")]
+    pub active_app_id: String,
+    #[prost(string, tag = "4")]
+    pub active_app_name: String,
+}
+
+// runs the grpc service on its own runtime thread, sharing state with the REST server
+pub fn spawn(addr: &str, state: Arc<Mutex<ServerState>>) -> io::Result<()> {
+    let addr: SocketAddr = addr
+        .parse()
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    let runtime = tokio::runtime::Runtime::new()?;
+    std::thread::spawn(move || {
+        let service = RokuRemoteServer::new(RokuRemoteService { state });
+        let result = runtime.block_on(
+            tonic::transport::Server::builder()
+                .add_service(service)
+                .serve(addr),
+        );
+        if let Err(err) = result {
+            eprintln!("gRPC server failed: {}", err);
+        }
+    });
+    Ok(())
+}
+
+struct RokuRemoteService {
+    state: Arc<Mutex<ServerState>>,
+}
+
+impl RokuRemoteService {
+    // the state's lock is a std one, held by REST handlers while they save the config, so it's
+    // only ever taken on the blocking pool and never on an async worker
+    async fn read<R: Send + 'static>(
+        &self,
+        f: impl FnOnce(&ServerState) -> R + Send + 'static,
+    ) -> Result<R, Status> {
+        let state = Arc::clone(&self.state);
+        tokio::task::spawn_blocking(move || f(&state.lock().unwrap()))
+            .await
+            .map_err(|err| Status::internal(err.to_string()))
+    }
+
+    async fn authorize<T>(&self, request: &Request<T>) -> Result<ApiToken, Status> {
+        let presented = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(|value| value.trim().to_string());
+        self.read(move |state| token_for(&state.config, presented.as_deref()))
+            .await?
+            .ok_or_else(|| Status::unauthenticated("missing or unknown API token"))
+    }
+
+    // unknown and out-of-scope devices look the same to the caller
+    async fn check_device(&self, token: &ApiToken, device: &str) -> Result<(), Status> {
+        let wanted = device.to_string();
+        let known = self
+            .read(move |state| state.devices.contains(&wanted))
+            .await?;
+        if known && token.allows(device) {
+            Ok(())
+        } else {
+            Err(Status::not_found("unknown device"))
+        }
+    }
+
+    fn check_control(token: &ApiToken) -> Result<(), Status> {
+        if token.role.can_control() {
+            Ok(())
+        } else {
+            Err(Status::permission_denied(
+                "this token can only view device state",
+            ))
+        }
+    }
+
+    // served from the daemon's state cache; only a device it hasn't polled yet costs a request
+    async fn query_state(&self, device: String) -> Result<DeviceState, Status> {
+        let state = Arc::clone(&self.state);
+        // the first poll uses the blocking client, which must stay off the async workers
+        let cached = tokio::task::spawn_blocking(move || cached_state(&state, &device))
+            .await
+            .map_err(|err| Status::internal(err.to_string()))?;
+        Ok(DeviceState {
+            power_mode: cached.power_mode.unwrap_or_default(),
+            active_app_id: cached
+                .active_app
+                .as_ref()
+                .map(|a| a.id.clone())
+                .unwrap_or_default(),
+            active_app_name: cached.active_app.map(|a| a.name).unwrap_or_default(),
+            device: cached.device,
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl RokuRemote for RokuRemoteService {
+    async fn list_devices(
+        &self,
+        request: Request<ListDevicesRequest>,
+    ) -> Result<Response<ListDevicesResponse>, Status> {
+        let token = self.authorize(&request).await?;
+        let devices = self
+            .read(move |state| {
+                state
+                    .devices
+                    .iter()
+                    .filter(|d| token.allows(d))
+                    .cloned()
+                    .collect()
+            })
+            .await?;
+        Ok(Response::new(ListDevicesResponse { devices }))
+    }
+
+    async fn get_state(
+        &self,
+        request: Request<DeviceRequest>,
+    ) -> Result<Response<DeviceState>, Status> {
+        let token = self.authorize(&request).await?;
+        let device = request.into_inner().device;
+        self.check_device(&token, &device).await?;
+        self.query_state(device).await.map(Response::new)
+    }
+
+    async fn keypress(
+        &self,
+        request: Request<KeypressRequest>,
+    ) -> Result<Response<CommandReply>, Status> {
+        let token = self.authorize(&request).await?;
+        let KeypressRequest { device, key } = request.into_inner();
+        self.check_device(&token, &device).await?;
+        Self::check_control(&token)?;
+        // like REST, a key ECP doesn't know is the caller's mistake, not the device's
+        let key: RokuKey = key
+            .parse()
+            .map_err(|err: UnknownKey| Status::invalid_argument(err.to_string()))?;
+        tokio::task::spawn_blocking(move || ecp::press(&device, key))
+            .await
+            .map_err(|err| Status::internal(err.to_string()))?
+            .map_err(|err| Status::unavailable(err.to_string()))?;
+        Ok(Response::new(CommandReply {}))
+    }
+
+    async fn launch(
+        &self,
+        request: Request<LaunchRequest>,
+    ) -> Result<Response<CommandReply>, Status> {
+        let token = self.authorize(&request).await?;
+        let LaunchRequest { device, app_id } = request.into_inner();
+        self.check_device(&token, &device).await?;
+        Self::check_control(&token)?;
+        tokio::task::spawn_blocking(move || ecp::launch_app(&device, &app_id))
+            .await
+            .map_err(|err| Status::internal(err.to_string()))?
+            .map_err(|err| Status::unavailable(err.to_string()))?;
+        Ok(Response::new(CommandReply {}))
+    }
+
+    type WatchStateStream = ReceiverStream<Result<DeviceState, Status>>;
+
+    async fn watch_state(
+        &self,
+        request: Request<DeviceRequest>,
+    ) -> Result<Response<Self::WatchStateStream>, Status> {
+        let token = self.authorize(&request).await?;
+        let device = request.into_inner().device;
+        self.check_device(&token, &device).await?;
+
+        let (tx, rx) = mpsc::channel(4);
+        let service = RokuRemoteService {
+            state: Arc::clone(&self.state),
+        };
+        tokio::spawn(async move {
+            let mut last = None;
+            loop {
+                let state = service.query_state(device.clone()).await;
+                let changed = match (&state, &last) {
+                    (Ok(state), Some(last)) => state != last,
+                    _ => true,
+                };
+                if changed {
+                    if let Ok(state) = &state {
+                        last = Some(state.clone());
+                    }
+                    // the receiver is gone once the client hangs up
+                    if tx.send(state).await.is_err() {
+                        break;
+                    }
+                }
+                tokio::time::sleep(WATCH_INTERVAL).await;
+            }
+        });
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
@@ -0,0 +1,86 @@
+// every command the GUI sent, where to, when and whether it got through, for the History panel
+// and for saving to a file when a device didn't do what it was told
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::SystemTime;
+
+// a few hours of steady use; the oldest go first
+const KEPT: usize = 2000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub at: DateTime<Local>,
+    // empty for a command that names its device itself, e.g. "Launching Netflix on …"
+    pub device: String,
+    pub command: String,
+    pub result: Result<(), String>,
+}
+
+#[derive(Default)]
+pub struct History {
+    pub entries: VecDeque<Entry>,
+}
+
+impl History {
+    pub fn record(
+        &mut self,
+        at: SystemTime,
+        device: &str,
+        command: &str,
+        result: Result<(), String>,
+    ) {
+        if self.entries.len() == KEPT {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(Entry {
+            at: at.into(),
+            device: device.to_string(),
+            command: command.to_string(),
+            result,
+        });
+    }
+
+    // entries from a backup, among the ones kept since, in order of time
+    pub fn restore(&mut self, entries: Vec<Entry>) {
+        let mut all: Vec<Entry> = self.entries.drain(..).chain(entries).collect();
+        all.sort_by_key(|entry| entry.at);
+        all.dedup();
+        let skip = all.len().saturating_sub(KEPT);
+        self.entries = all.into_iter().skip(skip).collect();
+    }
+
+    pub fn failures(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|entry| entry.result.is_err())
+            .count()
+    }
+
+    // time,device,command,result with the time in RFC 3339, oldest first
+    pub fn to_csv(&self) -> String {
+        let field = |value: &str| {
+            if value.contains([',', '"', '\n']) {
+                format!("\"{}\"", value.replace('"', "\"\""))
+            } else {
+                value.to_string()
+            }
+        };
+        let mut csv = "time,device,command,result\n".to_string();
+        for entry in &self.entries {
+            let result = match &entry.result {
+                Ok(()) => "ok".to_string(),
+                Err(err) => format!("failed: {}", err),
+            };
+            csv += &format!(
+                "{},{},{},{}\n",
+                entry.at.to_rfc3339(),
+                field(&entry.device),
+                field(&entry.command),
+                field(&result)
+            );
+        }
+        csv
+    }
+}
@@ -0,0 +1,76 @@
+// telling a tap on a remote button or key from holding it. a tap goes out as a keypress once
+// it ends; anything held past HOLD_DELAY is sent as a keydown, and as its keyup when let go, so
+// the device keeps fast-forwarding or changing the volume for as long as it's held
+
+use eframe::egui;
+use roku_remote::ecp::KeyAction;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+// longer than a deliberate tap, short enough that holding feels immediate
+pub const HOLD_DELAY: Duration = Duration::from_millis(400);
+
+// what is being held: a button on screen under the mouse, or a bound keyboard key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Source {
+    Pointer,
+    Key(egui::Key),
+}
+
+struct Held {
+    cmd: String,
+    since: Instant,
+    // the keydown has gone out, so the end of the hold is a keyup rather than a press
+    down: bool,
+}
+
+#[derive(Default)]
+pub struct Holds {
+    held: HashMap<Source, Held>,
+}
+
+impl Holds {
+    // takes what is down this frame and returns what to send for it
+    pub fn update(&mut self, down: &[(Source, String)]) -> Vec<(String, KeyAction)> {
+        let mut send = Vec::new();
+        self.held.retain(|source, held| {
+            let still = down
+                .iter()
+                .any(|(other, cmd)| other == source && *cmd == held.cmd);
+            if !still {
+                let action = if held.down {
+                    KeyAction::Up
+                } else {
+                    KeyAction::Press
+                };
+                send.push((held.cmd.clone(), action));
+            }
+            still
+        });
+        for (source, cmd) in down {
+            let held = self.held.entry(*source).or_insert_with(|| Held {
+                cmd: cmd.clone(),
+                since: Instant::now(),
+                down: false,
+            });
+            if !held.down && held.since.elapsed() >= HOLD_DELAY {
+                held.down = true;
+                send.push((cmd.clone(), KeyAction::Down));
+            }
+        }
+        send
+    }
+
+    pub fn holding(&self, source: Source) -> bool {
+        self.held.contains_key(&source)
+    }
+
+    // while something is down the next frame has to come in time to notice it became a hold
+    pub fn waiting(&self) -> Option<Duration> {
+        self.held
+            .values()
+            .filter(|held| !held.down)
+            .map(|held| HOLD_DELAY.saturating_sub(held.since.elapsed()))
+            .min()
+    }
+}
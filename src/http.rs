@@ -0,0 +1,372 @@
+// the blocking http calls ECP needs, on reqwest by default or on ureq with the ureq feature,
+// which builds in a fraction of the time for programs that only send keypresses. wasm32 has
+// no blocking http at all and gets async fetch instead
+
+#[cfg(all(
+    not(target_arch = "wasm32"),
+    not(any(feature = "reqwest", feature = "ureq"))
+))]
+compile_error!("enable an http backend: the reqwest or the ureq feature");
+
+use crate::error::RokuError;
+use serde::{Deserialize, Serialize};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
+
+// every request goes through one client per process, so connections to a device are kept
+// open between requests: text goes out one keypress per character, and a new connection for
+// each of them would add a round trip or more every time. the client's own timeouts are only
+// a backstop, each request carries the configured one
+#[cfg(not(target_arch = "wasm32"))]
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+#[cfg(not(target_arch = "wasm32"))]
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+// the wait before the first retry of a query, doubling for each one after
+#[cfg(not(target_arch = "wasm32"))]
+const RETRY_BACKOFF: Duration = Duration::from_millis(250);
+
+// how long a request may take and how often a query is tried again. a device in standby
+// never answers at all, so the timeout is how long a command sent to one hangs. typed text
+// goes out a character at a time, this far apart
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RequestSettings {
+    pub timeout_seconds: f32,
+    // further tries for a query that timed out or couldn't connect. commands go out once,
+    // since a keypress that got through but wasn't answered would be pressed twice
+    pub retries: u32,
+    pub typing_delay_ms: u64,
+}
+
+impl Default for RequestSettings {
+    fn default() -> Self {
+        Self {
+            timeout_seconds: 2.0,
+            retries: 2,
+            typing_delay_ms: 50,
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+static TIMEOUT_MS: AtomicU64 = AtomicU64::new(2000);
+#[cfg(not(target_arch = "wasm32"))]
+static RETRIES: AtomicU32 = AtomicU32::new(2);
+#[cfg(not(target_arch = "wasm32"))]
+static TYPING_DELAY_MS: AtomicU64 = AtomicU64::new(50);
+
+// applies to every request made from here on, on any thread
+#[cfg(not(target_arch = "wasm32"))]
+pub fn configure(settings: &RequestSettings) {
+    // NaN gets past clamp, and from_secs_f32 panics on it
+    let seconds = match settings.timeout_seconds {
+        seconds if seconds.is_nan() => RequestSettings::default().timeout_seconds,
+        seconds => seconds.clamp(0.1, 60.0),
+    };
+    let timeout = Duration::from_secs_f32(seconds);
+    TIMEOUT_MS.store(timeout.as_millis() as u64, Ordering::Relaxed);
+    RETRIES.store(settings.retries.min(10), Ordering::Relaxed);
+    TYPING_DELAY_MS.store(settings.typing_delay_ms.min(1000), Ordering::Relaxed);
+}
+
+// the configured timeout, for requests that don't go through here
+#[cfg(not(target_arch = "wasm32"))]
+pub fn timeout() -> Duration {
+    Duration::from_millis(TIMEOUT_MS.load(Ordering::Relaxed))
+}
+
+// the configured wait between the characters of typed text
+#[cfg(not(target_arch = "wasm32"))]
+pub fn typing_delay() -> Duration {
+    Duration::from_millis(TYPING_DELAY_MS.load(Ordering::Relaxed))
+}
+
+pub struct HttpResponse {
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+// any status counts as a response; Err means the device couldn't be reached at all. queries
+// change nothing on the device, so one that went unanswered is tried again, backing off in
+// between; a device that answered, with whatever status, isn't asked twice
+#[cfg(not(target_arch = "wasm32"))]
+pub fn get(url: &str) -> Result<HttpResponse, RokuError> {
+    let mut backoff = RETRY_BACKOFF;
+    let mut result = backend::get_within(url, timeout());
+    for _ in 0..RETRIES.load(Ordering::Relaxed) {
+        if !matches!(result, Err(RokuError::Timeout | RokuError::Connection(_))) {
+            break;
+        }
+        thread::sleep(backoff);
+        backoff *= 2;
+        result = backend::get_within(url, timeout());
+    }
+    result
+}
+
+// a single try, giving up after timeout rather than the configured one; for probes, which
+// would rather move on than wait on a device that's gone
+#[cfg(not(target_arch = "wasm32"))]
+pub fn get_within(url: &str, timeout: Duration) -> Result<HttpResponse, RokuError> {
+    backend::get_within(url, timeout)
+}
+
+// ECP commands are bodyless POSTs
+#[cfg(not(target_arch = "wasm32"))]
+pub fn post(url: &str) -> Result<HttpResponse, RokuError> {
+    if dry_run::enabled() {
+        dry_run::record(&format!("POST {}", url));
+        return Ok(HttpResponse {
+            status: 200,
+            content_type: None,
+            body: Vec::new(),
+        });
+    }
+    backend::post(url, timeout())
+}
+
+// dry run: commands are kept for showing instead of being sent, so macros can be tried out and
+// the GUI shown off without anything changing on the TV. that covers everything that acts on a
+// device, ECP posts and also wake-on-lan, the developer installer and casting, which check
+// enabled themselves and record what they'd have done. queries still go out, they change
+// nothing and the GUI has nothing to show without them. a program that wants each command as
+// it happens, like the CLI printing them, gives on_record a function
+#[cfg(not(target_arch = "wasm32"))]
+pub mod dry_run {
+    use std::collections::VecDeque;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Mutex;
+
+    // commands kept for the GUI's log
+    const KEPT: usize = 200;
+
+    static ENABLED: AtomicBool = AtomicBool::new(false);
+    static LOG: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+    static ON_RECORD: Mutex<Option<fn(&str)>> = Mutex::new(None);
+
+    pub fn enabled() -> bool {
+        ENABLED.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(on: bool) {
+        ENABLED.store(on, Ordering::Relaxed);
+    }
+
+    // called with each command as it's recorded, e.g. "POST http://.../keypress/Home"
+    pub fn on_record(f: Option<fn(&str)>) {
+        *ON_RECORD.lock().unwrap() = f;
+    }
+
+    // keeps what would have been done, a method and where, e.g. "WAKE a8:b5:7c:12:34:56"
+    pub fn record(line: &str) {
+        if let Some(f) = *ON_RECORD.lock().unwrap() {
+            f(line);
+        }
+        let mut log = LOG.lock().unwrap();
+        if log.len() == KEPT {
+            log.pop_front();
+        }
+        log.push_back(line.to_string());
+    }
+
+    // the commands that would have been sent, oldest first
+    pub fn log() -> Vec<String> {
+        LOG.lock().unwrap().iter().cloned().collect()
+    }
+
+    pub fn clear() {
+        LOG.lock().unwrap().clear();
+    }
+}
+
+// with both features on, ureq wins so that turning it on is all it takes to switch
+#[cfg(all(feature = "ureq", not(target_arch = "wasm32")))]
+mod backend {
+    use super::{HttpResponse, RokuError, CONNECT_TIMEOUT, REQUEST_TIMEOUT};
+    use std::io::{self, Read};
+    use std::sync::OnceLock;
+    use std::time::Duration;
+    use ureq::{Agent, AgentBuilder};
+
+    // ureq::get and friends make a new agent, and so a new connection, every call
+    fn agent() -> &'static Agent {
+        static AGENT: OnceLock<Agent> = OnceLock::new();
+        AGENT.get_or_init(|| {
+            AgentBuilder::new()
+                .timeout_connect(CONNECT_TIMEOUT)
+                .timeout(REQUEST_TIMEOUT)
+                .max_idle_connections_per_host(4)
+                .build()
+        })
+    }
+
+    pub fn get_within(url: &str, timeout: Duration) -> Result<HttpResponse, RokuError> {
+        finish(agent().get(url).timeout(timeout).call())
+    }
+
+    pub fn post(url: &str, timeout: Duration) -> Result<HttpResponse, RokuError> {
+        finish(agent().post(url).timeout(timeout).send_bytes(&[]))
+    }
+
+    fn finish(result: Result<ureq::Response, ureq::Error>) -> Result<HttpResponse, RokuError> {
+        let resp = match result {
+            Ok(resp) | Err(ureq::Error::Status(_, resp)) => resp,
+            Err(ureq::Error::Transport(err)) => {
+                let timed_out = std::error::Error::source(&err)
+                    .and_then(|source| source.downcast_ref::<io::Error>())
+                    .is_some_and(|err| {
+                        matches!(
+                            err.kind(),
+                            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+                        )
+                    });
+                return Err(if timed_out {
+                    RokuError::Timeout
+                } else {
+                    RokuError::Connection(err.to_string())
+                });
+            }
+        };
+        let status = resp.status();
+        let content_type = resp.header("Content-Type").map(str::to_string);
+        let mut body = Vec::new();
+        resp.into_reader()
+            .read_to_end(&mut body)
+            .map_err(|err| RokuError::Connection(err.to_string()))?;
+        Ok(HttpResponse {
+            status,
+            content_type,
+            body,
+        })
+    }
+}
+
+#[cfg(all(
+    feature = "reqwest",
+    not(feature = "ureq"),
+    not(target_arch = "wasm32")
+))]
+mod backend {
+    use super::{HttpResponse, RokuError, CONNECT_TIMEOUT, REQUEST_TIMEOUT};
+    use reqwest::blocking::{Client, RequestBuilder};
+    use std::sync::OnceLock;
+    use std::time::Duration;
+
+    // how long an unused connection is kept; Rokus close theirs after a while anyway
+    const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+    // built once and never dropped: a blocking client can't be dropped inside an async
+    // runtime, which the gRPC server has running
+    fn client() -> &'static Client {
+        static CLIENT: OnceLock<Client> = OnceLock::new();
+        CLIENT.get_or_init(|| {
+            Client::builder()
+                .connect_timeout(CONNECT_TIMEOUT)
+                .timeout(REQUEST_TIMEOUT)
+                .pool_idle_timeout(IDLE_TIMEOUT)
+                .pool_max_idle_per_host(4)
+                .build()
+                .unwrap_or_default()
+        })
+    }
+
+    pub fn get_within(url: &str, timeout: Duration) -> Result<HttpResponse, RokuError> {
+        finish(client().get(url).timeout(timeout))
+    }
+
+    pub fn post(url: &str, timeout: Duration) -> Result<HttpResponse, RokuError> {
+        finish(client().post(url).timeout(timeout))
+    }
+
+    fn failed(err: reqwest::Error) -> RokuError {
+        if err.is_timeout() {
+            RokuError::Timeout
+        } else {
+            RokuError::Connection(err.to_string())
+        }
+    }
+
+    fn finish(request: RequestBuilder) -> Result<HttpResponse, RokuError> {
+        let resp = request.send().map_err(failed)?;
+        let status = resp.status().as_u16();
+        let content_type = resp
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let body = resp.bytes().map_err(failed)?.to_vec();
+        Ok(HttpResponse {
+            status,
+            content_type,
+            body,
+        })
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub mod fetch {
+    use super::{HttpResponse, RokuError};
+    use wasm_bindgen::{JsCast, JsValue};
+    use wasm_bindgen_futures::JsFuture;
+    use web_sys::{Request, RequestInit, RequestMode, Response, WorkerGlobalScope};
+
+    pub async fn get(url: &str) -> Result<HttpResponse, RokuError> {
+        request("GET", RequestMode::Cors, url).await
+    }
+
+    // the reply is opaque (status 0): the device gets the command, the page never hears back
+    pub async fn post(url: &str) -> Result<HttpResponse, RokuError> {
+        request("POST", RequestMode::NoCors, url).await
+    }
+
+    async fn request(
+        method: &str,
+        mode: RequestMode,
+        url: &str,
+    ) -> Result<HttpResponse, RokuError> {
+        let init = RequestInit::new();
+        init.set_method(method);
+        init.set_mode(mode);
+        let request = Request::new_with_str_and_init(url, &init).map_err(describe)?;
+        // pages have a window, extension service workers and web workers only a global scope
+        let promise = match web_sys::window() {
+            Some(window) => window.fetch_with_request(&request),
+            None => js_sys::global()
+                .dyn_into::<WorkerGlobalScope>()
+                .map_err(|_| RokuError::Connection("no fetch in this context".into()))?
+                .fetch_with_request(&request),
+        };
+        let resp: Response = JsFuture::from(promise)
+            .await
+            .and_then(|value| value.dyn_into())
+            .map_err(describe)?;
+        let content_type = resp.headers().get("content-type").ok().flatten();
+        let buffer = JsFuture::from(resp.array_buffer().map_err(describe)?)
+            .await
+            .map_err(describe)?;
+        Ok(HttpResponse {
+            status: resp.status(),
+            content_type,
+            body: js_sys::Uint8Array::new(&buffer).to_vec(),
+        })
+    }
+
+    fn describe(err: JsValue) -> RokuError {
+        RokuError::Connection(err.as_string().unwrap_or_else(|| format!("{:?}", err)))
+    }
+}
@@ -0,0 +1,182 @@
+// channel icons for the app list. an icon is only fetched once its row is on screen, is scaled
+// down to the size it's drawn at before it becomes a texture, and is dropped again once it
+// hasn't been drawn for a while, so a device with hundreds of channels costs what's visible.
+// the image files themselves are small and kept, so an evicted icon comes back without asking
+// the device again
+
+use crate::ecp;
+use eframe::egui::{self, ColorImage, TextureHandle, TextureOptions};
+use std::collections::{HashMap, VecDeque};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+// textures kept before the least recently drawn ones go
+const MAX_TEXTURES: usize = 96;
+// icons fetched at once, so opening a long list doesn't flood the device
+const MAX_LOADING: usize = 4;
+
+// device address and app id
+type Key = (String, String);
+
+enum Slot {
+    Queued([u32; 2]),
+    Loading,
+    Ready(TextureHandle),
+    // the device has no icon for it, or it isn't an image
+    Missing,
+}
+
+struct Entry {
+    slot: Slot,
+    // frame it was last asked for
+    used: u64,
+}
+
+pub struct IconCache {
+    entries: HashMap<Key, Entry>,
+    queue: VecDeque<Key>,
+    loading: usize,
+    frame: u64,
+    // the fetched files, None for an app the device has no icon for
+    files: Arc<Mutex<HashMap<Key, Option<Vec<u8>>>>>,
+    tx: Sender<(Key, Option<ColorImage>)>,
+    rx: Receiver<(Key, Option<ColorImage>)>,
+}
+
+impl Default for IconCache {
+    fn default() -> Self {
+        let (tx, rx) = mpsc::channel();
+        Self {
+            entries: HashMap::new(),
+            queue: VecDeque::new(),
+            loading: 0,
+            frame: 0,
+            files: Arc::default(),
+            tx,
+            rx,
+        }
+    }
+}
+
+impl IconCache {
+    // the icon files fetched so far, as device address, app id and image
+    pub fn files(&self) -> Vec<(String, String, Vec<u8>)> {
+        let files = self.files.lock().unwrap();
+        files
+            .iter()
+            .filter_map(|((device, app_id), bytes)| {
+                Some((device.clone(), app_id.clone(), bytes.clone()?))
+            })
+            .collect()
+    }
+
+    // icon files from a backup, used in place of asking the device; ones fetched since stay
+    pub fn restore(&mut self, icons: Vec<(String, String, Vec<u8>)>) {
+        let mut files = self.files.lock().unwrap();
+        for (device, app_id, bytes) in icons {
+            files.entry((device, app_id)).or_insert(Some(bytes));
+        }
+    }
+
+    // the icon if it's ready; otherwise it's queued, to be drawn size points big
+    pub fn get(
+        &mut self,
+        ctx: &egui::Context,
+        device: &str,
+        app_id: &str,
+        size: egui::Vec2,
+    ) -> Option<TextureHandle> {
+        let key = (device.to_string(), app_id.to_string());
+        let frame = self.frame;
+        let entry = self.entries.entry(key.clone()).or_insert_with(|| {
+            let pixels = size * ctx.pixels_per_point();
+            self.queue.push_back(key);
+            Entry {
+                slot: Slot::Queued([pixels.x.ceil() as u32, pixels.y.ceil() as u32]),
+                used: frame,
+            }
+        });
+        entry.used = frame;
+        match &entry.slot {
+            Slot::Ready(texture) => Some(texture.clone()),
+            _ => None,
+        }
+    }
+
+    // once a frame: turns fetched icons into textures, starts more fetches and evicts
+    pub fn poll(&mut self, ctx: &egui::Context) {
+        self.frame += 1;
+        while let Ok((key, image)) = self.rx.try_recv() {
+            self.loading -= 1;
+            let Some(entry) = self.entries.get_mut(&key) else {
+                continue;
+            };
+            entry.slot = match image {
+                Some(image) => {
+                    let name = format!("icon-{}-{}", key.0, key.1);
+                    Slot::Ready(ctx.load_texture(name, image, TextureOptions::LINEAR))
+                }
+                None => Slot::Missing,
+            };
+        }
+
+        while self.loading < MAX_LOADING {
+            let Some(key) = self.queue.pop_front() else {
+                break;
+            };
+            let Some(entry) = self.entries.get_mut(&key) else {
+                continue;
+            };
+            // scrolled away again before its turn came
+            if entry.used + 1 < self.frame {
+                self.entries.remove(&key);
+                continue;
+            }
+            let Slot::Queued(size) = entry.slot else {
+                continue;
+            };
+            entry.slot = Slot::Loading;
+            self.loading += 1;
+            let (tx, files) = (self.tx.clone(), Arc::clone(&self.files));
+            thread::spawn(move || {
+                let cached = files.lock().unwrap().get(&key).cloned();
+                let bytes = cached.unwrap_or_else(|| {
+                    let bytes = ecp::get_app_icon(&key.0, &key.1).map(|(bytes, _)| bytes);
+                    files.lock().unwrap().insert(key.clone(), bytes.clone());
+                    bytes
+                });
+                let image = bytes.and_then(|bytes| decode(&bytes, size));
+                let _ = tx.send((key, image));
+            });
+        }
+        if self.loading > 0 || !self.queue.is_empty() {
+            ctx.request_repaint();
+        }
+
+        let ready = self
+            .entries
+            .values()
+            .filter(|entry| matches!(entry.slot, Slot::Ready(_)))
+            .count();
+        if ready > MAX_TEXTURES {
+            let mut by_age: Vec<(u64, Key)> = self
+                .entries
+                .iter()
+                .filter(|(_, entry)| matches!(entry.slot, Slot::Ready(_)))
+                .map(|(key, entry)| (entry.used, key.clone()))
+                .collect();
+            by_age.sort();
+            for (_, key) in by_age.into_iter().take(ready - MAX_TEXTURES) {
+                self.entries.remove(&key);
+            }
+        }
+    }
+}
+
+fn decode(bytes: &[u8], [width, height]: [u32; 2]) -> Option<ColorImage> {
+    let image = image::load_from_memory(bytes).ok()?;
+    let image = image.thumbnail(width.max(1), height.max(1)).into_rgba8();
+    let size = [image.width() as usize, image.height() as usize];
+    Some(ColorImage::from_rgba_unmultiplied(size, &image.into_raw()))
+}
@@ -0,0 +1,125 @@
+// "did I leave the TV on?": the --serve daemon powers a TV off once it has been on for a while
+// with nothing playing. it reads the state cache, so it costs no polling of its own
+
+use crate::macros;
+use crate::server::ServerState;
+use roku_remote::ecp;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// how often the daemon checks for idle TVs
+const TICK: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoPowerOff {
+    pub enabled: bool,
+    // hours on without playback before the TV is turned off
+    pub idle_hours: f32,
+    // group, room or address, as for macros::target_devices; None means every device
+    pub target: Option<String>,
+}
+
+impl Default for AutoPowerOff {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            idle_hours: 4.0,
+            target: None,
+        }
+    }
+}
+
+impl AutoPowerOff {
+    // six minutes to a month; NaN, which gets past clamp, is the default
+    fn idle_limit(&self) -> Duration {
+        let hours = match self.idle_hours {
+            hours if hours.is_nan() => AutoPowerOff::default().idle_hours,
+            hours => hours.clamp(0.1, 24.0 * 30.0),
+        };
+        Duration::from_secs_f32(hours * 3600.0)
+    }
+}
+
+// what was last seen on a device that's on, and since when nothing has happened on it
+struct Activity {
+    seen: (Option<String>, Option<String>),
+    since: Instant,
+}
+
+pub fn spawn(state: Arc<Mutex<ServerState>>) {
+    thread::spawn(move || {
+        let mut activity: BTreeMap<String, Activity> = BTreeMap::new();
+        loop {
+            thread::sleep(TICK);
+            let (settings, idle) = {
+                let state = state.lock().unwrap();
+                let settings = state.config.auto_power_off.clone();
+                if !settings.enabled {
+                    activity.clear();
+                    continue;
+                }
+                let devices = match &settings.target {
+                    Some(target) => macros::target_devices(&state.config, target),
+                    None => state.devices.clone(),
+                };
+                let idle = idle_devices(&state, &devices, settings.idle_limit(), &mut activity);
+                (settings, idle)
+            };
+            for device in idle {
+                println!(
+                    "Powering off {}: nothing played for {} hour(s)",
+                    device, settings.idle_hours
+                );
+                if let Err(err) = ecp::send_command(&device, "PowerOff") {
+                    eprintln!("Couldn't power off {}: {}", device, err);
+                }
+                activity.remove(&device);
+            }
+        }
+    });
+}
+
+// devices that have been on past the limit with the same app and no playback
+fn idle_devices(
+    state: &ServerState,
+    devices: &[String],
+    limit: Duration,
+    activity: &mut BTreeMap<String, Activity>,
+) -> Vec<String> {
+    let now = Instant::now();
+    let mut idle = Vec::new();
+    for device in devices {
+        let Some(current) = state.cache.get(device) else {
+            continue;
+        };
+        // off, asleep or unreachable devices start from scratch when they come back on
+        if !current.online || current.power_mode.as_deref() != Some("PowerOn") {
+            activity.remove(device);
+            continue;
+        }
+        let seen = (
+            current.active_app.as_ref().map(|app| app.id.clone()),
+            current.playback.clone(),
+        );
+        let playing = current.playback.as_deref() == Some("play");
+        match activity.get_mut(device) {
+            Some(entry) if entry.seen == seen && !playing => {
+                if now.duration_since(entry.since) >= limit {
+                    idle.push(device.clone());
+                }
+            }
+            Some(entry) => {
+                entry.seen = seen;
+                entry.since = now;
+            }
+            None => {
+                activity.insert(device.clone(), Activity { seen, since: now });
+            }
+        }
+    }
+    idle
+}
@@ -0,0 +1,207 @@
+// the keys of the Roku remote under the names ECP takes them by, so a misspelt key is caught by
+// the compiler instead of being turned down by the device. parsing goes by the name with case,
+// underscores and spaces ignored, and takes the names older versions of the remote used
+
+use crate::ecp::literal_key;
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// A key on the Roku remote, or one character typed into a text field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RokuKey {
+    Home,
+    Back,
+    Up,
+    Down,
+    Left,
+    Right,
+    Select,
+    Play,
+    Rev,
+    Fwd,
+    InstantReplay,
+    Info,
+    Search,
+    Enter,
+    Backspace,
+    FindRemote,
+    VolumeUp,
+    VolumeDown,
+    VolumeMute,
+    ChannelUp,
+    ChannelDown,
+    InputTuner,
+    InputHDMI1,
+    InputHDMI2,
+    InputHDMI3,
+    InputHDMI4,
+    InputAV1,
+    /// Toggles the power; `PowerOn` and `PowerOff` only go one way.
+    Power,
+    PowerOn,
+    PowerOff,
+    /// One character of text, sent as `Lit_` with the character percent-encoded.
+    Lit(char),
+}
+
+/// A key name `RokuKey` doesn't know.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("unknown key {0:?}")]
+pub struct UnknownKey(pub String);
+
+// every key but Lit, for parsing and listing
+const NAMED: &[RokuKey] = &[
+    RokuKey::Home,
+    RokuKey::Back,
+    RokuKey::Up,
+    RokuKey::Down,
+    RokuKey::Left,
+    RokuKey::Right,
+    RokuKey::Select,
+    RokuKey::Play,
+    RokuKey::Rev,
+    RokuKey::Fwd,
+    RokuKey::InstantReplay,
+    RokuKey::Info,
+    RokuKey::Search,
+    RokuKey::Enter,
+    RokuKey::Backspace,
+    RokuKey::FindRemote,
+    RokuKey::VolumeUp,
+    RokuKey::VolumeDown,
+    RokuKey::VolumeMute,
+    RokuKey::ChannelUp,
+    RokuKey::ChannelDown,
+    RokuKey::InputTuner,
+    RokuKey::InputHDMI1,
+    RokuKey::InputHDMI2,
+    RokuKey::InputHDMI3,
+    RokuKey::InputHDMI4,
+    RokuKey::InputAV1,
+    RokuKey::Power,
+    RokuKey::PowerOn,
+    RokuKey::PowerOff,
+];
+
+impl RokuKey {
+    /// Every key with a name of its own, that is all of them but `Lit`.
+    pub fn all() -> &'static [RokuKey] {
+        NAMED
+    }
+
+    /// The name ECP takes the key by, and just "Lit" for `Lit`; `to_string()` gives the
+    /// whole key, e.g. `Lit_a`.
+    pub fn name(self) -> &'static str {
+        match self {
+            RokuKey::Home => "Home",
+            RokuKey::Back => "Back",
+            RokuKey::Up => "Up",
+            RokuKey::Down => "Down",
+            RokuKey::Left => "Left",
+            RokuKey::Right => "Right",
+            RokuKey::Select => "Select",
+            RokuKey::Play => "Play",
+            RokuKey::Rev => "Rev",
+            RokuKey::Fwd => "Fwd",
+            RokuKey::InstantReplay => "InstantReplay",
+            RokuKey::Info => "Info",
+            RokuKey::Search => "Search",
+            RokuKey::Enter => "Enter",
+            RokuKey::Backspace => "Backspace",
+            RokuKey::FindRemote => "FindRemote",
+            RokuKey::VolumeUp => "VolumeUp",
+            RokuKey::VolumeDown => "VolumeDown",
+            RokuKey::VolumeMute => "VolumeMute",
+            RokuKey::ChannelUp => "ChannelUp",
+            RokuKey::ChannelDown => "ChannelDown",
+            RokuKey::InputTuner => "InputTuner",
+            RokuKey::InputHDMI1 => "InputHDMI1",
+            RokuKey::InputHDMI2 => "InputHDMI2",
+            RokuKey::InputHDMI3 => "InputHDMI3",
+            RokuKey::InputHDMI4 => "InputHDMI4",
+            RokuKey::InputAV1 => "InputAV1",
+            RokuKey::Power => "Power",
+            RokuKey::PowerOn => "PowerOn",
+            RokuKey::PowerOff => "PowerOff",
+            RokuKey::Lit(_) => "Lit",
+        }
+    }
+
+    /// What a button for the key says, e.g. "Channel Up".
+    pub fn label(self) -> String {
+        match self {
+            RokuKey::Rev => "Reverse".into(),
+            RokuKey::Fwd => "Forward".into(),
+            RokuKey::InstantReplay => "Replay".into(),
+            RokuKey::InputTuner => "Live TV".into(),
+            RokuKey::InputHDMI1 => "HDMI 1".into(),
+            RokuKey::InputHDMI2 => "HDMI 2".into(),
+            RokuKey::InputHDMI3 => "HDMI 3".into(),
+            RokuKey::InputHDMI4 => "HDMI 4".into(),
+            RokuKey::InputAV1 => "AV".into(),
+            RokuKey::Lit(c) => c.to_string(),
+            key => {
+                // a space before each capital after the first: "ChannelUp" -> "Channel Up"
+                let mut label = String::new();
+                for (i, c) in key.name().chars().enumerate() {
+                    if i > 0 && c.is_ascii_uppercase() {
+                        label.push(' ');
+                    }
+                    label.push(c);
+                }
+                label
+            }
+        }
+    }
+}
+
+impl fmt::Display for RokuKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RokuKey::Lit(c) => f.write_str(&literal_key(*c)),
+            key => f.write_str(key.name()),
+        }
+    }
+}
+
+impl FromStr for RokuKey {
+    type Err = UnknownKey;
+
+    fn from_str(s: &str) -> Result<RokuKey, UnknownKey> {
+        let unknown = || UnknownKey(s.to_string());
+        if let Some(text) = s.strip_prefix("Lit_") {
+            let text = url::form_urlencoded::parse(format!("c={}", text).as_bytes())
+                .next()
+                .map(|(_, c)| c.into_owned())
+                .unwrap_or_default();
+            let mut chars = text.chars();
+            return match (chars.next(), chars.next()) {
+                (Some(c), None) => Ok(RokuKey::Lit(c)),
+                _ => Err(unknown()),
+            };
+        }
+        let folded = |name: &str| {
+            name.chars()
+                .filter(|c| *c != '_' && *c != ' ')
+                .collect::<String>()
+                .to_ascii_lowercase()
+        };
+        let wanted = folded(s);
+        // what the GUI's buttons used to send
+        let renamed = match wanted.as_str() {
+            "reverse" => Some(RokuKey::Rev),
+            "forward" => Some(RokuKey::Fwd),
+            "replay" => Some(RokuKey::InstantReplay),
+            _ => None,
+        };
+        renamed
+            .or_else(|| {
+                NAMED
+                    .iter()
+                    .copied()
+                    .find(|key| folded(key.name()) == wanted)
+            })
+            .ok_or_else(unknown)
+    }
+}
@@ -0,0 +1,111 @@
+use eframe::egui::{self, Key};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// keyboard keys that can stand in for remote buttons while the window has focus
+pub const BINDABLE: &[Key] = &[
+    Key::ArrowUp,
+    Key::ArrowDown,
+    Key::ArrowLeft,
+    Key::ArrowRight,
+    Key::Enter,
+    Key::Space,
+    Key::Backspace,
+    Key::Escape,
+    Key::Tab,
+    Key::Plus,
+    Key::Equals,
+    Key::Minus,
+    Key::Home,
+    Key::End,
+    Key::PageUp,
+    Key::PageDown,
+    Key::Delete,
+    Key::Insert,
+];
+
+// remote keys bound to keyboard keys, by egui's key name; an unbound key is simply missing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub enabled: bool,
+    pub keys: BTreeMap<String, String>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let keys = [
+            (Key::ArrowUp, "Up"),
+            (Key::ArrowDown, "Down"),
+            (Key::ArrowLeft, "Left"),
+            (Key::ArrowRight, "Right"),
+            (Key::Enter, "Select"),
+            (Key::Backspace, "Back"),
+            (Key::Escape, "Home"),
+            // = is + without shift on most layouts
+            (Key::Plus, "VolumeUp"),
+            (Key::Equals, "VolumeUp"),
+            (Key::Minus, "VolumeDown"),
+            (Key::Space, "Play"),
+        ];
+        Self {
+            enabled: true,
+            keys: keys
+                .into_iter()
+                .map(|(key, cmd)| (key.name().to_string(), cmd.to_string()))
+                .collect(),
+        }
+    }
+}
+
+impl KeyBindings {
+    // every bindable key with what it sends, if anything, for the settings and the help
+    pub fn bindings(&self) -> Vec<(Key, Option<String>)> {
+        BINDABLE
+            .iter()
+            .map(|key| (*key, self.keys.get(key.name()).cloned()))
+            .collect()
+    }
+
+    pub fn set(&mut self, key: Key, cmd: Option<String>) {
+        match cmd {
+            Some(cmd) => self.keys.insert(key.name().to_string(), cmd),
+            None => self.keys.remove(key.name()),
+        };
+    }
+
+    // "↑  Up" lines for the bound keys, for a tooltip
+    pub fn help(&self) -> String {
+        self.bindings()
+            .into_iter()
+            .filter_map(|(key, cmd)| Some(format!("{}  {}", key.symbol_or_name(), cmd?)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    // the bound keys held down this frame with their remote keys, and the remote keys of taps
+    // so quick they were pressed and let go within the frame. nothing while a text field has
+    // the keyboard, and nothing with Ctrl, Alt or Cmd held so shortcuts keep working
+    pub fn keys(&self, ctx: &egui::Context) -> (Vec<(Key, String)>, Vec<String>) {
+        if !self.enabled || ctx.wants_keyboard_input() {
+            return Default::default();
+        }
+        ctx.input(|i| {
+            if i.modifiers.command || i.modifiers.ctrl || i.modifiers.alt {
+                return Default::default();
+            }
+            let (mut down, mut tapped) = (Vec::new(), Vec::new());
+            for (key, cmd) in self.bindings() {
+                let Some(cmd) = cmd else {
+                    continue;
+                };
+                if i.keys_down.contains(&key) {
+                    down.push((key, cmd));
+                } else if i.key_pressed(key) {
+                    tapped.push(cmd);
+                }
+            }
+            (down, tapped)
+        })
+    }
+}
@@ -0,0 +1,101 @@
+use roku_remote::RokuKey;
+use serde::{Deserialize, Serialize};
+
+// rows are this many buttons wide, blanks included
+pub const COLUMNS: usize = 3;
+
+// the remote's buttons, row by row; None leaves a cell blank
+pub const DEFAULT: &[&[Option<RokuKey>]] = {
+    use RokuKey::*;
+    &[
+        &[Some(Power), Some(PowerOn), Some(PowerOff)],
+        &[Some(Home), Some(Info), Some(Back)],
+        &[None, None, None],
+        &[None, Some(Up), None],
+        &[Some(Left), Some(Select), Some(Right)],
+        &[None, Some(Down), None],
+        &[None, None, Some(Play)],
+        &[Some(ChannelUp), Some(ChannelDown), Some(Search)],
+        &[Some(Enter), Some(Backspace), Some(FindRemote)],
+        &[Some(InstantReplay), Some(Rev), Some(Fwd)],
+    ]
+};
+
+// the remote's face as arranged in the layout editor. no rows is the built-in layout, so a
+// config that was never edited picks up changes to it
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RemoteLayout {
+    pub rows: Vec<Vec<Option<Button>>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Button {
+    // the ECP key it sends, e.g. "Home" or "Lit_a"
+    pub key: String,
+    // what it says; the key's own label when empty
+    #[serde(default)]
+    pub label: String,
+}
+
+impl Button {
+    pub fn new(key: RokuKey) -> Button {
+        Button {
+            key: key.to_string(),
+            label: String::new(),
+        }
+    }
+
+    pub fn roku_key(&self) -> Option<RokuKey> {
+        self.key.parse().ok()
+    }
+
+    pub fn label(&self) -> String {
+        if !self.label.trim().is_empty() {
+            return self.label.clone();
+        }
+        self.roku_key()
+            .map(RokuKey::label)
+            .unwrap_or_else(|| self.key.clone())
+    }
+}
+
+impl RemoteLayout {
+    pub fn is_default(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    // what the remote shows, every row padded out to the full width
+    pub fn rows(&self) -> Vec<Vec<Option<Button>>> {
+        let mut rows: Vec<Vec<Option<Button>>> = if self.is_default() {
+            DEFAULT
+                .iter()
+                .map(|row| row.iter().map(|key| key.map(Button::new)).collect())
+                .collect()
+        } else {
+            self.rows.clone()
+        };
+        for row in &mut rows {
+            row.resize(COLUMNS, None);
+        }
+        rows
+    }
+
+    // the rows to change, copied out of the built-in layout on the first edit
+    pub fn rows_mut(&mut self) -> &mut Vec<Vec<Option<Button>>> {
+        if self.is_default() || self.rows.iter().any(|row| row.len() != COLUMNS) {
+            self.rows = self.rows();
+        }
+        &mut self.rows
+    }
+
+    pub fn swap(&mut self, a: (usize, usize), b: (usize, usize)) {
+        let rows = self.rows_mut();
+        if a.0 >= rows.len() || b.0 >= rows.len() || a.1 >= COLUMNS || b.1 >= COLUMNS {
+            return;
+        }
+        let taken = rows[a.0][a.1].take();
+        rows[a.0][a.1] = rows[b.0][b.1].take();
+        rows[b.0][b.1] = taken;
+    }
+}
@@ -0,0 +1,28 @@
+// the parts of the remote that work without the GUI, for use from other programs
+
+// polling needs threads, which wasm32 doesn't have
+#[cfg(not(target_arch = "wasm32"))]
+pub mod client;
+pub mod ecp;
+pub mod error;
+pub mod events;
+// the blocking (or, on wasm32, fetch) http the ECP calls use, for requests of your own
+pub mod http;
+// the remote's keys as an enum, for callers that know at compile time what they send
+pub mod key;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod pacing;
+// plain http or, for devices in limited mode, the ECP-2 WebSocket
+#[cfg(not(target_arch = "wasm32"))]
+pub mod transport;
+// udp sockets, which wasm32 doesn't have either
+#[cfg(not(target_arch = "wasm32"))]
+pub mod wol;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use client::{Client, Subscription};
+pub use error::RokuError;
+pub use events::{DeviceEvent, DeviceState, EventKind};
+pub use key::{RokuKey, UnknownKey};
+#[cfg(not(target_arch = "wasm32"))]
+pub use pacing::{Failure, PaceStatus, Pacer, Sent};
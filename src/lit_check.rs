@@ -0,0 +1,88 @@
+// some channels silently drop Lit_ keypresses; compare the channel's ui before and after
+// typing so the user can be offered arrow+Select typing instead
+
+use crate::ecp::{get_ui_snapshot, send_text};
+use roku_remote::http;
+use roku_remote::RokuError;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+// time for the channel to redraw after the last character
+const SETTLE: Duration = Duration::from_millis(600);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LitCheck {
+    Accepted,
+    Ignored,
+    // the device offers no way to look at its ui, so there is nothing to compare
+    Unknown,
+    // a press didn't get through; the rest of the text wasn't sent
+    Failed(RokuError),
+    // stopped from the GUI partway through, after this many characters
+    Cancelled(usize),
+}
+
+// text being typed on a background thread, a character at a time
+pub struct Typing {
+    pub total: usize,
+    sent: Arc<AtomicUsize>,
+    cancel: Arc<AtomicBool>,
+    rx: Receiver<LitCheck>,
+}
+
+impl Typing {
+    // characters gone out so far
+    pub fn sent(&self) -> usize {
+        self.sent.load(Ordering::Relaxed)
+    }
+
+    // stops before the next character
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    pub fn try_recv(&self) -> Result<LitCheck, TryRecvError> {
+        self.rx.try_recv()
+    }
+}
+
+// types text with Lit_ presses on a background thread and reports whether anything changed
+pub fn send_and_check(ip: String, text: String) -> Typing {
+    let (tx, rx) = mpsc::channel();
+    let sent = Arc::new(AtomicUsize::new(0));
+    let cancel = Arc::new(AtomicBool::new(false));
+    let typing = Typing {
+        total: text.chars().count(),
+        sent: Arc::clone(&sent),
+        cancel: Arc::clone(&cancel),
+        rx,
+    };
+    thread::spawn(move || {
+        let before = get_ui_snapshot(&ip);
+        let progress = |count| {
+            sent.store(count, Ordering::Relaxed);
+            !cancel.load(Ordering::Relaxed)
+        };
+        if let Err(err) = send_text(&ip, &text, http::typing_delay(), progress) {
+            tx.send(LitCheck::Failed(err)).ok();
+            return;
+        }
+        let count = sent.load(Ordering::Relaxed);
+        if count < text.chars().count() {
+            tx.send(LitCheck::Cancelled(count)).ok();
+            return;
+        }
+        thread::sleep(SETTLE);
+        let after = get_ui_snapshot(&ip);
+        let result = match (before, after) {
+            (Some(before), Some(after)) if before == after => LitCheck::Ignored,
+            (Some(_), Some(_)) => LitCheck::Accepted,
+            _ => LitCheck::Unknown,
+        };
+        tx.send(result).ok();
+    });
+    typing
+}
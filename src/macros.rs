@@ -0,0 +1,394 @@
+// macros: named lists of steps saved in the config, run from the GUI, the fleet panel or the cli
+
+use crate::config::Config;
+use crate::ecp::{self, Ecp};
+use roku_remote::transport::{Auto, RokuTransport};
+use roku_remote::RokuKey;
+use serde::{Deserialize, Serialize};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// how long a launch step waits for the channel to come up before failing
+const LAUNCH_TIMEOUT: Duration = Duration::from_secs(20);
+// pause between steps so the device can keep up
+const STEP_DELAY: Duration = Duration::from_millis(300);
+// with verification on, how long a keypress gets to show on screen
+const VERIFY_TIMEOUT: Duration = Duration::from_secs(3);
+const VERIFY_POLL: Duration = Duration::from_millis(250);
+// a pause between recorded steps shorter than this is just the time between clicks
+const RECORDED_PAUSE: Duration = Duration::from_millis(800);
+// the longest a wait step may pause, a day
+const MAX_WAIT: Duration = Duration::from_secs(24 * 60 * 60);
+
+pub const MACRO_HELP: &str = "One step per line:\n\
+    key <Key>        press a remote key, e.g. key Home\n\
+    text <text>      type text with Lit_ presses\n\
+    launch <app id>  launch a channel and wait for it to come up\n\
+    store <app id>   open the channel's store page, e.g. to install it\n\
+    wait <seconds>   pause\n\
+    Lines starting with # are comments.";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    Key(String),
+    Text(String),
+    Launch(String),
+    Store(String),
+    Wait(Duration),
+}
+
+impl Step {
+    pub fn label(&self) -> String {
+        match self {
+            Step::Key(key) => format!("key {}", key),
+            Step::Text(text) => format!("text {}", text),
+            Step::Launch(app) => format!("launch {}", app),
+            Step::Store(app) => format!("store {}", app),
+            Step::Wait(d) => format!("wait {}", d.as_secs_f32()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MacroSettings {
+    // check that every press was taken, and showed where ECP can tell, before the next step,
+    // so a dropped press stops the run instead of sending the rest to the wrong screen
+    pub verify: bool,
+}
+
+// parses a macro, reporting the first bad line by number
+pub fn parse(script: &str) -> Result<Vec<Step>, String> {
+    let mut steps = Vec::new();
+    for (n, line) in script.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (command, arg) = line.split_once(' ').unwrap_or((line, ""));
+        let arg = arg.trim();
+        let bad = |why: &str| format!("line {}: {}", n + 1, why);
+        if arg.is_empty() {
+            return Err(bad("missing argument"));
+        }
+        steps.push(match command {
+            // written the way ECP spells it, whatever case the script used
+            "key" => match arg.parse::<RokuKey>() {
+                Ok(key) => Step::Key(key.to_string()),
+                Err(err) => return Err(bad(&err.to_string())),
+            },
+            "text" => Step::Text(arg.to_string()),
+            "launch" => Step::Launch(arg.to_string()),
+            "store" => Step::Store(arg.to_string()),
+            "wait" => {
+                let seconds = arg
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|s| s.is_finite() && *s >= 0.0)
+                    .ok_or_else(|| bad("wait needs a number of seconds"))?;
+                match Duration::try_from_secs_f32(seconds) {
+                    Ok(d) if d <= MAX_WAIT => Step::Wait(d),
+                    _ => return Err(bad("wait too long")),
+                }
+            }
+            other => return Err(bad(&format!("unknown step \"{}\"", other))),
+        });
+    }
+    Ok(steps)
+}
+
+// runs the steps on one device; on_step hears the index of each step as it starts
+pub fn run(
+    ip: &str,
+    steps: &[Step],
+    verify: bool,
+    on_step: impl FnMut(usize),
+) -> Result<(), String> {
+    run_via(ecp::via(&Auto), ip, steps, verify, on_step)
+}
+
+// run over a transport of the caller's, for tests to see what a macro sends
+pub fn run_via<T: RokuTransport + ?Sized>(
+    ecp: Ecp<'_, T>,
+    ip: &str,
+    steps: &[Step],
+    verify: bool,
+    mut on_step: impl FnMut(usize),
+) -> Result<(), String> {
+    if ecp.get_power_mode(ip).is_none() {
+        return Err("device not reachable".into());
+    }
+    for (i, step) in steps.iter().enumerate() {
+        on_step(i);
+        let result = match step {
+            Step::Key(key) if verify => press_verified(ecp, ip, key),
+            Step::Key(key) => ecp.send_command(ip, key).map_err(|err| err.to_string()),
+            Step::Text(text) if verify => type_verified(ecp, ip, text),
+            Step::Text(text) => ecp.send_key(ip, text).map_err(|err| err.to_string()),
+            Step::Launch(app) => match ecp.launch_and_wait(ip, app, LAUNCH_TIMEOUT) {
+                Ok(true) => Ok(()),
+                Ok(false) => Err("channel didn't come up".to_string()),
+                Err(err) => Err(err.to_string()),
+            },
+            Step::Store(app) => ecp.open_store_page(ip, app).map_err(|err| err.to_string()),
+            Step::Wait(d) => {
+                thread::sleep(*d);
+                Ok(())
+            }
+        };
+        // the first step the device turns down ends the run, so it isn't reported as done
+        result.map_err(|err| format!("step {} ({}): {}", i + 1, step.label(), err))?;
+        thread::sleep(STEP_DELAY);
+    }
+    Ok(())
+}
+
+// where a key's press shows over ECP, which is what press_verified waits on
+enum Shows {
+    // the home screen comes up in active-app
+    Home,
+    // play and pause flip the media player between playing and paused
+    Playback,
+    // focus moves or a screen opens, which only app-ui sees
+    Screen,
+    // volume, power, seeking and the like leave nothing to check; the accepted press is it
+    Nothing,
+}
+
+fn shows(key: &str) -> Shows {
+    use RokuKey::*;
+    match key.parse() {
+        Ok(Home) => Shows::Home,
+        Ok(Play) => Shows::Playback,
+        Ok(Up | Down | Left | Right | Select | Back | Info | Backspace | Enter) => Shows::Screen,
+        _ => Shows::Nothing,
+    }
+}
+
+// the press has to be accepted, then show where the key shows. with nothing to look at, like
+// app-ui outside developer mode or play with nothing loaded, the accepted press is all there is
+fn press_verified<T: RokuTransport + ?Sized>(
+    ecp: Ecp<'_, T>,
+    ip: &str,
+    key: &str,
+) -> Result<(), String> {
+    let shows = shows(key);
+    let before = match shows {
+        Shows::Playback => ecp
+            .get_playback_state(ip)
+            .filter(|state| state == "play" || state == "pause"),
+        Shows::Screen => ecp.get_app_ui(ip),
+        Shows::Home | Shows::Nothing => None,
+    };
+    ecp.send_command(ip, key).map_err(|err| err.to_string())?;
+    match shows {
+        Shows::Home => {
+            if !wait_for(|| ecp.get_active_app(ip).is_some_and(|app| app.id.is_empty())) {
+                return Err("the home screen didn't come up".into());
+            }
+            Ok(())
+        }
+        Shows::Playback => changed_from(before, || ecp.get_playback_state(ip))
+            .map_err(|_| "playback didn't start or pause".to_string()),
+        Shows::Screen => changed_from(before, || ecp.get_app_ui(ip)),
+        Shows::Nothing => Ok(()),
+    }
+}
+
+fn type_verified<T: RokuTransport + ?Sized>(
+    ecp: Ecp<'_, T>,
+    ip: &str,
+    text: &str,
+) -> Result<(), String> {
+    let before = ecp.get_ui_snapshot(ip);
+    ecp.send_key(ip, text).map_err(|err| err.to_string())?;
+    changed_from(before, || ecp.get_ui_snapshot(ip))
+}
+
+// waits for what now() reads to differ from before; nothing before means nothing to compare
+fn changed_from(
+    before: Option<String>,
+    mut now: impl FnMut() -> Option<String>,
+) -> Result<(), String> {
+    let Some(before) = before else {
+        return Ok(());
+    };
+    if !wait_for(|| now().is_some_and(|now| now != before)) {
+        return Err("nothing changed on screen".into());
+    }
+    Ok(())
+}
+
+fn wait_for(mut check: impl FnMut() -> bool) -> bool {
+    let started = Instant::now();
+    loop {
+        if check() {
+            return true;
+        }
+        if started.elapsed() > VERIFY_TIMEOUT {
+            return false;
+        }
+        thread::sleep(VERIFY_POLL);
+    }
+}
+
+// a macro being recorded from the remote: each press, launch and typed text becomes a step,
+// with a wait step for a pause long enough to have been waiting on the device
+#[derive(Debug, Default)]
+pub struct Recorder {
+    pub steps: Vec<Step>,
+    last: Option<Instant>,
+}
+
+impl Recorder {
+    pub fn record(&mut self, step: Step) {
+        if let Some(last) = self.last {
+            // playback already leaves STEP_DELAY between steps
+            let pause = last.elapsed().saturating_sub(STEP_DELAY);
+            if pause >= RECORDED_PAUSE {
+                // to the tenth of a second, so the script reads "wait 2.5"
+                let tenths = (pause.as_millis() as u64 + 50) / 100;
+                self.steps
+                    .push(Step::Wait(Duration::from_millis(tenths * 100)));
+            }
+        }
+        self.last = Some(Instant::now());
+        self.steps.push(step);
+    }
+
+    // the steps in the form parse reads, for saving with the other macros
+    pub fn script(&self) -> String {
+        self.steps.iter().map(|step| step.label() + "\n").collect()
+    }
+}
+
+// devices a macro target names: a group, a room from the device notes, or else an address
+pub fn target_devices(config: &Config, target: &str) -> Vec<String> {
+    if let Some(members) = config.groups.get(target) {
+        return members.clone();
+    }
+    let in_room: Vec<String> = config
+        .devices
+        .iter()
+        .filter(|(_, meta)| meta.room.eq_ignore_ascii_case(target))
+        .map(|(addr, _)| addr.clone())
+        .collect();
+    if !in_room.is_empty() {
+        return in_room;
+    }
+    vec![ecp::device_addr(target)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, run_via, Step};
+    use crate::ecp;
+    use roku_remote::transport::Mock;
+    use std::thread;
+    use std::time::Duration;
+
+    const DEVICE: &str = "192.0.2.7:8060";
+
+    fn powered_on() -> Mock {
+        let mock = Mock::new();
+        mock.reply(
+            "query/device-info",
+            "<device-info><power-mode>PowerOn</power-mode></device-info>",
+        );
+        mock
+    }
+
+    #[test]
+    fn keys_are_checked_when_parsing() {
+        let steps = parse("key home\nkey Lit_a\nkey Lit_%26").unwrap();
+        assert_eq!(
+            steps,
+            [
+                Step::Key("Home".into()),
+                Step::Key("Lit_a".into()),
+                Step::Key("Lit_%26".into())
+            ]
+        );
+        assert_eq!(
+            parse("# warm up\nkey Home\nkey Teleport").unwrap_err(),
+            "line 3: unknown key \"Teleport\""
+        );
+        assert!(parse("key Lit_ab").unwrap_err().starts_with("line 1: "));
+    }
+
+    #[test]
+    fn waits_are_bounded() {
+        assert_eq!(
+            parse("wait 1.5").unwrap(),
+            [Step::Wait(Duration::from_millis(1500))]
+        );
+        assert_eq!(
+            parse("key Home\nwait 1e20").unwrap_err(),
+            "line 2: wait too long"
+        );
+        assert_eq!(parse("wait 90000").unwrap_err(), "line 1: wait too long");
+        assert_eq!(
+            parse("wait -1").unwrap_err(),
+            "line 1: wait needs a number of seconds"
+        );
+    }
+
+    #[test]
+    fn every_step_is_sent_in_order() {
+        let mock = powered_on();
+        let steps = parse("key Home\ntext ab\nstore 12").unwrap();
+        run_via(ecp::via(&mock), DEVICE, &steps, false, |_| {}).unwrap();
+        assert_eq!(
+            mock.commands(),
+            [
+                "keypress/Home",
+                "keypress/Lit_a",
+                "keypress/Lit_b",
+                "install/12"
+            ]
+        );
+    }
+
+    #[test]
+    fn a_refused_step_stops_the_run_and_is_reported() {
+        let mock = powered_on();
+        mock.reply_with("keypress/Lit_b", 503, "");
+        let steps = parse("key Home\ntext ab\nkey Select").unwrap();
+        let err = run_via(ecp::via(&mock), DEVICE, &steps, false, |_| {}).unwrap_err();
+        assert!(err.starts_with("step 2 (text ab)"), "{}", err);
+        assert_eq!(
+            mock.commands(),
+            ["keypress/Home", "keypress/Lit_a", "keypress/Lit_b"]
+        );
+    }
+
+    #[test]
+    fn keys_with_nothing_to_show_pass_on_their_http_result() {
+        // textedit-state answers the same whatever the arrows do; it mustn't be what's watched
+        let mock = powered_on();
+        mock.reply("query/textedit-state", "<textedit-state/>");
+        let steps = parse("key VolumeUp\nkey Up\nkey Select").unwrap();
+        run_via(ecp::via(&mock), DEVICE, &steps, true, |_| {}).unwrap();
+
+        mock.reply_with("keypress/VolumeUp", 503, "");
+        let err = run_via(ecp::via(&mock), DEVICE, &steps, true, |_| {}).unwrap_err();
+        assert!(err.starts_with("step 1 (key VolumeUp)"), "{}", err);
+    }
+
+    #[test]
+    fn a_press_app_ui_can_see_has_to_change_it() {
+        let mock = powered_on();
+        mock.reply("query/app-ui", "<app-ui><focused>Movies</focused></app-ui>");
+        let steps = parse("key Down").unwrap();
+        let err = run_via(ecp::via(&mock), DEVICE, &steps, true, |_| {}).unwrap_err();
+        assert!(err.ends_with("nothing changed on screen"), "{}", err);
+
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                thread::sleep(Duration::from_millis(300));
+                mock.reply("query/app-ui", "<app-ui><focused>Shows</focused></app-ui>");
+            });
+            run_via(ecp::via(&mock), DEVICE, &steps, true, |_| {}).unwrap();
+        });
+    }
+}
@@ -1,10 +1,88 @@
 use eframe::{egui, App as EApp, Frame};
-use html_escape::decode_html_entities;
-use regex::Regex;
-use reqwest::blocking::Client;
-use std::net::{Ipv4Addr, SocketAddrV4, UdpSocket};
-use std::time::Duration;
-use url::Url;
+use roku_remote::ecp;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+mod backup;
+mod bulk_install;
+mod cast;
+mod cli;
+mod config;
+mod console;
+mod continue_watching;
+mod demo;
+mod dev_installer;
+mod fleet;
+mod gallery;
+mod global_hotkeys;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod history;
+mod hold;
+mod icons;
+mod idle_off;
+mod key_bindings;
+mod layout;
+mod lit_check;
+mod macros;
+mod mouse_buttons;
+mod mqtt;
+mod now_playing;
+mod osk;
+mod palette;
+mod reachability;
+mod schedule;
+mod scripting;
+mod scroll;
+mod server;
+mod session;
+mod sse;
+mod state_cache;
+mod tabs;
+mod theme;
+mod timers;
+mod tmdb;
+mod tray;
+mod tui;
+mod volume;
+mod watchlist;
+mod worker;
+
+use backup::Backup;
+use bulk_install::BulkInstall;
+use clap::Parser;
+use config::{ApiToken, Config, ConfigStore, Role};
+use console::ConsoleView;
+use continue_watching::ContinueWatching;
+use ecp::{
+    get_apps, launch_app, launch_content, AppEntry, DeviceInfo, Discovered, KeyAction,
+    TvActiveChannel, TvChannel,
+};
+use fleet::{FleetRun, Progress};
+use gallery::Gallery;
+use global_hotkeys::GlobalHotkeys;
+use history::History;
+use hold::{Holds, Source};
+use icons::IconCache;
+use lit_check::LitCheck;
+use now_playing::NowPlayingWatcher;
+use palette::{
+    CommandPalette, PaletteAction, PaletteEntry, PALETTE_SHORTCUT, PALETTE_SHORTCUT_ALT,
+};
+use reachability::Reachability;
+use roku_remote::http::{self, dry_run};
+use roku_remote::{transport, wol, Pacer, RokuKey};
+use scripting::ScriptRun;
+use scroll::{ScrollZones, Zone};
+use session::Session;
+use tabs::{Tab, Tabs};
+use timers::Timers;
+use tmdb::ContentSearch;
+use tray::Tray;
+use volume::VolumeRepeat;
+use watchlist::DeepLink;
+use worker::{Reply, Worker};
 
 #[derive(Default)]
 struct RokuRemoteApp {
@@ -14,112 +92,4428 @@ struct RokuRemoteApp {
     selected_app: Option<String>,
     last_msg: String,
     text_input: String,
+    palette: CommandPalette,
+    config: Config,
+    store: ConfigStore,
+    config_dirty: bool,
+    sync_folder_input: String,
+    last_sync_check: Option<Instant>,
+    restore_path: String,
+    pending_restore: Option<Backup>,
+    new_token_name: String,
+    new_token_role: Role,
+    new_token_devices: Vec<String>,
+    scroll_zones: ScrollZones,
+    // the volume rocker's deflection while it's dragged, -1 to 1, and the presses it repeats
+    volume_rocker: f32,
+    volume_repeat: VolumeRepeat,
+    // whether each device was last muted from here; ECP can't ask the device
+    muted: HashMap<String, bool>,
+    osk_mode: bool,
+    osk_layout: usize,
+    // the device and text being typed with Lit_ presses, how the text is named in messages and
+    // the history (not at all when it was pasted, e.g. a password), and the pending check of
+    // whether it arrived
+    lit_check: Option<(String, String, String, lit_check::Typing)>,
+    // text the channel ignored, offered for retyping with arrows + Select
+    lit_fallback: Option<String>,
+    fleet_script_name: String,
+    fleet_script: String,
+    fleet_devices: Vec<String>,
+    fleet_run: Option<FleetRun>,
+    fleet_running: bool,
+    // a saved macro running from the Macros panel, by name
+    macro_run: Option<(String, FleetRun)>,
+    // what the remote has sent since Record was clicked, and the name to save it under
+    recorder: Option<macros::Recorder>,
+    recording_name: String,
+    // the Rhai script run last from the Scripts panel, by name, kept after it ends for its
+    // output; and the form for adding one
+    script_run: Option<(String, ScriptRun)>,
+    new_script_name: String,
+    new_script_path: String,
+    // devices a developer-mode screenshot is being taken of, and ones a channel zip is being
+    // installed on or deleted from; and the zip typed into the Developer panel
+    capturing: HashSet<String>,
+    sideloading: HashSet<String>,
+    sideload_path: String,
+    // the file the Play on Roku panel is serving to a device, and the path typed there
+    cast: Option<cast::MediaServer>,
+    cast_path: String,
+    // keys and macros set to go off later, and the form for setting another: what, then in
+    // how many minutes or at what time of day
+    timers: Timers,
+    timer_action: Option<schedule::Action>,
+    timer_minutes: u32,
+    timer_at: String,
+    timer_at_clock: bool,
+    now_playing: NowPlayingWatcher,
+    continue_watching: ContinueWatching,
+    tray: Tray,
+    global_hotkeys: GlobalHotkeys,
+    // the system-wide shortcut being added in the settings, and the remote key it's for
+    new_hotkey: String,
+    new_hotkey_cmd: String,
+    new_link_name: String,
+    new_link_folder: String,
+    new_link_content_id: String,
+    new_link_media_type: String,
+    new_link_app: Option<String>,
+    // index into watchlist::PRESETS; None for a custom link
+    new_link_preset: Option<usize>,
+    content_search: ContentSearch,
+    // the device's own search: what to look for, whether it's an exact title and which
+    // channel to prefer
+    roku_search: ecp::SearchQuery,
+    roku_search_exact: bool,
+    // each Roku TV's antenna/cable lineup, None while it's being fetched, and what's tuned
+    tv_channels: HashMap<String, Option<Vec<TvChannel>>>,
+    tv_active: HashMap<String, TvActiveChannel>,
+    tv_channel_input: String,
+    show_hidden_channels: bool,
+    tmdb_query: String,
+    tmdb_results: Vec<tmdb::Title>,
+    // the title picked from the results and the installed channels that carry it
+    tmdb_channels: Option<(tmdb::Title, Vec<AppEntry>)>,
+    gallery: Gallery,
+    console: ConsoleView,
+    // every keypress from the remote goes through here
+    pacer: Pacer,
+    // and everything sent is logged here
+    history: History,
+    // remote buttons and keys being held, and the on-screen one the mouse is holding down now
+    holds: Holds,
+    pointer_held: Option<String>,
+    // clicked between two frames, too quickly to have been seen held down
+    pointer_tapped: Option<String>,
+    // the remote's buttons are being rearranged, and the cell picked to change
+    editing_layout: bool,
+    layout_cell: Option<(usize, usize)>,
+    new_tag: String,
+    new_group: String,
+    group_filter: Option<String>,
+    broadcast_to_group: bool,
+    reachability: Reachability,
+    // when background discovery and the online pings last ran; set on the first frame
+    last_rediscovery: Option<Instant>,
+    last_ping: Option<Instant>,
+    // discovery came back empty and the saved devices are being probed instead
+    trying_saved: bool,
+    icons: IconCache,
+    bulk_list_path: String,
+    // channel-store ids typed in, as an alternative to a list file
+    install_ids: String,
+    // the devices to install on; the selected device when none are ticked
+    install_devices: Vec<String>,
+    // one run per device, side by side
+    bulk_installs: Vec<BulkInstall>,
+    bulk_installing: bool,
+    // the two devices being compared and how their channels differ
+    lineup_source: Option<String>,
+    lineup_target: Option<String>,
+    lineup_diff: Option<bulk_install::LineupDiff>,
+    // network calls that would otherwise hold up a frame
+    worker: Worker,
+    discovering: bool,
+    // name, model and so on for each device that has answered device-info
+    device_info: HashMap<String, DeviceInfo>,
+    // an address typed in for a device discovery can't see, e.g. on another VLAN
+    manual_addr: String,
+    adding_device: bool,
+    // MACs to wake devices by, and the devices woken that haven't come back yet
+    macs: BTreeMap<String, String>,
+    waking: HashSet<String>,
+    // serial numbers by address, for following a device to a new address
+    serials: BTreeMap<String, String>,
+    // devices kept open to switch between, with the state of those not in front
+    tabs: Tabs,
+    // as last saved, and since when the live state has differed from it
+    session: Session,
+    session_changed: Option<Instant>,
+}
+
+const SYNC_POLL_INTERVAL: Duration = Duration::from_secs(5);
+// the session is written once it has stopped changing for this long, e.g. after a window drag
+const SESSION_SAVE_DELAY: Duration = Duration::from_secs(2);
+// a TV out of deep standby takes a while to bring its network and ECP back up
+const WAKE_TIMEOUT: Duration = Duration::from_secs(40);
+const WAKE_POLL: Duration = Duration::from_secs(2);
+// the tuner takes a moment to report the channel it was just sent to
+const TUNE_SETTLE: Duration = Duration::from_millis(1500);
+// channel icons in the app grid, at the 4:3 of Roku's artwork, with room for the name below
+const APP_ICON_SIZE: egui::Vec2 = egui::vec2(80.0, 60.0);
+const APP_TILE_SIZE: egui::Vec2 = egui::vec2(92.0, 86.0);
+// about three rows of tiles before the grid scrolls
+const APP_GRID_HEIGHT: f32 = 280.0;
+
+// Roku TV inputs: the key that switches to each and the input's id among the apps, which
+// carries the name it was given in the TV's settings; otherwise the key's label is the name
+const TV_INPUTS: &[(RokuKey, &str)] = &[
+    (RokuKey::InputTuner, "tvinput.dtv"),
+    (RokuKey::InputHDMI1, "tvinput.hdmi1"),
+    (RokuKey::InputHDMI2, "tvinput.hdmi2"),
+    (RokuKey::InputHDMI3, "tvinput.hdmi3"),
+    (RokuKey::InputHDMI4, "tvinput.hdmi4"),
+    (RokuKey::InputAV1, "tvinput.cvbs"),
+];
+// drawn as the volume control below the grid rather than as buttons in it
+const VOLUME_KEYS: &[RokuKey] = &[RokuKey::VolumeUp, RokuKey::VolumeDown, RokuKey::VolumeMute];
+// the names of every key in the built-in remote layout, skipping the blank spacer cells
+fn remote_keys() -> Vec<&'static str> {
+    layout::DEFAULT
+        .iter()
+        .flat_map(|row| row.iter().flatten())
+        .chain(VOLUME_KEYS)
+        .map(|key| key.name())
+        .collect()
+}
+
+// "Select", or "Right keydown" for one half of a hold
+fn key_label(key: &str, action: KeyAction) -> String {
+    match action {
+        KeyAction::Press => key.to_string(),
+        action => format!("{} {}", key, action.endpoint()),
+    }
+}
+
+// app
+fn main() -> Result<(), eframe::Error> {
+    let args = cli::Cli::parse();
+    dry_run::set_enabled(args.dry_run);
+    // the terminal remote shows the commands itself, printing them would garble its screen
+    if !matches!(args.command, None | Some(cli::Command::Tui { .. })) || args.serve.is_some() {
+        dry_run::on_record(Some(|line| eprintln!("dry run: {}", line)));
+    }
+    if let Some(command) = args.command {
+        http::configure(&ConfigStore::open().1.requests);
+        if let Err(err) = cli::run(command) {
+            eprintln!("error: {}", err);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if let Some(addr) = args.serve {
+        if let Err(err) = server::serve(&addr, args.grpc.as_deref()) {
+            eprintln!("Server failed: {}", err);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let session = Session::load();
+    let mut native_options = eframe::NativeOptions {
+        // so the desktop's theme is known for the Follow system setting, wherever it's told
+        follow_system_theme: true,
+        ..Default::default()
+    };
+    if let Some(window) = session.window {
+        native_options.viewport = native_options.viewport.with_inner_size(window.size);
+        if let Some(position) = window.position {
+            native_options.viewport = native_options.viewport.with_position(position);
+        }
+    }
+    eframe::run_native(
+        now_playing::APP_TITLE,
+        native_options,
+        Box::new(|_cc| Box::new(RokuRemoteApp::new(session))),
+    )
 }
 
-#[derive(Debug, Clone)]
-struct AppEntry {
-    id: String,
-    name: String,
-}
+impl RokuRemoteApp {
+    // picks up where the last run left off: the known devices are listed straight away and the
+    // last one is selected again, without waiting on a discovery
+    fn new(session: Session) -> Self {
+        let (store, config) = ConfigStore::open();
+        http::configure(&config.requests);
+        let mut app = Self {
+            sync_folder_input: store
+                .sync
+                .folder
+                .as_ref()
+                .map(|folder| folder.display().to_string())
+                .unwrap_or_default(),
+            store,
+            config,
+            continue_watching: ContinueWatching::load(),
+            gallery: Gallery::load(),
+            session: session.clone(),
+            timer_minutes: 45,
+            ..Default::default()
+        };
+        app.take_session(&session, session.last_device.as_ref());
+        if let Some(device) = session
+            .last_device
+            .filter(|device| app.devices.contains(device))
+        {
+            app.select_device(device);
+            app.selected_app = session.last_app;
+        }
+        if let Some(err) = &app.store.load_error {
+            app.last_msg = format!("Settings weren't loaded: {}", err);
+        }
+        app
+    }
+
+    // a saved session on top of what's already known: its devices, MACs and serials are added,
+    // its tabs opened behind the device in front, and its preferences taken as they are
+    fn take_session(&mut self, session: &Session, front: Option<&String>) {
+        self.osk_mode = session.osk_mode;
+        self.osk_layout = session.osk_layout.min(osk::LAYOUTS.len() - 1);
+        self.broadcast_to_group = session.broadcast_to_group;
+        self.macs.extend(session.macs.clone());
+        self.serials.extend(session.serials.clone());
+        let filter = session.group_filter.clone();
+        self.group_filter = filter.filter(|group| self.config.groups.contains_key(group));
+        self.add_known_devices(session.devices.clone());
+        for device in session
+            .tabs
+            .iter()
+            .filter(|device| self.devices.contains(device))
+        {
+            if self.tabs.is_open(device) {
+                continue;
+            }
+            self.tabs.open(device);
+            if front != Some(device) {
+                self.tabs
+                    .waiting
+                    .insert(device.clone(), Tab::watching(device));
+                self.fetch_apps(device);
+            }
+        }
+    }
+
+    // lists these and the config's devices along with the ones already there, and asks them all
+    // for device-info again
+    fn add_known_devices(&mut self, devices: Vec<String>) {
+        self.devices.extend(devices);
+        self.devices.extend(self.config.devices.keys().cloned());
+        self.devices.sort();
+        self.devices.dedup();
+        self.reachability.probe(&self.devices);
+        for device in &self.devices {
+            self.fetch_device_info(device);
+        }
+    }
+
+    // the session as things stand; the demo device gets a new port every run, so it's left out
+    fn current_session(&self, ctx: &egui::Context) -> Session {
+        let window = ctx.input(|i| {
+            let viewport = i.viewport();
+            let size = viewport.inner_rect?.size();
+            Some(session::Window {
+                size: [size.x, size.y],
+                position: viewport.outer_rect.map(|rect| [rect.min.x, rect.min.y]),
+            })
+        });
+        Session {
+            devices: self
+                .devices
+                .iter()
+                .filter(|device| !demo::is_demo(device))
+                .cloned()
+                .collect(),
+            last_device: self
+                .selected_device
+                .clone()
+                .filter(|device| !demo::is_demo(device)),
+            last_app: self.selected_app.clone(),
+            window: window.or(self.session.window),
+            osk_mode: self.osk_mode,
+            osk_layout: self.osk_layout,
+            group_filter: self.group_filter.clone(),
+            broadcast_to_group: self.broadcast_to_group,
+            macs: self.macs.clone(),
+            serials: self.serials.clone(),
+            tabs: self
+                .tabs
+                .open
+                .iter()
+                .filter(|device| !demo::is_demo(device))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    fn save_session_if_due(&mut self, ctx: &egui::Context) {
+        let current = self.current_session(ctx);
+        if current == self.session {
+            self.session_changed = None;
+            return;
+        }
+        let changed = *self.session_changed.get_or_insert_with(Instant::now);
+        if changed.elapsed() < SESSION_SAVE_DELAY {
+            ctx.request_repaint_after(SESSION_SAVE_DELAY);
+            return;
+        }
+        if let Err(err) = current.save() {
+            self.last_msg = format!("Couldn't save the session: {}", err);
+        }
+        self.session = current;
+        self.session_changed = None;
+    }
+
+    // the device's name and model with its address, or just the address until device-info is
+    // in, followed by its room when one has been noted
+    fn device_label(&self, device: &str) -> String {
+        if demo::is_demo(device) {
+            return format!("Demo Roku ({})", device);
+        }
+        let label = match self.device_info.get(device) {
+            Some(info) if info.model_name.is_empty() || info.model_name == info.name => {
+                format!("{} ({})", info.name, device)
+            }
+            Some(info) => format!("{} · {} ({})", info.name, info.model_name, device),
+            None => device.to_string(),
+        };
+        match self.config.devices.get(device) {
+            Some(meta) if !meta.room.is_empty() => format!("{} ({})", label, meta.room),
+            _ => label,
+        }
+    }
+
+    fn fetch_device_info(&self, device: &str) {
+        let device = device.to_string();
+        self.worker.ask(move || {
+            let info = ecp::get_device_info(&device).ok()?;
+            Some(Reply::DeviceInfo(device, info))
+        });
+    }
+
+    // device-info as it comes in, keeping the MAC for when the device stops answering
+    fn got_device_info(&mut self, device: String, info: DeviceInfo) {
+        if !info.mac.is_empty() && !demo::is_demo(&device) {
+            self.macs.insert(device.clone(), info.mac.clone());
+        }
+        self.note_serial(&device, &info.serial_number);
+        self.device_info.insert(device, info);
+    }
+
+    // a serial already known at another address means the device has moved; everything kept
+    // for the old address goes with it rather than the device being listed twice
+    // true if the device had moved
+    fn note_serial(&mut self, device: &str, serial: &str) -> bool {
+        if serial.is_empty() || demo::is_demo(device) {
+            return false;
+        }
+        let moved_from = self
+            .serials
+            .iter()
+            .find(|(other, known)| *other != device && *known == serial)
+            .map(|(other, _)| other.clone());
+        if let Some(old) = &moved_from {
+            self.move_device(old, device);
+        }
+        self.serials.insert(device.to_string(), serial.to_string());
+        moved_from.is_some()
+    }
+
+    fn move_device(&mut self, old: &str, new: &str) {
+        for device in self.devices.iter_mut().filter(|device| *device == old) {
+            *device = new.to_string();
+        }
+        self.devices.sort();
+        self.devices.dedup();
+        self.config.rename_device(old, new);
+        self.config_dirty = true;
+        for map in [&mut self.macs, &mut self.serials] {
+            if let Some(value) = map.remove(old) {
+                map.entry(new.to_string()).or_insert(value);
+            }
+        }
+        if let Some(info) = self.device_info.remove(old) {
+            self.device_info.entry(new.to_string()).or_insert(info);
+        }
+        if self.selected_device.as_deref() == Some(old) {
+            self.selected_device = Some(new.to_string());
+        }
+        self.tabs.rename(old, new);
+        self.last_msg = format!("{} moved from {} to {}", self.device_label(new), old, new);
+    }
+
+    // sends the magic packet, then keeps asking for device-info until the device is back up
+    fn wake_device(&mut self, device: &str) {
+        let Some(mac) = self.macs.get(device).cloned() else {
+            return;
+        };
+        self.waking.insert(device.to_string());
+        self.last_msg = format!("Waking {}…", self.device_label(device));
+        let device = device.to_string();
+        self.worker.ask(move || {
+            if let Err(err) = wol::wake(&mac) {
+                return Some(Reply::Failed(format!("Waking {} failed: {}", device, err)));
+            }
+            let started = Instant::now();
+            while started.elapsed() < WAKE_TIMEOUT {
+                if let Ok(info) = ecp::get_device_info(&device) {
+                    return Some(Reply::Woke(device, Some(info)));
+                }
+                std::thread::sleep(WAKE_POLL);
+            }
+            Some(Reply::Woke(device, None))
+        });
+    }
+
+    fn finish_waking(&mut self, device: String, info: Option<DeviceInfo>) {
+        self.waking.remove(&device);
+        let Some(info) = info else {
+            self.last_msg = format!("{} didn't wake up", self.device_label(&device));
+            return;
+        };
+        self.got_device_info(device.clone(), info);
+        self.last_msg = format!("{} is awake", self.device_label(&device));
+        self.reachability.probe(&self.devices);
+        // the apps asked for while it was asleep never came
+        if self.selected_device.as_ref() == Some(&device) {
+            self.select_device(device);
+        }
+    }
+
+    fn wake_button(&mut self, ui: &mut egui::Ui, device: &str) {
+        let Some(mac) = self.macs.get(device) else {
+            return;
+        };
+        if self.waking.contains(device) {
+            ui.spinner();
+            return;
+        }
+        let hover = format!(
+            "Send a Wake-on-LAN packet to {}, for a TV in deep standby",
+            mac
+        );
+        if ui.button("Wake").on_hover_text(hover).clicked() {
+            self.wake_device(device);
+        }
+    }
+
+    fn device_info_ui(&mut self, ui: &mut egui::Ui, device: &str) {
+        egui::CollapsingHeader::new("Device Info").show(ui, |ui| {
+            let forget = "Take it off the list until discovery finds it again";
+            let Some(info) = self.device_info.get(device) else {
+                ui.weak("The device hasn't answered device-info yet");
+                ui.horizontal(|ui| {
+                    if ui.button("Ask Again").clicked() {
+                        self.fetch_device_info(device);
+                    }
+                    self.wake_button(ui, device);
+                    if ui.button("Forget Device").on_hover_text(forget).clicked() {
+                        self.forget_device(device);
+                    }
+                });
+                return;
+            };
+            egui::Grid::new("device_info_grid")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    let model = format!("{} {}", info.model_name, info.model_number);
+                    let features = [
+                        (info.is_tv, "TV"),
+                        (info.is_stick, "stick"),
+                        (info.supports(RokuKey::VolumeUp), "volume and power"),
+                        (info.supports_find_remote, "Find Remote"),
+                        (info.supports_private_listening, "private listening"),
+                    ];
+                    let features: Vec<&str> = features
+                        .iter()
+                        .filter(|(has, _)| *has)
+                        .map(|(_, name)| *name)
+                        .collect();
+                    let features = features.join(", ");
+                    // the device turned plain ECP down, so its "control by mobile apps" is limited
+                    let control = if transport::uses_ecp2(device) {
+                        "ECP-2 (limited mode)"
+                    } else {
+                        "ECP"
+                    };
+                    let rows = [
+                        ("Name:", info.name.as_str()),
+                        ("Model:", model.trim()),
+                        ("Software:", info.software_version.as_str()),
+                        ("Serial:", info.serial_number.as_str()),
+                        ("MAC:", info.mac.as_str()),
+                        ("Address:", device),
+                        ("Features:", features.as_str()),
+                        ("Control:", control),
+                    ];
+                    for (label, value) in rows {
+                        ui.label(label);
+                        // selectable, for copying the serial into a support form
+                        ui.add(egui::Label::new(value).selectable(true));
+                        ui.end_row();
+                    }
+                });
+            ui.horizontal(|ui| {
+                self.wake_button(ui, device);
+                if ui.button("Forget Device").on_hover_text(forget).clicked() {
+                    self.forget_device(device);
+                }
+            });
+        });
+    }
+
+    // for a device that's gone or moved; notes saved for it are kept
+    fn forget_device(&mut self, device: &str) {
+        self.devices.retain(|other| other != device);
+        self.device_info.remove(device);
+        self.tabs.close(device);
+        if self.selected_device.as_deref() == Some(device) {
+            self.selected_device = None;
+            self.apps.clear();
+        }
+        self.last_msg = format!("Forgot {}", device);
+    }
+
+    // notes and tags for the selected device, saved once editing finishes
+    fn device_notes_ui(&mut self, ui: &mut egui::Ui, device: &str) {
+        let meta = self.config.devices.entry(device.to_string()).or_default();
+        let summary = meta.summary();
+        if !summary.is_empty() {
+            ui.label(summary);
+        }
+
+        egui::CollapsingHeader::new("Notes & Tags").show(ui, |ui| {
+            let mut changed = false;
+            egui::Grid::new("device_meta_grid")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label("Room:");
+                    changed |= ui.text_edit_singleline(&mut meta.room).changed();
+                    ui.end_row();
+                    ui.label("Owner:");
+                    changed |= ui.text_edit_singleline(&mut meta.owner).changed();
+                    ui.end_row();
+                    ui.label("HDMI port:");
+                    changed |= ui.text_edit_singleline(&mut meta.hdmi_port).changed();
+                    ui.end_row();
+                });
+
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Tags:");
+                let mut removed = None;
+                for (i, tag) in meta.tags.iter().enumerate() {
+                    if ui.small_button(format!("{} ✖", tag)).clicked() {
+                        removed = Some(i);
+                    }
+                }
+                if let Some(i) = removed {
+                    meta.tags.remove(i);
+                    changed = true;
+                }
+                let edit = ui.add(
+                    egui::TextEdit::singleline(&mut self.new_tag)
+                        .hint_text("add tag")
+                        .desired_width(80.0),
+                );
+                let tag = self.new_tag.trim().to_string();
+                if edit.lost_focus() && !tag.is_empty() {
+                    if !meta.tags.contains(&tag) {
+                        meta.tags.push(tag);
+                        changed = true;
+                    }
+                    self.new_tag.clear();
+                }
+            });
+
+            ui.label("Notes:");
+            changed |= ui.text_edit_multiline(&mut meta.notes).changed();
+            self.config_dirty |= changed;
+        });
+    }
+
+    // devices shown in the picker, narrowed to the active group filter
+    fn visible_devices(&self) -> Vec<String> {
+        match &self.group_filter {
+            Some(group) => {
+                let members = self.config.group_members(group);
+                self.devices
+                    .iter()
+                    .filter(|d| members.contains(d))
+                    .cloned()
+                    .collect()
+            }
+            None => self.devices.clone(),
+        }
+    }
+
+    // where remote buttons and launches go: the whole filtered group, or every device without
+    // a group, when broadcasting, else the selected device
+    fn command_targets(&self) -> Vec<String> {
+        match (&self.group_filter, self.broadcast_to_group) {
+            (Some(group), true) => self.config.group_members(group).to_vec(),
+            (None, true) => self.devices.clone(),
+            _ => self.selected_device.iter().cloned().collect(),
+        }
+    }
+
+    // each target launches on its own worker, so one slow device doesn't hold up the rest
+    fn launch_on_targets(&mut self, app_id: &str, app_name: &str) {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record(macros::Step::Launch(app_id.to_string()));
+        }
+        let targets = self.command_targets();
+        for ip in &targets {
+            let (ip, app) = (ip.clone(), app_id.to_string());
+            self.worker
+                .run(format!("Launching {} on {}", app_name, ip), move || {
+                    launch_app(&ip, &app)
+                });
+        }
+        self.last_msg = match targets.len() {
+            0 => "No Roku selected".into(),
+            1 => format!("Launching app: {}", app_name),
+            n => format!("Launching {} on {} devices", app_name, n),
+        };
+    }
+
+    // − and + buttons either side of a rocker, each repeating while held, and a mute toggle
+    fn volume_ui(&mut self, ui: &mut egui::Ui, device: &str) {
+        let rate = self.config.volume.repeat_per_sec;
+        let mut pressed = None;
+        let mut tapped = None;
+        let row = ui.horizontal(|ui| {
+            ui.label("Volume:");
+            let down = ui
+                .button("−")
+                .on_hover_text("VolumeDown; hold to keep turning it down");
+            if down.is_pointer_button_down_on() {
+                pressed = Some(("VolumeDown", rate));
+            } else if down.clicked() {
+                tapped = Some("VolumeDown");
+            }
+            let rocker = egui::Slider::new(&mut self.volume_rocker, -1.0..=1.0).show_value(false);
+            let rocker = ui
+                .add(rocker)
+                .on_hover_text("Push either way to turn the volume; further goes faster");
+            if rocker.dragged() {
+                let push = self.volume_rocker;
+                if push.abs() > 0.1 {
+                    let key = if push > 0.0 { "VolumeUp" } else { "VolumeDown" };
+                    pressed = Some((key, rate * push.abs()));
+                }
+            } else {
+                self.volume_rocker = 0.0;
+            }
+            let up = ui
+                .button("+")
+                .on_hover_text("VolumeUp; hold to keep turning it up");
+            if up.is_pointer_button_down_on() {
+                pressed = Some(("VolumeUp", rate));
+            } else if up.clicked() {
+                tapped = Some("VolumeUp");
+            }
+            let muted = self.muted.get(device).copied().unwrap_or_default();
+            let mute = if muted { "🔇 Muted" } else { "🔈 Mute" };
+            let hover = "Mute as far as this remote knows; the device can't be asked";
+            if ui
+                .selectable_label(muted, mute)
+                .on_hover_text(hover)
+                .clicked()
+            {
+                tapped = Some("VolumeMute");
+            }
+        });
+        self.scroll_zones.add(Some(Zone::Volume), row.response.rect);
+        // letting go of a held button clicks it too, but its presses have all gone out already
+        let tapped = tapped.filter(|key| *key == "VolumeMute" || !self.volume_repeat.holding());
+        if let Some(key) = self.volume_repeat.update(pressed).or(tapped) {
+            self.send_to_targets(key);
+        }
+        if let Some(wait) = self.volume_repeat.waiting() {
+            ui.ctx().request_repaint_after(wait);
+        }
+    }
+
+    // a button per input, on Roku TVs only; other devices have no inputs to switch
+    fn tv_inputs_ui(&mut self, ui: &mut egui::Ui, device: &str) {
+        if !self.device_info.get(device).is_some_and(|info| info.is_tv) {
+            return;
+        }
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Inputs:");
+            for (key, input) in TV_INPUTS {
+                let named = self.apps.iter().find(|app| app.id == *input);
+                let name = named.map_or_else(|| key.label(), |app| app.name.clone());
+                if ui.button(name).on_hover_text(key.name()).clicked() {
+                    self.send_to_targets(key.name());
+                }
+            }
+        });
+    }
+
+    fn send_to_targets(&mut self, cmd: &str) {
+        self.send_action_to_targets(cmd, KeyAction::Press);
+    }
+
+    fn send_action_to_targets(&mut self, cmd: &str, action: KeyAction) {
+        // a macro has no holds, only presses
+        if let Some(recorder) = self
+            .recorder
+            .as_mut()
+            .filter(|_| action == KeyAction::Press)
+        {
+            recorder.record(macros::Step::Key(cmd.to_string()));
+        }
+        let targets = self.command_targets();
+        for ip in &targets {
+            self.pacer.send_action(ip, cmd, action);
+            // mute toggles, and turning the volume either way unmutes
+            match cmd {
+                "VolumeMute" if action != KeyAction::Up => {
+                    let muted = self.muted.entry(ip.clone()).or_default();
+                    *muted = !*muted;
+                }
+                "VolumeUp" | "VolumeDown" => {
+                    self.muted.remove(ip);
+                }
+                _ => {}
+            }
+        }
+        let what = match action {
+            KeyAction::Press => format!("Sent command: {}", cmd),
+            KeyAction::Down => format!("Holding {}", cmd),
+            KeyAction::Up => format!("Let go of {}", cmd),
+        };
+        self.last_msg = match targets.len() {
+            0 => "No Roku selected".into(),
+            1 => what,
+            n => format!("{} on {} devices", what, n),
+        };
+    }
+
+    // taps and holds from the on-screen remote and the keyboard, gathered over the frame
+    fn send_held_keys(&mut self, ctx: &egui::Context) {
+        let mut down = Vec::new();
+        if self.selected_device.is_some() && !self.palette.open {
+            let (keys, tapped) = self.config.keyboard.keys(ctx);
+            down.extend(keys.into_iter().map(|(key, cmd)| (Source::Key(key), cmd)));
+            for cmd in tapped {
+                self.send_to_targets(&cmd);
+            }
+        }
+        if let Some(cmd) = self.pointer_held.take() {
+            down.push((Source::Pointer, cmd));
+        }
+        if let Some(cmd) = self.pointer_tapped.take() {
+            if !self.holds.holding(Source::Pointer) {
+                self.send_to_targets(&cmd);
+            }
+        }
+        for (cmd, action) in self.holds.update(&down) {
+            self.send_action_to_targets(&cmd, action);
+        }
+        if let Some(wait) = self.holds.waiting() {
+            ctx.request_repaint_after(wait);
+        }
+    }
+
+    // shows up when a device can't keep up with the presses and they're being spaced out
+    fn pacing_status_ui(&self, ui: &mut egui::Ui) {
+        let Some((device, status)) = self.pacer.busiest() else {
+            return;
+        };
+        if status.queued == 0 {
+            return;
+        }
+        ui.ctx().request_repaint_after(Duration::from_millis(100));
+        if !status.throttled() && status.queued <= 1 {
+            return;
+        }
+        let mut text = format!("⏱ {} is slow to respond", device);
+        if status.throttled() {
+            text += &format!(", pacing presses {} ms apart", status.gap.as_millis());
+        }
+        text += &format!(", {} waiting", status.queued);
+        ui.colored_label(ui.visuals().warn_fg_color, text);
+    }
+
+    fn group_filter_ui(&mut self, ui: &mut egui::Ui) {
+        if self.config.groups.is_empty() {
+            self.group_filter = None;
+        }
+        ui.horizontal(|ui| {
+            if !self.config.groups.is_empty() {
+                egui::ComboBox::from_label("Group")
+                    .selected_text(
+                        self.group_filter
+                            .clone()
+                            .unwrap_or_else(|| "All devices".into()),
+                    )
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.group_filter, None, "All devices");
+                        for group in self.config.groups.keys() {
+                            ui.selectable_value(&mut self.group_filter, Some(group.clone()), group);
+                        }
+                    });
+            }
+            let label = match &self.group_filter {
+                Some(group) => {
+                    format!(
+                        "Send commands to all {} in group",
+                        self.config.group_members(group).len()
+                    )
+                }
+                None if self.devices.len() > 1 => {
+                    format!("Send to all {} devices", self.devices.len())
+                }
+                None => return,
+            };
+            ui.checkbox(&mut self.broadcast_to_group, label)
+                .on_hover_text(
+                    "Remote buttons and channel launches go to every one of them at once",
+                );
+        });
+    }
+
+    // membership of the selected device plus creating/deleting groups
+    fn device_groups_ui(&mut self, ui: &mut egui::Ui, device: &str) {
+        egui::CollapsingHeader::new("Groups").show(ui, |ui| {
+            let groups: Vec<String> = self.config.groups.keys().cloned().collect();
+            let mut deleted = None;
+            for group in groups {
+                ui.horizontal(|ui| {
+                    let mut member = self
+                        .config
+                        .group_members(&group)
+                        .iter()
+                        .any(|d| d == device);
+                    if ui.checkbox(&mut member, &group).changed() {
+                        self.config.set_group_membership(&group, device, member);
+                        self.config_dirty = true;
+                    }
+                    if ui.small_button("Delete").clicked() {
+                        deleted = Some(group.clone());
+                    }
+                });
+            }
+            if let Some(group) = deleted {
+                self.config.groups.remove(&group);
+                if self.group_filter.as_ref() == Some(&group) {
+                    self.group_filter = None;
+                }
+                self.config_dirty = true;
+            }
+
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.new_group)
+                        .hint_text("new group")
+                        .desired_width(120.0),
+                );
+                let name = self.new_group.trim().to_string();
+                if ui.button("Create").clicked() && !name.is_empty() {
+                    self.config.set_group_membership(&name, device, true);
+                    self.new_group.clear();
+                    self.config_dirty = true;
+                }
+            });
+        });
+    }
+
+    // the session-wide dry run switch and what it held back
+    fn dry_run_ui(&mut self, ui: &mut egui::Ui) {
+        let mut on = dry_run::enabled();
+        if ui
+            .checkbox(&mut on, "Dry run")
+            .on_hover_text(
+                "Log every command instead of sending it: keypresses, launches, wake-on-LAN, \
+                 sideloading and casting. Queries still go out to keep the remote up to date",
+            )
+            .changed()
+        {
+            dry_run::set_enabled(on);
+        }
+        if !on {
+            return;
+        }
+        let log = dry_run::log();
+        egui::CollapsingHeader::new(format!("Held back ({})", log.len()))
+            .id_source("dry_run_log")
+            .show(ui, |ui| {
+                if ui.button("Clear").clicked() {
+                    dry_run::clear();
+                }
+                egui::ScrollArea::vertical()
+                    .max_height(160.0)
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        for command in &log {
+                            ui.monospace(command);
+                        }
+                    });
+            });
+    }
+
+    fn settings_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Settings").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Theme:");
+                for theme in theme::Theme::ALL {
+                    let choice = ui.selectable_value(&mut self.config.theme, theme, theme.label());
+                    self.config_dirty |= choice.changed();
+                }
+            });
+
+            ui.separator();
+            ui.label("Sync folder (Dropbox, Syncthing, …):");
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut self.sync_folder_input);
+                let folder = self.sync_folder_input.trim().to_string();
+                if ui.button("Use folder").clicked() && !folder.is_empty() {
+                    self.set_sync_folder(Some(PathBuf::from(folder)));
+                }
+                if self.store.sync.folder.is_some() && ui.button("Stop syncing").clicked() {
+                    self.set_sync_folder(None);
+                }
+            });
+            if let Some(path) = self.store.path() {
+                ui.weak(format!("Config file: {}", path.display()));
+            }
+            if self.store.sync.folder.is_some() {
+                ui.weak("Server tokens and passwords stay on this machine");
+            }
+
+            ui.separator();
+            ui.label("Scroll wheel over the remote:");
+            ui.horizontal_wrapped(|ui| {
+                let scroll = &mut self.config.scroll;
+                let mut changed = ui.checkbox(&mut scroll.dpad, "D-pad Up/Down").changed();
+                changed |= ui.checkbox(&mut scroll.volume, "Volume row").changed();
+                changed |= ui.checkbox(&mut scroll.seek, "Sideways to seek").changed();
+                changed |= ui.checkbox(&mut scroll.invert, "Invert").changed();
+                self.config_dirty |= changed;
+            });
+            let repeat = egui::Slider::new(&mut self.config.volume.repeat_per_sec, 1.0..=20.0)
+                .text("volume presses a second while held")
+                .max_decimals(0);
+            self.config_dirty |= ui.add(repeat).changed();
+
+            ui.separator();
+            ui.label("Mouse buttons:");
+            let keys = remote_keys();
+            for (label, key) in self.config.mouse_buttons.bindings_mut() {
+                egui::ComboBox::from_label(label)
+                    .selected_text(if key.is_empty() {
+                        "(none)"
+                    } else {
+                        key.as_str()
+                    })
+                    .show_ui(ui, |ui| {
+                        let mut changed =
+                            ui.selectable_value(key, String::new(), "(none)").changed();
+                        for cmd in &keys {
+                            changed |= ui.selectable_value(key, cmd.to_string(), *cmd).changed();
+                        }
+                        self.config_dirty |= changed;
+                    });
+            }
+
+            ui.separator();
+            self.keyboard_ui(ui);
+
+            ui.separator();
+            self.discovery_ui(ui);
+
+            ui.separator();
+            self.backup_ui(ui);
+
+            ui.separator();
+            self.api_tokens_ui(ui);
+        });
+    }
+
+    // the remote's buttons as cells to drag onto one another, which swaps them, or to click
+    // and change. saved to the config like every other setting
+    fn layout_editor_ui(&mut self, ui: &mut egui::Ui) {
+        let rows = self.config.layout.rows();
+        let mut dropped = None;
+        egui::Grid::new("layout_editor_grid")
+            .num_columns(layout::COLUMNS)
+            .min_col_width(100.0)
+            .spacing([10.0, 10.0])
+            .show(ui, |ui| {
+                for (r, row) in rows.iter().enumerate() {
+                    for (c, button) in row.iter().enumerate() {
+                        let text = match button {
+                            Some(button) => egui::RichText::new(button.label()),
+                            None => egui::RichText::new("＋").weak(),
+                        };
+                        let picked = self.layout_cell == Some((r, c));
+                        let cell = egui::Button::new(text)
+                            .selected(picked)
+                            .min_size(egui::vec2(100.0, 20.0))
+                            .sense(egui::Sense::click_and_drag());
+                        let cell = ui.add(cell);
+                        if button.is_some() {
+                            cell.dnd_set_drag_payload((r, c));
+                        }
+                        if let Some(from) = cell.dnd_release_payload::<(usize, usize)>() {
+                            dropped = Some((*from, (r, c)));
+                        }
+                        if cell.clicked() {
+                            self.layout_cell = (!picked).then_some((r, c));
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+        if let Some((from, to)) = dropped.filter(|(from, to)| from != to) {
+            self.config.layout.swap(from, to);
+            self.layout_cell = Some(to);
+            self.config_dirty = true;
+        }
+        ui.weak("Drag a button onto another cell to move it there; click one to change it");
+
+        if let Some((r, c)) = self.layout_cell.filter(|(r, _)| *r < rows.len()) {
+            ui.separator();
+            match rows[r][c].clone() {
+                Some(mut button) => {
+                    let before = button.clone();
+                    egui::Grid::new("layout_cell_grid")
+                        .num_columns(2)
+                        .show(ui, |ui| {
+                            ui.label("Key:");
+                            ui.horizontal(|ui| {
+                                egui::ComboBox::from_id_source("layout_key")
+                                    .selected_text(button.key.as_str())
+                                    .show_ui(ui, |ui| {
+                                        for key in RokuKey::all() {
+                                            ui.selectable_value(
+                                                &mut button.key,
+                                                key.to_string(),
+                                                key.name(),
+                                            );
+                                        }
+                                    });
+                                ui.add(
+                                    egui::TextEdit::singleline(&mut button.key).desired_width(90.0),
+                                )
+                                .on_hover_text("Any key ECP takes, e.g. Lit_a to type an a");
+                                if button.roku_key().is_none() {
+                                    ui.colored_label(
+                                        ui.visuals().warn_fg_color,
+                                        "Not a key ECP knows",
+                                    );
+                                }
+                            });
+                            ui.end_row();
+                            ui.label("Label:");
+                            let hint = button.roku_key().map(RokuKey::label).unwrap_or_default();
+                            ui.add(egui::TextEdit::singleline(&mut button.label).hint_text(hint));
+                            ui.end_row();
+                        });
+                    let remove = ui.button("🗑 Remove button").clicked();
+                    if remove || button != before {
+                        self.config.layout.rows_mut()[r][c] = (!remove).then_some(button);
+                        self.config_dirty = true;
+                    }
+                }
+                None => {
+                    if ui.button("＋ Add a button here").clicked() {
+                        self.config.layout.rows_mut()[r][c] =
+                            Some(layout::Button::new(RokuKey::Home));
+                        self.config_dirty = true;
+                    }
+                }
+            }
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("＋ Row").clicked() {
+                self.config
+                    .layout
+                    .rows_mut()
+                    .push(vec![None; layout::COLUMNS]);
+                self.config_dirty = true;
+            }
+            let row = self.layout_cell.map(|(r, _)| r);
+            let remove_row = ui
+                .add_enabled(row.is_some() && rows.len() > 1, egui::Button::new("🗑 Row"))
+                .on_hover_text("Remove the row of the picked cell");
+            if let (true, Some(r)) = (remove_row.clicked(), row) {
+                self.config.layout.rows_mut().remove(r);
+                self.layout_cell = None;
+                self.config_dirty = true;
+            }
+            let reset = egui::Button::new("Reset to default");
+            if ui
+                .add_enabled(!self.config.layout.is_default(), reset)
+                .clicked()
+            {
+                self.config.layout = layout::RemoteLayout::default();
+                self.layout_cell = None;
+                self.config_dirty = true;
+            }
+            if ui.button("Done").clicked() {
+                self.editing_layout = false;
+                self.layout_cell = None;
+            }
+        });
+    }
+
+    fn keyboard_ui(&mut self, ui: &mut egui::Ui) {
+        let keyboard = &mut self.config.keyboard;
+        self.config_dirty |= ui
+            .checkbox(&mut keyboard.enabled, "Keyboard works the remote")
+            .on_hover_text("While the window has focus and no text field is being typed in")
+            .changed();
+        egui::CollapsingHeader::new("Keyboard shortcuts").show(ui, |ui| {
+            let keys = remote_keys();
+            egui::Grid::new("key_bindings_grid")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    for (key, bound) in keyboard.bindings() {
+                        ui.label(key.symbol_or_name());
+                        let mut picked = bound.clone();
+                        egui::ComboBox::from_id_source(("key_binding", key.name()))
+                            .selected_text(bound.as_deref().unwrap_or("(none)"))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut picked, None, "(none)");
+                                for cmd in &keys {
+                                    ui.selectable_value(&mut picked, Some(cmd.to_string()), *cmd);
+                                }
+                            });
+                        if picked != bound {
+                            keyboard.set(key, picked);
+                            self.config_dirty = true;
+                        }
+                        ui.end_row();
+                    }
+                });
+            if ui.button("Reset to Defaults").clicked() {
+                *keyboard = key_bindings::KeyBindings::default();
+                self.config_dirty = true;
+            }
+        });
+        self.global_hotkeys_ui(ui);
+    }
+
+    // shortcuts that work the remote from anywhere, even with the window minimized
+    fn global_hotkeys_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("System-wide shortcuts").show(ui, |ui| {
+            if !global_hotkeys::AVAILABLE {
+                ui.weak(
+                    "This build has no system-wide shortcuts; build it with the hotkeys feature",
+                );
+            }
+            let hotkeys = &mut self.config.global_hotkeys;
+            self.config_dirty |= ui
+                .checkbox(
+                    &mut hotkeys.enabled,
+                    "Shortcuts work while the window is in the background",
+                )
+                .on_hover_text(
+                    "They go to the selected Roku, or its group when sending to the group",
+                )
+                .changed();
+            let keys = remote_keys();
+            let mut remove = None;
+            egui::Grid::new("global_hotkeys_grid")
+                .num_columns(3)
+                .show(ui, |ui| {
+                    for (shortcut, bound) in hotkeys.keys.iter_mut() {
+                        ui.label(shortcut);
+                        egui::ComboBox::from_id_source(("global_hotkey", shortcut.as_str()))
+                            .selected_text(bound.as_str())
+                            .show_ui(ui, |ui| {
+                                for cmd in &keys {
+                                    let picked = ui.selectable_value(bound, cmd.to_string(), *cmd);
+                                    self.config_dirty |= picked.changed();
+                                }
+                            });
+                        if ui
+                            .small_button("🗑")
+                            .on_hover_text("Remove this shortcut")
+                            .clicked()
+                        {
+                            remove = Some(shortcut.clone());
+                        }
+                        ui.end_row();
+                    }
+                });
+            if let Some(shortcut) = remove {
+                hotkeys.keys.remove(&shortcut);
+                self.config_dirty = true;
+            }
+            ui.horizontal(|ui| {
+                let field = egui::TextEdit::singleline(&mut self.new_hotkey)
+                    .hint_text("Ctrl+Alt+M or MediaPlayPause")
+                    .desired_width(160.0);
+                ui.add(field);
+                if self.new_hotkey_cmd.is_empty() {
+                    self.new_hotkey_cmd = "Play".into();
+                }
+                egui::ComboBox::from_id_source("new_global_hotkey")
+                    .selected_text(self.new_hotkey_cmd.as_str())
+                    .show_ui(ui, |ui| {
+                        for cmd in &keys {
+                            ui.selectable_value(&mut self.new_hotkey_cmd, cmd.to_string(), *cmd);
+                        }
+                    });
+                let shortcut = self.new_hotkey.trim().to_string();
+                let checked = global_hotkeys::check(&shortcut);
+                let ready = !shortcut.is_empty() && checked.is_ok();
+                if ui.add_enabled(ready, egui::Button::new("Add")).clicked() {
+                    hotkeys
+                        .keys
+                        .insert(shortcut.clone(), self.new_hotkey_cmd.clone());
+                    self.new_hotkey.clear();
+                    self.config_dirty = true;
+                }
+                if let (false, Err(err)) = (shortcut.is_empty(), checked) {
+                    ui.colored_label(ui.visuals().warn_fg_color, err);
+                }
+            });
+            for err in self.global_hotkeys.errors() {
+                ui.colored_label(ui.visuals().error_fg_color, err);
+            }
+            if ui.button("Reset to Defaults").clicked() {
+                *hotkeys = global_hotkeys::GlobalHotkeySettings {
+                    enabled: hotkeys.enabled,
+                    ..Default::default()
+                };
+                self.config_dirty = true;
+            }
+        });
+    }
+
+    // how Discover searches; only worth touching for devices that don't show up
+    fn discovery_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Discovery (advanced)").show(ui, |ui| {
+            let ssdp = &mut self.config.ssdp;
+            let mut changed = false;
+            ui.horizontal(|ui| {
+                ui.label("Search target (ST):");
+                changed |= ui.text_edit_singleline(&mut ssdp.search_target).changed();
+            });
+            ui.horizontal(|ui| {
+                changed |= ui
+                    .add(
+                        egui::DragValue::new(&mut ssdp.mx)
+                            .clamp_range(1..=5)
+                            .prefix("MX "),
+                    )
+                    .on_hover_text("Seconds a device may wait before answering")
+                    .changed();
+                changed |= ui
+                    .add(
+                        egui::DragValue::new(&mut ssdp.ttl)
+                            .clamp_range(1..=32)
+                            .prefix("TTL "),
+                    )
+                    .on_hover_text("Multicast hops; more than 1 reaches past routers")
+                    .changed();
+            });
+            changed |= ui
+                .checkbox(&mut ssdp.search_all, "Also search ssdp:all")
+                .on_hover_text(
+                    "For devices that don't answer roku:ecp; keeps answers from a Roku server",
+                )
+                .changed();
+            changed |= ui
+                .checkbox(&mut ssdp.deep_scan, "Deep scan when nothing answers")
+                .on_hover_text(
+                    "Tries every address on this computer's /24 networks, for routers that block \
+                     multicast and access points that keep devices apart. Takes a few seconds",
+                )
+                .changed();
+            if ui.button("Reset to defaults").clicked() {
+                *ssdp = Default::default();
+                changed = true;
+            }
+            let background = &mut self.config.background;
+            ui.horizontal(|ui| {
+                ui.label("Look for new devices every");
+                let minutes = egui::DragValue::new(&mut background.rediscover_minutes)
+                    .clamp_range(0..=240)
+                    .suffix(" min");
+                changed |= ui
+                    .add(minutes)
+                    .on_hover_text("0 only looks when asked to")
+                    .changed();
+            });
+            let requests = &mut self.config.requests;
+            ui.horizontal(|ui| {
+                ui.label("Give up on a device after");
+                let seconds = egui::DragValue::new(&mut requests.timeout_seconds)
+                    .clamp_range(0.5..=30.0)
+                    .speed(0.1)
+                    .suffix(" s");
+                changed |= ui
+                    .add(seconds)
+                    .on_hover_text(
+                        "How long a command waits on a device in standby, which never answers",
+                    )
+                    .changed();
+                let retries = egui::DragValue::new(&mut requests.retries)
+                    .clamp_range(0..=5)
+                    .suffix(" retries");
+                changed |= ui
+                    .add(retries)
+                    .on_hover_text(
+                        "Queries only, backing off between tries; keypresses go out once",
+                    )
+                    .changed();
+            });
+            ui.horizontal(|ui| {
+                ui.label("Type text with");
+                let delay = egui::DragValue::new(&mut requests.typing_delay_ms)
+                    .clamp_range(0..=1000)
+                    .suffix(" ms");
+                changed |= ui
+                    .add(delay)
+                    .on_hover_text(
+                        "Devices drop characters sent back to back; raise it if text has gaps",
+                    )
+                    .changed();
+                ui.label("between characters");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Check which devices are online every");
+                let seconds = egui::DragValue::new(&mut background.ping_seconds)
+                    .clamp_range(0..=3600)
+                    .suffix(" s");
+                changed |= ui
+                    .add(seconds)
+                    .on_hover_text("0 stops checking, leaving the dots as they last were")
+                    .changed();
+            });
+            self.config_dirty |= changed;
+        });
+    }
+
+    // tokens for clients of the --serve control server
+    fn api_tokens_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("Control server tokens:");
+        let mut revoked = None;
+        for (name, token) in &self.config.api_tokens {
+            ui.horizontal(|ui| {
+                ui.label(format!("{} ({})", name, token.scope_label()));
+                ui.monospace(&token.token);
+                if ui.small_button("Revoke").clicked() {
+                    revoked = Some(name.clone());
+                }
+            });
+        }
+        if let Some(name) = revoked {
+            self.config.api_tokens.remove(&name);
+            self.config_dirty = true;
+        }
+
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.new_token_name)
+                    .hint_text("token name")
+                    .desired_width(120.0),
+            );
+            egui::ComboBox::from_id_source("new_token_role")
+                .selected_text(self.new_token_role.label())
+                .show_ui(ui, |ui| {
+                    for role in Role::ALL {
+                        ui.selectable_value(&mut self.new_token_role, role, role.label());
+                    }
+                });
+            let name = self.new_token_name.trim().to_string();
+            if ui.button("Create token").clicked() && !name.is_empty() {
+                let devices = std::mem::take(&mut self.new_token_devices);
+                self.config
+                    .api_tokens
+                    .insert(name, ApiToken::generate(self.new_token_role, devices));
+                self.new_token_name.clear();
+                self.config_dirty = true;
+            }
+        });
+
+        // leaving every box unticked gives the token access to all devices
+        let mut known: Vec<String> = self.config.devices.keys().cloned().collect();
+        known.extend(
+            self.devices
+                .iter()
+                .filter(|d| !self.config.devices.contains_key(*d))
+                .cloned(),
+        );
+        if !known.is_empty() {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Limit to:");
+                for device in known {
+                    let mut scoped = self.new_token_devices.contains(&device);
+                    if ui
+                        .checkbox(&mut scoped, self.device_label(&device))
+                        .changed()
+                    {
+                        self.new_token_devices.retain(|d| *d != device);
+                        if scoped {
+                            self.new_token_devices.push(device);
+                        }
+                    }
+                }
+            });
+        }
+    }
+
+    fn backup_ui(&mut self, ui: &mut egui::Ui) {
+        if ui.button("Back up everything").clicked() {
+            let session = self.current_session(ui.ctx());
+            let backup = Backup::new(
+                &self.config,
+                &session,
+                &self.continue_watching,
+                &self.gallery,
+                &self.history,
+                &self.icons,
+            );
+            self.last_msg = match backup::default_backup_dir()
+                .ok_or_else(|| std::io::Error::other("no home directory"))
+                .and_then(|dir| backup::write_backup(&dir, &backup))
+            {
+                Ok(path) => {
+                    self.restore_path = path.display().to_string();
+                    format!("Saved backup to {}", path.display())
+                }
+                Err(err) => format!("Backup failed: {}", err),
+            };
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Restore from:");
+            ui.text_edit_singleline(&mut self.restore_path);
+            if ui.button("Open").clicked() {
+                match backup::read_backup(self.restore_path.trim().as_ref()) {
+                    Ok(backup) => self.pending_restore = Some(backup),
+                    Err(err) => self.last_msg = format!("Can't restore: {}", err),
+                }
+            }
+        });
+
+        if let Some(backup) = &self.pending_restore {
+            ui.label(format!(
+                "Replace current settings with {}?",
+                backup.summary()
+            ));
+            ui.horizontal(|ui| {
+                if ui.button("Restore").clicked() {
+                    if let Some(backup) = self.pending_restore.take() {
+                        self.restore_backup(backup);
+                    }
+                }
+                if ui.button("Cancel").clicked() {
+                    self.pending_restore = None;
+                }
+            });
+        }
+    }
+
+    // the backup's settings and local state in place of the current ones, with the setup that
+    // startup does from them done again
+    fn restore_backup(&mut self, backup: Backup) {
+        // read_backup has checked these, but nothing is replaced if they don't decode
+        let (shots, icons) = match backup
+            .shot_images()
+            .and_then(|shots| Ok((shots, backup.icon_images()?)))
+        {
+            Ok(images) => images,
+            Err(err) => {
+                self.last_msg = format!("Can't restore: {}", err);
+                return;
+            }
+        };
+        self.config = backup.config;
+        self.config_dirty = true;
+        http::configure(&self.config.requests);
+        match &backup.session {
+            Some(session) => {
+                let front = self.selected_device.clone();
+                self.take_session(session, front.as_ref());
+            }
+            None => {
+                let filter = self.group_filter.take();
+                self.group_filter = filter.filter(|group| self.config.groups.contains_key(group));
+                self.add_known_devices(Vec::new());
+            }
+        }
+        if let Some(entries) = backup.continue_watching {
+            self.continue_watching.replace(entries);
+        }
+        let mut failed = Vec::new();
+        if let Some(runs) = backup.schedule_state {
+            if let Err(err) = runs.save() {
+                failed.push(format!("schedule state ({})", err));
+            }
+        }
+        if let Some(entries) = backup.history {
+            self.history.restore(entries);
+        }
+        self.icons.restore(icons);
+        if let Err(err) = self.gallery.restore(shots) {
+            failed.push(format!("screenshots ({})", err));
+        }
+        self.last_msg = if failed.is_empty() {
+            "Restored settings from backup".into()
+        } else {
+            format!(
+                "Restored settings from backup, but not the {}",
+                failed.join(" or ")
+            )
+        };
+    }
+
+    // the installed channels as icon tiles; one click on a tile launches it
+    fn app_grid_ui(&mut self, ui: &mut egui::Ui) {
+        let Some(ip) = self.selected_device.clone() else {
+            return;
+        };
+        if self.apps.is_empty() {
+            ui.weak("No channels to show yet");
+            return;
+        }
+        let active_app = self.now_playing.current.app_id().map(str::to_string);
+        let favorites = self
+            .config
+            .devices
+            .get(&ip)
+            .map(|meta| meta.favorites.clone());
+        let favorites = favorites.unwrap_or_default();
+        let (mut launched, mut pinned) = (None, None);
+        self.favorites_ui(ui, &favorites, &mut launched, &mut pinned);
+        egui::ScrollArea::vertical()
+            .max_height(APP_GRID_HEIGHT)
+            .show(ui, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    for app in &self.apps {
+                        let (rect, response) =
+                            ui.allocate_exact_size(APP_TILE_SIZE, egui::Sense::click());
+                        // the icon is only fetched once its tile scrolls into view
+                        if !ui.is_rect_visible(rect) {
+                            continue;
+                        }
+                        let visuals = ui.style().interact(&response);
+                        // the running channel stands out, so the grid doubles as a status
+                        let running = active_app.as_ref() == Some(&app.id);
+                        let fill = if running {
+                            ui.visuals().selection.bg_fill
+                        } else {
+                            visuals.bg_fill
+                        };
+                        ui.painter()
+                            .rect(rect, visuals.rounding, fill, visuals.bg_stroke);
+
+                        let icon_rect = egui::Rect::from_center_size(
+                            rect.center_top() + egui::vec2(0.0, 4.0 + APP_ICON_SIZE.y / 2.0),
+                            APP_ICON_SIZE,
+                        );
+                        let ctx = ui.ctx().clone();
+                        match self.icons.get(&ctx, &ip, &app.id, APP_ICON_SIZE) {
+                            Some(icon) => egui::Image::new(&icon).paint_at(ui, icon_rect),
+                            None => {
+                                ui.painter().rect_filled(
+                                    icon_rect,
+                                    2.0,
+                                    ui.visuals().faint_bg_color,
+                                );
+                            }
+                        }
+
+                        let mut name = egui::text::LayoutJob::simple_singleline(
+                            app.name.clone(),
+                            egui::TextStyle::Small.resolve(ui.style()),
+                            visuals.text_color(),
+                        );
+                        name.wrap = egui::text::TextWrapping {
+                            max_width: rect.width() - 6.0,
+                            max_rows: 1,
+                            break_anywhere: true,
+                            overflow_character: Some('…'),
+                        };
+                        let name = ui.fonts(|fonts| fonts.layout_job(name));
+                        let at = egui::pos2(
+                            rect.center().x - name.size().x / 2.0,
+                            icon_rect.bottom() + 3.0,
+                        );
+                        ui.painter().galley(at, name, visuals.text_color());
+
+                        let favorite = favorites.contains(&app.id);
+                        if favorite {
+                            ui.painter().text(
+                                rect.right_top() + egui::vec2(-3.0, 2.0),
+                                egui::Align2::RIGHT_TOP,
+                                "★",
+                                egui::TextStyle::Body.resolve(ui.style()),
+                                ui.visuals().warn_fg_color,
+                            );
+                        }
+                        let hint = format!("{}\nRight-click to pin or unpin", app.name);
+                        let response = response.on_hover_text(hint);
+                        if response.clicked() {
+                            launched = Some(app.clone());
+                        }
+                        response.context_menu(|ui| {
+                            let label = match favorite {
+                                true => "Unpin from favorites",
+                                false => "☆ Pin to favorites",
+                            };
+                            if ui.button(label).clicked() {
+                                pinned = Some((app.id.clone(), !favorite));
+                                ui.close_menu();
+                            }
+                        });
+                    }
+                });
+            });
+
+        if let Some((app_id, pin)) = pinned {
+            let meta = self.config.devices.entry(ip.clone()).or_default();
+            meta.favorites.retain(|id| *id != app_id);
+            if pin {
+                meta.favorites.push(app_id);
+            }
+            self.config_dirty = true;
+        }
+
+        if let Some(app) = launched {
+            // still the channel the deep link form fills in
+            self.selected_app = Some(app.id.clone());
+            self.launch_on_targets(&app.id, &app.name);
+        }
+    }
+
+    // a launch button for each channel pinned on this device, above the grid so they're always
+    // in sight; the ones no longer installed are left out
+    fn favorites_ui(
+        &self,
+        ui: &mut egui::Ui,
+        favorites: &[String],
+        launched: &mut Option<AppEntry>,
+        pinned: &mut Option<(String, bool)>,
+    ) {
+        let apps: Vec<&AppEntry> = favorites
+            .iter()
+            .filter_map(|id| self.apps.iter().find(|app| app.id == *id))
+            .collect();
+        if apps.is_empty() {
+            return;
+        }
+        ui.horizontal_wrapped(|ui| {
+            ui.label("★");
+            for app in apps {
+                let button = ui.button(&app.name).on_hover_text("Right-click to unpin");
+                if button.clicked() {
+                    *launched = Some(app.clone());
+                }
+                button.context_menu(|ui| {
+                    if ui.button("Unpin from favorites").clicked() {
+                        *pinned = Some((app.id.clone(), false));
+                        ui.close_menu();
+                    }
+                });
+            }
+        });
+    }
+
+    // what the selected device is playing, between the watcher's polls moved along by the clock
+    fn now_playing_ui(&mut self, ui: &mut egui::Ui) {
+        if self.selected_device.is_none() {
+            return;
+        }
+        egui::CollapsingHeader::new("Now Playing")
+            .default_open(true)
+            .show(ui, |ui| {
+                let now = &self.now_playing.current;
+                let app = match &now.app {
+                    Some(app) if !app.id.is_empty() => app.name.clone(),
+                    Some(_) => "Home screen".to_string(),
+                    None => "Not known yet".to_string(),
+                };
+                // turned on and off from the remote or the mobile app; ECP has no request for it
+                if now.private_listening == Some(true) {
+                    ui.label("🎧 Private listening: the sound is going to headphones")
+                        .on_hover_text("Turn it off from the Roku remote or the mobile app");
+                }
+                let loaded = |p: &ecp::Playback| !matches!(p.state.as_str(), "close" | "none");
+                let Some(playback) = now.playback.clone().filter(loaded) else {
+                    ui.label(format!("{} — nothing playing", app));
+                    return;
+                };
+                let (symbol, state) = match playback.state.as_str() {
+                    "play" => ("▶", "Playing"),
+                    "pause" => ("⏸", "Paused"),
+                    "buffer" | "startup" => ("⏳", "Loading"),
+                    "stop" => ("⏹", "Stopped"),
+                    other => ("", other),
+                };
+                ui.horizontal(|ui| {
+                    let toggle = if playback.state == "play" {
+                        "⏸"
+                    } else {
+                        "▶"
+                    };
+                    if ui.button(toggle).on_hover_text("Play/Pause").clicked() {
+                        self.send_to_targets("Play");
+                        self.now_playing.refresh();
+                    }
+                    ui.label(format!("{} {} — {}", symbol, state, app));
+                });
+
+                let position = self.now_playing.position().unwrap_or_default();
+                match playback.duration.filter(|duration| !duration.is_zero()) {
+                    Some(duration) => {
+                        let done = position.as_secs_f32() / duration.as_secs_f32();
+                        let clock = now_playing::clock;
+                        let text = format!("{} / {}", clock(position), clock(duration));
+                        ui.add(egui::ProgressBar::new(done).text(text));
+                    }
+                    // live streams have no end to measure against
+                    None => {
+                        ui.label(format!("{} in", now_playing::clock(position)));
+                    }
+                }
+                if let Some(buffer) = playback.buffering.filter(|_| state == "Loading") {
+                    ui.add(
+                        egui::ProgressBar::new(buffer)
+                            .text(format!("Buffering {:.0}%", buffer * 100.0)),
+                    );
+                }
+                if playback.state == "play" {
+                    ui.ctx().request_repaint_after(Duration::from_secs(1));
+                }
+            });
+    }
+
+    fn fetch_tv_channels(&mut self, device: &str) {
+        self.tv_channels.insert(device.to_string(), None);
+        let device = device.to_string();
+        self.worker.ask(move || {
+            let channels = ecp::get_tv_channels(&device);
+            let active = ecp::get_tv_active_channel(&device);
+            Some(Reply::TvChannels(device, channels, active))
+        });
+    }
+
+    fn tune(&mut self, device: &str, number: &str) {
+        self.last_msg = format!("Tuning to {}…", number);
+        let (device, number) = (device.to_string(), number.trim().to_string());
+        self.worker.ask(move || {
+            if let Err(err) = ecp::tune_tv_channel(&device, &number) {
+                return Some(Reply::Failed(format!(
+                    "Tuning to {} failed: {}",
+                    number, err
+                )));
+            }
+            std::thread::sleep(TUNE_SETTLE);
+            Some(Reply::TvActive(
+                device.clone(),
+                ecp::get_tv_active_channel(&device),
+            ))
+        });
+    }
+
+    // the antenna and cable channels of a Roku TV, each a click away from being tuned to
+    fn tv_channels_ui(&mut self, ui: &mut egui::Ui) {
+        let Some(device) = self.selected_device.clone() else {
+            return;
+        };
+        if !self.device_info.get(&device).is_some_and(|info| info.is_tv) {
+            return;
+        }
+        egui::CollapsingHeader::new("TV Channels").show(ui, |ui| {
+            if !self.tv_channels.contains_key(&device) {
+                self.fetch_tv_channels(&device);
+            }
+            match self.tv_active.get(&device) {
+                Some(now) => {
+                    let mut on = format!("On {} {}", now.channel.number, now.channel.name);
+                    if !now.program_title.is_empty() {
+                        on += &format!(" — {}", now.program_title);
+                    }
+                    if let Some(quality) = now.signal_quality {
+                        on += &format!(" · signal {}%", quality);
+                    }
+                    if !now.active {
+                        on += " (another input is on screen)";
+                    }
+                    ui.label(on);
+                }
+                None => {
+                    ui.weak("The tuner isn't on a channel");
+                }
+            }
+            let mut tune = None;
+            ui.horizontal(|ui| {
+                let field = egui::TextEdit::singleline(&mut self.tv_channel_input)
+                    .hint_text("7.1")
+                    .desired_width(60.0);
+                let entered =
+                    ui.add(field).lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                let ready = !self.tv_channel_input.trim().is_empty();
+                if ui.add_enabled(ready, egui::Button::new("Tune")).clicked() || (ready && entered)
+                {
+                    tune = Some(self.tv_channel_input.trim().to_string());
+                }
+                if ui
+                    .small_button("⟳")
+                    .on_hover_text("Ask the TV for its channels again")
+                    .clicked()
+                {
+                    self.fetch_tv_channels(&device);
+                }
+                ui.checkbox(&mut self.show_hidden_channels, "Show hidden")
+                    .on_hover_text("Channels taken out of the guide in the TV's settings");
+            });
+            if let Some(number) = self.keypad_ui(ui) {
+                tune = Some(number);
+            }
+            match self.tv_channels.get(&device) {
+                Some(None) => {
+                    ui.spinner();
+                }
+                Some(Some(channels)) if channels.is_empty() => {
+                    ui.weak("No channels; run a channel scan in the TV's Live TV settings");
+                }
+                Some(Some(channels)) => {
+                    let tuned = self
+                        .tv_active
+                        .get(&device)
+                        .map(|now| now.channel.number.as_str());
+                    let shown = |c: &&TvChannel| self.show_hidden_channels || !c.hidden;
+                    egui::ScrollArea::vertical()
+                        .max_height(240.0)
+                        .show(ui, |ui| {
+                            egui::Grid::new("tv_channels_grid")
+                                .num_columns(4)
+                                .striped(true)
+                                .show(ui, |ui| {
+                                    for channel in channels.iter().filter(shown) {
+                                        let button = ui
+                                            .small_button("▶")
+                                            .on_hover_text("Tune to this channel");
+                                        if button.clicked() {
+                                            tune = Some(channel.number.clone());
+                                        }
+                                        let number = egui::RichText::new(&channel.number);
+                                        let name = egui::RichText::new(&channel.name);
+                                        if tuned == Some(channel.number.as_str()) {
+                                            ui.label(number.strong());
+                                            ui.label(name.strong());
+                                        } else {
+                                            ui.label(number);
+                                            ui.label(name);
+                                        }
+                                        ui.weak(&channel.kind);
+                                        ui.end_row();
+                                    }
+                                });
+                        });
+                }
+                None => {}
+            }
+            if let Some(number) = tune {
+                self.tune(&device, &number);
+            }
+        });
+    }
+
+    // digits punched in as on a real remote, into the same channel number the field above holds.
+    // Tune goes through the tuner; Type sends them as keypresses to whatever is on screen, for
+    // TVs that only take a number that way. returns the number to tune to
+    fn keypad_ui(&mut self, ui: &mut egui::Ui) -> Option<String> {
+        let mut tune = None;
+        let size = egui::vec2(36.0, 28.0);
+        egui::Grid::new("keypad_grid")
+            .spacing([4.0, 4.0])
+            .show(ui, |ui| {
+                for row in ["123", "456", "789", ".0⌫"] {
+                    for key in row.chars() {
+                        if !ui
+                            .add_sized(size, egui::Button::new(key.to_string()))
+                            .clicked()
+                        {
+                            continue;
+                        }
+                        let input = &mut self.tv_channel_input;
+                        match key {
+                            '⌫' => {
+                                input.pop();
+                            }
+                            // a subchannel takes one dot, after the main number
+                            '.' if input.is_empty() || input.contains('.') => {}
+                            digit => input.push(digit),
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+        let number = self.tv_channel_input.trim().to_string();
+        ui.horizontal(|ui| {
+            let ready = !number.is_empty();
+            if ui.add_enabled(ready, egui::Button::new("Tune")).clicked() {
+                tune = Some(number.clone());
+            }
+            let typed = ui.add_enabled(ready, egui::Button::new("Type"));
+            if typed
+                .on_hover_text("Send the digits as keypresses")
+                .clicked()
+            {
+                for c in number.chars() {
+                    self.send_to_targets(&ecp::literal_key(c));
+                }
+                self.last_msg = format!("Typed {}", number);
+            }
+            if ui.add_enabled(ready, egui::Button::new("Clear")).clicked() {
+                self.tv_channel_input.clear();
+            }
+        });
+        tune
+    }
+
+    fn set_tv_active(&mut self, device: String, active: Option<TvActiveChannel>) {
+        match active {
+            Some(active) => self.tv_active.insert(device, active),
+            None => self.tv_active.remove(&device),
+        };
+    }
+
+    fn continue_watching_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Continue Watching").show(ui, |ui| {
+            if self.continue_watching.entries.is_empty() {
+                ui.label("Nothing watched yet");
+                return;
+            }
+            let mut resume = None;
+            let mut remove = None;
+            for (index, entry) in self.continue_watching.entries.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui.button("▶").on_hover_text("Resume").clicked() {
+                        resume = Some(entry.clone());
+                    }
+                    match entry.position() {
+                        Some(position) => ui.label(format!(
+                            "{} — at {}",
+                            entry.app_name,
+                            now_playing::clock(position)
+                        )),
+                        None => ui.label(&entry.app_name),
+                    };
+                    if ui.small_button("✖").on_hover_text("Remove").clicked() {
+                        remove = Some(index);
+                    }
+                });
+            }
+            if let Some(entry) = resume {
+                match &self.selected_device {
+                    Some(ip) => {
+                        self.last_msg = format!("Resuming {}", entry.app_name);
+                        let ip = ip.clone();
+                        let what = format!("Resuming {}", entry.app_name);
+                        self.worker.run(what, move || match &entry.content_id {
+                            Some(content_id) => launch_content(
+                                &ip,
+                                &entry.app_id,
+                                content_id,
+                                entry.media_type.as_deref(),
+                            ),
+                            None => launch_app(&ip, &entry.app_id),
+                        });
+                    }
+                    None => self.last_msg = "No Roku selected".into(),
+                }
+            }
+            if let Some(index) = remove {
+                self.continue_watching.remove(index);
+            }
+        });
+    }
+
+    fn watchlist_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Watchlist").show(ui, |ui| {
+            let mut launch = None;
+            let mut remove = None;
+            for (folder, links) in watchlist::by_folder(&self.config.watchlist) {
+                let rows = |ui: &mut egui::Ui| {
+                    for (name, link) in links {
+                        ui.horizontal(|ui| {
+                            if ui.button(format!("▶ {}", name)).clicked() {
+                                launch = Some(name.to_string());
+                            }
+                            let app_name = self
+                                .apps
+                                .iter()
+                                .find(|app| app.id == link.app_id)
+                                .map(|app| app.name.as_str())
+                                .unwrap_or(&link.app_id);
+                            ui.weak(app_name);
+                            if ui.small_button("✖").on_hover_text("Remove").clicked() {
+                                remove = Some(name.to_string());
+                            }
+                        });
+                    }
+                };
+                if folder.is_empty() {
+                    rows(ui);
+                } else {
+                    egui::CollapsingHeader::new(format!("📁 {}", folder))
+                        .id_source(("watchlist_folder", folder))
+                        .show(ui, rows);
+                }
+            }
+            if let Some(name) = launch {
+                self.launch_deep_link(&name);
+            }
+            if let Some(name) = remove {
+                self.config.watchlist.remove(&name);
+                self.config_dirty = true;
+            }
+
+            ui.separator();
+            self.deep_link_form_ui(ui);
+        });
+    }
+
+    // a deep link straight to one title: opened now, or saved to the watchlist for later
+    fn deep_link_form_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("Open a title directly:");
+        let preset = self.new_link_preset.map(|i| &watchlist::PRESETS[i]);
+        egui::Grid::new("watchlist_new")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Preset:");
+                let mut picked = self.new_link_preset;
+                egui::ComboBox::from_id_source("deep_link_preset")
+                    .selected_text(preset.map_or("Custom", |preset| preset.name))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut picked, None, "Custom");
+                        for (i, preset) in watchlist::PRESETS.iter().enumerate() {
+                            ui.selectable_value(&mut picked, Some(i), preset.name);
+                        }
+                    });
+                if picked != self.new_link_preset {
+                    self.new_link_preset = picked;
+                    if let Some(preset) = picked.map(|i| &watchlist::PRESETS[i]) {
+                        self.new_link_app = Some(preset.app_id.to_string());
+                        self.new_link_media_type =
+                            preset.media_type.unwrap_or_default().to_string();
+                    }
+                }
+                ui.end_row();
+
+                ui.label("Channel:");
+                let channel = self.new_link_app.as_ref().map(|id| {
+                    self.apps
+                        .iter()
+                        .find(|app| app.id == *id)
+                        .map_or_else(|| id.clone(), |app| app.name.clone())
+                });
+                egui::ComboBox::from_id_source("deep_link_app")
+                    .selected_text(channel.unwrap_or_else(|| "Pick a channel".into()))
+                    .show_ui(ui, |ui| {
+                        for app in self.apps.iter().filter(|app| app.kind == "appl") {
+                            ui.selectable_value(
+                                &mut self.new_link_app,
+                                Some(app.id.clone()),
+                                &app.name,
+                            );
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("Content ID:");
+                let hint = preset.map_or("the channel's id for the title", |preset| preset.hint);
+                let edit = ui
+                    .add(egui::TextEdit::singleline(&mut self.new_link_content_id).hint_text(hint));
+                // a pasted link becomes the id in it
+                if let (Some(preset), true) = (preset, edit.changed()) {
+                    self.new_link_content_id = preset.content_id(&self.new_link_content_id);
+                }
+                ui.end_row();
+                ui.label("Media type:");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.new_link_media_type)
+                        .hint_text("movie, episode, series…"),
+                );
+                ui.end_row();
+            });
+
+        let content_id = self.new_link_content_id.trim().to_string();
+        let media_type = self.new_link_media_type.trim();
+        let link = self
+            .new_link_app
+            .clone()
+            .filter(|_| !content_id.is_empty())
+            .map(|app_id| DeepLink {
+                app_id,
+                content_id,
+                media_type: (!media_type.is_empty()).then(|| media_type.to_string()),
+                folder: self.new_link_folder.trim().to_string(),
+            });
+        if ui
+            .add_enabled(link.is_some(), egui::Button::new("▶ Open"))
+            .clicked()
+        {
+            match (&link, &self.selected_device) {
+                (Some(link), Some(ip)) => {
+                    let (link, ip) = (link.clone(), ip.clone());
+                    self.last_msg = format!("Opening {}", link.content_id);
+                    self.worker
+                        .run(format!("Opening {}", link.content_id), move || {
+                            link.launch(&ip)
+                        });
+                }
+                _ => self.last_msg = "No Roku selected".into(),
+            }
+        }
+
+        egui::Grid::new("watchlist_save")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Name:");
+                ui.text_edit_singleline(&mut self.new_link_name);
+                ui.end_row();
+                ui.label("Folder:");
+                ui.text_edit_singleline(&mut self.new_link_folder);
+                ui.end_row();
+            });
+        let name = self.new_link_name.trim().to_string();
+        let ready = !name.is_empty() && link.is_some();
+        if ui
+            .add_enabled(ready, egui::Button::new("Save to Watchlist"))
+            .clicked()
+        {
+            if let Some(link) = link {
+                self.config.watchlist.insert(name.clone(), link);
+                self.config_dirty = true;
+                self.new_link_name.clear();
+                self.new_link_content_id.clear();
+                self.last_msg = format!("Saved {} to the watchlist", name);
+            }
+        }
+    }
+
+    // hands a search to the device, so it needn't be typed with the on-screen keyboard
+    fn roku_search_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Roku Search").show(ui, |ui| {
+            let search = &mut self.roku_search;
+            let submitted = ui
+                .horizontal(|ui| {
+                    let field = egui::TextEdit::singleline(&mut search.keyword)
+                        .hint_text("Search the Roku");
+                    let edit = ui.add(field);
+                    let entered =
+                        edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    let ready = !search.keyword.trim().is_empty();
+                    ui.add_enabled(ready, egui::Button::new("Search")).clicked()
+                        || (ready && entered)
+                })
+                .inner;
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_source("roku_search_type")
+                    .selected_text(if search.kind.is_empty() {
+                        "Anything"
+                    } else {
+                        search.kind.as_str()
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut search.kind, String::new(), "Anything");
+                        for kind in ecp::SEARCH_TYPES {
+                            ui.selectable_value(&mut search.kind, kind.to_string(), *kind);
+                        }
+                    });
+                ui.checkbox(&mut self.roku_search_exact, "Exact title");
+            });
+            ui.horizontal(|ui| {
+                let provider = search.provider_ids.first().cloned();
+                let name = provider
+                    .as_ref()
+                    .and_then(|id| self.apps.iter().find(|app| app.id == *id))
+                    .map(|app| app.name.as_str())
+                    .unwrap_or("Any channel");
+                let mut picked = provider.clone();
+                egui::ComboBox::from_id_source("roku_search_provider")
+                    .selected_text(name)
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut picked, None, "Any channel");
+                        for app in self.apps.iter().filter(|app| app.kind == "appl") {
+                            ui.selectable_value(&mut picked, Some(app.id.clone()), &app.name);
+                        }
+                    });
+                if picked != provider {
+                    search.provider_ids = picked.into_iter().collect();
+                }
+                // the device only plays a match straight away in a given channel
+                if search.provider_ids.is_empty() {
+                    search.launch = false;
+                }
+                let launch = egui::Checkbox::new(&mut search.launch, "Start playing");
+                ui.add_enabled(!search.provider_ids.is_empty(), launch)
+                    .on_disabled_hover_text("Pick a channel to play the match in");
+            });
+
+            if !submitted {
+                return;
+            }
+            let Some(ip) = self.selected_device.clone() else {
+                self.last_msg = "No Roku selected".into();
+                return;
+            };
+            let mut query = search.clone();
+            if self.roku_search_exact {
+                query.title = std::mem::take(&mut query.keyword);
+            }
+            self.last_msg = format!("Searching for {}", search.keyword.trim());
+            let what = format!("Searching for {}", search.keyword.trim());
+            self.worker
+                .run(what, move || ecp::search_browse(&ip, &query));
+        });
+    }
+
+    fn content_search_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Find Something to Watch").show(ui, |ui| {
+            if self.config.tmdb.api_key.trim().is_empty() {
+                ui.label(
+                    "Searching uses TMDB; paste an API key from themoviedb.org to turn it on.",
+                );
+                ui.horizontal(|ui| {
+                    ui.label("TMDB API key:");
+                    if ui
+                        .text_edit_singleline(&mut self.config.tmdb.api_key)
+                        .changed()
+                    {
+                        self.config_dirty = true;
+                    }
+                });
+                return;
+            }
+            ui.horizontal(|ui| {
+                let edit = ui.text_edit_singleline(&mut self.tmdb_query);
+                let submitted = edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                let query = self.tmdb_query.trim().to_string();
+                let ready = !query.is_empty() && !self.content_search.busy();
+                if ready && (submitted || ui.button("Search").clicked()) {
+                    self.content_search.search(&self.config.tmdb, &query);
+                    self.tmdb_channels = None;
+                }
+                if self.content_search.busy() {
+                    ui.spinner();
+                }
+            });
+
+            let mut picked = None;
+            for title in &self.tmdb_results {
+                let selected = self.tmdb_channels.as_ref().is_some_and(|(t, _)| t == title);
+                if ui.selectable_label(selected, title.label()).clicked() {
+                    picked = Some(title.clone());
+                }
+            }
+            if let Some(title) = picked {
+                if !self.content_search.busy() {
+                    self.content_search
+                        .find_channels(&self.config.tmdb, &title, &self.apps);
+                }
+            }
+
+            let Some((title, channels)) = &self.tmdb_channels else {
+                return;
+            };
+            ui.separator();
+            if channels.is_empty() {
+                ui.label(format!(
+                    "None of the installed channels carry {}",
+                    title.name
+                ));
+                return;
+            }
+            ui.label(format!("Play {} on:", title.label()));
+            let mut launch = None;
+            ui.horizontal_wrapped(|ui| {
+                for channel in channels {
+                    if ui.button(&channel.name).clicked() {
+                        launch = Some(channel.clone());
+                    }
+                }
+            });
+            if let (Some(channel), Some(ip)) = (launch, self.selected_device.clone()) {
+                self.last_msg = format!("Looking for {} on {}", title.name, channel.name);
+                let title = title.clone();
+                let what = format!("Searching {} for {}", channel.name, title.name);
+                self.worker.run(what, move || {
+                    ecp::search_and_launch(&ip, &title.name, title.kind.ecp_type(), &channel.id)
+                });
+            }
+        });
+    }
+
+    fn poll_content_search(&mut self, ctx: &egui::Context) {
+        if self.content_search.busy() {
+            ctx.request_repaint_after(Duration::from_millis(200));
+        }
+        match self.content_search.poll() {
+            Some(tmdb::Reply::Results(Ok(results))) => {
+                self.last_msg = format!("Found {} titles", results.len());
+                self.tmdb_results = results;
+            }
+            Some(tmdb::Reply::Channels(title, Ok(channels))) => {
+                self.tmdb_channels = Some((title, channels));
+            }
+            Some(tmdb::Reply::Results(Err(err)) | tmdb::Reply::Channels(_, Err(err))) => {
+                self.last_msg = format!("TMDB search failed: {}", err);
+            }
+            None => {}
+        }
+    }
+
+    // run a macro over several devices, one after another
+    fn fleet_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Fleet Setup").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                let mut loaded = None;
+                egui::ComboBox::from_id_source("fleet_macros")
+                    .selected_text("Saved macros")
+                    .show_ui(ui, |ui| {
+                        for name in self.config.macros.keys() {
+                            if ui
+                                .selectable_label(*name == self.fleet_script_name, name)
+                                .clicked()
+                            {
+                                loaded = Some(name.clone());
+                            }
+                        }
+                    });
+                if let Some(name) = loaded {
+                    self.fleet_script = self.config.macros[&name].clone();
+                    self.fleet_script_name = name;
+                }
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.fleet_script_name)
+                        .hint_text("macro name")
+                        .desired_width(120.0),
+                );
+                let name = self.fleet_script_name.trim().to_string();
+                if ui.button("Save").clicked() && !name.is_empty() {
+                    self.config
+                        .macros
+                        .insert(name.clone(), self.fleet_script.clone());
+                    self.config_dirty = true;
+                }
+                if self.config.macros.contains_key(&name) && ui.button("Delete").clicked() {
+                    self.config.macros.remove(&name);
+                    self.config_dirty = true;
+                }
+            });
+            ui.add(
+                egui::TextEdit::multiline(&mut self.fleet_script)
+                    .code_editor()
+                    .desired_rows(5)
+                    .hint_text("launch 12\nwait 5\nkey Home"),
+            )
+            .on_hover_text(macros::MACRO_HELP);
+
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Run on:");
+                for device in self.visible_devices() {
+                    let mut chosen = self.fleet_devices.contains(&device);
+                    if ui
+                        .checkbox(&mut chosen, self.device_label(&device))
+                        .changed()
+                    {
+                        if chosen {
+                            self.fleet_devices.push(device);
+                        } else {
+                            self.fleet_devices.retain(|d| *d != device);
+                        }
+                    }
+                }
+            });
+
+            let verify = ui
+                .checkbox(&mut self.config.macro_settings.verify, "Verify each step")
+                .on_hover_text(
+                    "Stop a device's run when a keypress isn't taken or doesn't change the screen",
+                );
+            if verify.changed() {
+                self.config_dirty = true;
+            }
+
+            let run = ui.add_enabled(
+                !self.fleet_running && !self.fleet_devices.is_empty(),
+                egui::Button::new("Run on selected devices"),
+            );
+            if run.clicked() {
+                match macros::parse(&self.fleet_script) {
+                    Ok(steps) if steps.is_empty() => {
+                        self.last_msg = "The macro has no steps".into()
+                    }
+                    Ok(steps) => {
+                        let verify = self.config.macro_settings.verify;
+                        self.fleet_run =
+                            Some(FleetRun::start(self.fleet_devices.clone(), steps, verify));
+                        self.fleet_running = true;
+                    }
+                    Err(err) => self.last_msg = format!("Macro error: {}", err),
+                }
+            }
+
+            if let Some(run) = &self.fleet_run {
+                egui::Grid::new("fleet_progress")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        for (device, progress) in run.devices.iter().zip(&run.progress) {
+                            ui.label(self.device_label(device));
+                            match progress {
+                                Progress::Pending => ui.weak("waiting"),
+                                Progress::Running(step) => {
+                                    ui.label(format!("step {}/{}", step + 1, run.steps))
+                                }
+                                Progress::Done => ui.label("done"),
+                                Progress::Failed(err) => {
+                                    ui.colored_label(ui.visuals().error_fg_color, err)
+                                }
+                            };
+                            ui.end_row();
+                        }
+                    });
+            }
+        });
+    }
+
+    // saved macros, run on the selected device or on every target while broadcasting, and
+    // recording a new one from the remote as it's used
+    fn macros_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Macros").show(ui, |ui| {
+            let running = self.macro_run.is_some();
+            let mut run = None;
+            for (name, script) in &self.config.macros {
+                ui.horizontal(|ui| {
+                    let parses = macros::parse(script).is_ok();
+                    if ui
+                        .add_enabled(!running && parses, egui::Button::new("▶ Run"))
+                        .clicked()
+                    {
+                        run = Some(name.clone());
+                    }
+                    ui.label(name).on_hover_text(script);
+                });
+            }
+            if self.config.macros.is_empty() {
+                ui.weak("No macros yet: record one, or write one under Fleet Setup");
+            }
+            if let Some(name) = run {
+                self.run_macro(&name);
+            }
+            if let Some((name, run)) = &self.macro_run {
+                for (device, progress) in run.devices.iter().zip(&run.progress) {
+                    let status = match progress {
+                        Progress::Pending => "waiting".to_string(),
+                        Progress::Running(step) => format!("step {}/{}", step + 1, run.steps),
+                        Progress::Done => "done".to_string(),
+                        Progress::Failed(err) => err.clone(),
+                    };
+                    ui.weak(format!(
+                        "{} on {}: {}",
+                        name,
+                        self.device_label(device),
+                        status
+                    ));
+                }
+            }
+
+            ui.horizontal(|ui| match &mut self.recorder {
+                None => {
+                    let record = ui.button("⏺ Record").on_hover_text(
+                        "Remote buttons, launches and text from here on become steps",
+                    );
+                    if record.clicked() {
+                        self.recorder = Some(macros::Recorder::default());
+                        self.last_msg = "Recording a macro; use the remote, then save it".into();
+                    }
+                }
+                Some(recorder) => {
+                    ui.colored_label(
+                        ui.visuals().error_fg_color,
+                        format!("⏺ Recording, {} step(s)", recorder.steps.len()),
+                    )
+                    .on_hover_text(recorder.script());
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.recording_name)
+                            .hint_text("macro name")
+                            .desired_width(120.0),
+                    );
+                    let name = self.recording_name.trim().to_string();
+                    let save = egui::Button::new("Stop and Save");
+                    if ui
+                        .add_enabled(!name.is_empty() && !recorder.steps.is_empty(), save)
+                        .clicked()
+                    {
+                        self.config.macros.insert(name.clone(), recorder.script());
+                        self.config_dirty = true;
+                        self.recorder = None;
+                        self.recording_name.clear();
+                        self.last_msg = format!("Saved macro {}", name);
+                    } else if ui.button("Discard").clicked() {
+                        self.recorder = None;
+                    }
+                }
+            });
+        });
+    }
+
+    fn scripts_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Scripts").show(ui, |ui| {
+            if !scripting::AVAILABLE {
+                ui.weak("Built without the scripting feature");
+                return;
+            }
+            let running = self
+                .script_run
+                .as_ref()
+                .is_some_and(|(_, run)| run.result.is_none());
+            let (mut run, mut remove) = (None, None);
+            for (name, path) in &self.config.scripts {
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(!running, egui::Button::new("▶ Run"))
+                        .clicked()
+                    {
+                        run = Some(name.clone());
+                    }
+                    ui.label(name).on_hover_text(path);
+                    let forget = ui
+                        .small_button("🗑")
+                        .on_hover_text("Forget the script; the file stays");
+                    if forget.clicked() {
+                        remove = Some(name.clone());
+                    }
+                });
+            }
+            if self.config.scripts.is_empty() {
+                ui.weak("No scripts yet: add a .rhai file below");
+            }
+            if let Some(name) = remove {
+                self.config.scripts.remove(&name);
+                self.config_dirty = true;
+            }
+            if let Some(name) = run {
+                self.run_script(ui.ctx(), &name);
+            }
+
+            let mut clear = false;
+            if let Some((name, run)) = &self.script_run {
+                ui.horizontal(|ui| match &run.result {
+                    None => {
+                        ui.spinner();
+                        ui.label(format!("Running {}", name));
+                        if ui.button("⏹ Stop").clicked() {
+                            run.stop();
+                        }
+                    }
+                    Some(result) => {
+                        match result {
+                            Ok(()) => ui.label(format!("{} finished", name)),
+                            Err(err) => ui.colored_label(
+                                ui.visuals().error_fg_color,
+                                format!("{}: {}", name, err),
+                            ),
+                        };
+                        clear = ui.button("Clear").clicked();
+                    }
+                });
+                egui::ScrollArea::vertical()
+                    .id_source("script_output")
+                    .max_height(160.0)
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        for line in &run.output {
+                            ui.monospace(line);
+                        }
+                    });
+            }
+            if clear {
+                self.script_run = None;
+            }
+
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.new_script_name)
+                        .hint_text("script name")
+                        .desired_width(100.0),
+                );
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.new_script_path)
+                        .hint_text("path to a .rhai file")
+                        .desired_width(180.0),
+                )
+                .on_hover_text(scripting::SCRIPT_HELP);
+                let (name, path) = (self.new_script_name.trim(), self.new_script_path.trim());
+                let add = ui.add_enabled(
+                    !name.is_empty() && !path.is_empty(),
+                    egui::Button::new("Add"),
+                );
+                if add.clicked() {
+                    self.config
+                        .scripts
+                        .insert(name.to_string(), path.to_string());
+                    self.config_dirty = true;
+                    self.new_script_name.clear();
+                    self.new_script_path.clear();
+                }
+            });
+        });
+    }
+
+    fn cast_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Play on Roku").show(ui, |ui| {
+            let mut stop = false;
+            if let Some(served) = &self.cast {
+                ui.horizontal(|ui| {
+                    let name = served
+                        .path
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy();
+                    ui.label(format!("Serving {} to {}", name, served.device))
+                        .on_hover_text(&served.url);
+                    stop = ui
+                        .button("⏹ Stop")
+                        .on_hover_text("Stop serving the file")
+                        .clicked();
+                });
+            }
+            if stop {
+                self.cast = None;
+            }
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.cast_path)
+                        .hint_text("path to a video, song or photo")
+                        .desired_width(240.0),
+                )
+                .on_hover_text("Or drop a video or song on the window");
+                let path = self.cast_path.trim().to_string();
+                let ready = !path.is_empty() && self.selected_device.is_some();
+                if ui.add_enabled(ready, egui::Button::new("▶ Play")).clicked() {
+                    self.cast_file(PathBuf::from(path));
+                }
+            });
+        });
+    }
+
+    // serves the file to the selected device in place of whatever was served before
+    fn cast_file(&mut self, path: PathBuf) {
+        let Some(device) = self.selected_device.clone() else {
+            self.last_msg = "Select a device to play on first".into();
+            return;
+        };
+        // the port is free again before the new server wants it
+        self.cast = None;
+        match cast::play_on_roku(&device, &path, cast::DEFAULT_PORT) {
+            Ok(served) => {
+                self.last_msg = format!("Playing {} on {}", path.display(), served.device);
+                self.cast_path = path.display().to_string();
+                self.cast = Some(served);
+            }
+            Err(err) => self.last_msg = err,
+        }
+    }
+
+    // videos and songs dropped on the window play on the selected device; photos are left to
+    // import_dropped_images
+    fn cast_dropped_media(&mut self, ctx: &egui::Context) {
+        let dropped = ctx.input(|i| i.raw.dropped_files.clone());
+        let media = dropped
+            .into_iter()
+            .filter_map(|file| file.path)
+            .find(|path| {
+                cast::media_type(path).is_some_and(|(kind, _, _)| kind != cast::MediaKind::Photo)
+            });
+        if let Some(path) = media {
+            self.cast_file(path);
+        }
+    }
+
+    // the file is read now, so edits made since it was added are picked up
+    fn run_script(&mut self, ctx: &egui::Context, name: &str) {
+        let Some(path) = self.config.scripts.get(name) else {
+            return;
+        };
+        match std::fs::read_to_string(path) {
+            Ok(source) => {
+                self.last_msg = format!("Running {}…", name);
+                let run = ScriptRun::start(source, self.config.ssdp.clone(), ctx);
+                self.script_run = Some((name.to_string(), run));
+            }
+            Err(err) => self.last_msg = format!("Script {}: {}: {}", name, path, err),
+        }
+    }
+
+    fn poll_script_run(&mut self) {
+        let Some((name, run)) = &mut self.script_run else {
+            return;
+        };
+        if run.result.is_some() || run.poll() {
+            return;
+        }
+        self.last_msg = match &run.result {
+            Some(Err(err)) => format!("Script {} failed: {}", name, err),
+            _ => format!("Script {} finished", name),
+        };
+    }
+
+    // a key or macro for later, e.g. PowerOff in 45 minutes, on the devices commands go to now
+    fn timers_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Timers").show(ui, |ui| {
+            let sleep = schedule::Action::Key(RokuKey::PowerOff.to_string());
+            ui.horizontal(|ui| {
+                ui.label("Sleep timer:");
+                for minutes in [15, 30, 60, 90] {
+                    if ui.button(format!("{} min", minutes)).clicked() {
+                        self.add_timer(sleep.clone(), timers::minutes_from_now(minutes));
+                    }
+                }
+            });
+
+            let action = self.timer_action.get_or_insert(sleep).clone();
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_source("timer_action")
+                    .selected_text(action.label())
+                    .show_ui(ui, |ui| {
+                        for name in self.config.macros.keys() {
+                            let pick = schedule::Action::Macro(name.clone());
+                            let label = pick.label();
+                            ui.selectable_value(&mut self.timer_action, Some(pick), label);
+                        }
+                        for key in remote_keys() {
+                            let pick = schedule::Action::Key(key.to_string());
+                            ui.selectable_value(&mut self.timer_action, Some(pick), key);
+                        }
+                    });
+                ui.selectable_value(&mut self.timer_at_clock, false, "in");
+                ui.selectable_value(&mut self.timer_at_clock, true, "at");
+                if self.timer_at_clock {
+                    let at = egui::TextEdit::singleline(&mut self.timer_at).hint_text("22:30");
+                    ui.add(at.desired_width(50.0));
+                } else {
+                    let minutes =
+                        egui::DragValue::new(&mut self.timer_minutes).clamp_range(1..=24 * 60);
+                    ui.add(minutes.suffix(" min"));
+                }
+                if ui.button("Add Timer").clicked() {
+                    let at = match self.timer_at_clock {
+                        true => timers::next_at(&self.timer_at),
+                        false => Some(timers::minutes_from_now(self.timer_minutes)),
+                    };
+                    match at {
+                        Some(at) => self.add_timer(action, at),
+                        None => {
+                            self.last_msg = "A time of day is hours and minutes, e.g. 22:30".into()
+                        }
+                    }
+                }
+            });
+
+            let mut cancelled = None;
+            for timer in self.timers.pending() {
+                ui.horizontal(|ui| {
+                    let devices = match timer.devices.as_slice() {
+                        [device] => self.device_label(device),
+                        devices => format!("{} devices", devices.len()),
+                    };
+                    ui.label(format!(
+                        "{} on {} at {}, in {}",
+                        timer.label(),
+                        devices,
+                        timer.at.format("%H:%M"),
+                        timer.remaining()
+                    ));
+                    if ui.small_button("Cancel").clicked() {
+                        cancelled = Some(timer.id);
+                    }
+                });
+            }
+            if let Some(id) = cancelled {
+                self.timers.cancel(id);
+                self.last_msg = "Timer cancelled".into();
+            }
+        });
+    }
+
+    fn add_timer(&mut self, action: schedule::Action, at: chrono::DateTime<chrono::Local>) {
+        let targets = self.command_targets();
+        if targets.is_empty() {
+            self.last_msg = "No Roku selected".into();
+            return;
+        }
+        let steps = match &action {
+            schedule::Action::Key(key) => Ok(vec![macros::Step::Key(key.clone())]),
+            schedule::Action::Macro(name) => match self.config.macros.get(name) {
+                Some(script) => macros::parse(script),
+                None => Err(format!("no macro named \"{}\"", name)),
+            },
+        };
+        match steps {
+            Ok(steps) => {
+                let verify = self.config.macro_settings.verify;
+                self.timers.add(action, steps, verify, targets, at);
+                self.last_msg = format!("Timer set for {}", at.format("%H:%M"));
+            }
+            Err(err) => self.last_msg = format!("Macro error: {}", err),
+        }
+    }
+
+    fn poll_timers(&mut self, ctx: &egui::Context) {
+        for (timer, results) in self.timers.fired() {
+            let failed: Vec<String> = results
+                .into_iter()
+                .filter_map(|(device, result)| Some(format!("{}: {}", device, result.err()?)))
+                .collect();
+            self.last_msg = match failed.is_empty() {
+                true => format!("Timer went off: {}", timer.label()),
+                false => format!("Timer {} failed on {}", timer.label(), failed.join(", ")),
+            };
+        }
+        // the countdowns tick over every second
+        if self.timers.busy() {
+            ctx.request_repaint_after(Duration::from_secs(1));
+        }
+    }
+
+    fn run_macro(&mut self, name: &str) {
+        if self.macro_run.is_some() {
+            self.last_msg = "Another macro is still running".into();
+            return;
+        }
+        let steps = match self
+            .config
+            .macros
+            .get(name)
+            .map(|script| macros::parse(script))
+        {
+            Some(Ok(steps)) => steps,
+            Some(Err(err)) => {
+                self.last_msg = format!("Macro error: {}", err);
+                return;
+            }
+            None => return,
+        };
+        let targets = self.command_targets();
+        if targets.is_empty() {
+            self.last_msg = "No Roku selected".into();
+            return;
+        }
+        self.last_msg = format!("Running {}…", name);
+        let verify = self.config.macro_settings.verify;
+        self.macro_run = Some((name.to_string(), FleetRun::start(targets, steps, verify)));
+    }
+
+    fn poll_macro_run(&mut self, ctx: &egui::Context) {
+        let Some((name, run)) = &mut self.macro_run else {
+            return;
+        };
+        if run.poll() {
+            ctx.request_repaint_after(Duration::from_millis(200));
+            return;
+        }
+        let failed = run
+            .devices
+            .iter()
+            .zip(&run.progress)
+            .find_map(|(device, progress)| match progress {
+                Progress::Failed(err) => Some((device, err)),
+                _ => None,
+            });
+        self.last_msg = match (failed, run.failures()) {
+            (None, _) => format!("Macro {} finished", name),
+            (Some((device, err)), 1) => format!("Macro {} failed on {}: {}", name, device, err),
+            (_, n) => format!(
+                "Macro {} failed on {} of {} device(s)",
+                name,
+                n,
+                run.devices.len()
+            ),
+        };
+        self.macro_run = None;
+    }
+
+    fn poll_fleet_run(&mut self, ctx: &egui::Context) {
+        let Some(run) = &mut self.fleet_run else {
+            return;
+        };
+        if run.poll() {
+            ctx.request_repaint_after(Duration::from_millis(200));
+        } else if self.fleet_running {
+            self.fleet_running = false;
+            self.last_msg = format!(
+                "Fleet setup finished: {} of {} device(s) failed",
+                run.failures(),
+                run.devices.len()
+            );
+        }
+    }
+
+    // a file of channel ids installed one after another on the selected device
+    fn bulk_install_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Bulk Install").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Channel list:");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.bulk_list_path)
+                        .hint_text("channels.txt or channels.json"),
+                )
+                .on_hover_text("One channel id per line, or a JSON array of ids");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Export installed channels:");
+                for ext in ["json", "csv"] {
+                    let button = egui::Button::new(ext.to_uppercase());
+                    if ui.add_enabled(!self.apps.is_empty(), button).clicked() {
+                        self.last_msg = match self.export_channels(ext) {
+                            Ok(path) => format!("Channel list saved to {}", path.display()),
+                            Err(err) => format!("Couldn't save the channel list: {}", err),
+                        };
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Or channel IDs:");
+                let ids =
+                    egui::TextEdit::singleline(&mut self.install_ids).hint_text("12, 837, 291097");
+                ui.add(ids).on_hover_text(
+                    "Channel-store ids split by commas or spaces; used over the list",
+                );
+            });
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Install on:");
+                for device in self.visible_devices() {
+                    let mut chosen = self.install_devices.contains(&device);
+                    if ui
+                        .checkbox(&mut chosen, self.device_label(&device))
+                        .changed()
+                    {
+                        if chosen {
+                            self.install_devices.push(device);
+                        } else {
+                            self.install_devices.retain(|d| *d != device);
+                        }
+                    }
+                }
+            });
+            let targets = if self.install_devices.is_empty() {
+                self.selected_device.iter().cloned().collect()
+            } else {
+                self.install_devices.clone()
+            };
+            let label = match targets.len() {
+                0 | 1 => "Install on selected device".to_string(),
+                n => format!("Install on {} devices", n),
+            };
+            let ready = !self.bulk_installing && !targets.is_empty();
+            let start = ui.add_enabled(ready, egui::Button::new(label));
+            if start.clicked() {
+                let ids: Vec<String> = self
+                    .install_ids
+                    .split([',', ' ', '\t', '\n'])
+                    .map(str::trim)
+                    .filter(|id| !id.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                let path = self.bulk_list_path.trim();
+                let list = if ids.is_empty() {
+                    std::fs::read_to_string(path)
+                        .map_err(|err| err.to_string())
+                        .and_then(|text| bulk_install::parse_list(&text))
+                        .map_err(|err| format!("Channel list {}: {}", path, err))
+                } else {
+                    Ok(ids)
+                };
+                match list {
+                    Ok(channels) if channels.is_empty() => {
+                        self.last_msg = "The channel list is empty".into()
+                    }
+                    Ok(channels) => self.start_installs(targets, channels),
+                    Err(err) => self.last_msg = err,
+                }
+            }
+
+            self.lineup_ui(ui);
+
+            for run in &self.bulk_installs {
+                ui.label(format!("On {}:", self.device_label(&run.device)));
+                let names: HashMap<&str, &str> = self
+                    .apps
+                    .iter()
+                    .map(|app| (app.id.as_str(), app.name.as_str()))
+                    .collect();
+                egui::Grid::new(("bulk_install_progress", &run.device))
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        for (channel, status) in run.channels.iter().zip(&run.status) {
+                            match names.get(channel.as_str()) {
+                                Some(name) => ui.label(format!("{} ({})", name, channel)),
+                                None => ui.label(channel),
+                            };
+                            match status {
+                                bulk_install::Status::Failed(err) => {
+                                    ui.colored_label(ui.visuals().error_fg_color, err)
+                                }
+                                bulk_install::Status::Pending => ui.weak(status.label()),
+                                _ => ui.label(status.label()),
+                            };
+                            ui.end_row();
+                        }
+                    });
+            }
+        });
+    }
+
+    // compares two devices' channels and offers to install what the target lacks
+    fn lineup_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Match Another Device").show(ui, |ui| {
+            let devices: Vec<(String, String)> = self
+                .visible_devices()
+                .into_iter()
+                .map(|device| {
+                    let label = self.device_label(&device);
+                    (device, label)
+                })
+                .collect();
+            let mut changed = false;
+            ui.horizontal(|ui| {
+                let pickers = [
+                    ("Copy from", &mut self.lineup_source),
+                    ("to", &mut self.lineup_target),
+                ];
+                for (label, chosen) in pickers {
+                    ui.label(label);
+                    let shown = devices
+                        .iter()
+                        .find(|(device, _)| Some(device) == chosen.as_ref())
+                        .map(|(_, name)| name.as_str())
+                        .unwrap_or("None");
+                    egui::ComboBox::from_id_source(label)
+                        .selected_text(shown)
+                        .show_ui(ui, |ui| {
+                            for (device, name) in &devices {
+                                if ui
+                                    .selectable_label(chosen.as_ref() == Some(device), name)
+                                    .clicked()
+                                {
+                                    *chosen = Some(device.clone());
+                                    changed = true;
+                                }
+                            }
+                        });
+                }
+            });
+            if changed {
+                self.lineup_diff = None;
+            }
+
+            let (Some(source), Some(target)) =
+                (self.lineup_source.clone(), self.lineup_target.clone())
+            else {
+                return;
+            };
+            if source == target {
+                ui.weak("Pick two different devices");
+                return;
+            }
+            if ui.button("Compare").clicked() {
+                self.worker.ask(move || {
+                    let (from, to) = match (ecp::get_apps(&source), ecp::get_apps(&target)) {
+                        (Ok(from), Ok(to)) => (from, to),
+                        (Err(err), _) | (_, Err(err)) => {
+                            return Some(Reply::Failed(format!(
+                                "Comparing lineups failed: {}",
+                                err
+                            )));
+                        }
+                    };
+                    Some(Reply::Lineups(source, from, target, to))
+                });
+                return;
+            }
+            let Some(diff) = &self.lineup_diff else {
+                return;
+            };
+            if diff.missing.is_empty() {
+                ui.label("The target already has every channel");
+            } else {
+                ui.label(format!("Missing on {}:", self.device_label(&target)));
+                for app in &diff.missing {
+                    ui.label(format!("  {} ({})", app.name, app.id));
+                }
+            }
+            if !diff.extra.is_empty() {
+                ui.weak(format!("Only on {}:", self.device_label(&target)));
+                for app in &diff.extra {
+                    ui.weak(format!("  {} ({})", app.name, app.id));
+                }
+            }
+            let install = ui.add_enabled(
+                !self.bulk_installing && !diff.missing.is_empty(),
+                egui::Button::new(format!("Install {} missing channel(s)", diff.missing.len())),
+            );
+            if install.clicked() {
+                let channels = diff.missing.iter().map(|app| app.id.clone()).collect();
+                self.start_installs(vec![target], channels);
+                self.lineup_diff = None;
+            }
+        });
+    }
+
+    // the selected device's channels into the documents folder (or home)
+    fn export_channels(&self, ext: &str) -> std::io::Result<PathBuf> {
+        let dirs = directories::UserDirs::new()
+            .ok_or_else(|| std::io::Error::other("no home directory"))?;
+        let dir = dirs.document_dir().unwrap_or_else(|| dirs.home_dir());
+        let device: String = self
+            .selected_device
+            .as_deref()
+            .unwrap_or_default()
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '-' })
+            .collect();
+        let path = dir.join(format!("roku-channels-{}.{}", device, ext));
+        std::fs::write(&path, bulk_install::export_for(&path, &self.apps))?;
+        Ok(path)
+    }
+
+    // the same channels on every device at once, each device working through them in turn
+    fn start_installs(&mut self, devices: Vec<String>, channels: Vec<String>) {
+        self.bulk_installs = devices
+            .into_iter()
+            .map(|device| BulkInstall::start(device, channels.clone()))
+            .collect();
+        self.bulk_installing = true;
+    }
+
+    fn poll_bulk_install(&mut self, ctx: &egui::Context) {
+        let mut busy = false;
+        for run in &mut self.bulk_installs {
+            busy |= run.poll();
+        }
+        if busy {
+            ctx.request_repaint_after(Duration::from_millis(500));
+        } else if self.bulk_installing {
+            self.bulk_installing = false;
+            let failures: usize = self.bulk_installs.iter().map(BulkInstall::failures).sum();
+            let total: usize = self
+                .bulk_installs
+                .iter()
+                .map(|run| run.channels.len())
+                .sum();
+            self.last_msg = match self.bulk_installs.len() {
+                1 => format!(
+                    "Bulk install finished: {} of {} channel(s) failed",
+                    failures, total
+                ),
+                n => format!(
+                    "Bulk install on {} devices finished: {} of {} install(s) failed",
+                    n, failures, total
+                ),
+            };
+        }
+    }
+
+    fn gallery_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Screenshots").show(ui, |ui| {
+            if let Some(device) = self.selected_device.clone() {
+                self.capture_ui(ui, &device);
+            }
+            if self.gallery.shots.is_empty() {
+                ui.label(
+                    "No screenshots yet. Capture one from a device in developer mode, or drop \
+                     images on the window to file them under the selected device.",
+                );
+                return;
+            }
+            let mut action = None;
+            egui::ScrollArea::vertical()
+                .max_height(420.0)
+                .show(ui, |ui| {
+                    for (index, shot) in self.gallery.shots.clone().iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            match self.gallery.thumbnail(ui.ctx(), shot) {
+                                Some(texture) => {
+                                    ui.add(egui::Image::new(&texture).max_width(160.0));
+                                }
+                                None => {
+                                    ui.weak("(missing)");
+                                }
+                            }
+                            ui.vertical(|ui| {
+                                ui.label(shot.label());
+                                ui.horizontal(|ui| {
+                                    let save = ui.button("Save");
+                                    if save
+                                        .on_hover_text("Copy into your pictures folder")
+                                        .clicked()
+                                    {
+                                        action = Some(("save", index));
+                                    }
+                                    let copy = ui.button("Copy");
+                                    if copy
+                                        .on_hover_text("Copy the image to the clipboard")
+                                        .clicked()
+                                    {
+                                        action = Some(("copy", index));
+                                    }
+                                    if ui.button("Delete").clicked() {
+                                        action = Some(("delete", index));
+                                    }
+                                });
+                            });
+                        });
+                    }
+                });
+
+            let Some((action, index)) = action else {
+                return;
+            };
+            let shot = self.gallery.shots[index].clone();
+            self.last_msg = match action {
+                "save" => match self.gallery.export(&shot) {
+                    Ok(path) => format!("Saved to {}", path.display()),
+                    Err(err) => format!("Couldn't save the screenshot: {}", err),
+                },
+                "copy" => match self.gallery.copy_to_clipboard(&shot) {
+                    Ok(()) => "Screenshot copied".into(),
+                    Err(err) => format!("Couldn't copy the screenshot: {}", err),
+                },
+                _ => match self.gallery.delete(index) {
+                    Ok(()) => "Screenshot deleted".into(),
+                    Err(err) => format!("Couldn't delete the screenshot: {}", err),
+                },
+            };
+        });
+    }
+
+    // the dev channel's screen, through the developer installer with the device's password
+    fn capture_ui(&mut self, ui: &mut egui::Ui, device: &str) {
+        if self
+            .device_info
+            .get(device)
+            .map(|info| info.developer_enabled)
+            == Some(false)
+        {
+            ui.weak("Turn on developer mode on the device to capture its screen");
+            return;
+        }
+        let password = self.dev_password(device);
+        ui.horizontal(|ui| {
+            if self.capturing.contains(device) {
+                ui.spinner();
+                return;
+            }
+            let capture = ui
+                .add_enabled(!password.is_empty(), egui::Button::new("📷 Capture"))
+                .on_hover_text("Screenshot the dev channel through the developer installer")
+                .on_disabled_hover_text("Set the developer password under Developer first");
+            if capture.clicked() {
+                self.capture_screenshot(device, password);
+            }
+        });
+    }
+
+    fn dev_password(&self, device: &str) -> String {
+        self.config
+            .devices
+            .get(device)
+            .map(|meta| meta.dev_password.clone())
+            .unwrap_or_default()
+    }
+
+    // the developer installer: the password for it, and a channel zip to sideload
+    fn developer_ui(&mut self, ui: &mut egui::Ui, device: &str) {
+        egui::CollapsingHeader::new("Developer").show(ui, |ui| {
+            if self
+                .device_info
+                .get(device)
+                .map(|info| info.developer_enabled)
+                == Some(false)
+            {
+                // the remote sequence that opens the developer settings screen
+                let sequence = "Home ×3, Up ×2, Right, Left, Right, Left, Right";
+                ui.weak(format!("Developer mode is off: {} turns it on", sequence));
+                return;
+            }
+            ui.horizontal(|ui| {
+                let meta = self.config.devices.entry(device.to_string()).or_default();
+                ui.label("Installer password:");
+                let field = ui.add(
+                    egui::TextEdit::singleline(&mut meta.dev_password)
+                        .password(true)
+                        .desired_width(120.0),
+                );
+                self.config_dirty |= field.changed();
+            });
+            let password = self.dev_password(device);
+            if self.sideloading.contains(device) {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Talking to the developer installer…");
+                });
+                return;
+            }
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.sideload_path)
+                        .hint_text("path to a channel .zip")
+                        .desired_width(200.0),
+                );
+                let zip = PathBuf::from(self.sideload_path.trim());
+                let ready = !password.is_empty() && !self.sideload_path.trim().is_empty();
+                if ui
+                    .add_enabled(ready, egui::Button::new("Install"))
+                    .clicked()
+                {
+                    self.sideload(device, password.clone(), Some((zip.clone(), false)));
+                }
+                let replace = ui
+                    .add_enabled(ready, egui::Button::new("Replace"))
+                    .on_hover_text("Install even if it's the same zip as the installed one");
+                if replace.clicked() {
+                    self.sideload(device, password.clone(), Some((zip, true)));
+                }
+                let delete = ui
+                    .add_enabled(!password.is_empty(), egui::Button::new("🗑 Delete"))
+                    .on_hover_text("Take the dev channel off the device");
+                if delete.clicked() {
+                    self.sideload(device, password, None);
+                }
+            });
+        });
+    }
+
+    // installs the zip, replacing what's there if asked, or with None deletes the dev channel
+    fn sideload(&mut self, device: &str, password: String, zip: Option<(PathBuf, bool)>) {
+        let bytes = match &zip {
+            Some((path, _)) => match std::fs::read(path) {
+                Ok(bytes) => bytes,
+                Err(err) => {
+                    self.last_msg = format!("{}: {}", path.display(), err);
+                    return;
+                }
+            },
+            None => Vec::new(),
+        };
+        self.sideloading.insert(device.to_string());
+        let device = device.to_string();
+        self.worker.ask(move || {
+            let result = match zip {
+                Some((path, replace)) => {
+                    let name = path
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .into_owned();
+                    dev_installer::install(&device, &password, &name, &bytes, replace)
+                }
+                None => dev_installer::delete(&device, &password),
+            };
+            Some(Reply::Sideloaded(device, result))
+        });
+    }
+
+    fn capture_screenshot(&mut self, device: &str, password: String) {
+        self.capturing.insert(device.to_string());
+        let device = device.to_string();
+        self.worker.ask(move || {
+            let shot = dev_installer::screenshot(&device, &password);
+            Some(Reply::Screenshot(device, shot))
+        });
+    }
+
+    fn finish_screenshot(&mut self, device: String, shot: Result<(Vec<u8>, String), String>) {
+        self.capturing.remove(&device);
+        self.last_msg = match shot.and_then(|(bytes, ext)| {
+            self.gallery
+                .add(&device, &bytes, &ext)
+                .map_err(|err| err.to_string())
+        }) {
+            Ok(_) => format!("Captured {}", self.device_label(&device)),
+            Err(err) => format!("Couldn't capture {}: {}", self.device_label(&device), err),
+        };
+    }
+
+    // images dropped on the window go into the gallery, filed under the selected device
+    fn import_dropped_images(&mut self, ctx: &egui::Context) {
+        let dropped = ctx.input(|i| i.raw.dropped_files.clone());
+        for path in dropped.into_iter().filter_map(|file| file.path) {
+            let ext = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .map(str::to_lowercase)
+                .unwrap_or_default();
+            if !matches!(ext.as_str(), "png" | "jpg" | "jpeg") {
+                continue;
+            }
+            let device = self
+                .selected_device
+                .clone()
+                .unwrap_or_else(|| "unknown device".into());
+            let added =
+                std::fs::read(&path).and_then(|bytes| self.gallery.add(&device, &bytes, &ext));
+            self.last_msg = match added {
+                Ok(_) => format!("Added {} to the screenshots", path.display()),
+                Err(err) => format!("Couldn't add {}: {}", path.display(), err),
+            };
+        }
+    }
+
+    fn set_sync_folder(&mut self, folder: Option<PathBuf>) {
+        let syncing = folder.is_some();
+        self.last_msg = match self.store.set_sync_folder(folder, &mut self.config) {
+            Ok(0) if syncing => "Syncing settings through folder".into(),
+            Ok(0) => "Settings stored locally".into(),
+            Ok(n) => format!(
+                "Merged settings, kept local version of {} conflicting edit(s)",
+                n
+            ),
+            Err(err) => format!("Failed to change sync folder: {}", err),
+        };
+    }
+
+    fn save_config(&mut self) {
+        http::configure(&self.config.requests);
+        match self.store.save(&mut self.config) {
+            Ok(0) => {}
+            Ok(n) => {
+                self.last_msg = format!(
+                    "Merged synced settings, kept local version of {} conflicting edit(s)",
+                    n
+                )
+            }
+            Err(err) => self.last_msg = format!("Failed to save config: {}", err),
+        }
+    }
+
+    // another machine may have saved to the synced folder while we were running
+    fn poll_synced_config(&mut self, ctx: &egui::Context) {
+        if self.store.sync.folder.is_none() || self.config_dirty {
+            return;
+        }
+        ctx.request_repaint_after(SYNC_POLL_INTERVAL);
+        if self
+            .last_sync_check
+            .is_some_and(|at| at.elapsed() < SYNC_POLL_INTERVAL)
+        {
+            return;
+        }
+        self.last_sync_check = Some(Instant::now());
+        if self.store.poll_external(&mut self.config) {
+            http::configure(&self.config.requests);
+            self.last_msg = "Loaded settings changed on another machine".into();
+        }
+    }
+
+    fn type_via_keyboard(&mut self, ip: &str, text: &str) {
+        let plan = osk::LAYOUTS[self.osk_layout].plan(text);
+        self.last_msg = format!("Typing \"{}\" with {} keypresses", text, plan.keys.len());
+        if !plan.skipped.is_empty() {
+            let skipped: String = plan.skipped.iter().collect();
+            self.last_msg += &format!(" (no key for \"{}\")", skipped);
+        }
+        let ip = ip.to_string();
+        self.worker.ask(move || {
+            let result = osk::type_keys(&ip, &plan.keys);
+            let err = result.err()?;
+            Some(Reply::Failed(format!(
+                "Typing with the on-screen keyboard stopped: {}",
+                err
+            )))
+        });
+    }
 
-// establish a list of roku commands
-const ROKU_COMMANDS: &[&[&str]] = &[
-    &["Power", "Poweron", "Poweroff"],
-    &["Home", "Info", "Back"],
-    &[" ", " ", " "],
-    &[" ", "Up", " "],
-    &["Left", "Select", "Right"],
-    &[" ", "Down", " "],
-    &[" ", " ", "Play"],
-    &["VolumeUp", "VolumeDown", "VolumeMute"],
-    &["Channel_up", "Channel_down", "Search"],
-    &["Enter", "Backspace", "Find_remote"],
-    &["Replay", "Reverse", "Forward"],
-];
-// app
-fn main() -> Result<(), eframe::Error> {
-    let native_options = eframe::NativeOptions::default();
-    eframe::run_native(
-        "🦀 Roku Remote",
-        native_options,
-        Box::new(|_cc| Box::new(RokuRemoteApp::default())),
-    )
+    // looks for new devices and pings the listed ones every so often, as the settings say
+    fn run_background_checks(&mut self, ctx: &egui::Context) {
+        let checks = self.config.background.clone();
+        if let Some(every) = checks.rediscover_every() {
+            let last = *self.last_rediscovery.get_or_insert_with(Instant::now);
+            if last.elapsed() >= every && !self.discovering {
+                self.last_rediscovery = Some(Instant::now());
+                let ssdp = self.config.ssdp.clone();
+                self.worker
+                    .ask(move || Some(Reply::Rediscovered(ecp::discover_devices(&ssdp))));
+            }
+            ctx.request_repaint_after(every.saturating_sub(last.elapsed()));
+        }
+        if let Some(every) = checks.ping_every() {
+            let last = *self.last_ping.get_or_insert_with(Instant::now);
+            if last.elapsed() >= every {
+                self.last_ping = Some(Instant::now());
+                self.reachability.refresh(&self.devices);
+            }
+            ctx.request_repaint_after(every.saturating_sub(last.elapsed()));
+        }
+    }
+
+    // the addresses discovery found, after moving known devices whose serial turned up at
+    // another address over to it, and how many had moved
+    fn follow_moved(&mut self, found: Vec<Discovered>) -> (Vec<String>, usize) {
+        let mut moved = 0;
+        let found = found
+            .into_iter()
+            .map(|device| {
+                if let Some(serial) = &device.serial {
+                    moved += self.note_serial(&device.address, serial) as usize;
+                }
+                device.address
+            })
+            .collect();
+        (found, moved)
+    }
+
+    // devices background discovery turned up that aren't listed yet; the rest stays as it is
+    fn merge_rediscovered(&mut self, found: Vec<Discovered>) {
+        let (found, _) = self.follow_moved(found);
+        let new: Vec<String> = found
+            .into_iter()
+            .filter(|device| !self.devices.contains(device))
+            .collect();
+        if new.is_empty() {
+            return;
+        }
+        self.last_msg = format!("Found {} new device(s)", new.len());
+        for device in &new {
+            self.fetch_device_info(device);
+        }
+        self.devices.extend(new);
+        self.reachability.refresh(&self.devices);
+    }
+
+    // green for a device that answered its last ping, red for one that didn't
+    fn status_dot(&self, ui: &mut egui::Ui, device: &str) {
+        let (color, hover) = match self.reachability.online(device) {
+            Some(true) => (egui::Color32::from_rgb(60, 180, 75), "Online"),
+            Some(false) => (
+                egui::Color32::from_rgb(220, 50, 47),
+                "Offline: not answering device-info",
+            ),
+            None => (ui.visuals().weak_text_color(), "Not checked yet"),
+        };
+        ui.colored_label(color, "●").on_hover_text(hover);
+    }
+
+    // re-sorts the device list as probe results come in
+    fn poll_reachability(&mut self, ctx: &egui::Context) {
+        if self.reachability.poll() {
+            self.reachability.sort(&mut self.devices);
+        }
+        if self.reachability.busy() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+            return;
+        }
+        if std::mem::take(&mut self.trying_saved) {
+            let answered: Vec<String> = self
+                .devices
+                .iter()
+                .filter(|device| matches!(self.reachability.latency(device), Some(Some(_))))
+                .filter(|device| !demo::is_demo(device))
+                .cloned()
+                .collect();
+            self.last_msg = match answered.first() {
+                Some(first) => {
+                    if self.selected_device.is_none() {
+                        self.select_device(first.clone());
+                    }
+                    format!(
+                        "Discovery found nothing; {} saved device(s) answered at their last address",
+                        answered.len()
+                    )
+                }
+                None => "Discovery found nothing and no saved device answered".into(),
+            };
+        }
+    }
+
+    // what was sent where and when, newest at the bottom, failures in red
+    fn history_ui(&mut self, ui: &mut egui::Ui) {
+        let title = match self.history.failures() {
+            0 => format!("History ({})", self.history.entries.len()),
+            n => format!("History ({}, {} failed)", self.history.entries.len(), n),
+        };
+        egui::CollapsingHeader::new(title)
+            .id_source("history")
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    let any = !self.history.entries.is_empty();
+                    if ui.add_enabled(any, egui::Button::new("Export…")).clicked() {
+                        self.last_msg = match self.export_history() {
+                            Ok(path) => format!("Saved the history to {}", path.display()),
+                            Err(err) => format!("Couldn't save the history: {}", err),
+                        };
+                    }
+                    if ui.add_enabled(any, egui::Button::new("Clear")).clicked() {
+                        self.history.entries.clear();
+                    }
+                });
+                egui::ScrollArea::vertical()
+                    .max_height(200.0)
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        egui::Grid::new("history_grid")
+                            .num_columns(4)
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for entry in &self.history.entries {
+                                    ui.weak(entry.at.format("%H:%M:%S").to_string());
+                                    ui.label(&entry.device);
+                                    ui.label(&entry.command);
+                                    match &entry.result {
+                                        Ok(()) => ui.label("ok"),
+                                        Err(err) => {
+                                            ui.colored_label(ui.visuals().error_fg_color, err)
+                                        }
+                                    };
+                                    ui.end_row();
+                                }
+                            });
+                    });
+            });
+    }
+
+    // as CSV into the documents folder (or home), named for when it was saved
+    fn export_history(&self) -> std::io::Result<PathBuf> {
+        let dirs = directories::UserDirs::new()
+            .ok_or_else(|| std::io::Error::other("no home directory"))?;
+        let dir = dirs.document_dir().unwrap_or_else(|| dirs.home_dir());
+        let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+        let path = dir.join(format!("roku-history-{}.csv", stamp));
+        std::fs::write(&path, self.history.to_csv())?;
+        Ok(path)
+    }
+
+    // types the text on the device, with Lit_ presses or the channel's keyboard. pasted text
+    // is left out of messages, the history and a macro being recorded
+    fn send_text_input(&mut self, ip: &str, text: String, pasted: bool) {
+        let shown = if pasted {
+            format!("the pasted text ({} characters)", text.chars().count())
+        } else {
+            format!("\"{}\"", text)
+        };
+        if let Some(recorder) = self.recorder.as_mut().filter(|_| !pasted) {
+            recorder.record(macros::Step::Text(text.clone()));
+        }
+        if self.osk_mode {
+            self.type_via_keyboard(ip, &text);
+            if pasted {
+                self.last_msg = format!("Typing {} with arrows + Select", shown);
+            }
+            return;
+        }
+        self.last_msg = format!("Sent text: {}", shown);
+        self.lit_fallback = None;
+        let check = lit_check::send_and_check(ip.to_string(), text.clone());
+        self.lit_check = Some((ip.to_string(), text, shown, check));
+    }
+
+    // the clipboard's text, or what a paste shortcut brought with it, typed on the selected
+    // device. a copied password usually ends in a newline, which isn't part of it
+    fn paste_and_send(&mut self, pasted: Option<String>) {
+        let Some(ip) = self.selected_device.clone() else {
+            self.last_msg = "No Roku selected".into();
+            return;
+        };
+        let text = match pasted {
+            Some(text) => Ok(text),
+            None => arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get_text()),
+        };
+        match text.map(|text| text.trim_end_matches(['\r', '\n']).to_string()) {
+            Ok(text) if !text.is_empty() => self.send_text_input(&ip, text, true),
+            Ok(_) => self.last_msg = "Nothing to paste: the clipboard has no text".into(),
+            Err(err) => self.last_msg = format!("Couldn't read the clipboard: {}", err),
+        }
+    }
+
+    fn poll_lit_check(&mut self, ctx: &egui::Context) {
+        let Some((device, text, shown, rx)) = &self.lit_check else {
+            return;
+        };
+        let typed = format!("text {}", shown);
+        match rx.try_recv() {
+            Ok(LitCheck::Ignored) => {
+                self.lit_fallback = Some(text.clone());
+                self.last_msg = format!("{} may not have arrived", shown);
+                let result = Err("the channel didn't react".into());
+                self.history
+                    .record(SystemTime::now(), device, &typed, result);
+            }
+            Ok(LitCheck::Failed(err)) => {
+                self.last_msg = format!("Typing {} failed: {}", shown, err);
+                self.history
+                    .record(SystemTime::now(), device, &typed, Err(err.to_string()));
+            }
+            Ok(LitCheck::Cancelled(sent)) => {
+                let total = text.chars().count();
+                self.last_msg = format!("Stopped typing after {} of {} characters", sent, total);
+                self.history
+                    .record(SystemTime::now(), device, &typed, Err("cancelled".into()));
+            }
+            Ok(_) => self
+                .history
+                .record(SystemTime::now(), device, &typed, Ok(())),
+            Err(std::sync::mpsc::TryRecvError::Empty) => {
+                ctx.request_repaint_after(Duration::from_millis(100));
+                return;
+            }
+            Err(std::sync::mpsc::TryRecvError::Disconnected) => {}
+        }
+        self.lit_check = None;
+    }
+
+    // a device with a tab comes back as it was left; any other has its channels fetched anew
+    fn select_device(&mut self, device: String) {
+        let leaving = self.selected_device.take().filter(|old| *old != device);
+        if let Some(old) = leaving.filter(|old| self.tabs.is_open(old)) {
+            let mut tab = Tab::default();
+            self.swap_tab(&mut tab);
+            self.tabs.waiting.insert(old, tab);
+        }
+        self.selected_device = Some(device.clone());
+        if let Some(mut tab) = self.tabs.waiting.remove(&device) {
+            // what was in front without a tab is dropped, its watcher with it
+            self.swap_tab(&mut tab);
+            self.now_playing.reshow();
+            return;
+        }
+        self.apps.clear();
+        self.last_msg = "Fetching apps…".into();
+        if !self.device_info.contains_key(&device) {
+            self.fetch_device_info(&device);
+        }
+        self.fetch_apps(&device);
+    }
+
+    fn fetch_apps(&self, device: &str) {
+        let device = device.to_string();
+        self.worker.ask(move || {
+            let apps = get_apps(&device);
+            Some(Reply::Apps(device, apps))
+        });
+    }
+
+    // trades the state of the device in front for a tab's
+    fn swap_tab(&mut self, tab: &mut Tab) {
+        std::mem::swap(&mut self.apps, &mut tab.apps);
+        std::mem::swap(&mut self.selected_app, &mut tab.selected_app);
+        std::mem::swap(&mut self.last_msg, &mut tab.last_msg);
+        std::mem::swap(&mut self.now_playing, &mut tab.now_playing);
+    }
+
+    // the device's name and what it's showing, shorter than device_label to fit a tab
+    fn tab_label(&self, device: &str) -> String {
+        let name = match self.device_info.get(device) {
+            Some(info) if !info.name.is_empty() => info.name.clone(),
+            _ if demo::is_demo(device) => "Demo Roku".into(),
+            _ => device.to_string(),
+        };
+        let now = match self.tabs.waiting.get(device) {
+            Some(tab) => &tab.now_playing.current,
+            None => &self.now_playing.current,
+        };
+        match &now.app {
+            Some(app) if now.app_id().is_some() => format!("{} · {}", name, app.name),
+            _ => name,
+        }
+    }
+
+    // one tab per device kept open; each one's last status shows on hover
+    fn tabs_ui(&mut self, ui: &mut egui::Ui) {
+        if self.tabs.open.is_empty() {
+            return;
+        }
+        let (mut picked, mut closed) = (None, None);
+        ui.horizontal_wrapped(|ui| {
+            for device in &self.tabs.open {
+                let front = self.selected_device.as_ref() == Some(device);
+                let last_msg = match self.tabs.waiting.get(device) {
+                    Some(tab) => &tab.last_msg,
+                    None => &self.last_msg,
+                };
+                ui.group(|ui| {
+                    self.status_dot(ui, device);
+                    let tab = ui.selectable_label(front, self.tab_label(device));
+                    if tab
+                        .on_hover_text(format!("{}\n{}", device, last_msg))
+                        .clicked()
+                    {
+                        picked = Some(device.clone());
+                    }
+                    if ui
+                        .small_button("×")
+                        .on_hover_text("Close the tab")
+                        .clicked()
+                    {
+                        closed = Some(device.clone());
+                    }
+                });
+            }
+        });
+        if let Some(device) = closed {
+            self.tabs.close(&device);
+        } else if let Some(device) = picked {
+            self.select_device(device);
+        }
+    }
+
+    fn add_device_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let field = egui::TextEdit::singleline(&mut self.manual_addr)
+                .hint_text("192.168.1.40 or roku.lan")
+                .desired_width(160.0);
+            let entered =
+                ui.add(field).lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            let add = ui
+                .add_enabled(!self.adding_device, egui::Button::new("Add Device"))
+                .on_hover_text("For a Roku discovery can't find, such as one on another network");
+            if self.adding_device {
+                ui.spinner();
+                return;
+            }
+            if !(add.clicked() || entered) {
+                return;
+            }
+            match ecp::parse_device_addr(&self.manual_addr) {
+                Ok(device) => {
+                    self.adding_device = true;
+                    self.last_msg = format!("Checking {}…", device);
+                    self.worker.ask(move || {
+                        let info = ecp::get_device_info(&device);
+                        Some(Reply::Added(device, info))
+                    });
+                }
+                Err(err) => self.last_msg = err,
+            }
+        });
+    }
+
+    // a device that answered is listed with the rest, and remembered with them for next time
+    fn finish_adding(
+        &mut self,
+        device: String,
+        result: Result<DeviceInfo, roku_remote::RokuError>,
+    ) {
+        self.adding_device = false;
+        let info = match result {
+            Ok(info) => info,
+            Err(err) => {
+                self.last_msg = format!("{} doesn't look like a Roku: {}", device, err);
+                return;
+            }
+        };
+        self.last_msg = format!("Added {}", info.name);
+        self.got_device_info(device.clone(), info);
+        if !self.devices.contains(&device) {
+            self.devices.push(device.clone());
+            self.devices.sort();
+        }
+        self.manual_addr.clear();
+        self.select_device(device);
+    }
+
+    fn finish_discovery(&mut self, found: Vec<Discovered>) {
+        self.discovering = false;
+        let (found, moved) = self.follow_moved(found);
+        let known = std::mem::replace(&mut self.devices, found);
+        self.last_msg = format!("Found {} device(s)", self.devices.len());
+        if moved > 0 {
+            self.last_msg += &format!(", {} of them at a new address", moved);
+        }
+        let nothing_found = self.devices.is_empty();
+        // saved devices and ones seen before stay listed even when they didn't answer, marked
+        // once probed; the demo device among them stays in the list once started
+        self.devices.extend(self.config.devices.keys().cloned());
+        self.devices.extend(known);
+        self.devices.sort();
+        self.devices.dedup();
+        // multicast hiccups happen; the known devices are probably still where they were
+        let saved = self
+            .devices
+            .iter()
+            .filter(|device| !demo::is_demo(device))
+            .count();
+        self.trying_saved = nothing_found && saved > 0;
+        if self.trying_saved {
+            self.last_msg = format!("Discovery found nothing, trying {} saved device(s)…", saved);
+        }
+        self.reachability.probe(&self.devices);
+        for device in &self.devices {
+            if !self.device_info.contains_key(device) {
+                self.fetch_device_info(device);
+            }
+        }
+    }
+
+    fn poll_worker(&mut self, ctx: &egui::Context) {
+        for reply in self.worker.replies(ctx) {
+            match reply {
+                Reply::Discovered(found) => self.finish_discovery(found),
+                Reply::Rediscovered(found) => self.merge_rediscovered(found),
+                Reply::Ran(what, result) => {
+                    if let Err(err) = &result {
+                        self.last_msg = format!("{} failed: {}", what, err);
+                    }
+                    let result = result.map_err(|err| err.to_string());
+                    self.history.record(SystemTime::now(), "", &what, result);
+                }
+                Reply::Failed(msg) => self.last_msg = msg,
+                Reply::Screenshot(device, shot) => self.finish_screenshot(device, shot),
+                Reply::Sideloaded(device, result) => {
+                    self.sideloading.remove(&device);
+                    self.last_msg = match result {
+                        Ok(message) => format!("{}: {}", self.device_label(&device), message),
+                        Err(err) => {
+                            format!("Sideload to {} failed: {}", self.device_label(&device), err)
+                        }
+                    };
+                }
+                Reply::DeviceInfo(device, info) => self.got_device_info(device, info),
+                Reply::Woke(device, info) => self.finish_waking(device, info),
+                Reply::TvChannels(device, channels, active) => {
+                    match channels {
+                        Ok(channels) => {
+                            self.tv_channels.insert(device.clone(), Some(channels));
+                        }
+                        Err(err) => {
+                            self.last_msg = format!("Couldn't list the TV's channels: {}", err);
+                            self.tv_channels.insert(device.clone(), Some(Vec::new()));
+                        }
+                    }
+                    self.set_tv_active(device, active);
+                }
+                Reply::TvActive(device, active) => {
+                    if let Some(now) = &active {
+                        self.last_msg =
+                            format!("Tuned to {} {}", now.channel.number, now.channel.name);
+                    }
+                    self.set_tv_active(device, active);
+                }
+                Reply::Added(device, result) => self.finish_adding(device, result),
+                // a device picked since the request was made wins
+                Reply::Apps(device, apps) if self.selected_device.as_ref() == Some(&device) => {
+                    match apps {
+                        Ok(apps) => {
+                            self.apps = apps;
+                            self.last_msg = format!("Fetched {} apps", self.apps.len());
+                        }
+                        Err(err) => self.last_msg = format!("Fetching apps failed: {}", err),
+                    }
+                }
+                Reply::Apps(device, apps) => {
+                    if let Some(tab) = self.tabs.waiting.get_mut(&device) {
+                        match apps {
+                            Ok(apps) => {
+                                tab.last_msg = format!("Fetched {} apps", apps.len());
+                                tab.apps = apps;
+                            }
+                            Err(err) => tab.last_msg = format!("Fetching apps failed: {}", err),
+                        }
+                    }
+                }
+                Reply::Lineups(source, from, target, to) => {
+                    let current = (self.lineup_source.as_ref(), self.lineup_target.as_ref());
+                    if current != (Some(&source), Some(&target)) {
+                        continue;
+                    }
+                    if from.is_empty() {
+                        self.last_msg =
+                            format!("{} reported no channels", self.device_label(&source));
+                    } else {
+                        self.lineup_diff = Some(bulk_install::diff(&from, &to));
+                    }
+                }
+            }
+        }
+    }
+
+    // everything the command palette can search: remote keys, installed apps, devices, saved
+    // links and macros
+    fn palette_entries(&self) -> Vec<PaletteEntry> {
+        let keys = remote_keys().into_iter().map(|cmd| PaletteEntry {
+            label: cmd.to_string(),
+            kind: "key",
+            action: PaletteAction::Key(cmd.to_string()),
+        });
+        let apps = self.apps.iter().map(|app| PaletteEntry {
+            label: app.name.clone(),
+            kind: "app",
+            action: PaletteAction::LaunchApp(app.id.clone()),
+        });
+        let devices = self.devices.iter().map(|device| PaletteEntry {
+            label: self.device_label(device),
+            kind: "device",
+            action: PaletteAction::SelectDevice(device.clone()),
+        });
+        let links = self.config.watchlist.keys().map(|name| PaletteEntry {
+            label: name.clone(),
+            kind: "watchlist",
+            action: PaletteAction::DeepLink(name.clone()),
+        });
+        let macros = self.config.macros.keys().map(|name| PaletteEntry {
+            label: name.clone(),
+            kind: "macro",
+            action: PaletteAction::Macro(name.clone()),
+        });
+        keys.chain(apps)
+            .chain(devices)
+            .chain(links)
+            .chain(macros)
+            .collect()
+    }
+
+    fn run_palette_action(&mut self, action: PaletteAction) {
+        match action {
+            PaletteAction::SelectDevice(device) => self.select_device(device),
+            PaletteAction::Key(cmd) => self.send_to_targets(&cmd),
+            PaletteAction::LaunchApp(app_id) => {
+                let app_name = self
+                    .apps
+                    .iter()
+                    .find(|app| app.id == app_id)
+                    .map(|app| app.name.clone())
+                    .unwrap_or_else(|| "Unknown App".to_string());
+                self.launch_on_targets(&app_id, &app_name);
+                if self.selected_device.is_some() {
+                    self.selected_app = Some(app_id);
+                }
+            }
+            PaletteAction::DeepLink(name) => self.launch_deep_link(&name),
+            PaletteAction::Macro(name) => self.run_macro(&name),
+        }
+    }
+
+    fn launch_deep_link(&mut self, name: &str) {
+        let Some(link) = self.config.watchlist.get(name) else {
+            return;
+        };
+        match &self.selected_device {
+            Some(ip) => {
+                let (link, ip) = (link.clone(), ip.clone());
+                self.worker
+                    .run(format!("Opening {}", name), move || link.launch(&ip));
+                self.last_msg = format!("Opening {}", name);
+            }
+            None => self.last_msg = "No Roku selected".into(),
+        }
+    }
 }
 
+// types the clipboard on the selected device
+const PASTE_AND_SEND: egui::KeyboardShortcut = egui::KeyboardShortcut::new(
+    egui::Modifiers::COMMAND.plus(egui::Modifiers::SHIFT),
+    egui::Key::V,
+);
+
 impl EApp for RokuRemoteApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut Frame) {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut Frame) {
+        self.config.theme.apply(ctx, frame.info().system_theme);
+        let shortcut = |i: &mut egui::InputState| {
+            i.consume_shortcut(&PALETTE_SHORTCUT) || i.consume_shortcut(&PALETTE_SHORTCUT_ALT)
+        };
+        if ctx.input_mut(shortcut) {
+            self.palette.toggle();
+        }
+        // the window turns Ctrl+V into a paste of the clipboard, so Ctrl+Shift+V mostly comes
+        // as one with Shift held; where it comes as keys it's the shortcut
+        if !ctx.wants_keyboard_input() {
+            let pasted = ctx.input_mut(|i| {
+                if i.consume_shortcut(&PASTE_AND_SEND) {
+                    return Some(None);
+                }
+                let shift = i.modifiers.shift;
+                i.events.iter().find_map(|event| match event {
+                    egui::Event::Paste(text) if shift => Some(Some(text.clone())),
+                    _ => None,
+                })
+            });
+            if let Some(pasted) = pasted {
+                self.paste_and_send(pasted);
+            }
+        }
+        if self.palette.open {
+            let entries = self.palette_entries();
+            if let Some(action) = self.palette.show(ctx, &entries) {
+                self.run_palette_action(action);
+            }
+        }
+
+        self.poll_worker(ctx);
+        for sent in self.pacer.sent() {
+            let result = sent.result.map_err(|err| err.to_string());
+            self.history.record(
+                sent.at,
+                &sent.device,
+                &key_label(&sent.key, sent.action),
+                result,
+            );
+        }
+        let targets = self.command_targets();
+        self.global_hotkeys
+            .update(ctx, &self.config.global_hotkeys, targets);
+        for (device, cmd, at, result) in self.global_hotkeys.sent() {
+            self.history.record(at, &device, &cmd, result);
+        }
+        if let Some(failure) = self.pacer.failures().pop() {
+            let key = key_label(&failure.key, failure.action);
+            self.last_msg = format!("{} didn't reach {}: {}", key, failure.device, failure.error);
+        }
+        self.run_background_checks(ctx);
+        self.poll_reachability(ctx);
+        self.icons.poll(ctx);
+        self.poll_lit_check(ctx);
+        self.poll_fleet_run(ctx);
+        self.poll_macro_run(ctx);
+        self.poll_script_run();
+        self.poll_timers(ctx);
+        self.poll_bulk_install(ctx);
+        self.poll_content_search(ctx);
+        self.import_dropped_images(ctx);
+        self.cast_dropped_media(ctx);
+        self.console.poll(ctx);
+        self.now_playing.watch(self.selected_device.as_deref());
+        self.tabs.poll();
+        if self.now_playing.update(ctx) {
+            self.tray
+                .show(ctx, self.now_playing.device(), &self.now_playing.current);
+            self.continue_watching.record(&self.now_playing.current);
+        }
+        self.continue_watching.save_if_due();
+        if self.selected_device.is_some() {
+            for cmd in self.config.mouse_buttons.pressed_keys(ctx) {
+                self.send_to_targets(&cmd);
+            }
+        }
+
+        if self.console.show(
+            ctx,
+            self.selected_device.as_deref(),
+            &mut self.config.console,
+        ) {
+            self.config_dirty = true;
+        }
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.heading("Roku Remote");
+            ui.weak(format!(
+                "{} opens the command palette",
+                ctx.format_shortcut(&PALETTE_SHORTCUT)
+            ));
 
-            if ui.button("Discover Roku Devices").clicked() {
-                self.devices = discover_roku_devices();
-                self.devices.sort();
-                self.devices.dedup();
-                self.last_msg = format!("Found {} device(s)", self.devices.len());
-            }
+            ui.horizontal(|ui| {
+                let discover = egui::Button::new("Discover Roku Devices");
+                if ui.add_enabled(!self.discovering, discover).clicked() {
+                    self.discovering = true;
+                    self.last_rediscovery = Some(Instant::now());
+                    self.last_msg = "Looking for devices…".into();
+                    let ssdp = self.config.ssdp.clone();
+                    self.worker.ask(move || Some(Reply::Discovered(ecp::discover_or_scan(&ssdp))));
+                }
+                if self.discovering {
+                    ui.spinner();
+                }
+                if ui
+                    .button("Try the Demo Device")
+                    .on_hover_text("A pretend Roku on this computer, for looking around without one")
+                    .clicked()
+                {
+                    match demo::start() {
+                        Ok(addr) => {
+                            if !self.devices.contains(&addr) {
+                                self.devices.push(addr.clone());
+                            }
+                            self.select_device(addr);
+                            self.last_msg = "Connected to the demo device".into();
+                        }
+                        Err(err) => self.last_msg = format!("Demo device failed to start: {}", err),
+                    }
+                }
+            });
+            self.add_device_ui(ui);
 
             if !self.devices.is_empty() {
                 ui.separator();
+                self.tabs_ui(ui);
                 ui.label("Select a Roku Device:");
+                self.group_filter_ui(ui);
 
-                egui::ComboBox::from_label("Devices")
-                    .selected_text(self.selected_device.clone().unwrap_or_else(|| "None".into()))
-                    .show_ui(ui, |ui| {
-                        for device in &self.devices {
-                            if ui
-                                .selectable_label(Some(device) == self.selected_device.as_ref(), device)
-                                .clicked()
-                            {
-                                self.selected_device = Some(device.clone());
-                                self.apps = get_apps(device);
-                                self.last_msg = format!("Fetched {} apps", self.apps.len());
+                let mut picked_device = None;
+                ui.horizontal(|ui| {
+                    if let Some(device) = &self.selected_device {
+                        self.status_dot(ui, device);
+                    }
+                    egui::ComboBox::from_label("Devices")
+                        .selected_text(
+                            self.selected_device
+                                .as_deref()
+                                .map(|device| self.device_label(device))
+                                .unwrap_or_else(|| "None".into()),
+                        )
+                        .show_ui(ui, |ui| {
+                            for device in &self.visible_devices() {
+                                let row = ui.horizontal(|ui| {
+                                    self.status_dot(ui, device);
+                                    ui.selectable_label(
+                                        Some(device) == self.selected_device.as_ref(),
+                                        self.device_label(device) + self.reachability.marker(device).as_str(),
+                                    )
+                                }).inner;
+                                let row = match self.device_info.get(device) {
+                                    Some(info) => row.on_hover_text(format!(
+                                        "Software {}\nSerial {}",
+                                        info.software_version, info.serial_number
+                                    )),
+                                    None => row,
+                                };
+                                if row.clicked()
+                                {
+                                    picked_device = Some(device.clone());
+                                }
                             }
+                        });
+                    if let Some(device) = &self.selected_device {
+                        let keep = ui
+                            .add_enabled(!self.tabs.is_open(device), egui::Button::new("Keep in a Tab"))
+                            .on_hover_text("Switch back to it later as it was left, with its channels");
+                        if keep.clicked() {
+                            self.tabs.open(device);
                         }
-                    });
+                    }
+                });
+                if let Some(device) = picked_device {
+                    self.select_device(device);
+                }
+                if let Some(device) = self.selected_device.clone() {
+                    self.device_info_ui(ui, &device);
+                    self.device_notes_ui(ui, &device);
+                    self.device_groups_ui(ui, &device);
+                    self.developer_ui(ui, &device);
+                }
 
                 ui.separator();
-                ui.label("Commands:");
+                ui.horizontal(|ui| {
+                    ui.label("Commands:").on_hover_text(format!(
+                        "Keyboard shortcuts, changed in Settings:\n{}",
+                        self.config.keyboard.help()
+                    ));
+                    let edit = ui.selectable_label(self.editing_layout, "✏ Edit layout");
+                    if edit.on_hover_text("Move, add, rename and remove the remote's buttons").clicked() {
+                        self.editing_layout = !self.editing_layout;
+                        self.layout_cell = None;
+                    }
+                });
 
-                if let Some(ip) = &self.selected_device {
+                if self.editing_layout {
+                    self.layout_editor_ui(ui);
+                } else if let Some(ip) = self.selected_device.clone() {
+                    // a tap is sent once the button is let go, a hold as keydown and keyup
+                    let (mut held_cmd, mut tapped_cmd) = (None, None);
+                    // buttons the device can't do anything with are greyed out, once it has said
+                    let info = self.device_info.get(&ip).cloned();
+                    let supported = |key: RokuKey| info.as_ref().is_none_or(|info| info.supports(key));
+                    self.scroll_zones.clear();
+                    let rows = self.config.layout.rows();
                     egui::Grid::new("commands_grid")
-                        .num_columns(3)
-                        .min_col_width(100.0) 
-                        .spacing([10.0, 10.0]) 
+                        .num_columns(layout::COLUMNS)
+                        .min_col_width(100.0)
+                        .spacing([10.0, 10.0])
                         .show(ui, |ui| {
-                            for row in ROKU_COMMANDS {
-                                for &cmd in *row {
-                                    if cmd != " " {
+                            for row in &rows {
+                                for button in row {
+                                    if let Some(button) = button {
+                                        let cmd = &button.key;
+                                        // a key of the device's own that this build doesn't know is
+                                        // left for the device to turn down
+                                        let enabled = button.roku_key().is_none_or(supported);
                                         // Create a fixed-size button with centered text
-                                        ui.allocate_ui(egui::vec2(60.0, 20.0), |ui| {
+                                        let cell = ui.allocate_ui(egui::vec2(60.0, 20.0), |ui| {
                                             ui.with_layout(
                                                 egui::Layout::centered_and_justified(egui::Direction::LeftToRight),
                                                 |ui| {
-                                                    if ui.button(cmd).clicked() {
-                                                        send_command(ip, cmd);
-                                                        self.last_msg = format!("Sent command: {}", cmd);
+                                                    let widget = egui::Button::new(button.label());
+                                                    let widget = ui
+                                                        .add_enabled(enabled, widget)
+                                                        .on_disabled_hover_text("Not on this device");
+                                                    if widget.is_pointer_button_down_on() {
+                                                        held_cmd = Some(cmd.clone());
+                                                    } else if widget.clicked() {
+                                                        tapped_cmd = Some(cmd.clone());
                                                     }
                                                 },
                                             );
                                         });
+                                        self.scroll_zones.add(Zone::for_key(cmd), cell.response.rect);
                                     } else {
-                                        
-                                        ui.label("");
+
+                                        let blank = ui.label("");
+                                        self.scroll_zones.add(None, blank.rect);
                                     }
                                 }
                                 ui.end_row();
                             }
                         });
+                    (self.pointer_held, self.pointer_tapped) = (held_cmd, tapped_cmd);
+                    if supported(RokuKey::VolumeUp) {
+                        self.volume_ui(ui, &ip);
+                    }
+                    self.tv_inputs_ui(ui, &ip);
+                    for cmd in self.scroll_zones.keys(ui.ctx(), &self.config.scroll) {
+                        self.send_to_targets(cmd);
+                    }
 
                     ui.separator();
                     ui.label("Send Text Input:");
                     ui.horizontal(|ui| {
                         ui.text_edit_singleline(&mut self.text_input);
-                        if ui.button("Send Text").clicked() {
-                            if !self.text_input.trim().is_empty() {
-                                send_key(ip, &self.text_input);
-                                self.last_msg = format!("Sent text: {}", self.text_input);
-                                self.text_input.clear();
+                        if ui.button("Send Text").clicked() && !self.text_input.trim().is_empty() {
+                            let text = std::mem::take(&mut self.text_input);
+                            self.send_text_input(&ip, text, false);
+                        }
+                        let paste = ui.button("📋 Paste & Send").on_hover_text(format!(
+                            "Type what's on the clipboard, e.g. a long Wi-Fi password ({})",
+                            ui.ctx().format_shortcut(&PASTE_AND_SEND)
+                        ));
+                        if paste.clicked() {
+                            self.paste_and_send(None);
+                        }
+                    });
+                    // a long text takes a while at the typing delay, so it shows how far it got
+                    // and can be stopped; a short one is done before a bar could be read
+                    let mut cancel = false;
+                    let typing = self.lit_check.as_ref().map(|(_, _, _, typing)| typing);
+                    if let Some(typing) = typing.filter(|typing| typing.total > 8) {
+                        ui.horizontal(|ui| {
+                            let done = typing.sent() as f32 / typing.total.max(1) as f32;
+                            let text = format!("Typing {} / {}", typing.sent(), typing.total);
+                            ui.add(egui::ProgressBar::new(done).text(text).desired_width(200.0));
+                            cancel = ui.small_button("⏹ Stop").clicked();
+                        });
+                    }
+                    if cancel {
+                        if let Some((_, _, _, typing)) = &self.lit_check {
+                            typing.cancel();
+                        }
+                    }
+                    if let Some(text) = self.lit_fallback.clone() {
+                        ui.horizontal(|ui| {
+                            ui.colored_label(
+                                ui.visuals().warn_fg_color,
+                                "The channel didn't react to the typed text.",
+                            );
+                            if ui.button("Retype with arrows + Select").clicked() {
+                                self.osk_mode = true;
+                                self.lit_fallback = None;
+                                self.type_via_keyboard(&ip, &text);
                             }
+                            if ui.small_button("Dismiss").clicked() {
+                                self.lit_fallback = None;
+                            }
+                        });
+                    }
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.osk_mode, "Type with arrows + Select")
+                            .on_hover_text("For channels whose keyboard ignores typed text; start with focus on the top-left key");
+                        if self.osk_mode {
+                            egui::ComboBox::from_id_source("osk_layout")
+                                .selected_text(osk::LAYOUTS[self.osk_layout].name)
+                                .show_ui(ui, |ui| {
+                                    for (i, layout) in osk::LAYOUTS.iter().enumerate() {
+                                        ui.selectable_value(&mut self.osk_layout, i, layout.name);
+                                    }
+                                });
                         }
                     });
                 } else {
@@ -127,144 +4521,75 @@ impl EApp for RokuRemoteApp {
                 }
 
                 ui.separator();
-                ui.label("Apps:");
-                egui::ComboBox::from_label("Pick an App")
-                    .selected_text(
-                        self.selected_app
-                            .as_ref()
-                            .and_then(|app_id| {
-                                self.apps
-                                    .iter()
-                                    .find(|app| app.id == *app_id)
-                                    .map(|app| app.name.clone())
-                            })
-                            .unwrap_or_else(|| "None".into()),
-                    )
-                    .show_ui(ui, |ui| {
-                        for app in &self.apps {
-                            if ui
-                                .selectable_label(Some(app.id.clone()) == self.selected_app, &app.name)
-                                .clicked()
-                            {
-                                self.selected_app = Some(app.id.clone());
-                            }
-                        }
-                    });
+                self.now_playing_ui(ui);
 
-                if ui.button("Launch App").clicked() {
-                    if let (Some(ip), Some(app_id)) = (&self.selected_device, &self.selected_app) {
-                        launch_app(ip, app_id);
-                        let app_name = self
-                            .apps
-                            .iter()
-                            .find(|app| app.id == *app_id)
-                            .map(|app| app.name.clone())
-                            .unwrap_or_else(|| "Unknown App".to_string());
-                        self.last_msg = format!("Launching app: {}", app_name);
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Apps:");
+                    let running = match &self.now_playing.current.app {
+                        Some(app) if !app.id.is_empty() => format!("{} is running", app.name),
+                        Some(_) => "on the home screen".to_string(),
+                        None => "no word yet on what's running".to_string(),
+                    };
+                    ui.weak(running);
+                    if ui.small_button("⟳").on_hover_text("Check what's running now").clicked() {
+                        self.now_playing.refresh();
                     }
-                }
+                });
+                self.app_grid_ui(ui);
+                self.tv_channels_ui(ui);
+
+                ui.separator();
+                self.continue_watching_ui(ui);
+
+                ui.separator();
+                self.watchlist_ui(ui);
+
+                ui.separator();
+                self.roku_search_ui(ui);
+
+                ui.separator();
+                self.content_search_ui(ui);
+
+                ui.separator();
+                self.macros_ui(ui);
+
+                ui.separator();
+                self.scripts_ui(ui);
+
+                ui.separator();
+                self.cast_ui(ui);
+
+                ui.separator();
+                self.timers_ui(ui);
+
+                ui.separator();
+                self.fleet_ui(ui);
+
+                ui.separator();
+                self.bulk_install_ui(ui);
+
+                ui.separator();
+                self.gallery_ui(ui);
+                ui.toggle_value(&mut self.console.open, "🐞 Debug Console");
             }
 
+            ui.separator();
+            self.settings_ui(ui);
+            self.dry_run_ui(ui);
+
             ui.separator();
             ui.label(format!("Status: {}", self.last_msg));
+            self.pacing_status_ui(ui);
+            self.history_ui(ui);
         });
-    }
-}
-// discover roku devices on the network using SSDP(simple service discovery protocol)
-fn discover_roku_devices() -> Vec<String> {
-    const SSDP_ADDR: &str = "239.255.255.250";
-    const SSDP_PORT: u16 = 1900;
-    const ST: &str = "roku:ecp";
-    const TIMEOUT_SECS: u64 = 2;
-    const RETRIES: usize = 1;
-
-    let dest = SocketAddrV4::new(Ipv4Addr::new(239, 255, 255, 250), SSDP_PORT);
-    let msg = format!(
-        "M-SEARCH * HTTP/1.1\r\n\
-         HOST: {SSDP_ADDR}:{SSDP_PORT}\r\n\
-         MAN: \"ssdp:discover\"\r\n\
-         ST: {ST}\r\n\
-         MX: 3\r\n\r\n"
-    );
-
-    let mut found = Vec::new();
-
-    for _ in 0..RETRIES {
-        let sock = UdpSocket::bind("0.0.0.0:0").expect("bind failed");
-        sock.set_read_timeout(Some(Duration::from_secs(TIMEOUT_SECS))).ok();
-        sock.set_multicast_loop_v4(true).ok();
-        sock.set_multicast_ttl_v4(4).ok();
-        sock.send_to(msg.as_bytes(), dest).ok();
-
-        let mut buf = [0u8; 2048];
-        loop {
-            match sock.recv_from(&mut buf) {
-                Ok((amt, _)) => {
-                    let data = String::from_utf8_lossy(&buf[..amt]);
-                    if let Some(line) = data
-                        .lines()
-                        .find(|l| l.len() >= 9 && l[..9].eq_ignore_ascii_case("location:"))
-                    {
-                        let location = line[9..].trim();
-                        if let Ok(url) = Url::parse(location) {
-                            if let (Some(host), Some(port)) = (url.host_str(), url.port()) {
-                                let address = format!("{}:{}", host, port);
-                                if !found.contains(&address) {
-                                    found.push(address);
-                                }
-                            }
-                        }
-                    }
-                }
-                Err(_) => break,
-            }
-        }
-    }
 
-    found
-}
-// query available apps to create a list and launch apps directly 
-fn get_apps(ip: &str) -> Vec<AppEntry> {
-    let url = format!("http://{}/query/apps", ip);
-    let client = Client::new();
-
-    if let Ok(resp) = client.get(&url).send() {
-        if let Ok(text) = resp.text() {
-            let re = Regex::new(r#"<app[^>]*id="([^"]+)"[^>]*>(.*?)</app>"#).unwrap();
-            return re
-                .captures_iter(&text)
-                .map(|cap| AppEntry {
-                    id: cap[1].to_string(),
-                    name: decode_html_entities(&cap[2]).to_string(),
-                })
-                .collect();
+        self.send_held_keys(ctx);
+        if self.config_dirty && !ctx.wants_keyboard_input() {
+            self.config_dirty = false;
+            self.save_config();
         }
+        self.poll_synced_config(ctx);
+        self.save_session_if_due(ctx);
     }
-
-    vec![]
-}
-// form commands and send over the network using http
-fn send_command(ip: &str, command: &str) {
-    let url = format!("http://{}/keypress/{}", ip, command);
-    let _ = Client::new().post(&url).send();
 }
-// launch specific apps without having to manually navigate to them
-fn launch_app(ip: &str, app_id: &str) {
-    let url = format!("http://{}/launch/{}", ip, app_id);
-    let _ = Client::new().post(&url).send();
-}
-// send strings to roku device 
-// the literal function only sends one character at a time  
-// so for loop 
-fn send_key(ip: &str, key: &str) {
-    let client = Client::new();
-    for c in key.chars() {
-        let encoded_char = if c == ' ' {
-            "%20".to_string()
-        } else {
-            c.to_string()
-        };
-        let url = format!("http://{}/keypress/Lit_{}", ip, encoded_char);
-        let _ = client.post(&url).send();
-    }
-}
\ No newline at end of file
@@ -0,0 +1,49 @@
+use eframe::egui::{self, PointerButton};
+use serde::{Deserialize, Serialize};
+
+// remote keys bound to the mouse's extra buttons; an empty string leaves a button unbound
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MouseButtonSettings {
+    pub middle: String,
+    // button 4, usually "back" on the side of the mouse
+    pub back: String,
+    // button 5, usually "forward"
+    pub forward: String,
+}
+
+impl Default for MouseButtonSettings {
+    fn default() -> Self {
+        Self {
+            middle: "Select".into(),
+            back: "Back".into(),
+            forward: "Home".into(),
+        }
+    }
+}
+
+impl MouseButtonSettings {
+    pub fn bindings_mut(&mut self) -> [(&'static str, &mut String); 3] {
+        [
+            ("Middle click", &mut self.middle),
+            ("Back button", &mut self.back),
+            ("Forward button", &mut self.forward),
+        ]
+    }
+
+    // keys for the buttons pressed anywhere in the window this frame
+    pub fn pressed_keys(&self, ctx: &egui::Context) -> Vec<String> {
+        let bindings = [
+            (PointerButton::Middle, &self.middle),
+            (PointerButton::Extra1, &self.back),
+            (PointerButton::Extra2, &self.forward),
+        ];
+        ctx.input(|i| {
+            bindings
+                .into_iter()
+                .filter(|(button, key)| !key.is_empty() && i.pointer.button_pressed(*button))
+                .map(|(_, key)| key.clone())
+                .collect()
+        })
+    }
+}
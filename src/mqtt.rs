@@ -0,0 +1,327 @@
+// a bridge from the --serve daemon to an MQTT broker, for home automation. each device's state
+// is published, retained, under <prefix>/<serial>/, and keypresses and launches are taken on
+// <prefix>/<serial>/keypress and /launch. Home Assistant discovery messages make the devices
+// show up in HA by themselves. built with the mqtt feature; without it a broker in the config
+// is only warned about
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MqttSettings {
+    // host or host:port of the broker; empty leaves the bridge off
+    pub broker: String,
+    pub username: String,
+    pub password: String,
+    pub topic_prefix: String,
+    // where Home Assistant listens for discovery messages; empty to not announce the devices
+    pub discovery_prefix: String,
+}
+
+impl Default for MqttSettings {
+    fn default() -> Self {
+        Self {
+            broker: String::new(),
+            username: String::new(),
+            password: String::new(),
+            topic_prefix: "roku".into(),
+            discovery_prefix: "homeassistant".into(),
+        }
+    }
+}
+
+impl MqttSettings {
+    pub fn enabled(&self) -> bool {
+        !self.broker.trim().is_empty()
+    }
+}
+
+#[cfg(feature = "mqtt")]
+pub use imp::spawn;
+
+#[cfg(not(feature = "mqtt"))]
+pub fn spawn(
+    settings: MqttSettings,
+    _state: std::sync::Arc<std::sync::Mutex<crate::server::ServerState>>,
+) {
+    eprintln!(
+        "Built without the mqtt feature, not connecting to {}",
+        settings.broker
+    );
+}
+
+#[cfg(feature = "mqtt")]
+mod imp {
+    use super::MqttSettings;
+    use crate::ecp;
+    use crate::server::ServerState;
+    use roku_remote::events::DeviceState;
+    use roku_remote::RokuKey;
+    use rumqttc::{Client, Event, LastWill, MqttOptions, Packet, QoS};
+    use serde_json::json;
+    use std::collections::BTreeMap;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    // the broker's port unless the address says otherwise
+    const DEFAULT_PORT: u16 = 1883;
+    // how often the state cache is looked at for changes; it is itself polled every 5s
+    const TICK: Duration = Duration::from_secs(1);
+    // before trying a broker that went away again
+    const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+    // the keys Home Assistant gets a button for
+    const BUTTONS: &[&str] = &[
+        "Home",
+        "Back",
+        "Select",
+        "Play",
+        "VolumeUp",
+        "VolumeDown",
+        "VolumeMute",
+        "PowerOff",
+    ];
+
+    // serial number -> device address, for the commands that come in by serial
+    type Serials = Arc<Mutex<BTreeMap<String, String>>>;
+
+    pub fn spawn(settings: MqttSettings, state: Arc<Mutex<ServerState>>) {
+        let (host, port) = address(&settings.broker);
+        let mut options =
+            MqttOptions::new(format!("roku-remote-{}", std::process::id()), host, port);
+        options.set_keep_alive(Duration::from_secs(30));
+        if !settings.username.is_empty() {
+            options.set_credentials(&settings.username, &settings.password);
+        }
+        // the broker says the bridge is gone for us if the daemon dies
+        let bridge = format!("{}/bridge", settings.topic_prefix);
+        options.set_last_will(LastWill::new(&bridge, "offline", QoS::AtLeastOnce, true));
+        let (client, mut connection) = Client::new(options, 64);
+        println!("Bridging to the MQTT broker at {}", settings.broker);
+
+        let serials: Serials = Arc::default();
+        // set on every (re)connect, so everything retained is published again
+        let connected = Arc::new(AtomicBool::new(false));
+        {
+            let (client, serials, connected) =
+                (client.clone(), Arc::clone(&serials), Arc::clone(&connected));
+            let (prefix, broker) = (settings.topic_prefix.clone(), settings.broker.clone());
+            thread::spawn(move || {
+                for notification in connection.iter() {
+                    match notification {
+                        Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                            for command in ["keypress", "launch"] {
+                                let topic = format!("{}/+/{}", prefix, command);
+                                let _ = client.try_subscribe(topic, QoS::AtLeastOnce);
+                            }
+                            let _ = client.try_publish(&bridge, QoS::AtLeastOnce, true, "online");
+                            connected.store(true, Ordering::Relaxed);
+                        }
+                        Ok(Event::Incoming(Packet::Publish(publish))) => {
+                            let payload = String::from_utf8_lossy(&publish.payload);
+                            command(&prefix, &serials, &publish.topic, payload.trim());
+                        }
+                        Ok(_) => {}
+                        Err(err) => {
+                            eprintln!("MQTT broker {}: {}", broker, err);
+                            thread::sleep(RECONNECT_DELAY);
+                        }
+                    }
+                }
+            });
+        }
+
+        thread::spawn(move || {
+            // what was last published per serial, so only changes go out
+            let mut published: BTreeMap<String, serde_json::Value> = BTreeMap::new();
+            loop {
+                thread::sleep(TICK);
+                if connected.swap(false, Ordering::Relaxed) {
+                    published.clear();
+                }
+                let states: Vec<DeviceState> = {
+                    let state = state.lock().unwrap();
+                    state
+                        .devices
+                        .iter()
+                        .filter_map(|device| state.cache.get(device).cloned())
+                        .collect()
+                };
+                for current in states {
+                    let known = serials
+                        .lock()
+                        .unwrap()
+                        .iter()
+                        .find(|(_, device)| **device == current.device)
+                        .map(|(serial, _)| serial.clone());
+                    let serial = match known {
+                        Some(serial) => serial,
+                        // nothing to announce until it answers device-info
+                        None if !current.online => continue,
+                        None => match announce(&client, &settings, &current.device) {
+                            Some(serial) => {
+                                serials
+                                    .lock()
+                                    .unwrap()
+                                    .insert(serial.clone(), current.device.clone());
+                                serial
+                            }
+                            // asked again next time round
+                            None => continue,
+                        },
+                    };
+                    let payload = json!({
+                        "online": current.online,
+                        "power": current.power_mode,
+                        "app": current.active_app.as_ref().map(|app| &app.name),
+                        "app_id": current.active_app.as_ref().map(|app| &app.id),
+                        "playback": current.playback,
+                    });
+                    if published.get(&serial) == Some(&payload) {
+                        continue;
+                    }
+                    let topic =
+                        |leaf: &str| format!("{}/{}/{}", settings.topic_prefix, serial, leaf);
+                    let online = if current.online { "online" } else { "offline" };
+                    // blocks while the broker is away, until the connection catches up
+                    let _ = client.publish(topic("availability"), QoS::AtLeastOnce, true, online);
+                    let _ =
+                        client.publish(topic("state"), QoS::AtLeastOnce, true, payload.to_string());
+                    published.insert(serial, payload);
+                }
+            }
+        });
+    }
+
+    fn address(broker: &str) -> (String, u16) {
+        let broker = broker.trim();
+        match broker.rsplit_once(':') {
+            Some((host, port)) => match port.parse() {
+                Ok(port) => (host.to_string(), port),
+                Err(_) => (broker.to_string(), DEFAULT_PORT),
+            },
+            None => (broker.to_string(), DEFAULT_PORT),
+        }
+    }
+
+    // <prefix>/<serial>/keypress with a key name, or /launch with a channel id
+    fn command(prefix: &str, serials: &Serials, topic: &str, payload: &str) {
+        let Some(rest) = topic
+            .strip_prefix(prefix)
+            .and_then(|rest| rest.strip_prefix('/'))
+        else {
+            return;
+        };
+        let Some((serial, command)) = rest.split_once('/') else {
+            return;
+        };
+        let Some(device) = serials.lock().unwrap().get(serial).cloned() else {
+            eprintln!("MQTT: no device with serial number {}", serial);
+            return;
+        };
+        if payload.is_empty() {
+            return;
+        }
+        // the payload ends up in the ECP path, so only a key or an app id gets that far
+        let payload = match command {
+            "keypress" => match payload.parse::<RokuKey>() {
+                Ok(key) => key.to_string(),
+                Err(err) => {
+                    eprintln!("MQTT keypress on {}: {}", device, err);
+                    return;
+                }
+            },
+            "launch" if is_app_id(payload) => payload.to_string(),
+            "launch" => {
+                eprintln!("MQTT launch on {}: not an app id: {}", device, payload);
+                return;
+            }
+            _ => return,
+        };
+        let command = command.to_string();
+        // off the connection's thread, which has to keep answering the broker
+        thread::spawn(move || {
+            let result = match command.as_str() {
+                "keypress" => ecp::send_command(&device, &payload),
+                _ => ecp::launch_app(&device, &payload),
+            };
+            if let Err(err) = result {
+                eprintln!("MQTT {} {} on {}: {}", command, payload, device, err);
+            }
+        });
+    }
+
+    // channel ids are numbers, "dev", or inputs like "tvinput.hdmi1"
+    fn is_app_id(id: &str) -> bool {
+        !id.contains("..")
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+    }
+
+    // the device's serial number from device-info, after its Home Assistant discovery messages
+    // have gone out
+    fn announce(client: &Client, settings: &MqttSettings, device: &str) -> Option<String> {
+        let info = ecp::get_device_info(device).ok()?;
+        let serial = info.serial_number.clone();
+        if serial.is_empty() || settings.discovery_prefix.is_empty() {
+            return (!serial.is_empty()).then_some(serial);
+        }
+        let topic = |leaf: &str| format!("{}/{}/{}", settings.topic_prefix, serial, leaf);
+        let base = json!({
+            "availability": [
+                { "topic": format!("{}/bridge", settings.topic_prefix) },
+                { "topic": topic("availability") },
+            ],
+            "availability_mode": "all",
+            "device": {
+                "identifiers": [format!("roku_{}", serial)],
+                "name": info.name,
+                "manufacturer": "Roku",
+                "model": info.model_name,
+                "sw_version": info.software_version,
+            },
+        });
+        let mut entities = vec![
+            (
+                "sensor",
+                "app",
+                json!({ "name": "App", "icon": "mdi:application", "state_topic": topic("state"), "value_template": "{{ value_json.app }}" }),
+            ),
+            (
+                "sensor",
+                "playback",
+                json!({ "name": "Playback", "icon": "mdi:play-pause", "state_topic": topic("state"), "value_template": "{{ value_json.playback }}" }),
+            ),
+            (
+                "sensor",
+                "power",
+                json!({ "name": "Power", "icon": "mdi:power", "state_topic": topic("state"), "value_template": "{{ value_json.power }}" }),
+            ),
+        ];
+        for key in BUTTONS {
+            let button =
+                json!({ "name": key, "command_topic": topic("keypress"), "payload_press": key });
+            entities.push(("button", key, button));
+        }
+        for (component, object, mut config) in entities {
+            let unique_id = format!("roku_{}_{}", serial, object.to_lowercase());
+            let (Some(config), Some(base)) = (config.as_object_mut(), base.as_object()) else {
+                continue;
+            };
+            config.extend(base.clone());
+            config.insert("unique_id".into(), unique_id.into());
+            let topic = format!(
+                "{}/{}/roku_{}/{}/config",
+                settings.discovery_prefix,
+                component,
+                serial,
+                object.to_lowercase()
+            );
+            let config = serde_json::to_string(config).unwrap_or_default();
+            let _ = client.publish(topic, QoS::AtLeastOnce, true, config);
+        }
+        Some(serial)
+    }
+}
@@ -0,0 +1,196 @@
+// keeps track of what the selected device is showing, for the window title and icon (and the
+// tray, where there is one). polled on a background thread so the window never waits on it
+
+use crate::ecp::{self, AppEntry, Playback};
+use eframe::egui::{self, IconData, ViewportCommand};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(3);
+pub const APP_TITLE: &str = "🦀 Roku Remote";
+
+#[derive(Clone, Default, PartialEq)]
+pub struct NowPlaying {
+    pub app: Option<AppEntry>,
+    // the channel icon, decoded for the window or tray
+    pub icon: Option<Arc<IconData>>,
+    // media-player state and position, as of the last poll
+    pub playback: Option<Playback>,
+    // whether the sound is going to headphones; None on devices without private listening
+    pub private_listening: Option<bool>,
+}
+
+impl NowPlaying {
+    // "Netflix — Roku Remote", or just the app name on the home screen / without a device
+    pub fn title(&self) -> String {
+        match &self.app {
+            Some(app) if !app.id.is_empty() => format!("{} — Roku Remote", app.name),
+            _ => APP_TITLE.to_string(),
+        }
+    }
+
+    // the channel in front; None on the home screen or before the first poll
+    pub fn app_id(&self) -> Option<&str> {
+        self.app
+            .as_ref()
+            .map(|app| app.id.as_str())
+            .filter(|id| !id.is_empty())
+    }
+}
+
+// 12:34, or 1:02:03 from an hour up
+pub fn clock(time: Duration) -> String {
+    let secs = time.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+#[derive(Default)]
+pub struct NowPlayingWatcher {
+    device: Option<String>,
+    rx: Option<Receiver<NowPlaying>>,
+    // cuts the wait before the next poll short
+    wake: Option<Sender<()>>,
+    stop: Arc<AtomicBool>,
+    pub current: NowPlaying,
+    // when current came in, for moving the position along between polls
+    pub updated: Option<Instant>,
+    // None forces the next update to report a change, e.g. after switching devices
+    shown_title: Option<String>,
+}
+
+impl NowPlayingWatcher {
+    // starts watching a different device, or stops when None
+    pub fn watch(&mut self, device: Option<&str>) {
+        if self.device.as_deref() == device {
+            return;
+        }
+        self.stop.store(true, Ordering::Relaxed);
+        self.device = device.map(str::to_string);
+        self.current = NowPlaying::default();
+        self.updated = None;
+        self.shown_title = None;
+        self.rx = None;
+        self.wake = None;
+        let Some(device) = self.device.clone() else {
+            return;
+        };
+
+        let (tx, rx) = mpsc::channel();
+        let (wake, woken) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        self.stop = Arc::clone(&stop);
+        self.rx = Some(rx);
+        self.wake = Some(wake);
+        thread::spawn(move || {
+            let mut last = NowPlaying::default();
+            while !stop.load(Ordering::Relaxed) {
+                let app = ecp::get_active_app(&device);
+                let icon = if app.as_ref().map(|a| &a.id) == last.app.as_ref().map(|a| &a.id) {
+                    last.icon.clone()
+                } else {
+                    app.as_ref()
+                        .filter(|app| !app.id.is_empty())
+                        .and_then(|app| ecp::get_app_icon(&device, &app.id))
+                        .and_then(|(bytes, _)| decode_icon(&bytes))
+                        .map(Arc::new)
+                };
+                let now = NowPlaying {
+                    playback: app.as_ref().and_then(|_| ecp::get_playback(&device)),
+                    private_listening: app
+                        .as_ref()
+                        .and_then(|_| ecp::get_private_listening(&device)),
+                    app,
+                    icon,
+                };
+                if now != last {
+                    if tx.send(now.clone()).is_err() {
+                        return;
+                    }
+                    last = now;
+                }
+                if let Err(RecvTimeoutError::Disconnected) = woken.recv_timeout(POLL_INTERVAL) {
+                    return;
+                }
+            }
+        });
+    }
+
+    // where playback is by now: the last reported position plus the time since, while playing
+    pub fn position(&self) -> Option<Duration> {
+        let playback = self.current.playback.as_ref()?;
+        let position = playback.position?;
+        let since = match (playback.state.as_str(), self.updated) {
+            ("play", Some(updated)) => updated.elapsed(),
+            _ => Duration::ZERO,
+        };
+        let position = position + since;
+        Some(
+            playback
+                .duration
+                .map_or(position, |duration| position.min(duration)),
+        )
+    }
+
+    // polls again now rather than at the next interval
+    pub fn refresh(&self) {
+        if let Some(wake) = &self.wake {
+            let _ = wake.send(());
+        }
+    }
+
+    pub fn device(&self) -> Option<&str> {
+        self.device.as_deref()
+    }
+
+    // takes the latest poll without touching the window, for a device in a tab out of sight
+    pub fn poll(&mut self) -> bool {
+        let mut changed = false;
+        if let Some(rx) = &self.rx {
+            while let Ok(now) = rx.try_recv() {
+                self.current = now;
+                self.updated = Some(Instant::now());
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    // the window's title and icon are set again on the next update, having been another
+    // device's while this one's tab was out of sight
+    pub fn reshow(&mut self) {
+        self.shown_title = None;
+    }
+
+    // takes the latest poll and keeps the window title and icon in step; true when it changed
+    pub fn update(&mut self, ctx: &egui::Context) -> bool {
+        let mut changed = self.poll();
+        let title = self.current.title();
+        if self.shown_title.as_ref() != Some(&title) {
+            ctx.send_viewport_cmd(ViewportCommand::Title(title.clone()));
+            ctx.send_viewport_cmd(ViewportCommand::Icon(self.current.icon.clone()));
+            self.shown_title = Some(title);
+            changed = true;
+        }
+        if self.rx.is_some() {
+            ctx.request_repaint_after(POLL_INTERVAL);
+        }
+        changed
+    }
+}
+
+fn decode_icon(bytes: &[u8]) -> Option<IconData> {
+    let image = image::load_from_memory(bytes).ok()?.into_rgba8();
+    let (width, height) = image.dimensions();
+    Some(IconData {
+        rgba: image.into_raw(),
+        width,
+        height,
+    })
+}
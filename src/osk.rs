@@ -0,0 +1,141 @@
+// "typing" through a channel's on-screen keyboard with arrow keys and Select,
+// for search screens that ignore Lit_ keypresses
+
+use std::time::Duration;
+
+// the keyboard needs a moment to move focus before the next press registers
+pub const NAV_DELAY: Duration = Duration::from_millis(120);
+
+pub struct KeyboardLayout {
+    pub name: &'static str,
+    // one string per row; ' ' is the space key. shorter rows clamp the column when moving onto them
+    rows: &'static [&'static str],
+}
+
+// the 6-column grid used by Roku's own search and the SceneGraph MiniKeyboard
+pub const ROKU_GRID: KeyboardLayout = KeyboardLayout {
+    name: "Roku grid (a-z, 6 columns)",
+    rows: &[
+        "abcdef", "ghijkl", "mnopqr", "stuvwx", "yz1234", "567890", " ",
+    ],
+};
+
+// the full SceneGraph Keyboard some channels use for sign-in and search
+pub const QWERTY: KeyboardLayout = KeyboardLayout {
+    name: "QWERTY",
+    rows: &["1234567890", "qwertyuiop", "asdfghjkl", "zxcvbnm", " "],
+};
+
+pub const LAYOUTS: &[KeyboardLayout] = &[ROKU_GRID, QWERTY];
+
+pub struct Plan {
+    pub keys: Vec<&'static str>,
+    // characters the layout has no key for; they are left out of the plan
+    pub skipped: Vec<char>,
+}
+
+impl KeyboardLayout {
+    fn find(&self, c: char) -> Option<(usize, usize)> {
+        self.rows
+            .iter()
+            .enumerate()
+            .find_map(|(row, keys)| keys.chars().position(|k| k == c).map(|col| (row, col)))
+    }
+
+    fn row_len(&self, row: usize) -> usize {
+        self.rows[row].chars().count()
+    }
+
+    // arrow+Select presses that type text, assuming focus starts on the top-left key
+    pub fn plan(&self, text: &str) -> Plan {
+        let mut keys = Vec::new();
+        let mut skipped = Vec::new();
+        let (mut row, mut col) = (0, 0);
+
+        for c in text.chars().flat_map(char::to_lowercase) {
+            let Some((target_row, target_col)) = self.find(c) else {
+                skipped.push(c);
+                continue;
+            };
+
+            // move vertically first; every row change clamps the column to that row's width
+            while row != target_row {
+                if row < target_row {
+                    row += 1;
+                    keys.push("Down");
+                } else {
+                    row -= 1;
+                    keys.push("Up");
+                }
+                col = col.min(self.row_len(row) - 1);
+            }
+            while col < target_col {
+                col += 1;
+                keys.push("Right");
+            }
+            while col > target_col {
+                col -= 1;
+                keys.push("Left");
+            }
+            keys.push("Select");
+        }
+
+        Plan { keys, skipped }
+    }
+}
+
+// presses the planned keys in order. a missed press leaves the selection somewhere unknown, so
+// the first one that doesn't get through stops the typing and is what comes back
+pub fn type_keys(ip: &str, keys: &[&str]) -> Result<(), String> {
+    for (i, key) in keys.iter().enumerate() {
+        if let Err(err) = crate::ecp::send_command(ip, key) {
+            return Err(format!(
+                "{} didn't get through after {} of {} presses: {}",
+                key,
+                i,
+                keys.len(),
+                err
+            ));
+        }
+        std::thread::sleep(NAV_DELAY);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{QWERTY, ROKU_GRID};
+
+    fn repeat(key: &'static str, n: usize) -> Vec<&'static str> {
+        vec![key; n]
+    }
+
+    #[test]
+    fn moving_onto_a_shorter_row_clamps_the_column() {
+        // f is at the end of the top row; the space row is one key wide, so the way back up
+        // to z starts from the first column
+        let plan = ROKU_GRID.plan("f z");
+        let mut expected = repeat("Right", 5);
+        expected.push("Select");
+        expected.extend(repeat("Down", 6));
+        expected.push("Select");
+        expected.extend(["Up", "Up", "Right", "Select"]);
+        assert_eq!(plan.keys, expected);
+        assert!(plan.skipped.is_empty());
+
+        // p to m: each row down is shorter, and the clamping alone lands on m
+        let plan = QWERTY.plan("pm");
+        let mut expected = vec!["Down"];
+        expected.extend(repeat("Right", 9));
+        expected.extend(["Select", "Down", "Down", "Select"]);
+        assert_eq!(plan.keys, expected);
+    }
+
+    #[test]
+    fn characters_without_a_key_are_skipped() {
+        let plan = ROKU_GRID.plan("A!é");
+        assert_eq!(plan.keys, ["Select"]);
+        assert_eq!(plan.skipped, ['!', 'é']);
+        assert!(QWERTY.plan("").keys.is_empty());
+    }
+}
@@ -0,0 +1,221 @@
+// Rokus drop or sit on keypresses when they come in faster than the device can take them.
+// a Pacer queues presses per device and sends them one at a time, spacing them further apart
+// while the device answers slowly or not at all and closing the gap again once it keeps up
+
+use crate::ecp::{self, KeyAction};
+use crate::error::RokuError;
+use crate::transport::{Auto, RokuTransport};
+use std::collections::{HashMap, VecDeque};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+// an answer slower than this means the device is falling behind
+const SLOW_ANSWER: Duration = Duration::from_millis(250);
+const MIN_GAP: Duration = Duration::from_millis(50);
+const MAX_GAP: Duration = Duration::from_secs(1);
+// presses kept for sent() when nobody asks for them
+const KEPT: usize = 256;
+
+/// How a device's keypress queue is doing, for showing in a status bar.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PaceStatus {
+    /// Presses waiting to go out, including the one being sent.
+    pub queued: usize,
+    /// The current wait between presses; zero while the device keeps up.
+    pub gap: Duration,
+    pub last_answer: Option<Duration>,
+}
+
+impl PaceStatus {
+    pub fn throttled(&self) -> bool {
+        !self.gap.is_zero()
+    }
+}
+
+/// A keypress the device never took, even after trying again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Failure {
+    pub device: String,
+    pub key: String,
+    pub action: KeyAction,
+    pub error: RokuError,
+}
+
+/// A keypress that went out, or was given up on, and when.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sent {
+    pub device: String,
+    pub key: String,
+    pub action: KeyAction,
+    pub at: SystemTime,
+    pub result: Result<(), RokuError>,
+}
+
+// what the lanes report back, shared with the Pacer
+#[derive(Default)]
+struct Reports {
+    failures: Mutex<Vec<Failure>>,
+    sent: Mutex<VecDeque<Sent>>,
+}
+
+struct Lane {
+    tx: Sender<(String, KeyAction)>,
+    status: Arc<Mutex<PaceStatus>>,
+}
+
+/// Sends keypresses in order, one device at a time, without blocking the caller.
+pub struct Pacer {
+    lanes: Mutex<HashMap<String, Lane>>,
+    reports: Arc<Reports>,
+    transport: Arc<dyn RokuTransport>,
+}
+
+impl Default for Pacer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Pacer {
+    pub fn new() -> Pacer {
+        Pacer::with_transport(Arc::new(Auto))
+    }
+
+    /// Sends through a transport of your own, e.g. a `transport::Mock` in tests.
+    pub fn with_transport(transport: Arc<dyn RokuTransport>) -> Pacer {
+        Pacer {
+            lanes: Mutex::default(),
+            reports: Arc::default(),
+            transport,
+        }
+    }
+
+    /// Queues a keypress for an "ip:port" device.
+    pub fn send(&self, device: &str, key: &str) {
+        self.send_action(device, key, KeyAction::Press);
+    }
+
+    /// Queues a press, or the keydown or keyup of holding a key. Downs and ups go out in
+    /// order with the presses, so a release never overtakes its hold.
+    pub fn send_action(&self, device: &str, key: &str, action: KeyAction) {
+        let mut lanes = self.lanes.lock().unwrap();
+        let lane = lanes
+            .entry(device.to_string())
+            .or_insert_with(|| self.spawn_lane(device));
+        lane.status.lock().unwrap().queued += 1;
+        if lane.tx.send((key.to_string(), action)).is_err() {
+            // the lane's thread is gone; start over with a fresh one
+            let fresh = self.spawn_lane(device);
+            fresh.status.lock().unwrap().queued = 1;
+            let _ = fresh.tx.send((key.to_string(), action));
+            *lane = fresh;
+        }
+    }
+
+    /// The presses that failed since the last call.
+    pub fn failures(&self) -> Vec<Failure> {
+        std::mem::take(&mut *self.reports.failures.lock().unwrap())
+    }
+
+    /// Every press sent since the last call, oldest first, whether it got through or not.
+    /// Only the latest few hundred are kept between calls.
+    pub fn sent(&self) -> Vec<Sent> {
+        self.reports.sent.lock().unwrap().drain(..).collect()
+    }
+
+    pub fn status(&self, device: &str) -> Option<PaceStatus> {
+        let lanes = self.lanes.lock().unwrap();
+        lanes.get(device).map(|lane| *lane.status.lock().unwrap())
+    }
+
+    /// The device with the most presses waiting, or the slowest one.
+    pub fn busiest(&self) -> Option<(String, PaceStatus)> {
+        let lanes = self.lanes.lock().unwrap();
+        lanes
+            .iter()
+            .map(|(device, lane)| (device.clone(), *lane.status.lock().unwrap()))
+            .max_by_key(|(_, status)| (status.queued, status.gap))
+    }
+
+    fn spawn_lane(&self, device: &str) -> Lane {
+        spawn_lane(
+            device.to_string(),
+            Arc::clone(&self.reports),
+            Arc::clone(&self.transport),
+        )
+    }
+}
+
+fn spawn_lane(device: String, reports: Arc<Reports>, transport: Arc<dyn RokuTransport>) -> Lane {
+    let (tx, rx) = mpsc::channel::<(String, KeyAction)>();
+    let status = Arc::new(Mutex::new(PaceStatus::default()));
+    let shared = Arc::clone(&status);
+    thread::spawn(move || {
+        let mut gap = Duration::ZERO;
+        let mut last_sent = Instant::now();
+        for (key, action) in rx {
+            // a device that had a rest starts again at full speed
+            if last_sent.elapsed() > MAX_GAP * 2 {
+                gap = Duration::ZERO;
+            }
+            let started = Instant::now();
+            let ecp = ecp::via(&*transport);
+            let mut result = ecp.send_key_action(&device, &key, action);
+            // one more try for a press the device turned away, rather than losing it; a lost
+            // keyup would leave the key held down
+            if result.is_err() {
+                gap = next_gap(gap, true);
+                thread::sleep(gap);
+                result = ecp.send_key_action(&device, &key, action);
+            }
+            let failed = result.is_err();
+            let mut sent = reports.sent.lock().unwrap();
+            if sent.len() == KEPT {
+                sent.pop_front();
+            }
+            sent.push_back(Sent {
+                device: device.clone(),
+                key: key.clone(),
+                action,
+                at: SystemTime::now(),
+                result: result.clone(),
+            });
+            drop(sent);
+            if let Err(error) = result {
+                reports.failures.lock().unwrap().push(Failure {
+                    device: device.clone(),
+                    key: key.clone(),
+                    action,
+                    error,
+                });
+            }
+            let answer = started.elapsed();
+            last_sent = Instant::now();
+            gap = next_gap(gap, failed || answer > SLOW_ANSWER);
+
+            let mut status = shared.lock().unwrap();
+            status.queued = status.queued.saturating_sub(1);
+            status.gap = gap;
+            status.last_answer = Some(answer);
+            drop(status);
+            thread::sleep(gap);
+        }
+    });
+    Lane { tx, status }
+}
+
+// doubles the gap while the device struggles, then eases back off by a quarter per press
+fn next_gap(gap: Duration, struggling: bool) -> Duration {
+    if struggling {
+        (gap * 2).clamp(MIN_GAP, MAX_GAP)
+    } else {
+        let eased = gap * 3 / 4;
+        if eased < MIN_GAP / 2 {
+            Duration::ZERO
+        } else {
+            eased
+        }
+    }
+}
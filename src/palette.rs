@@ -0,0 +1,169 @@
+use eframe::egui;
+
+pub const PALETTE_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::K);
+// what opened it at first, still taken for the fingers that learned it
+pub const PALETTE_SHORTCUT_ALT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::P);
+
+const MAX_RESULTS: usize = 12;
+
+// what happens when a palette entry is picked
+#[derive(Debug, Clone, PartialEq)]
+pub enum PaletteAction {
+    Key(String),
+    LaunchApp(String),
+    SelectDevice(String),
+    // name of a watchlist entry
+    DeepLink(String),
+    // name of a saved macro
+    Macro(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct PaletteEntry {
+    pub label: String,
+    pub kind: &'static str,
+    pub action: PaletteAction,
+}
+
+#[derive(Default)]
+pub struct CommandPalette {
+    pub open: bool,
+    query: String,
+    selected: usize,
+}
+
+impl CommandPalette {
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.query.clear();
+        self.selected = 0;
+    }
+
+    // draw the palette over the remote and return the entry the user picked, if any
+    pub fn show(&mut self, ctx: &egui::Context, entries: &[PaletteEntry]) -> Option<PaletteAction> {
+        if !self.open {
+            return None;
+        }
+
+        let mut matches: Vec<(i32, &PaletteEntry)> = entries
+            .iter()
+            .filter_map(|entry| fuzzy_score(&self.query, &entry.label).map(|score| (score, entry)))
+            .collect();
+        matches.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.label.cmp(&b.1.label)));
+        matches.truncate(MAX_RESULTS);
+
+        let (up, down, enter, escape) = ctx.input(|i| {
+            (
+                i.key_pressed(egui::Key::ArrowUp),
+                i.key_pressed(egui::Key::ArrowDown),
+                i.key_pressed(egui::Key::Enter),
+                i.key_pressed(egui::Key::Escape),
+            )
+        });
+        if escape {
+            self.open = false;
+            return None;
+        }
+        if down {
+            self.selected += 1;
+        }
+        if up {
+            self.selected = self.selected.saturating_sub(1);
+        }
+        self.selected = self.selected.min(matches.len().saturating_sub(1));
+
+        let mut picked = None;
+        egui::Window::new("Command Palette")
+            .title_bar(false)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 40.0])
+            .show(ctx, |ui| {
+                let edit = ui.add(
+                    egui::TextEdit::singleline(&mut self.query)
+                        .hint_text("Search keys, apps, devices, macros…")
+                        .desired_width(300.0),
+                );
+                edit.request_focus();
+                if edit.changed() {
+                    self.selected = 0;
+                }
+                ui.separator();
+
+                if matches.is_empty() {
+                    ui.label("No matches");
+                }
+                for (i, (_, entry)) in matches.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui
+                            .selectable_label(i == self.selected, &entry.label)
+                            .clicked()
+                        {
+                            picked = Some(entry.action.clone());
+                        }
+                        ui.weak(entry.kind);
+                    });
+                }
+            });
+
+        if enter {
+            picked = matches
+                .get(self.selected)
+                .map(|(_, entry)| entry.action.clone());
+        }
+        if picked.is_some() {
+            self.open = false;
+        }
+        picked
+    }
+}
+
+// subsequence match that rewards consecutive hits and word starts
+// returns None unless every query character appears in order
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i32> {
+    let query: Vec<char> = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(lower)
+        .collect();
+    if query.is_empty() {
+        return Some(0);
+    }
+
+    let chars: Vec<char> = candidate.chars().collect();
+    let mut score = 0;
+    let mut matched = 0;
+    let mut prev: Option<usize> = None;
+
+    for (i, &c) in chars.iter().enumerate() {
+        if matched == query.len() {
+            break;
+        }
+        if lower(c) != query[matched] {
+            continue;
+        }
+
+        score += 1;
+        let word_start = i == 0
+            || !chars[i - 1].is_alphanumeric()
+            || (chars[i - 1].is_lowercase() && c.is_uppercase());
+        if word_start {
+            score += 8;
+        }
+        match prev {
+            Some(p) if p + 1 == i => score += 5,
+            Some(p) => score -= (i - p - 1).min(5) as i32,
+            None => score -= i.min(5) as i32,
+        }
+        prev = Some(i);
+        matched += 1;
+    }
+
+    (matched == query.len()).then_some(score)
+}
+
+fn lower(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
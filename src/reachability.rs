@@ -0,0 +1,123 @@
+// how quickly each device in the list answers, so the ones that will actually respond come
+// first and addresses remembered from earlier that no longer answer sink to the bottom
+
+use crate::ecp;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+// how often the GUI looks again on its own: discovery for devices that have turned up since,
+// and a device-info ping of each listed device to tell which are still online
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackgroundChecks {
+    // 0 turns background discovery off
+    pub rediscover_minutes: u32,
+    // 0 turns the pings off
+    pub ping_seconds: u32,
+}
+
+impl Default for BackgroundChecks {
+    fn default() -> Self {
+        Self {
+            rediscover_minutes: 10,
+            ping_seconds: 30,
+        }
+    }
+}
+
+impl BackgroundChecks {
+    pub fn rediscover_every(&self) -> Option<Duration> {
+        (self.rediscover_minutes > 0)
+            .then(|| Duration::from_secs(self.rediscover_minutes as u64 * 60))
+    }
+
+    pub fn ping_every(&self) -> Option<Duration> {
+        (self.ping_seconds > 0).then(|| Duration::from_secs(self.ping_seconds as u64))
+    }
+}
+
+#[derive(Default)]
+pub struct Reachability {
+    // None for a device that didn't answer; missing until its first probe is back
+    latency: HashMap<String, Option<Duration>>,
+    rx: Option<Receiver<(String, Option<Duration>)>>,
+    // devices whose probe is still out
+    pending: HashSet<String>,
+}
+
+impl Reachability {
+    // probes every device on its own thread; earlier results are forgotten
+    pub fn probe(&mut self, devices: &[String]) {
+        self.latency.clear();
+        self.send_probes(devices);
+    }
+
+    // probes again, keeping the last results until the new ones are in so the list doesn't
+    // jump around; left alone while a probe is still out
+    pub fn refresh(&mut self, devices: &[String]) {
+        if !self.busy() {
+            self.send_probes(devices);
+        }
+    }
+
+    fn send_probes(&mut self, devices: &[String]) {
+        let (tx, rx) = mpsc::channel();
+        for device in devices {
+            let (tx, device) = (tx.clone(), device.clone());
+            thread::spawn(move || {
+                let latency = ecp::probe_latency(&device);
+                let _ = tx.send((device, latency));
+            });
+        }
+        self.rx = Some(rx);
+        self.pending = devices.iter().cloned().collect();
+    }
+
+    pub fn busy(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    // takes in finished probes; true if any came in
+    pub fn poll(&mut self) -> bool {
+        let Some(rx) = &self.rx else {
+            return false;
+        };
+        let mut changed = false;
+        while let Ok((device, latency)) = rx.try_recv() {
+            self.pending.remove(&device);
+            self.latency.insert(device, latency);
+            changed = true;
+        }
+        changed
+    }
+
+    // whether the device answered its last probe; None before the first is back
+    pub fn online(&self, device: &str) -> Option<bool> {
+        self.latency(device).map(|latency| latency.is_some())
+    }
+
+    pub fn latency(&self, device: &str) -> Option<Option<Duration>> {
+        self.latency.get(device).copied()
+    }
+
+    // fastest first, then the ones still being probed, then the ones that didn't answer
+    pub fn sort(&self, devices: &mut [String]) {
+        devices.sort_by_key(|device| match self.latency(device) {
+            Some(Some(latency)) => (0, latency),
+            None => (1, Duration::ZERO),
+            Some(None) => (2, Duration::ZERO),
+        });
+    }
+
+    // what the device list shows after a device's name
+    pub fn marker(&self, device: &str) -> String {
+        match self.latency(device) {
+            Some(Some(latency)) => format!(" · {} ms", latency.as_millis()),
+            Some(None) => " ⚠ not answering".to_string(),
+            None => String::new(),
+        }
+    }
+}
@@ -0,0 +1,356 @@
+// timed keypresses and macros run by the --serve daemon. when each schedule last fired is
+// kept on disk, so a daemon that was down when a trigger came due can still catch up on it
+
+use crate::config::{config_path, write_json, Config};
+use crate::macros::{self, Step};
+use crate::server::ServerState;
+use chrono::{DateTime, Datelike, Duration as Days, Local, LocalResult, NaiveTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+// how often the daemon looks for due schedules
+const TICK: Duration = Duration::from_secs(20);
+
+const DAY_NAMES: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Key(String),
+    // name of a saved macro
+    Macro(String),
+}
+
+impl Action {
+    pub fn label(&self) -> String {
+        match self {
+            Action::Key(key) => key.clone(),
+            Action::Macro(name) => format!("macro {}", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Schedule {
+    // local time of day, "22:30"
+    pub at: String,
+    // "mon".."sun"; empty means every day
+    #[serde(default)]
+    pub days: Vec<String>,
+    // group, room or address, as for macros::target_devices
+    pub target: String,
+    pub action: Action,
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScheduleSettings {
+    // triggers missed while the daemon was down still run if they are at most this old
+    pub catch_up_minutes: u32,
+}
+
+impl Default for ScheduleSettings {
+    fn default() -> Self {
+        Self {
+            catch_up_minutes: 30,
+        }
+    }
+}
+
+impl Schedule {
+    fn time(&self) -> Option<NaiveTime> {
+        NaiveTime::parse_from_str(self.at.trim(), "%H:%M").ok()
+    }
+
+    fn runs_on(&self, day: chrono::Weekday) -> bool {
+        let name = DAY_NAMES[day.num_days_from_monday() as usize];
+        self.days.is_empty() || self.days.iter().any(|d| d.eq_ignore_ascii_case(name))
+    }
+
+    // the latest time this schedule came due at or before now, in now's time zone
+    fn last_trigger<Tz: TimeZone>(&self, now: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let time = self.time()?;
+        let zone = now.timezone();
+        (0..8).find_map(|back| {
+            let date = now.date_naive() - Days::days(back);
+            let local = date.and_time(time);
+            // a time that happens twice when DST ends comes due the first time, and one the
+            // clocks skip over when it starts comes due when they land, an hour on
+            let trigger = match zone.from_local_datetime(&local) {
+                LocalResult::Single(trigger) => trigger,
+                LocalResult::Ambiguous(a, b) => a.min(b),
+                LocalResult::None => zone
+                    .from_local_datetime(&(local + Days::hours(1)))
+                    .earliest()?,
+            };
+            (trigger <= *now && self.runs_on(date.weekday())).then_some(trigger)
+        })
+    }
+
+    fn steps(&self, config: &Config) -> Result<Vec<Step>, String> {
+        match &self.action {
+            Action::Key(key) => Ok(vec![Step::Key(key.clone())]),
+            Action::Macro(name) => config
+                .macros
+                .get(name)
+                .ok_or_else(|| format!("no macro named \"{}\"", name))
+                .and_then(|script| macros::parse(script)),
+        }
+    }
+}
+
+// when each schedule last fired; local to this machine, the daemon is what runs them
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScheduleState {
+    pub last_run: BTreeMap<String, i64>,
+}
+
+fn state_path() -> Option<PathBuf> {
+    config_path().map(|path| path.with_file_name("schedule-state.json"))
+}
+
+impl ScheduleState {
+    pub fn load() -> ScheduleState {
+        state_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = state_path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+        write_json(&path, self)
+    }
+}
+
+// schedules that are due now: not yet run for their latest trigger and not too old to catch up
+// on. times of day are read in now's time zone, the machine's own for the daemon
+fn due<Tz: TimeZone>(
+    config: &Config,
+    state: &mut ScheduleState,
+    now: DateTime<Tz>,
+) -> Vec<(String, Schedule)>
+where
+    Tz::Offset: Display,
+{
+    // a trigger has to stay due for at least a couple of ticks, even with catch-up turned off
+    let window = Days::minutes(config.schedule_settings.catch_up_minutes.into())
+        .max(Days::from_std(TICK * 2).unwrap_or_default());
+    let mut due = Vec::new();
+    for (name, schedule) in &config.schedules {
+        // a schedule seen for the first time counts from now: a trigger from before it was
+        // made wasn't missed
+        let Some(&last_run) = state.last_run.get(name) else {
+            state.last_run.insert(name.clone(), now.timestamp());
+            continue;
+        };
+        let Some(trigger) = schedule.last_trigger(&now).filter(|_| schedule.enabled) else {
+            continue;
+        };
+        if trigger.timestamp() <= last_run {
+            continue;
+        }
+        if now.clone() - trigger.clone() <= window {
+            due.push((name.clone(), schedule.clone()));
+        } else {
+            println!(
+                "Skipping {}: missed its {} run",
+                name,
+                trigger.format("%a %H:%M")
+            );
+        }
+        state.last_run.insert(name.clone(), trigger.timestamp());
+    }
+    due
+}
+
+// runs due schedules on a background thread for as long as the daemon is up
+pub fn spawn(state: Arc<Mutex<ServerState>>) {
+    thread::spawn(move || {
+        let mut runs = ScheduleState::load();
+        loop {
+            let config = state.lock().unwrap().config.clone();
+            let before = runs.last_run.clone();
+            let due = due(&config, &mut runs, Local::now());
+            if runs.last_run != before {
+                runs.save().ok();
+            }
+            for (name, schedule) in due {
+                run(&config, &name, &schedule);
+            }
+            thread::sleep(TICK);
+        }
+    });
+}
+
+fn run(config: &Config, name: &str, schedule: &Schedule) {
+    let steps = match schedule.steps(config) {
+        Ok(steps) => steps,
+        Err(err) => {
+            eprintln!("Schedule {}: {}", name, err);
+            return;
+        }
+    };
+    for ip in macros::target_devices(config, &schedule.target) {
+        match macros::run(&ip, &steps, config.macro_settings.verify, |_| {}) {
+            Ok(()) => println!("Schedule {} ran on {}", name, ip),
+            Err(err) => eprintln!("Schedule {} failed on {}: {}", name, ip, err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{due, Action, Schedule, ScheduleState};
+    use crate::config::Config;
+    use chrono::{DateTime, NaiveDate, TimeZone};
+    use chrono_tz::America::New_York;
+    use chrono_tz::Tz;
+
+    // both moments a wall-clock time names in New York, a zone with DST so the changeover
+    // nights can be tried: the same one twice on all but one night a year
+    fn moments(day: (i32, u32, u32), hms: (u32, u32, u32)) -> (DateTime<Tz>, DateTime<Tz>) {
+        let time = NaiveDate::from_ymd_opt(day.0, day.1, day.2)
+            .and_then(|date| date.and_hms_opt(hms.0, hms.1, hms.2))
+            .unwrap();
+        let found = New_York.from_local_datetime(&time);
+        let (a, b) = (found.earliest().unwrap(), found.latest().unwrap());
+        (a.min(b), a.max(b))
+    }
+
+    fn local(day: (i32, u32, u32), hms: (u32, u32, u32)) -> DateTime<Tz> {
+        moments(day, hms).0
+    }
+
+    fn config(at: &str, days: &[&str]) -> Config {
+        let mut config = Config::default();
+        config.schedules.insert(
+            "lights".into(),
+            Schedule {
+                at: at.into(),
+                days: days.iter().map(|day| day.to_string()).collect(),
+                target: "Living room".into(),
+                action: Action::Key("PowerOff".into()),
+                enabled: true,
+            },
+        );
+        config
+    }
+
+    fn ran_at(time: DateTime<Tz>) -> ScheduleState {
+        let mut state = ScheduleState::default();
+        state.last_run.insert("lights".into(), time.timestamp());
+        state
+    }
+
+    fn names(due: Vec<(String, Schedule)>) -> Vec<String> {
+        due.into_iter().map(|(name, _)| name).collect()
+    }
+
+    #[test]
+    fn a_run_missed_while_down_is_caught_up_once_if_recent() {
+        let config = config("07:00", &[]);
+
+        // down from before 07:00 until 07:20, inside the 30 minute catch-up
+        let mut state = ran_at(local((2026, 5, 11), (7, 0, 0)));
+        let now = local((2026, 5, 12), (7, 20, 0));
+        assert_eq!(names(due(&config, &mut state, now)), ["lights"]);
+        assert!(due(&config, &mut state, now).is_empty());
+
+        // down for days: only the latest trigger counts, and it's too old
+        let mut state = ran_at(local((2026, 5, 8), (7, 0, 0)));
+        let now = local((2026, 5, 12), (8, 0, 0));
+        assert!(due(&config, &mut state, now).is_empty());
+        // it's marked as handled, so it isn't run later either
+        let later = local((2026, 5, 12), (8, 0, 20));
+        assert!(due(&config, &mut state, later).is_empty());
+        assert_eq!(
+            state.last_run["lights"],
+            local((2026, 5, 12), (7, 0, 0)).timestamp()
+        );
+    }
+
+    #[test]
+    fn a_poll_exactly_on_the_trigger_runs_it() {
+        let mut config = config("22:30", &[]);
+        let trigger = local((2026, 5, 12), (22, 30, 0));
+        let mut state = ran_at(local((2026, 5, 11), (22, 30, 0)));
+
+        let before = local((2026, 5, 12), (22, 29, 59));
+        assert!(due(&config, &mut state, before).is_empty());
+        assert_eq!(names(due(&config, &mut state, trigger)), ["lights"]);
+        assert!(due(&config, &mut state, trigger).is_empty());
+
+        // with catch-up off a trigger stays due for two ticks, to the second
+        config.schedule_settings.catch_up_minutes = 0;
+        let mut state = ran_at(local((2026, 5, 11), (22, 30, 0)));
+        let two_ticks = local((2026, 5, 12), (22, 30, 40));
+        assert_eq!(names(due(&config, &mut state, two_ticks)), ["lights"]);
+        let mut state = ran_at(local((2026, 5, 11), (22, 30, 0)));
+        let too_late = local((2026, 5, 12), (22, 30, 41));
+        assert!(due(&config, &mut state, too_late).is_empty());
+    }
+
+    #[test]
+    fn day_lists_hold_across_midnight() {
+        // 2026-05-12 is a Tuesday
+        let config = config("00:00", &["tue"]);
+        let mut state = ran_at(local((2026, 5, 5), (0, 0, 0)));
+        let monday_night = local((2026, 5, 11), (23, 59, 59));
+        assert!(due(&config, &mut state, monday_night).is_empty());
+        let midnight = local((2026, 5, 12), (0, 0, 0));
+        assert_eq!(names(due(&config, &mut state, midnight)), ["lights"]);
+
+        // a late schedule isn't mistaken for due again just after midnight
+        let config = self::config("23:59", &[]);
+        let mut state = ran_at(local((2026, 5, 11), (23, 59, 0)));
+        let after_midnight = local((2026, 5, 12), (0, 0, 10));
+        assert!(due(&config, &mut state, after_midnight).is_empty());
+    }
+
+    #[test]
+    fn dst_changeovers_run_a_schedule_once() {
+        // 2026-03-08: clocks go from 02:00 straight to 03:00, so 02:30 never happens
+        let config = config("02:30", &[]);
+        let mut state = ran_at(local((2026, 3, 7), (2, 30, 0)));
+        let landed = local((2026, 3, 8), (3, 30, 0));
+        assert_eq!(names(due(&config, &mut state, landed)), ["lights"]);
+        assert!(due(&config, &mut state, local((2026, 3, 8), (3, 45, 0))).is_empty());
+
+        // 2026-11-01: 01:30 happens twice, an hour apart; only the first one runs it
+        let config = self::config("01:30", &[]);
+        let mut state = ran_at(local((2026, 10, 31), (1, 30, 0)));
+        let first = local((2026, 11, 1), (1, 30, 0));
+        assert_eq!(names(due(&config, &mut state, first)), ["lights"]);
+        let second = moments((2026, 11, 1), (1, 30, 0)).1;
+        assert_eq!(second - first, chrono::Duration::hours(1));
+        assert!(due(&config, &mut state, second).is_empty());
+    }
+
+    #[test]
+    fn a_new_schedule_waits_for_its_next_trigger() {
+        let config = config("20:00", &[]);
+        // made at 20:05, inside the catch-up window of today's 20:00
+        let mut state = ScheduleState::default();
+        let made = local((2026, 5, 12), (20, 5, 0));
+        assert!(due(&config, &mut state, made).is_empty());
+        assert!(due(&config, &mut state, local((2026, 5, 12), (20, 5, 20))).is_empty());
+        let tomorrow = local((2026, 5, 13), (20, 0, 0));
+        assert_eq!(names(due(&config, &mut state, tomorrow)), ["lights"]);
+    }
+}
@@ -0,0 +1,188 @@
+// automation scripts in Rhai, for what macros can't do: loops, conditions, acting on what's on
+// screen. a script gets discover(), keypress(ip, key), launch(ip, app), active_app(ip) and
+// sleep(ms), and what it print()s goes to the GUI or the terminal. built with the scripting
+// feature; without it a script only fails to run
+
+use crate::ecp::SsdpSettings;
+use eframe::egui;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+
+pub const SCRIPT_HELP: &str = "Rhai scripts, with these on top of the language:\n\
+    discover()          the addresses of the Rokus on the network\n\
+    keypress(ip, key)   press a remote key, e.g. keypress(ip, \"Home\")\n\
+    launch(ip, app)     launch a channel by its id or name\n\
+    active_app(ip)      the name of the channel on screen, \"Roku\" on the home screen\n\
+    sleep(ms)           pause\n\
+    A failed call stops the script with its error.";
+
+// whether this build runs scripts at all, for the GUI
+pub const AVAILABLE: bool = cfg!(feature = "scripting");
+
+enum Message {
+    Print(String),
+    Done(Result<(), String>),
+}
+
+// a script running on its own thread from the GUI, and what it has printed so far
+pub struct ScriptRun {
+    pub output: Vec<String>,
+    // set once the script has finished
+    pub result: Option<Result<(), String>>,
+    rx: Receiver<Message>,
+    stop: Arc<AtomicBool>,
+}
+
+impl ScriptRun {
+    pub fn start(source: String, ssdp: SsdpSettings, ctx: &egui::Context) -> ScriptRun {
+        let (tx, rx) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        {
+            let (stop, ctx) = (Arc::clone(&stop), ctx.clone());
+            thread::spawn(move || {
+                let print = {
+                    let (tx, ctx) = (tx.clone(), ctx.clone());
+                    move |line: &str| {
+                        tx.send(Message::Print(line.to_string())).ok();
+                        ctx.request_repaint();
+                    }
+                };
+                let result = run(&source, &ssdp, stop, print);
+                tx.send(Message::Done(result)).ok();
+                ctx.request_repaint();
+            });
+        }
+        ScriptRun {
+            output: Vec::new(),
+            result: None,
+            rx,
+            stop,
+        }
+    }
+
+    // takes what the script printed since the last frame; false once it has finished
+    pub fn poll(&mut self) -> bool {
+        while let Ok(message) = self.rx.try_recv() {
+            match message {
+                Message::Print(line) => self.output.push(line),
+                Message::Done(result) => self.result = Some(result),
+            }
+        }
+        self.result.is_none()
+    }
+
+    // the script stops at its next statement, or a few milliseconds into a sleep
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for ScriptRun {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(feature = "scripting")]
+pub use imp::run;
+
+#[cfg(not(feature = "scripting"))]
+pub fn run(
+    _source: &str,
+    _ssdp: &SsdpSettings,
+    _stop: Arc<AtomicBool>,
+    _print: impl Fn(&str) + 'static,
+) -> Result<(), String> {
+    Err("built without the scripting feature".into())
+}
+
+#[cfg(feature = "scripting")]
+mod imp {
+    use crate::ecp::{self, SsdpSettings};
+    use rhai::{Array, Dynamic, Engine, EvalAltResult};
+    use roku_remote::RokuKey;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    // how often a sleep looks whether the script was stopped
+    const STOP_POLL: Duration = Duration::from_millis(50);
+
+    type Fallible<T> = Result<T, Box<EvalAltResult>>;
+
+    // runs the script to the end, to its first error, or until stop is set
+    pub fn run(
+        source: &str,
+        ssdp: &SsdpSettings,
+        stop: Arc<AtomicBool>,
+        print: impl Fn(&str) + 'static,
+    ) -> Result<(), String> {
+        let mut engine = Engine::new();
+        engine.on_print(print);
+        {
+            let stop = Arc::clone(&stop);
+            engine.on_progress(move |_| {
+                stop.load(Ordering::Relaxed)
+                    .then(|| Dynamic::from("stopped"))
+            });
+        }
+
+        let ssdp = ssdp.clone();
+        engine.register_fn("discover", move || -> Array {
+            ecp::discover_with(&ssdp)
+                .into_iter()
+                .map(Dynamic::from)
+                .collect()
+        });
+        engine.register_fn("keypress", |ip: &str, key: &str| -> Fallible<()> {
+            let key: RokuKey = key.parse().map_err(|err| format!("{}", err))?;
+            let ip = ecp::device_addr(ip);
+            ecp::press(&ip, key).map_err(|err| format!("{} on {}: {}", key, ip, err).into())
+        });
+        engine.register_fn("launch", |ip: &str, app: &str| -> Fallible<()> {
+            let ip = ecp::device_addr(ip);
+            let id = app_id(&ip, app)?;
+            ecp::launch_app(&ip, &id)
+                .map_err(|err| format!("launching {} on {}: {}", app, ip, err).into())
+        });
+        engine.register_fn("active_app", |ip: &str| -> Fallible<String> {
+            let ip = ecp::device_addr(ip);
+            ecp::get_active_app(&ip)
+                .map(|app| app.name)
+                .ok_or_else(|| format!("{} didn't say what's on screen", ip).into())
+        });
+        engine.register_fn("sleep", move |ms: i64| {
+            let until = Instant::now() + Duration::from_millis(ms.max(0) as u64);
+            while !stop.load(Ordering::Relaxed) {
+                let left = until.saturating_duration_since(Instant::now());
+                if left.is_zero() {
+                    break;
+                }
+                thread::sleep(left.min(STOP_POLL));
+            }
+        });
+
+        engine.run(source).map_err(|err| match *err {
+            EvalAltResult::ErrorTerminated(..) => "stopped".to_string(),
+            // a failed call reads better without rhai's "in call to function" around it
+            EvalAltResult::ErrorInFunctionCall(_, _, inner, _) => inner.to_string(),
+            err => err.to_string(),
+        })
+    }
+
+    // the id itself, or the id of the channel on the device with that name
+    fn app_id(ip: &str, app: &str) -> Fallible<String> {
+        if !app.is_empty() && app.chars().all(|c| c.is_ascii_digit()) {
+            return Ok(app.to_string());
+        }
+        ecp::get_apps(ip)
+            .map_err(|err| format!("listing the channels on {}: {}", ip, err))?
+            .into_iter()
+            .find(|entry| entry.name.eq_ignore_ascii_case(app))
+            .map(|entry| entry.id)
+            .ok_or_else(|| format!("no channel named {} on {}", app, ip).into())
+    }
+}
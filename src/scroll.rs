@@ -0,0 +1,153 @@
+use eframe::egui::{self, Event, MouseWheelUnit, Pos2, Rect, Vec2};
+use serde::{Deserialize, Serialize};
+
+// touchpads report points rather than wheel notches; this many points count as one notch
+const POINTS_PER_STEP: f32 = 50.0;
+
+// which parts of the remote react to the scroll wheel
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScrollSettings {
+    // vertical scroll over the d-pad presses Up/Down
+    pub dpad: bool,
+    // vertical scroll over the volume row presses VolumeUp/VolumeDown
+    pub volume: bool,
+    // horizontal (or shift+) scroll anywhere over the remote presses Fwd/Rev
+    pub seek: bool,
+    pub invert: bool,
+}
+
+impl Default for ScrollSettings {
+    fn default() -> Self {
+        Self {
+            dpad: true,
+            volume: true,
+            seek: true,
+            invert: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Zone {
+    Dpad,
+    Volume,
+}
+
+impl Zone {
+    pub fn for_key(cmd: &str) -> Option<Zone> {
+        match cmd {
+            "Up" | "Down" | "Left" | "Right" | "Select" => Some(Zone::Dpad),
+            "VolumeUp" | "VolumeDown" | "VolumeMute" => Some(Zone::Volume),
+            _ => None,
+        }
+    }
+}
+
+// screen areas of the remote collected while drawing, plus partial scroll carried between frames
+#[derive(Default)]
+pub struct ScrollZones {
+    dpad: Option<Rect>,
+    volume: Option<Rect>,
+    remote: Option<Rect>,
+    pending: Vec2,
+}
+
+impl ScrollZones {
+    pub fn clear(&mut self) {
+        self.dpad = None;
+        self.volume = None;
+        self.remote = None;
+    }
+
+    pub fn add(&mut self, zone: Option<Zone>, rect: Rect) {
+        let slot = match zone {
+            Some(Zone::Dpad) => &mut self.dpad,
+            Some(Zone::Volume) => &mut self.volume,
+            None => &mut self.remote,
+        };
+        *slot = Some(slot.map_or(rect, |r| r.union(rect)));
+        self.remote = Some(self.remote.map_or(rect, |r| r.union(rect)));
+    }
+
+    fn zone_at(&self, pos: Pos2) -> Option<Zone> {
+        if self.dpad.is_some_and(|r| r.contains(pos)) {
+            Some(Zone::Dpad)
+        } else if self.volume.is_some_and(|r| r.contains(pos)) {
+            Some(Zone::Volume)
+        } else {
+            None
+        }
+    }
+
+    // turns this frame's wheel movement over the remote into keypresses
+    pub fn keys(&mut self, ctx: &egui::Context, settings: &ScrollSettings) -> Vec<&'static str> {
+        let (pos, delta) = ctx.input(|i| {
+            let delta = i
+                .events
+                .iter()
+                .filter_map(|event| match event {
+                    Event::MouseWheel {
+                        unit,
+                        delta,
+                        modifiers,
+                    } => {
+                        let steps = match unit {
+                            MouseWheelUnit::Point => *delta / POINTS_PER_STEP,
+                            MouseWheelUnit::Line | MouseWheelUnit::Page => *delta,
+                        };
+                        // shift+wheel is the usual way to scroll sideways with a plain mouse
+                        Some(if modifiers.shift && steps.x == 0.0 {
+                            Vec2::new(steps.y, 0.0)
+                        } else {
+                            steps
+                        })
+                    }
+                    _ => None,
+                })
+                .fold(Vec2::ZERO, |sum, d| sum + d);
+            (i.pointer.hover_pos(), delta)
+        });
+
+        let Some(pos) = pos.filter(|p| self.remote.is_some_and(|r| r.contains(*p))) else {
+            self.pending = Vec2::ZERO;
+            return Vec::new();
+        };
+        self.pending += if settings.invert { -delta } else { delta };
+
+        let mut keys = Vec::new();
+        let zone = self.zone_at(pos);
+        let vertical = match zone {
+            Some(Zone::Dpad) if settings.dpad => Some(("Up", "Down")),
+            Some(Zone::Volume) if settings.volume => Some(("VolumeUp", "VolumeDown")),
+            _ => None,
+        };
+        match vertical {
+            Some((up, down)) => take_steps(&mut self.pending.y, up, down, &mut keys),
+            None => self.pending.y = 0.0,
+        }
+        if settings.seek {
+            take_steps(&mut self.pending.x, "Rev", "Fwd", &mut keys);
+        } else {
+            self.pending.x = 0.0;
+        }
+        keys
+    }
+}
+
+// wheel deltas follow the content: positive y is scrolling up, positive x is scrolling left
+fn take_steps(
+    pending: &mut f32,
+    positive: &'static str,
+    negative: &'static str,
+    keys: &mut Vec<&'static str>,
+) {
+    while *pending >= 1.0 {
+        keys.push(positive);
+        *pending -= 1.0;
+    }
+    while *pending <= -1.0 {
+        keys.push(negative);
+        *pending += 1.0;
+    }
+}
@@ -0,0 +1,675 @@
+use crate::config::{ApiToken, Config, ConfigStore, Role};
+use crate::ecp::{self, AppEntry};
+use crate::macros;
+use crate::state_cache;
+use roku_remote::events::{self, DeviceEvent, DeviceState, EventKind, StateCache};
+use roku_remote::RokuKey;
+use serde::Serialize;
+use std::io::{self, Cursor};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tiny_http::{Header, Method, Request, Response, Server};
+use url::form_urlencoded;
+use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityScheme};
+use utoipa::{Modify, OpenApi, ToSchema};
+
+pub const DEFAULT_ADDR: &str = "127.0.0.1:8061";
+#[cfg(feature = "grpc")]
+pub const DEFAULT_GRPC_ADDR: &str = "127.0.0.1:8062";
+
+type JsonResponse = Response<Cursor<Vec<u8>>>;
+
+const NOT_CONTROL: &str = "this token can only view device state";
+// launch?wait= ties up a connection while it polls, so keep it short
+const MAX_LAUNCH_WAIT: u64 = 30;
+
+pub struct ServerState {
+    store: ConfigStore,
+    pub config: Config,
+    pub devices: Vec<String>,
+    pub cache: StateCache,
+}
+
+impl ServerState {
+    // adds devices found by find_devices, which runs without the lock
+    fn add_devices(&mut self, found: Vec<String>) {
+        for device in found {
+            if !self.devices.contains(&device) {
+                self.cache.discovered(&device);
+                self.devices.push(device);
+            }
+        }
+    }
+
+    fn visible_devices(&self, token: &ApiToken) -> Vec<String> {
+        self.devices
+            .iter()
+            .filter(|d| token.allows(d))
+            .cloned()
+            .collect()
+    }
+}
+
+// the configured devices plus whatever answers SSDP
+fn find_devices(config: &Config) -> Vec<String> {
+    config
+        .devices
+        .keys()
+        .cloned()
+        .chain(ecp::discover_with(&config.ssdp))
+        .collect()
+}
+
+// the cached state, polling the device itself only before the cache has caught up with it.
+// the poll happens with the lock released, so a slow device holds up nothing else
+pub fn cached_state(state: &Mutex<ServerState>, device: &str) -> DeviceState {
+    if let Some(cached) = state.lock().unwrap().cache.get(device) {
+        return cached.clone();
+    }
+    let fresh = events::poll(device);
+    let mut state = state.lock().unwrap();
+    state.cache.update(fresh);
+    state.cache.get(device).cloned().unwrap()
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Roku Remote control server"),
+    paths(
+        list_devices,
+        discover,
+        all_states,
+        device_state,
+        events,
+        list_apps,
+        keypress,
+        launch,
+        run_macro,
+        list_tokens,
+        create_token,
+        revoke_token
+    ),
+    components(schemas(
+        AppEntry,
+        Role,
+        DeviceState,
+        DeviceEvent,
+        EventKind,
+        Sent,
+        Launched,
+        Ran,
+        TokenInfo,
+        CreatedToken,
+        Revoked,
+        ApiError
+    )),
+    modifiers(&BearerAuth)
+)]
+struct ApiDoc;
+
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "token",
+                SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+            );
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct Sent {
+    sent: String,
+}
+
+#[derive(Serialize, ToSchema)]
+struct Launched {
+    launched: String,
+    // only present when the caller asked to wait for the channel to come up
+    #[serde(skip_serializing_if = "Option::is_none")]
+    foreground: Option<bool>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct Ran {
+    ran: String,
+}
+
+#[derive(Serialize, ToSchema)]
+struct TokenInfo {
+    name: String,
+    role: Role,
+    devices: Vec<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct CreatedToken {
+    name: String,
+    token: String,
+    role: Role,
+    devices: Vec<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct Revoked {
+    revoked: String,
+}
+
+#[derive(Serialize, ToSchema)]
+struct ApiError {
+    error: String,
+}
+
+// loads swagger ui from a cdn and points it at our spec
+const DOCS_PAGE: &str = r##"<!doctype html>
+<html>
+<head>
+  <title>Roku Remote API</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+  <script>SwaggerUIBundle({ url: "/openapi.json", dom_id: "#swagger-ui" });</script>
+</body>
+</html>"##;
+
+// local control server so other programs (and dashboards) can drive the remote's devices
+// with the grpc feature the same state is also served over grpc on grpc_addr
+pub fn serve(addr: &str, grpc_addr: Option<&str>) -> io::Result<()> {
+    let (store, config) = ConfigStore::open();
+    roku_remote::http::configure(&config.requests);
+    let mut state = ServerState {
+        store,
+        config,
+        devices: Vec::new(),
+        cache: StateCache::default(),
+    };
+    state.add_devices(find_devices(&state.config));
+
+    let server = Server::http(addr).map_err(io::Error::other)?;
+    println!(
+        "Serving {} device(s) on http://{} (API docs at /docs)",
+        state.devices.len(),
+        addr
+    );
+    if state.config.api_tokens.is_empty() {
+        println!("No API tokens configured, every client gets full admin access");
+    }
+
+    if !state.config.schedules.is_empty() {
+        println!("Running {} schedule(s)", state.config.schedules.len());
+    }
+    if state.config.auto_power_off.enabled {
+        println!(
+            "Powering off TVs left idle for {} hour(s)",
+            state.config.auto_power_off.idle_hours
+        );
+    }
+    let mqtt = state.config.mqtt.clone();
+    let state = Arc::new(Mutex::new(state));
+    state_cache::spawn(Arc::clone(&state));
+    if mqtt.enabled() {
+        crate::mqtt::spawn(mqtt, Arc::clone(&state));
+    }
+    crate::schedule::spawn(Arc::clone(&state));
+    crate::idle_off::spawn(Arc::clone(&state));
+    #[cfg(feature = "grpc")]
+    {
+        let grpc_addr = grpc_addr.unwrap_or(DEFAULT_GRPC_ADDR);
+        crate::grpc::spawn(grpc_addr, Arc::clone(&state))?;
+        println!("Serving gRPC on {}", grpc_addr);
+    }
+    #[cfg(not(feature = "grpc"))]
+    if grpc_addr.is_some() {
+        eprintln!("Built without the grpc feature, ignoring --grpc");
+    }
+
+    // each request gets its own thread, so a slow device or a long macro only holds up
+    // the client that asked for it
+    for request in server.incoming_requests() {
+        if crate::sse::wants_stream(&request) {
+            crate::sse::spawn(request, Arc::clone(&state));
+            continue;
+        }
+        let state = Arc::clone(&state);
+        thread::spawn(move || {
+            let response = handle(&request, &state);
+            request.respond(response).ok();
+        });
+    }
+    Ok(())
+}
+
+// handlers take the lock only to read or change the state, never across a device request
+fn handle(request: &Request, state: &Mutex<ServerState>) -> JsonResponse {
+    let (path, query) = request.url().split_once('?').unwrap_or((request.url(), ""));
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+    // the description of the api is public, everything else needs a token
+    match (request.method(), segments.as_slice()) {
+        (Method::Get, ["openapi.json"]) => {
+            let spec = ApiDoc::openapi().to_json().unwrap_or_default();
+            return with_type(Response::from_string(spec), "application/json");
+        }
+        (Method::Get, ["docs"]) => {
+            return with_type(Response::from_string(DOCS_PAGE), "text/html");
+        }
+        _ => {}
+    }
+
+    let (token, devices) = {
+        let state = state.lock().unwrap();
+        (authorize(request, &state.config), state.devices.clone())
+    };
+    let Some(token) = token else {
+        return error(401, "missing or unknown API token");
+    };
+    let visible = |id: &str| devices.iter().any(|d| d == id) && token.allows(id);
+
+    match (request.method(), segments.as_slice()) {
+        (Method::Get, ["devices"]) => list_devices(&devices, &token),
+        (Method::Post, ["discover"]) => discover(state, &token),
+        (Method::Get, ["state"]) => all_states(state, &token),
+        (Method::Get, ["events"]) => events(state, &token, query),
+        (Method::Get, ["devices", id, "state"]) if visible(id) => device_state(state, id),
+        (Method::Get, ["devices", id, "apps"]) if visible(id) => list_apps(id),
+        (Method::Post, ["devices", id, "keypress", key]) if visible(id) => {
+            keypress(&token, id, key)
+        }
+        (Method::Post, ["devices", id, "launch", app_id]) if visible(id) => {
+            launch(&token, id, app_id, query)
+        }
+        (Method::Post, ["devices", id, "macros", name]) if visible(id) => {
+            run_macro(state, &token, id, name)
+        }
+        (_, ["devices", _, ..]) => error(404, "unknown device"),
+        (_, ["tokens", ..]) if !token.role.is_admin() => {
+            error(403, "only admin tokens can manage tokens")
+        }
+        (Method::Get, ["tokens"]) => list_tokens(&state.lock().unwrap()),
+        (Method::Post, ["tokens", name]) => create_token(&mut state.lock().unwrap(), name, query),
+        (Method::Delete, ["tokens", name]) => revoke_token(&mut state.lock().unwrap(), name),
+        _ => error(404, "no such endpoint"),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/devices",
+    responses((status = 200, description = "Device addresses this token can see", body = [String])),
+    security(("token" = []))
+)]
+fn list_devices(devices: &[String], token: &ApiToken) -> JsonResponse {
+    let visible: Vec<&String> = devices.iter().filter(|d| token.allows(d)).collect();
+    respond(200, &visible)
+}
+
+#[utoipa::path(
+    post,
+    path = "/discover",
+    responses(
+        (status = 200, description = "Devices after running SSDP discovery again", body = [String]),
+        (status = 403, description = "Viewer token", body = ApiError)
+    ),
+    security(("token" = []))
+)]
+fn discover(state: &Mutex<ServerState>, token: &ApiToken) -> JsonResponse {
+    if !token.role.can_control() {
+        return error(403, NOT_CONTROL);
+    }
+    let config = state.lock().unwrap().config.clone();
+    let found = find_devices(&config);
+    let mut state = state.lock().unwrap();
+    state.add_devices(found);
+    respond(200, &state.visible_devices(token))
+}
+
+#[utoipa::path(
+    get,
+    path = "/state",
+    responses((status = 200, description = "Cached state of every device this token can see", body = [DeviceState])),
+    security(("token" = []))
+)]
+fn all_states(state: &Mutex<ServerState>, token: &ApiToken) -> JsonResponse {
+    let devices = state.lock().unwrap().visible_devices(token);
+    let states: Vec<DeviceState> = devices
+        .iter()
+        .map(|device| cached_state(state, device))
+        .collect();
+    respond(200, &states)
+}
+
+#[utoipa::path(
+    get,
+    path = "/devices/{id}/state",
+    params(("id" = String, Path, description = "Device address, e.g. 192.168.1.40:8060")),
+    responses(
+        (status = 200, description = "Cached power mode, foreground channel and playback", body = DeviceState),
+        (status = 404, description = "Unknown or out-of-scope device", body = ApiError)
+    ),
+    security(("token" = []))
+)]
+fn device_state(state: &Mutex<ServerState>, ip: &str) -> JsonResponse {
+    respond(200, &cached_state(state, ip))
+}
+
+#[utoipa::path(
+    get,
+    path = "/events",
+    params(("since" = Option<u64>, Query, description = "Only events after this seq")),
+    responses((
+        status = 200,
+        description = "Recent state changes, oldest first. With Accept: text/event-stream this is a \
+            server-sent event stream instead, one DeviceEvent per message with seq as the event id",
+        body = [DeviceEvent],
+        content_type = ["application/json", "text/event-stream"]
+    )),
+    security(("token" = []))
+)]
+fn events(state: &Mutex<ServerState>, token: &ApiToken, query: &str) -> JsonResponse {
+    let since = form_urlencoded::parse(query.as_bytes())
+        .find(|(key, _)| key == "since")
+        .and_then(|(_, value)| value.parse().ok())
+        .unwrap_or(0);
+    let state = state.lock().unwrap();
+    let events: Vec<&DeviceEvent> = state
+        .cache
+        .events_since(since)
+        .filter(|event| token.allows(&event.device))
+        .collect();
+    respond(200, &events)
+}
+
+#[utoipa::path(
+    get,
+    path = "/devices/{id}/apps",
+    params(("id" = String, Path, description = "Device address, e.g. 192.168.1.40:8060")),
+    responses(
+        (status = 200, description = "Installed channels and inputs, with the ids to launch them by", body = [AppEntry]),
+        (status = 502, description = "The device couldn't be reached or wouldn't list its channels", body = ApiError),
+        (status = 404, description = "Unknown or out-of-scope device", body = ApiError)
+    ),
+    security(("token" = []))
+)]
+fn list_apps(ip: &str) -> JsonResponse {
+    match ecp::get_apps(ip) {
+        Ok(apps) => respond(200, &apps),
+        Err(err) => error(502, &err.to_string()),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/devices/{id}/keypress/{key}",
+    params(
+        ("id" = String, Path, description = "Device address, e.g. 192.168.1.40:8060"),
+        ("key" = String, Path, description = "ECP key name, e.g. Home or VolumeUp")
+    ),
+    responses(
+        (status = 200, description = "Keypress sent", body = Sent),
+        (status = 400, description = "Not a key ECP knows", body = ApiError),
+        (status = 502, description = "The device couldn't be reached or turned the keypress down", body = ApiError),
+        (status = 403, description = "Viewer token", body = ApiError),
+        (status = 404, description = "Unknown or out-of-scope device", body = ApiError)
+    ),
+    security(("token" = []))
+)]
+fn keypress(token: &ApiToken, ip: &str, key: &str) -> JsonResponse {
+    if !token.role.can_control() {
+        return error(403, NOT_CONTROL);
+    }
+    let key = match key.parse::<RokuKey>() {
+        Ok(key) => key,
+        Err(err) => return error(400, &err.to_string()),
+    };
+    if let Err(err) = ecp::press(ip, key) {
+        return error(502, &err.to_string());
+    }
+    respond(
+        200,
+        &Sent {
+            sent: key.to_string(),
+        },
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/devices/{id}/launch/{app_id}",
+    params(
+        ("id" = String, Path, description = "Device address, e.g. 192.168.1.40:8060"),
+        ("app_id" = String, Path, description = "Channel id from the device's app list"),
+        ("wait" = Option<u64>, Query, description = "Seconds to wait for the channel to be in front, at most 30")
+    ),
+    responses(
+        (status = 200, description = "Launch requested, or channel in front when waiting", body = Launched),
+        (status = 504, description = "Channel wasn't in front before the wait ran out", body = Launched),
+        (status = 502, description = "The device couldn't be reached or turned the launch down", body = ApiError),
+        (status = 403, description = "Viewer token", body = ApiError),
+        (status = 404, description = "Unknown or out-of-scope device", body = ApiError)
+    ),
+    security(("token" = []))
+)]
+fn launch(token: &ApiToken, ip: &str, app_id: &str, query: &str) -> JsonResponse {
+    if !token.role.can_control() {
+        return error(403, NOT_CONTROL);
+    }
+    let wait = form_urlencoded::parse(query.as_bytes())
+        .find(|(key, _)| key == "wait")
+        .and_then(|(_, value)| value.parse::<u64>().ok());
+    let Some(wait) = wait else {
+        if let Err(err) = ecp::launch_app(ip, app_id) {
+            return error(502, &err.to_string());
+        }
+        return respond(
+            200,
+            &Launched {
+                launched: app_id.to_string(),
+                foreground: None,
+            },
+        );
+    };
+    let wait = Duration::from_secs(wait.min(MAX_LAUNCH_WAIT));
+    let foreground = match ecp::launch_and_wait(ip, app_id, wait) {
+        Ok(foreground) => foreground,
+        Err(err) => return error(502, &err.to_string()),
+    };
+    respond(
+        if foreground { 200 } else { 504 },
+        &Launched {
+            launched: app_id.to_string(),
+            foreground: Some(foreground),
+        },
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/devices/{id}/macros/{name}",
+    params(
+        ("id" = String, Path, description = "Device address, e.g. 192.168.1.40:8060"),
+        ("name" = String, Path, description = "A macro saved in the config")
+    ),
+    responses(
+        (status = 200, description = "Every step of the macro went through", body = Ran),
+        (status = 502, description = "A step failed or the device couldn't be reached", body = ApiError),
+        (status = 403, description = "Viewer token", body = ApiError),
+        (status = 404, description = "Unknown or out-of-scope device, or unknown macro", body = ApiError)
+    ),
+    security(("token" = []))
+)]
+fn run_macro(state: &Mutex<ServerState>, token: &ApiToken, ip: &str, name: &str) -> JsonResponse {
+    if !token.role.can_control() {
+        return error(403, NOT_CONTROL);
+    }
+    let (script, verify) = {
+        let state = state.lock().unwrap();
+        let script = state.config.macros.get(name).cloned();
+        (script, state.config.macro_settings.verify)
+    };
+    let Some(script) = script else {
+        return error(404, "unknown macro");
+    };
+    let steps = match macros::parse(&script) {
+        Ok(steps) => steps,
+        Err(err) => return error(500, &format!("macro {}: {}", name, err)),
+    };
+    if let Err(err) = macros::run(ip, &steps, verify, |_| {}) {
+        return error(502, &err);
+    }
+    respond(
+        200,
+        &Ran {
+            ran: name.to_string(),
+        },
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/tokens",
+    responses(
+        (status = 200, description = "Configured tokens, without their secrets", body = [TokenInfo]),
+        (status = 403, description = "Not an admin token", body = ApiError)
+    ),
+    security(("token" = []))
+)]
+fn list_tokens(state: &ServerState) -> JsonResponse {
+    let tokens: Vec<TokenInfo> = state
+        .config
+        .api_tokens
+        .iter()
+        .map(|(name, t)| TokenInfo {
+            name: name.clone(),
+            role: t.role,
+            devices: t.devices.clone(),
+        })
+        .collect();
+    respond(200, &tokens)
+}
+
+#[utoipa::path(
+    post,
+    path = "/tokens/{name}",
+    params(
+        ("name" = String, Path, description = "Who the token is for"),
+        ("role" = Option<Role>, Query, description = "Defaults to control"),
+        ("devices" = Option<String>, Query, description = "Comma-separated addresses to limit the token to")
+    ),
+    responses(
+        (status = 200, description = "The new token", body = CreatedToken),
+        (status = 400, description = "Unknown role", body = ApiError),
+        (status = 403, description = "Not an admin token", body = ApiError)
+    ),
+    security(("token" = []))
+)]
+fn create_token(state: &mut ServerState, name: &str, query: &str) -> JsonResponse {
+    let mut role = Role::default();
+    let mut devices = Vec::new();
+    for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+        match key.as_ref() {
+            "role" => match Role::from_label(&value) {
+                Some(r) => role = r,
+                None => return error(400, "role must be viewer, control or admin"),
+            },
+            "devices" => devices = value.split(',').map(|d| d.trim().to_string()).collect(),
+            _ => {}
+        }
+    }
+
+    let token = ApiToken::generate(role, devices);
+    let created = CreatedToken {
+        name: name.to_string(),
+        token: token.token.clone(),
+        role,
+        devices: token.devices.clone(),
+    };
+    state.config.api_tokens.insert(name.to_string(), token);
+    save(state, &created)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/tokens/{name}",
+    params(("name" = String, Path, description = "Token to revoke")),
+    responses(
+        (status = 200, description = "Token revoked", body = Revoked),
+        (status = 403, description = "Not an admin token", body = ApiError),
+        (status = 404, description = "Unknown token", body = ApiError)
+    ),
+    security(("token" = []))
+)]
+fn revoke_token(state: &mut ServerState, name: &str) -> JsonResponse {
+    if state.config.api_tokens.remove(name).is_none() {
+        return error(404, "unknown token");
+    }
+    save(
+        state,
+        &Revoked {
+            revoked: name.to_string(),
+        },
+    )
+}
+
+fn save(state: &mut ServerState, body: &impl Serialize) -> JsonResponse {
+    match state.store.save(&mut state.config) {
+        Ok(_) => respond(200, body),
+        Err(err) => error(500, &format!("failed to save config: {}", err)),
+    }
+}
+
+// a bearer token or ?token= query parameter
+pub fn authorize(request: &Request, config: &Config) -> Option<ApiToken> {
+    let header = request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Authorization"))
+        .and_then(|h| h.value.as_str().strip_prefix("Bearer "))
+        .map(|t| t.trim().to_string());
+    let query = request.url().split_once('?').and_then(|(_, query)| {
+        form_urlencoded::parse(query.as_bytes())
+            .find(|(key, _)| key == "token")
+            .map(|(_, value)| value.into_owned())
+    });
+    token_for(config, header.or(query).as_deref())
+}
+
+// looks up a presented token; with no tokens configured everyone is trusted
+pub fn token_for(config: &Config, presented: Option<&str>) -> Option<ApiToken> {
+    if config.api_tokens.is_empty() {
+        return Some(ApiToken {
+            token: String::new(),
+            role: Role::Admin,
+            devices: Vec::new(),
+        });
+    }
+    let presented = presented?;
+    config
+        .api_tokens
+        .values()
+        .find(|t| t.token == presented)
+        .cloned()
+}
+
+fn respond(status: u16, body: &impl Serialize) -> JsonResponse {
+    let body = serde_json::to_string(body).unwrap_or_default();
+    with_type(Response::from_string(body), "application/json").with_status_code(status)
+}
+
+fn error(status: u16, message: &str) -> JsonResponse {
+    respond(
+        status,
+        &ApiError {
+            error: message.to_string(),
+        },
+    )
+}
+
+fn with_type(response: JsonResponse, content_type: &str) -> JsonResponse {
+    response.with_header(Header::from_bytes("Content-Type", content_type).unwrap())
+}
@@ -0,0 +1,56 @@
+// where the GUI was left: the devices it knows about, what was selected and the window. local
+// to this machine, unlike the config, since another machine sits on another network
+
+use crate::config::{config_path, write_json};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    // every device discovered or added by hand, listed at startup without discovering again
+    pub devices: Vec<String>,
+    pub last_device: Option<String>,
+    pub last_app: Option<String>,
+    pub window: Option<Window>,
+    pub osk_mode: bool,
+    pub osk_layout: usize,
+    pub group_filter: Option<String>,
+    pub broadcast_to_group: bool,
+    // each device's MAC from device-info, to wake it by once it has stopped answering
+    pub macs: BTreeMap<String, String>,
+    // each device's serial number, which stays put when DHCP hands the device a new address
+    pub serials: BTreeMap<String, String>,
+    // the devices kept open in tabs, in order
+    pub tabs: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Window {
+    pub size: [f32; 2],
+    // some window systems, Wayland among them, don't say where the window is
+    #[serde(default)]
+    pub position: Option<[f32; 2]>,
+}
+
+fn session_path() -> Option<PathBuf> {
+    config_path().map(|path| path.with_file_name("session.json"))
+}
+
+impl Session {
+    pub fn load() -> Session {
+        session_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = session_path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+        write_json(&path, self)
+    }
+}
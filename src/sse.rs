@@ -0,0 +1,97 @@
+// GET /events as a server-sent event stream, for browsers and other simple push clients.
+// each stream gets its own thread writing straight to the socket, so it never holds up the
+// request loop, and reads events from the daemon's state cache
+
+use crate::server::{authorize, ServerState};
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tiny_http::{Method, Request, Response};
+use url::form_urlencoded;
+
+// how often a stream looks for new events
+const POLL: Duration = Duration::from_millis(500);
+// a comment line this often keeps proxies from closing an idle stream
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+// EventSource asks for text/event-stream; anything else gets the plain json list
+pub fn wants_stream(request: &Request) -> bool {
+    let path = request.url().split('?').next().unwrap_or_default();
+    *request.method() == Method::Get
+        && path.trim_end_matches('/') == "/events"
+        && request
+            .headers()
+            .iter()
+            .any(|h| h.field.equiv("Accept") && h.value.as_str().contains("text/event-stream"))
+}
+
+pub fn spawn(request: Request, state: Arc<Mutex<ServerState>>) {
+    let Some(token) = authorize(&request, &state.lock().unwrap().config) else {
+        let body = r#"{"error":"missing or unknown API token"}"#;
+        request
+            .respond(Response::from_string(body).with_status_code(401))
+            .ok();
+        return;
+    };
+    // a reconnecting EventSource says where it left off; ?since= does the same for others
+    let since = request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Last-Event-ID"))
+        .map(|h| h.value.to_string())
+        .or_else(|| {
+            let query = request.url().split_once('?')?.1;
+            form_urlencoded::parse(query.as_bytes())
+                .find(|(key, _)| key == "since")
+                .map(|(_, value)| value.into_owned())
+        })
+        .and_then(|seq| seq.trim().parse().ok())
+        .unwrap_or(0);
+
+    thread::spawn(move || {
+        let mut out = request.into_writer();
+        // the stream ends when either side hangs up, which is all a write error means here
+        stream(&mut out, &state, since, |device| token.allows(device)).ok();
+    });
+}
+
+fn stream(
+    out: &mut impl Write,
+    state: &Mutex<ServerState>,
+    mut since: u64,
+    allows: impl Fn(&str) -> bool,
+) -> io::Result<()> {
+    out.write_all(
+        b"HTTP/1.1 200 OK\r\n\
+        Content-Type: text/event-stream\r\n\
+        Cache-Control: no-cache\r\n\
+        Connection: close\r\n\r\n",
+    )?;
+    out.flush()?;
+
+    let mut last_write = Instant::now();
+    loop {
+        let mut text = String::new();
+        {
+            let state = state.lock().unwrap();
+            for event in state.cache.events_since(since) {
+                since = event.seq;
+                if !allows(&event.device) {
+                    continue;
+                }
+                let data = serde_json::to_string(event).unwrap_or_default();
+                text.push_str(&format!("id: {}\ndata: {}\n\n", event.seq, data));
+            }
+        }
+        if text.is_empty() && last_write.elapsed() >= KEEP_ALIVE {
+            text.push_str(": keep-alive\n\n");
+        }
+        if !text.is_empty() {
+            out.write_all(text.as_bytes())?;
+            out.flush()?;
+            last_write = Instant::now();
+        }
+        thread::sleep(POLL);
+    }
+}
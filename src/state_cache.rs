@@ -0,0 +1,23 @@
+// the daemon polls every device itself and keeps the latest state, so api clients read a
+// shared snapshot instead of each querying the Rokus, and changes become events in one place
+
+use crate::server::ServerState;
+use roku_remote::events;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+// how often every device is polled
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+// keeps the cache fresh; the devices are queried without holding the lock
+pub fn spawn(state: Arc<Mutex<ServerState>>) {
+    thread::spawn(move || loop {
+        let devices = state.lock().unwrap().devices.clone();
+        for device in devices {
+            let fresh = events::poll(&device);
+            state.lock().unwrap().cache.update(fresh);
+        }
+        thread::sleep(POLL_INTERVAL);
+    });
+}
@@ -0,0 +1,72 @@
+// devices kept open side by side, so going from the living room's Roku to the bedroom's and
+// back neither fetches the channels again nor loses what either was last doing. the device in
+// front keeps its state in the app as always; the others wait here, still watched
+
+use crate::ecp::AppEntry;
+use crate::now_playing::NowPlayingWatcher;
+use std::collections::HashMap;
+
+#[derive(Default)]
+pub struct Tab {
+    pub apps: Vec<AppEntry>,
+    pub selected_app: Option<String>,
+    pub last_msg: String,
+    pub now_playing: NowPlayingWatcher,
+}
+
+impl Tab {
+    // a tab for a device that has yet to be in front, e.g. one reopened at startup; its
+    // channels still need fetching
+    pub fn watching(device: &str) -> Tab {
+        let mut tab = Tab {
+            last_msg: "Fetching apps…".into(),
+            ..Default::default()
+        };
+        tab.now_playing.watch(Some(device));
+        tab
+    }
+}
+
+#[derive(Default)]
+pub struct Tabs {
+    // the devices with a tab, in the order they were opened
+    pub open: Vec<String>,
+    // every device with a tab but the one in front
+    pub waiting: HashMap<String, Tab>,
+}
+
+impl Tabs {
+    pub fn is_open(&self, device: &str) -> bool {
+        self.open.iter().any(|open| open == device)
+    }
+
+    pub fn open(&mut self, device: &str) {
+        if !self.is_open(device) {
+            self.open.push(device.to_string());
+        }
+    }
+
+    pub fn close(&mut self, device: &str) {
+        self.open.retain(|open| open != device);
+        self.waiting.remove(device);
+    }
+
+    // a device that turned up at a new address keeps its tab
+    pub fn rename(&mut self, old: &str, new: &str) {
+        for device in self.open.iter_mut().filter(|device| *device == old) {
+            *device = new.to_string();
+        }
+        self.open.dedup();
+        if let Some(mut tab) = self.waiting.remove(old) {
+            tab.now_playing.watch(Some(new));
+            self.waiting.entry(new.to_string()).or_insert(tab);
+        }
+    }
+
+    // takes what the waiting tabs' devices are showing now
+    pub fn poll(&mut self) {
+        for tab in self.waiting.values_mut() {
+            tab.now_playing.poll();
+        }
+    }
+}
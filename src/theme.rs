@@ -0,0 +1,43 @@
+use eframe::egui::{self, Visuals};
+use serde::{Deserialize, Serialize};
+
+// dark or light, or whichever the desktop uses. the window system only says on some
+// platforms; where it doesn't, following it means egui's dark default
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    #[default]
+    System,
+    Dark,
+    Light,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::System, Theme::Dark, Theme::Light];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Theme::System => "Follow system",
+            Theme::Dark => "Dark",
+            Theme::Light => "Light",
+        }
+    }
+
+    // sets the look when it isn't already the one asked for; eframe switches the visuals
+    // itself when the desktop's theme changes, which is put back here for a fixed choice
+    pub fn apply(self, ctx: &egui::Context, system: Option<eframe::Theme>) {
+        let dark = match self {
+            Theme::Dark => true,
+            Theme::Light => false,
+            Theme::System => system != Some(eframe::Theme::Light),
+        };
+        if ctx.style().visuals.dark_mode != dark {
+            let visuals = if dark {
+                Visuals::dark()
+            } else {
+                Visuals::light()
+            };
+            ctx.set_visuals(visuals);
+        }
+    }
+}
@@ -0,0 +1,160 @@
+// one-off timers set from the GUI: a key or a macro at a time of day or after a countdown, such
+// as a sleep timer turning the TV off in 45 minutes. a background thread fires them, so they go
+// off with the window minimized; they end with the app, unlike the daemon's schedules
+
+use crate::macros::{self, Step};
+use crate::schedule::Action;
+use chrono::{DateTime, Duration as Days, Local, NaiveTime, TimeZone};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+// how often the thread looks for timers that are due; the countdowns are shown to the second
+const TICK: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone)]
+pub struct Timer {
+    pub id: u64,
+    pub action: Action,
+    pub devices: Vec<String>,
+    pub at: DateTime<Local>,
+    steps: Vec<Step>,
+    verify: bool,
+}
+
+impl Timer {
+    pub fn label(&self) -> String {
+        self.action.label()
+    }
+
+    // "44:59" to go, or "1:02:03" from an hour up
+    pub fn remaining(&self) -> String {
+        let left = (self.at - Local::now()).to_std().unwrap_or_default();
+        crate::now_playing::clock(left + Duration::from_millis(999))
+    }
+}
+
+pub fn minutes_from_now(minutes: u32) -> DateTime<Local> {
+    Local::now() + Days::minutes(minutes.into())
+}
+
+// a time of day, "22:30", as its next occurrence: today, or tomorrow once it has gone by
+pub fn next_at(at: &str) -> Option<DateTime<Local>> {
+    let time = NaiveTime::parse_from_str(at.trim(), "%H:%M").ok()?;
+    let now = Local::now();
+    (0..2).find_map(|ahead| {
+        let date = now.date_naive() + Days::days(ahead);
+        Local
+            .from_local_datetime(&date.and_time(time))
+            .earliest()
+            .filter(|at| *at > now)
+    })
+}
+
+// a timer that went off, with how the run went on each of its devices
+pub type Fired = (Timer, Vec<(String, Result<(), String>)>);
+
+pub struct Timers {
+    pending: Arc<Mutex<Vec<Timer>>>,
+    // timers that have gone off and are still running, e.g. a macro
+    running: Arc<AtomicUsize>,
+    fired: Receiver<Fired>,
+    next_id: u64,
+}
+
+impl Default for Timers {
+    fn default() -> Self {
+        let pending: Arc<Mutex<Vec<Timer>>> = Arc::default();
+        let running: Arc<AtomicUsize> = Arc::default();
+        let (tx, fired) = mpsc::channel();
+        spawn(Arc::clone(&pending), Arc::clone(&running), tx);
+        Self {
+            pending,
+            running,
+            fired,
+            next_id: 0,
+        }
+    }
+}
+
+impl Timers {
+    // steps are worked out now, so a macro edited in the meantime still runs as it was set
+    pub fn add(
+        &mut self,
+        action: Action,
+        steps: Vec<Step>,
+        verify: bool,
+        devices: Vec<String>,
+        at: DateTime<Local>,
+    ) {
+        self.next_id += 1;
+        let timer = Timer {
+            id: self.next_id,
+            action,
+            devices,
+            at,
+            steps,
+            verify,
+        };
+        let mut pending = self.pending.lock().unwrap();
+        pending.push(timer);
+        pending.sort_by_key(|timer| timer.at);
+    }
+
+    pub fn cancel(&self, id: u64) {
+        self.pending.lock().unwrap().retain(|timer| timer.id != id);
+    }
+
+    // soonest first
+    pub fn pending(&self) -> Vec<Timer> {
+        self.pending.lock().unwrap().clone()
+    }
+
+    // since the last call
+    pub fn fired(&self) -> Vec<Fired> {
+        self.fired.try_iter().collect()
+    }
+
+    // something is counting down or still running, and the window should keep up with it
+    pub fn busy(&self) -> bool {
+        self.running.load(Ordering::Relaxed) > 0 || !self.pending.lock().unwrap().is_empty()
+    }
+}
+
+fn spawn(pending: Arc<Mutex<Vec<Timer>>>, running: Arc<AtomicUsize>, tx: Sender<Fired>) {
+    thread::spawn(move || loop {
+        // the timers were dropped along with the app
+        if Arc::strong_count(&pending) == 1 {
+            return;
+        }
+        let now = Local::now();
+        let due: Vec<Timer> = {
+            let mut pending = pending.lock().unwrap();
+            let (due, waiting) = pending.drain(..).partition(|timer| timer.at <= now);
+            *pending = waiting;
+            due
+        };
+        for timer in due {
+            // on a thread of its own, so a long macro doesn't hold up the next timer
+            running.fetch_add(1, Ordering::Relaxed);
+            let (running, tx) = (Arc::clone(&running), tx.clone());
+            thread::spawn(move || {
+                let results = timer
+                    .devices
+                    .iter()
+                    .map(|ip| {
+                        (
+                            ip.clone(),
+                            macros::run(ip, &timer.steps, timer.verify, |_| {}),
+                        )
+                    })
+                    .collect();
+                running.fetch_sub(1, Ordering::Relaxed);
+                let _ = tx.send((timer, results));
+            });
+        }
+        thread::sleep(TICK);
+    });
+}
@@ -0,0 +1,209 @@
+// finding a film or show by name with TMDB, then playing it through the ECP search provider
+// flow on whichever installed channel carries it. optional: nothing happens without an api key
+
+use roku_remote::ecp::AppEntry;
+use roku_remote::http;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use url::Url;
+
+const API: &str = "https://api.themoviedb.org/3";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TmdbSettings {
+    // a v3 api key from themoviedb.org; empty turns the search off
+    pub api_key: String,
+    // whose streaming catalogue to check, as an ISO 3166 country code
+    pub region: String,
+}
+
+impl Default for TmdbSettings {
+    fn default() -> Self {
+        Self {
+            api_key: String::new(),
+            region: "US".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Movie,
+    Tv,
+}
+
+impl Kind {
+    // the type ECP search wants
+    pub fn ecp_type(self) -> &'static str {
+        match self {
+            Kind::Movie => "movie",
+            Kind::Tv => "tv-show",
+        }
+    }
+
+    fn path(self) -> &'static str {
+        match self {
+            Kind::Movie => "movie",
+            Kind::Tv => "tv",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Title {
+    pub id: u64,
+    pub kind: Kind,
+    pub name: String,
+    pub year: Option<String>,
+}
+
+impl Title {
+    pub fn label(&self) -> String {
+        match &self.year {
+            Some(year) => format!("{} ({})", self.name, year),
+            None => self.name.clone(),
+        }
+    }
+}
+
+fn get_json(path: &str, settings: &TmdbSettings, params: &[(&str, &str)]) -> Result<Value, String> {
+    let mut url = Url::parse(&format!("{}{}", API, path)).map_err(|err| err.to_string())?;
+    url.query_pairs_mut()
+        .append_pair("api_key", settings.api_key.trim())
+        .extend_pairs(params);
+    let resp = http::get(url.as_str()).map_err(|err| err.to_string())?;
+    if !resp.is_success() {
+        return Err(format!("TMDB answered {}", resp.status));
+    }
+    serde_json::from_slice(&resp.body).map_err(|err| err.to_string())
+}
+
+// films and shows matching the query, best match first; people are left out
+pub fn search(settings: &TmdbSettings, query: &str) -> Result<Vec<Title>, String> {
+    let found = get_json("/search/multi", settings, &[("query", query)])?;
+    let results = found["results"].as_array().cloned().unwrap_or_default();
+    Ok(results
+        .iter()
+        .filter_map(|result| {
+            let kind = match result["media_type"].as_str()? {
+                "movie" => Kind::Movie,
+                "tv" => Kind::Tv,
+                _ => return None,
+            };
+            let (name, date) = match kind {
+                Kind::Movie => (&result["title"], &result["release_date"]),
+                Kind::Tv => (&result["name"], &result["first_air_date"]),
+            };
+            Some(Title {
+                id: result["id"].as_u64()?,
+                kind,
+                name: name.as_str()?.to_string(),
+                year: date
+                    .as_str()
+                    .and_then(|date| date.get(..4))
+                    .map(str::to_string),
+            })
+        })
+        .collect())
+}
+
+// names of the services that stream, rent or sell the title in the configured region
+pub fn providers(settings: &TmdbSettings, title: &Title) -> Result<Vec<String>, String> {
+    let path = format!("/{}/{}/watch/providers", title.kind.path(), title.id);
+    let found = get_json(&path, settings, &[])?;
+    let region = &found["results"][settings.region.trim().to_uppercase()];
+    let mut names: Vec<String> = Vec::new();
+    for offer in ["flatrate", "free", "ads", "rent", "buy"] {
+        for provider in region[offer].as_array().into_iter().flatten() {
+            if let Some(name) = provider["provider_name"].as_str() {
+                if !names.iter().any(|known| known == name) {
+                    names.push(name.to_string());
+                }
+            }
+        }
+    }
+    Ok(names)
+}
+
+// "Disney Plus" and "Disney+" both become "disney", near enough to match channel names
+fn normalize(name: &str) -> String {
+    name.to_lowercase()
+        .replace("plus", "")
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .collect()
+}
+
+// the installed channels that one of the providers looks like
+pub fn matching_channels(providers: &[String], apps: &[AppEntry]) -> Vec<AppEntry> {
+    let mut channels: Vec<AppEntry> = Vec::new();
+    for provider in providers.iter().map(|p| normalize(p)) {
+        for app in apps {
+            let channel = normalize(&app.name);
+            if channel.is_empty() || provider.is_empty() {
+                continue;
+            }
+            let matches = provider.contains(&channel) || channel.contains(&provider);
+            if matches && !channels.contains(app) {
+                channels.push(app.clone());
+            }
+        }
+    }
+    channels
+}
+
+pub enum Reply {
+    Results(Result<Vec<Title>, String>),
+    Channels(Title, Result<Vec<AppEntry>, String>),
+}
+
+// TMDB lookups off the GUI thread
+#[derive(Default)]
+pub struct ContentSearch {
+    rx: Option<Receiver<Reply>>,
+}
+
+impl ContentSearch {
+    pub fn busy(&self) -> bool {
+        self.rx.is_some()
+    }
+
+    pub fn search(&mut self, settings: &TmdbSettings, query: &str) {
+        let (settings, query) = (settings.clone(), query.to_string());
+        self.spawn(move || Reply::Results(search(&settings, &query)));
+    }
+
+    // looks up where the title streams and which of those channels are installed
+    pub fn find_channels(&mut self, settings: &TmdbSettings, title: &Title, apps: &[AppEntry]) {
+        let (settings, title, apps) = (settings.clone(), title.clone(), apps.to_vec());
+        self.spawn(move || {
+            let channels = providers(&settings, &title).map(|p| matching_channels(&p, &apps));
+            Reply::Channels(title, channels)
+        });
+    }
+
+    fn spawn(&mut self, job: impl FnOnce() -> Reply + Send + 'static) {
+        let (tx, rx) = mpsc::channel();
+        self.rx = Some(rx);
+        thread::spawn(move || {
+            let _ = tx.send(job());
+        });
+    }
+
+    pub fn poll(&mut self) -> Option<Reply> {
+        match self.rx.as_ref()?.try_recv() {
+            Ok(reply) => {
+                self.rx = None;
+                Some(reply)
+            }
+            Err(TryRecvError::Disconnected) => {
+                self.rx = None;
+                None
+            }
+            Err(TryRecvError::Empty) => None,
+        }
+    }
+}
@@ -0,0 +1,295 @@
+// how ECP requests reach a device. plain http is what every Roku takes, until its "control by
+// mobile apps" setting is limited: then commands and most queries are turned down with a 403,
+// and only the authenticated ECP-2 WebSocket on the same port still works. a device that
+// turns a request down that way is switched to ECP-2 for the rest of the run, when the
+// library is built with the ecp2 feature
+
+use crate::error::RokuError;
+#[cfg(feature = "ecp2")]
+use crate::http::dry_run;
+use crate::http::{self, HttpResponse};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+
+#[cfg(feature = "ecp2")]
+mod ecp2;
+
+#[cfg(feature = "ecp2")]
+pub use ecp2::Ecp2;
+
+/// A way of sending ECP requests. Paths are those of the http API without the leading
+/// slash, e.g. `query/apps` or `keypress/Home`, and the answer is what the http API would
+/// have answered.
+pub trait RokuTransport: Send + Sync {
+    /// A query on an "ip:port" device.
+    fn get(&self, device: &str, path: &str) -> Result<HttpResponse, RokuError>;
+    /// A command, which the device answers with an empty 200.
+    fn post(&self, device: &str, path: &str) -> Result<HttpResponse, RokuError>;
+}
+
+/// Plain ECP over http.
+pub struct Http;
+
+impl RokuTransport for Http {
+    fn get(&self, device: &str, path: &str) -> Result<HttpResponse, RokuError> {
+        http::get(&format!("http://{}/{}", device, path))
+    }
+
+    fn post(&self, device: &str, path: &str) -> Result<HttpResponse, RokuError> {
+        http::post(&format!("http://{}/{}", device, path))
+    }
+}
+
+// the devices that take their requests over ECP-2
+static ECP2_DEVICES: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+/// Sends a device's requests over ECP-2 from now on, or back over http. Devices are
+/// switched over by themselves when they turn plain ECP down, so this is only needed to
+/// skip the first refused request.
+pub fn set_ecp2(device: &str, on: bool) {
+    let mut devices = ECP2_DEVICES.lock().unwrap();
+    if on && cfg!(feature = "ecp2") {
+        devices.insert(device.to_string());
+    } else {
+        devices.remove(device);
+    }
+}
+
+/// Whether a device's requests go over ECP-2.
+pub fn uses_ecp2(device: &str) -> bool {
+    ECP2_DEVICES.lock().unwrap().contains(device)
+}
+
+/// Plain ECP over http, with the switch to ECP-2 for devices in limited mode; what the
+/// free functions in `ecp` go through.
+pub struct Auto;
+
+impl RokuTransport for Auto {
+    fn get(&self, device: &str, path: &str) -> Result<HttpResponse, RokuError> {
+        send(device, path, false)
+    }
+
+    /// In a dry run the command goes to the dry run's log like any other.
+    fn post(&self, device: &str, path: &str) -> Result<HttpResponse, RokuError> {
+        send(device, path, true)
+    }
+}
+
+fn send(device: &str, path: &str, command: bool) -> Result<HttpResponse, RokuError> {
+    let over = |transport: &dyn RokuTransport| {
+        if command {
+            transport.post(device, path)
+        } else {
+            transport.get(device, path)
+        }
+    };
+    #[cfg(feature = "ecp2")]
+    if uses_ecp2(device) && !(command && dry_run::enabled()) {
+        return over(ecp2::shared());
+    }
+    let resp = over(&Http)?;
+    // limited mode's answer to plain ECP; the same request over ECP-2 tells whether that's
+    // what it was
+    #[cfg(feature = "ecp2")]
+    if resp.status == 403 && !dry_run::enabled() {
+        if let Ok(answer) = over(ecp2::shared()) {
+            set_ecp2(device, true);
+            return Ok(answer);
+        }
+    }
+    Ok(resp)
+}
+
+/// A request a `Mock` was given.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    pub device: String,
+    /// A command, as opposed to a query.
+    pub command: bool,
+    pub path: String,
+}
+
+/// A transport that sends nothing, for tests: it keeps every request to be looked at
+/// afterwards and answers from canned replies. Without one, commands get an empty 200 and
+/// queries a 404.
+#[derive(Default)]
+pub struct Mock {
+    requests: Mutex<Vec<Request>>,
+    // path -> status and body
+    replies: Mutex<HashMap<String, (u16, Vec<u8>)>>,
+}
+
+impl Mock {
+    pub fn new() -> Mock {
+        Mock::default()
+    }
+
+    /// Answers the path, e.g. `query/apps`, with a 200 and this body from now on.
+    pub fn reply(&self, path: &str, body: impl Into<Vec<u8>>) -> &Mock {
+        self.reply_with(path, 200, body)
+    }
+
+    /// Answers the path, a query or a command, with this status and body from now on.
+    pub fn reply_with(&self, path: &str, status: u16, body: impl Into<Vec<u8>>) -> &Mock {
+        let mut replies = self.replies.lock().unwrap();
+        replies.insert(path.to_string(), (status, body.into()));
+        self
+    }
+
+    /// Every request so far, oldest first.
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// The paths of the commands so far, e.g. `["keypress/Home", "launch/12"]`.
+    pub fn commands(&self) -> Vec<String> {
+        let requests = self.requests.lock().unwrap();
+        requests
+            .iter()
+            .filter(|request| request.command)
+            .map(|request| request.path.clone())
+            .collect()
+    }
+
+    fn answer(&self, device: &str, path: &str, command: bool) -> HttpResponse {
+        self.requests.lock().unwrap().push(Request {
+            device: device.to_string(),
+            command,
+            path: path.to_string(),
+        });
+        let replies = self.replies.lock().unwrap();
+        let (status, body) = match replies.get(path) {
+            Some(reply) => reply.clone(),
+            None if command => (200, Vec::new()),
+            None => (404, Vec::new()),
+        };
+        HttpResponse {
+            status,
+            content_type: Some("text/xml; charset=utf-8".into()),
+            body,
+        }
+    }
+}
+
+impl RokuTransport for Mock {
+    fn get(&self, device: &str, path: &str) -> Result<HttpResponse, RokuError> {
+        Ok(self.answer(device, path, false))
+    }
+
+    fn post(&self, device: &str, path: &str) -> Result<HttpResponse, RokuError> {
+        Ok(self.answer(device, path, true))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Mock;
+    use crate::ecp::{self, KeyAction};
+    use crate::{Pacer, RokuKey};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    const DEVICE: &str = "192.0.2.7:8060";
+
+    #[test]
+    fn keys_and_text_are_sent_as_written() {
+        let mock = Mock::new();
+        let ecp = ecp::via(&mock);
+        ecp.press(DEVICE, RokuKey::Home).unwrap();
+        ecp.send_key_action(DEVICE, "Fwd", KeyAction::Down).unwrap();
+        ecp.send_key_action(DEVICE, "Fwd", KeyAction::Up).unwrap();
+        ecp.send_key(DEVICE, "a&b").unwrap();
+        assert_eq!(
+            mock.commands(),
+            [
+                "keypress/Home",
+                "keydown/Fwd",
+                "keyup/Fwd",
+                "keypress/Lit_a",
+                "keypress/Lit_%26",
+                "keypress/Lit_b",
+            ]
+        );
+        assert!(mock
+            .requests()
+            .iter()
+            .all(|request| request.device == DEVICE));
+    }
+
+    #[test]
+    fn typing_stops_when_progress_says_so() {
+        let mock = Mock::new();
+        let mut seen = Vec::new();
+        ecp::via(&mock)
+            .send_text(DEVICE, "abcdef", Duration::ZERO, |sent| {
+                seen.push(sent);
+                sent < 2
+            })
+            .unwrap();
+        assert_eq!(seen, [1, 2]);
+        assert_eq!(mock.commands(), ["keypress/Lit_a", "keypress/Lit_b"]);
+    }
+
+    #[test]
+    fn launch_content_encodes_its_query() {
+        let mock = Mock::new();
+        ecp::via(&mock)
+            .launch_content(DEVICE, "12", "81234 567", Some("movie"))
+            .unwrap();
+        assert_eq!(
+            mock.commands(),
+            ["launch/12?contentId=81234+567&mediaType=movie"]
+        );
+    }
+
+    #[test]
+    fn a_refused_command_is_an_error() {
+        let mock = Mock::new();
+        mock.reply_with("launch/999", 404, "");
+        assert_eq!(
+            ecp::via(&mock).launch_app(DEVICE, "999"),
+            Err(crate::RokuError::Status(404))
+        );
+    }
+
+    #[test]
+    fn queries_read_the_canned_replies() {
+        let mock = Mock::new();
+        mock.reply(
+            "query/apps",
+            "<apps><app id=\"12\" type=\"appl\" version=\"1\">Netflix</app>\
+             <app id=\"837\" type=\"appl\" version=\"1\">YouTube</app></apps>",
+        )
+        .reply(
+            "query/active-app",
+            "<active-app><app>Roku</app></active-app>",
+        );
+        let ecp = ecp::via(&mock);
+        let apps: Vec<String> = ecp
+            .get_apps(DEVICE)
+            .unwrap()
+            .into_iter()
+            .map(|app| app.name)
+            .collect();
+        assert_eq!(apps, ["Netflix", "YouTube"]);
+        assert_eq!(ecp.get_active_app(DEVICE).unwrap().id, "");
+        // nothing canned: the device doesn't know the query
+        assert_eq!(ecp.get_power_mode(DEVICE), None);
+        assert!(mock.commands().is_empty());
+    }
+
+    #[test]
+    fn pacer_sends_through_its_transport() {
+        let mock = Arc::new(Mock::new());
+        let pacer = Pacer::with_transport(mock.clone());
+        for key in ["Up", "Select"] {
+            pacer.send(DEVICE, key);
+        }
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while mock.commands().len() < 2 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(mock.commands(), ["keypress/Up", "keypress/Select"]);
+    }
+}
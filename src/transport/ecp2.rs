@@ -0,0 +1,254 @@
+// ECP-2: the http API's requests as JSON over a WebSocket, ws://<device>/ecp-session with the
+// ecp-2 subprotocol. the device opens with a challenge, answered with a SHA-1 keyed on a
+// constant from Roku's mobile app; after that every request carries an id its response
+// repeats, and queries come back with the xml of their http versions base64-encoded. the
+// device also sends notifications of its own in between, which are skipped
+
+use super::RokuTransport;
+use crate::error::RokuError;
+use crate::http::{self, HttpResponse};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde_json::{json, Map, Value};
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
+use std::io;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex, OnceLock};
+use tungstenite::client::IntoClientRequest;
+use tungstenite::http::HeaderValue;
+use tungstenite::{Message, WebSocket};
+
+// the key the mobile app answers challenges with, before the digit swap in auth_response
+const AUTH_KEY: &str = "95E610D0-7C29-44EF-FB0F-97F1FCE4C297";
+
+struct Session {
+    socket: WebSocket<TcpStream>,
+    next_id: u64,
+}
+
+/// ECP over the device's authenticated WebSocket, for devices in limited mode. One
+/// connection is kept open per device and opened again when it drops.
+#[derive(Default)]
+pub struct Ecp2 {
+    sessions: Mutex<HashMap<String, Arc<Mutex<Option<Session>>>>>,
+}
+
+impl Ecp2 {
+    pub fn new() -> Ecp2 {
+        Ecp2::default()
+    }
+
+    fn request(
+        &self,
+        device: &str,
+        request: Map<String, Value>,
+    ) -> Result<HttpResponse, RokuError> {
+        let session = {
+            let mut sessions = self.sessions.lock().unwrap();
+            Arc::clone(sessions.entry(device.to_string()).or_default())
+        };
+        // one request at a time per device; the others' requests go on meanwhile
+        let mut session = session.lock().unwrap();
+        let reused = session.is_some();
+        if !reused {
+            *session = Some(connect(device)?);
+        }
+        let result = exchange(session.as_mut().unwrap(), request.clone());
+        match result {
+            Ok(resp) => Ok(resp),
+            // a connection left open can have been closed by the device since; one fresh try
+            Err(RokuError::Connection(_)) if reused => {
+                *session = Some(connect(device)?);
+                let result = exchange(session.as_mut().unwrap(), request);
+                if result.is_err() {
+                    *session = None;
+                }
+                result
+            }
+            Err(err) => {
+                *session = None;
+                Err(err)
+            }
+        }
+    }
+}
+
+impl RokuTransport for Ecp2 {
+    fn get(&self, device: &str, path: &str) -> Result<HttpResponse, RokuError> {
+        self.request(device, request_for(path, false)?)
+    }
+
+    fn post(&self, device: &str, path: &str) -> Result<HttpResponse, RokuError> {
+        self.request(device, request_for(path, true)?)
+    }
+}
+
+// the one the library's own requests go through
+pub fn shared() -> &'static Ecp2 {
+    static SHARED: OnceLock<Ecp2> = OnceLock::new();
+    SHARED.get_or_init(Ecp2::new)
+}
+
+// the ECP-2 request for an http path, e.g. keypress/Home -> key-press with param-key Home
+fn request_for(path: &str, command: bool) -> Result<Map<String, Value>, RokuError> {
+    let (path, query) = path.split_once('?').unwrap_or((path, ""));
+    let (endpoint, rest) = path.split_once('/').unwrap_or((path, ""));
+    let request = match (command, endpoint) {
+        (true, "keypress" | "keydown" | "keyup") => {
+            let name = match endpoint {
+                "keypress" => "key-press",
+                "keydown" => "key-down",
+                _ => "key-up",
+            };
+            json!({ "request": name, "param-key": rest })
+        }
+        (true, "launch") => {
+            let mut request = json!({ "request": "launch", "param-channel-id": rest });
+            if !query.is_empty() {
+                request["param-params"] = query.into();
+            }
+            request
+        }
+        (false, "query") => match rest.split_once('/') {
+            Some(("icon", app_id)) => {
+                json!({ "request": "query-icon", "param-channel-id": app_id })
+            }
+            _ => json!({ "request": format!("query-{}", rest) }),
+        },
+        // search, install and the like have no ECP-2 version; the device would answer the
+        // same to a path it doesn't know
+        _ => return Err(RokuError::Status(404)),
+    };
+    match request {
+        Value::Object(request) => Ok(request),
+        _ => unreachable!("built as objects above"),
+    }
+}
+
+fn connect(device: &str) -> Result<Session, RokuError> {
+    let timeout = http::timeout();
+    let addr = device
+        .to_socket_addrs()
+        .map_err(io_error)?
+        .next()
+        .ok_or_else(|| RokuError::Connection(format!("no address for {}", device)))?;
+    let stream = TcpStream::connect_timeout(&addr, timeout).map_err(io_error)?;
+    stream.set_read_timeout(Some(timeout)).map_err(io_error)?;
+    stream.set_write_timeout(Some(timeout)).map_err(io_error)?;
+    let mut request = format!("ws://{}/ecp-session", device)
+        .into_client_request()
+        .map_err(ws_error)?;
+    request
+        .headers_mut()
+        .insert("Sec-WebSocket-Protocol", HeaderValue::from_static("ecp-2"));
+    let (socket, _) = tungstenite::client(request, stream).map_err(|err| match err {
+        tungstenite::HandshakeError::Failure(err) => ws_error(err),
+        tungstenite::HandshakeError::Interrupted(_) => RokuError::Timeout,
+    })?;
+    let mut session = Session { socket, next_id: 0 };
+
+    let challenge = loop {
+        let message = receive(&mut session.socket)?;
+        if message["notify"] == "authenticate" {
+            break message["param-challenge"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string();
+        }
+    };
+    let mut auth = Map::new();
+    auth.insert("request".into(), "authenticate".into());
+    auth.insert("param-response".into(), auth_response(&challenge).into());
+    let resp = exchange(&mut session, auth)?;
+    if !resp.is_success() {
+        return Err(RokuError::Status(resp.status));
+    }
+    Ok(session)
+}
+
+// sends the request under the next id and waits for the response that carries it
+fn exchange(
+    session: &mut Session,
+    mut request: Map<String, Value>,
+) -> Result<HttpResponse, RokuError> {
+    let id = session.next_id.to_string();
+    session.next_id += 1;
+    request.insert("request-id".into(), id.clone().into());
+    session
+        .socket
+        .send(Message::text(Value::Object(request).to_string()))
+        .map_err(ws_error)?;
+    loop {
+        let message = receive(&mut session.socket)?;
+        if message["request-id"] != id.as_str() {
+            continue;
+        }
+        let status = message["response-code"]
+            .as_str()
+            .and_then(|code| code.parse().ok())
+            .unwrap_or(200);
+        let body = match message["content-data"].as_str() {
+            Some(data) => STANDARD
+                .decode(data)
+                .map_err(|err| RokuError::Parse(format!("content-data: {}", err)))?,
+            None => Vec::new(),
+        };
+        return Ok(HttpResponse {
+            status,
+            content_type: message["content-type"].as_str().map(str::to_string),
+            body,
+        });
+    }
+}
+
+// the next JSON message, past pings and anything else that isn't text
+fn receive(socket: &mut WebSocket<TcpStream>) -> Result<Value, RokuError> {
+    loop {
+        match socket.read().map_err(ws_error)? {
+            Message::Text(text) => {
+                return serde_json::from_str(&text).map_err(|err| RokuError::Parse(err.to_string()))
+            }
+            Message::Close(_) => {
+                return Err(RokuError::Connection(
+                    "the device closed the ECP-2 session".into(),
+                ))
+            }
+            _ => {}
+        }
+    }
+}
+
+// base64 of SHA-1 over the challenge and the key with each of its hex digits d swapped for
+// (24 - d) % 16, the way the mobile app does it
+fn auth_response(challenge: &str) -> String {
+    let key: String = AUTH_KEY
+        .chars()
+        .map(|c| match c.to_digit(16) {
+            Some(d) => char::from_digit((24 - d) % 16, 16)
+                .unwrap_or(c)
+                .to_ascii_uppercase(),
+            None => c,
+        })
+        .collect();
+    let mut hash = Sha1::new();
+    hash.update(challenge.as_bytes());
+    hash.update(key.as_bytes());
+    STANDARD.encode(hash.finalize())
+}
+
+fn io_error(err: io::Error) -> RokuError {
+    match err.kind() {
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => RokuError::Timeout,
+        _ => RokuError::Connection(err.to_string()),
+    }
+}
+
+fn ws_error(err: tungstenite::Error) -> RokuError {
+    match err {
+        tungstenite::Error::Io(err) => io_error(err),
+        // a device that answers the upgrade with a plain http status, e.g. older firmware
+        tungstenite::Error::Http(resp) => RokuError::Status(resp.status().as_u16()),
+        err => RokuError::Connection(err.to_string()),
+    }
+}
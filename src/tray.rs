@@ -0,0 +1,156 @@
+// system tray icon showing the current channel, with what's playing and play/pause in its
+// menu. built with the tray feature on Windows and macOS. Linux trays need GTK running
+// alongside winit, so there it is a no-op like without the feature
+
+use crate::now_playing::NowPlaying;
+
+#[cfg(all(feature = "tray", any(windows, target_os = "macos")))]
+mod imp {
+    use super::NowPlaying;
+    use crate::ecp;
+    use crate::now_playing::clock;
+    use eframe::egui;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use tray_icon::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+    use tray_icon::{Icon, TrayIcon, TrayIconBuilder};
+
+    // shown until a channel icon arrives
+    const ROKU_PURPLE: [u8; 4] = [0x6c, 0x3c, 0x97, 0xff];
+
+    #[derive(Default)]
+    pub struct Tray {
+        // created on the first update, once the event loop is running (macOS needs that)
+        menu: Option<TrayMenu>,
+        // the device play/pause goes to. the menu handler sends it straight away, because a
+        // minimized window gets no update() to pick the click up in
+        device: Arc<Mutex<Option<String>>>,
+    }
+
+    struct TrayMenu {
+        icon: TrayIcon,
+        app: MenuItem,
+        status: MenuItem,
+        play_pause: MenuItem,
+    }
+
+    impl Tray {
+        pub fn show(&mut self, ctx: &egui::Context, device: Option<&str>, now: &NowPlaying) {
+            *self.device.lock().unwrap() = device.map(str::to_string);
+            if self.menu.is_none() {
+                self.menu = self.build(ctx);
+            }
+            let Some(menu) = &self.menu else {
+                return;
+            };
+
+            let app = match (device, &now.app) {
+                (None, _) => "No device selected".to_string(),
+                (Some(_), Some(app)) if !app.id.is_empty() => app.name.clone(),
+                (Some(_), Some(_)) => "Home screen".to_string(),
+                (Some(_), None) => "Device not responding".to_string(),
+            };
+            menu.app.set_text(app);
+            menu.status
+                .set_text(status(now).unwrap_or_else(|| "Nothing playing".to_string()));
+            menu.play_pause.set_text(
+                if now.playback.as_ref().is_some_and(|p| p.state == "play") {
+                    "Pause"
+                } else {
+                    "Play"
+                },
+            );
+            menu.play_pause.set_enabled(device.is_some());
+
+            let icon = now
+                .icon
+                .as_ref()
+                .and_then(|icon| Icon::from_rgba(icon.rgba.clone(), icon.width, icon.height).ok())
+                .or_else(default_icon);
+            menu.icon.set_tooltip(Some(now.title())).ok();
+            menu.icon.set_icon(icon).ok();
+        }
+
+        fn build(&self, ctx: &egui::Context) -> Option<TrayMenu> {
+            let app = MenuItem::new("", false, None);
+            let status = MenuItem::new("", false, None);
+            let play_pause = MenuItem::new("Play", false, None);
+            let menu = Menu::new();
+            menu.append_items(&[&app, &status, &PredefinedMenuItem::separator(), &play_pause])
+                .ok()?;
+
+            let mut builder = TrayIconBuilder::new().with_menu(Box::new(menu));
+            if let Some(icon) = default_icon() {
+                builder = builder.with_icon(icon);
+            }
+            let icon = match builder.build() {
+                Ok(icon) => icon,
+                Err(err) => {
+                    eprintln!("No tray icon: {}", err);
+                    return None;
+                }
+            };
+
+            let (id, device, ctx) = (
+                play_pause.id().clone(),
+                Arc::clone(&self.device),
+                ctx.clone(),
+            );
+            MenuEvent::set_event_handler(Some(move |event: MenuEvent| {
+                if event.id != id {
+                    return;
+                }
+                if let Some(device) = device.lock().unwrap().clone() {
+                    // Play toggles; the next poll brings the new state into the menu
+                    thread::spawn(move || {
+                        let _ = ecp::send_command(&device, "Play");
+                    });
+                }
+                ctx.request_repaint();
+            }));
+            Some(TrayMenu {
+                icon,
+                app,
+                status,
+                play_pause,
+            })
+        }
+    }
+
+    // "Playing 12:34 / 43:00" while something is loaded in the player
+    fn status(now: &NowPlaying) -> Option<String> {
+        let playback = now.playback.as_ref()?;
+        let state = match playback.state.as_str() {
+            "play" => "Playing",
+            "pause" => "Paused",
+            "buffer" | "startup" => "Loading",
+            _ => return None,
+        };
+        Some(match (playback.position, playback.duration) {
+            (Some(position), Some(duration)) => {
+                format!("{} {} / {}", state, clock(position), clock(duration))
+            }
+            (Some(position), None) => format!("{} {}", state, clock(position)),
+            _ => state.to_string(),
+        })
+    }
+
+    fn default_icon() -> Option<Icon> {
+        Icon::from_rgba(ROKU_PURPLE.repeat(32 * 32), 32, 32).ok()
+    }
+}
+
+#[cfg(not(all(feature = "tray", any(windows, target_os = "macos"))))]
+mod imp {
+    use super::NowPlaying;
+    use eframe::egui;
+
+    #[derive(Default)]
+    pub struct Tray;
+
+    impl Tray {
+        pub fn show(&mut self, _ctx: &egui::Context, _device: Option<&str>, _now: &NowPlaying) {}
+    }
+}
+
+pub use imp::Tray;
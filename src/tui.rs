@@ -0,0 +1,561 @@
+// a remote in the terminal, for a Roku near a machine reached over ssh with no display to
+// open the GUI on: the devices, the GUI's buttons and the channels, one pane each. keys go out
+// through a Pacer like the GUI's and everything else on a thread of its own, so a slow device
+// never holds up the screen. built with the tui feature
+
+pub const TUI_HELP: &str = "Keys:\n\
+    Tab, Shift+Tab      move between the devices, the remote and the channels\n\
+    arrows, Enter       pick a device, press a button, launch a channel\n\
+    h  Backspace  Space Home, Back, Play/Pause on the device, from any pane\n\
+    +  -  m             volume up, down and mute\n\
+    t                   type text on the device; Enter sends it, Esc drops it\n\
+    r                   look for devices again\n\
+    q  Esc              quit";
+
+#[cfg(feature = "tui")]
+pub use imp::run;
+
+#[cfg(not(feature = "tui"))]
+pub fn run(_device: Option<String>) -> Result<(), String> {
+    Err("built without the tui feature".into())
+}
+
+#[cfg(feature = "tui")]
+mod imp {
+    use crate::config::{Config, ConfigStore};
+    use crate::ecp::{self, AppEntry};
+    use crate::layout::{self, Button};
+    use crate::session::Session;
+    use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+    use ratatui::layout::{Constraint, Layout, Rect};
+    use ratatui::style::{Color, Modifier, Style};
+    use ratatui::text::{Line, Span};
+    use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+    use ratatui::{DefaultTerminal, Frame};
+    use roku_remote::http::dry_run;
+    use roku_remote::{Pacer, RokuError, RokuKey};
+    use std::sync::mpsc::{self, Receiver, Sender};
+    use std::thread;
+    use std::time::Duration;
+
+    // the GUI draws these as its volume control under the grid; here they're one more row
+    const VOLUME_ROW: [RokuKey; 3] = [RokuKey::VolumeDown, RokuKey::VolumeMute, RokuKey::VolumeUp];
+    // how long a frame waits on the keyboard before looking for answers from the device
+    const TICK: Duration = Duration::from_millis(100);
+
+    #[derive(Clone, Copy, PartialEq)]
+    enum Pane {
+        Devices,
+        Remote,
+        Channels,
+    }
+
+    // what the threads doing the slow parts send back
+    enum Answer {
+        Devices(Vec<String>),
+        Name(String, String),
+        Apps(String, Result<Vec<AppEntry>, RokuError>),
+        Done(String),
+    }
+
+    struct Tui {
+        config: Config,
+        devices: Vec<String>,
+        // from each device's device-info, once it has answered
+        names: Vec<(String, String)>,
+        device_list: ListState,
+        selected: Option<String>,
+        // the row and column of the highlighted button
+        cursor: (usize, usize),
+        // the selected device's channels, its favorites first
+        apps: Vec<AppEntry>,
+        loading_apps: bool,
+        app_list: ListState,
+        pane: Pane,
+        // the text being typed after t, until Enter or Esc
+        typing: Option<String>,
+        status: String,
+        discovering: bool,
+        pacer: Pacer,
+        tx: Sender<Answer>,
+        rx: Receiver<Answer>,
+        quit: bool,
+    }
+
+    // starts on the device, or on the one the GUI last had, and looks for others when no
+    // device is known yet
+    pub fn run(device: Option<String>) -> Result<(), String> {
+        let (_, config) = ConfigStore::open();
+        let session = Session::load();
+        let (tx, rx) = mpsc::channel();
+        let mut tui = Tui {
+            config,
+            devices: session.devices.clone(),
+            names: Vec::new(),
+            device_list: ListState::default(),
+            selected: None,
+            cursor: (1, 0),
+            apps: Vec::new(),
+            loading_apps: false,
+            app_list: ListState::default(),
+            pane: Pane::Remote,
+            typing: None,
+            status: "Tab moves between panes; q quits".into(),
+            discovering: false,
+            pacer: Pacer::new(),
+            tx,
+            rx,
+            quit: false,
+        };
+        let first = device
+            .map(|device| ecp::device_addr(&device))
+            .or(session.last_device)
+            .or_else(|| tui.devices.first().cloned());
+        for device in tui.devices.clone() {
+            tui.fetch_name(device);
+        }
+        match first {
+            Some(device) => tui.select(device),
+            None => tui.discover(),
+        }
+
+        let mut terminal = ratatui::init();
+        let result = tui.event_loop(&mut terminal);
+        ratatui::restore();
+        result
+    }
+
+    impl Tui {
+        fn event_loop(&mut self, terminal: &mut DefaultTerminal) -> Result<(), String> {
+            while !self.quit {
+                terminal
+                    .draw(|frame| self.draw(frame))
+                    .map_err(|err| format!("drawing the terminal: {}", err))?;
+                while let Ok(answer) = self.rx.try_recv() {
+                    self.take(answer);
+                }
+                if let Some(failure) = self.pacer.failures().pop() {
+                    self.status = format!(
+                        "{} didn't reach {}: {}",
+                        failure.key, failure.device, failure.error
+                    );
+                }
+                let ready = event::poll(TICK).map_err(|err| format!("reading keys: {}", err))?;
+                if !ready {
+                    continue;
+                }
+                let event = event::read().map_err(|err| format!("reading keys: {}", err))?;
+                // some terminals report releases too; only a press does anything
+                if let Event::Key(key) = event {
+                    if key.kind == KeyEventKind::Press {
+                        self.on_key(key.code, key.modifiers);
+                    }
+                }
+            }
+            Ok(())
+        }
+
+        fn take(&mut self, answer: Answer) {
+            match answer {
+                Answer::Devices(found) => {
+                    self.discovering = false;
+                    let new: Vec<String> = found
+                        .into_iter()
+                        .filter(|device| !self.devices.contains(device))
+                        .collect();
+                    self.status = match new.len() {
+                        0 if self.devices.is_empty() => {
+                            "No Roku devices found; r tries again".into()
+                        }
+                        0 => "No new devices found".into(),
+                        n => format!("Found {} new device(s)", n),
+                    };
+                    for device in new {
+                        self.devices.push(device.clone());
+                        self.fetch_name(device);
+                    }
+                    if self.selected.is_none() {
+                        if let Some(device) = self.devices.first().cloned() {
+                            self.select(device);
+                        }
+                    }
+                }
+                Answer::Name(device, name) => {
+                    self.names.retain(|(known, _)| *known != device);
+                    self.names.push((device, name));
+                }
+                Answer::Apps(device, apps) => {
+                    if self.selected.as_ref() != Some(&device) {
+                        return;
+                    }
+                    self.loading_apps = false;
+                    let mut apps = match apps {
+                        Ok(apps) => apps,
+                        Err(err) => {
+                            self.status = format!("Couldn't list the channels: {}", err);
+                            return;
+                        }
+                    };
+                    let favorites = self
+                        .config
+                        .devices
+                        .get(&device)
+                        .map(|meta| meta.favorites.clone())
+                        .unwrap_or_default();
+                    // the key is the favorite's place, past every favorite for the rest
+                    apps.sort_by_key(|app| {
+                        favorites
+                            .iter()
+                            .position(|id| *id == app.id)
+                            .unwrap_or(favorites.len())
+                    });
+                    self.app_list.select((!apps.is_empty()).then_some(0));
+                    self.apps = apps;
+                }
+                Answer::Done(message) => self.status = message,
+            }
+        }
+
+        fn on_key(&mut self, code: KeyCode, modifiers: KeyModifiers) {
+            if let Some(text) = &mut self.typing {
+                match code {
+                    KeyCode::Enter => {
+                        let text = self.typing.take().unwrap_or_default();
+                        self.type_text(text);
+                    }
+                    KeyCode::Esc => self.typing = None,
+                    KeyCode::Backspace => {
+                        text.pop();
+                    }
+                    KeyCode::Char(c) => text.push(c),
+                    _ => {}
+                }
+                return;
+            }
+            if modifiers.contains(KeyModifiers::CONTROL) && code == KeyCode::Char('c') {
+                self.quit = true;
+                return;
+            }
+            match code {
+                KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+                KeyCode::Tab => self.pane = self.next_pane(1),
+                KeyCode::BackTab => self.pane = self.next_pane(2),
+                KeyCode::Char('h') => self.press(RokuKey::Home),
+                KeyCode::Backspace => self.press(RokuKey::Back),
+                KeyCode::Char(' ') => self.press(RokuKey::Play),
+                KeyCode::Char('+') | KeyCode::Char('=') => self.press(RokuKey::VolumeUp),
+                KeyCode::Char('-') => self.press(RokuKey::VolumeDown),
+                KeyCode::Char('m') => self.press(RokuKey::VolumeMute),
+                KeyCode::Char('t') if self.selected.is_some() => self.typing = Some(String::new()),
+                KeyCode::Char('r') => self.discover(),
+                KeyCode::Up => self.step(0, -1),
+                KeyCode::Down => self.step(0, 1),
+                KeyCode::Left => self.step(-1, 0),
+                KeyCode::Right => self.step(1, 0),
+                KeyCode::Enter => self.activate(),
+                _ => {}
+            }
+        }
+
+        fn next_pane(&self, by: usize) -> Pane {
+            let panes = [Pane::Devices, Pane::Remote, Pane::Channels];
+            let at = panes
+                .iter()
+                .position(|pane| *pane == self.pane)
+                .unwrap_or(0);
+            panes[(at + by) % panes.len()]
+        }
+
+        // moves the highlight in the focused pane
+        fn step(&mut self, dx: isize, dy: isize) {
+            match self.pane {
+                Pane::Devices => step_list(&mut self.device_list, self.devices.len(), dy),
+                Pane::Channels => step_list(&mut self.app_list, self.apps.len(), dy),
+                Pane::Remote => {
+                    let rows = self.rows();
+                    let (mut row, mut col) = self.cursor;
+                    // skips over the blank cells, and stays put with nowhere to go
+                    loop {
+                        let next_row = row as isize + dy;
+                        let next_col = col as isize + dx;
+                        if next_row < 0 || next_row >= rows.len() as isize || next_col < 0 {
+                            return;
+                        }
+                        (row, col) = (next_row as usize, next_col as usize);
+                        match rows[row].get(col) {
+                            None => return,
+                            Some(Some(_)) => break,
+                            Some(None) => {}
+                        }
+                    }
+                    self.cursor = (row, col);
+                }
+            }
+        }
+
+        fn activate(&mut self) {
+            match self.pane {
+                Pane::Devices => {
+                    let picked = self.device_list.selected();
+                    if let Some(device) = picked.and_then(|i| self.devices.get(i)).cloned() {
+                        self.select(device);
+                        self.pane = Pane::Remote;
+                    }
+                }
+                Pane::Remote => {
+                    let (row, col) = self.cursor;
+                    let rows = self.rows();
+                    if let Some(Some(button)) = rows.get(row).and_then(|row| row.get(col)) {
+                        self.send(&button.key, &button.label());
+                    }
+                }
+                Pane::Channels => {
+                    let picked = self.app_list.selected();
+                    if let Some(app) = picked.and_then(|i| self.apps.get(i)).cloned() {
+                        self.launch(app);
+                    }
+                }
+            }
+        }
+
+        fn select(&mut self, device: String) {
+            if !self.devices.contains(&device) {
+                self.devices.push(device.clone());
+                self.fetch_name(device.clone());
+            }
+            let at = self.devices.iter().position(|known| *known == device);
+            self.device_list.select(at);
+            self.apps.clear();
+            self.loading_apps = true;
+            self.app_list.select(None);
+            self.selected = Some(device.clone());
+            let tx = self.tx.clone();
+            thread::spawn(move || {
+                let apps = ecp::get_apps(&device);
+                tx.send(Answer::Apps(device, apps)).ok();
+            });
+        }
+
+        fn fetch_name(&self, device: String) {
+            let tx = self.tx.clone();
+            thread::spawn(move || {
+                if let Ok(info) = ecp::get_device_info(&device) {
+                    tx.send(Answer::Name(device, info.name)).ok();
+                }
+            });
+        }
+
+        fn discover(&mut self) {
+            if self.discovering {
+                return;
+            }
+            self.discovering = true;
+            self.status = "Looking for devices…".into();
+            let (tx, ssdp) = (self.tx.clone(), self.config.ssdp.clone());
+            thread::spawn(move || {
+                let found = ecp::discover_or_scan(&ssdp)
+                    .into_iter()
+                    .map(|device| device.address)
+                    .collect();
+                tx.send(Answer::Devices(found)).ok();
+            });
+        }
+
+        fn press(&mut self, key: RokuKey) {
+            self.send(key.name(), &key.label());
+        }
+
+        fn send(&mut self, cmd: &str, label: &str) {
+            let Some(device) = &self.selected else {
+                self.status = "No device selected".into();
+                return;
+            };
+            self.pacer.send(device, cmd);
+            self.status = format!("{} on {}", label, self.label(device));
+        }
+
+        // the GUI's rows as laid out in its editor, and the volume keys under them
+        fn rows(&self) -> Vec<Vec<Option<Button>>> {
+            let mut rows = self.config.layout.rows();
+            rows.push(VOLUME_ROW.map(|key| Some(Button::new(key))).to_vec());
+            rows
+        }
+
+        fn launch(&mut self, app: AppEntry) {
+            let Some(device) = self.selected.clone() else {
+                return;
+            };
+            self.status = format!("Launching {}…", app.name);
+            let tx = self.tx.clone();
+            thread::spawn(move || {
+                let message = match ecp::launch_app(&device, &app.id) {
+                    Ok(()) => format!("Launched {}", app.name),
+                    Err(err) => format!("Launching {} failed: {}", app.name, err),
+                };
+                tx.send(Answer::Done(message)).ok();
+            });
+        }
+
+        // the text doesn't go into the status line: it's as often a password as not
+        fn type_text(&mut self, text: String) {
+            let Some(device) = self.selected.clone() else {
+                return;
+            };
+            if text.is_empty() {
+                return;
+            }
+            let count = text.chars().count();
+            self.status = format!("Typing {} character(s)…", count);
+            let tx = self.tx.clone();
+            thread::spawn(move || {
+                let delay = roku_remote::http::typing_delay();
+                let message = match ecp::send_text(&device, &text, delay, |_| true) {
+                    Ok(()) => format!("Typed {} character(s)", count),
+                    Err(err) => format!("Typing failed: {}", err),
+                };
+                tx.send(Answer::Done(message)).ok();
+            });
+        }
+
+        // the name the device goes by in its settings, with its address
+        fn label(&self, device: &str) -> String {
+            match self.names.iter().find(|(known, _)| known == device) {
+                Some((_, name)) if !name.is_empty() => format!("{} ({})", name, device),
+                _ => device.to_string(),
+            }
+        }
+
+        fn draw(&mut self, frame: &mut Frame) {
+            let [main, status] =
+                Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+            let [devices, remote, channels] = Layout::horizontal([
+                Constraint::Length(32),
+                Constraint::Length(48),
+                Constraint::Min(20),
+            ])
+            .areas(main);
+            self.draw_devices(frame, devices);
+            self.draw_remote(frame, remote);
+            self.draw_channels(frame, channels);
+
+            let line = match &self.typing {
+                // a password shows as dots
+                Some(text) => Line::from(vec![
+                    Span::styled("Type: ", Style::new().add_modifier(Modifier::BOLD)),
+                    Span::raw("•".repeat(text.chars().count())),
+                    Span::raw("  (Enter sends, Esc cancels)"),
+                ]),
+                None if dry_run::enabled() => Line::from(format!("[dry run] {}", self.status)),
+                None => Line::from(self.status.as_str()),
+            };
+            frame.render_widget(Paragraph::new(line), status);
+        }
+
+        fn block(&self, title: &str, pane: Pane) -> Block<'static> {
+            let block = Block::bordered().title(format!(" {} ", title));
+            if self.pane == pane {
+                block.border_style(Style::new().fg(Color::Cyan))
+            } else {
+                block
+            }
+        }
+
+        fn draw_devices(&mut self, frame: &mut Frame, area: Rect) {
+            let items: Vec<ListItem> = self
+                .devices
+                .iter()
+                .map(|device| {
+                    let mark = if self.selected.as_ref() == Some(device) {
+                        "● "
+                    } else {
+                        "  "
+                    };
+                    ListItem::new(format!("{}{}", mark, self.label(device)))
+                })
+                .collect();
+            let title = if self.discovering {
+                "Devices (looking…)"
+            } else {
+                "Devices"
+            };
+            let list = List::new(items)
+                .block(self.block(title, Pane::Devices))
+                .highlight_style(highlight(self.pane == Pane::Devices));
+            frame.render_stateful_widget(list, area, &mut self.device_list);
+        }
+
+        fn draw_remote(&self, frame: &mut Frame, area: Rect) {
+            let block = self.block("Remote", Pane::Remote);
+            let inner = block.inner(area);
+            frame.render_widget(block, area);
+            let rows = self.rows();
+            let constraints = vec![Constraint::Length(1); rows.len()];
+            let lines = Layout::vertical(constraints).spacing(0).split(inner);
+            for (i, (row, line)) in rows.iter().zip(lines.iter()).enumerate() {
+                let width = Constraint::Ratio(1, layout::COLUMNS as u32);
+                let cells = Layout::horizontal([width; layout::COLUMNS]).split(*line);
+                for (j, (button, cell)) in row.iter().zip(cells.iter()).enumerate() {
+                    let Some(button) = button else {
+                        continue;
+                    };
+                    let style = if self.cursor == (i, j) {
+                        highlight(self.pane == Pane::Remote)
+                    } else {
+                        Style::new()
+                    };
+                    let label = Paragraph::new(format!("[{}]", button.label()))
+                        .style(style)
+                        .centered();
+                    frame.render_widget(label, *cell);
+                }
+            }
+        }
+
+        fn draw_channels(&mut self, frame: &mut Frame, area: Rect) {
+            let favorites = self
+                .selected
+                .as_ref()
+                .and_then(|device| self.config.devices.get(device))
+                .map(|meta| meta.favorites.clone())
+                .unwrap_or_default();
+            let items: Vec<ListItem> = self
+                .apps
+                .iter()
+                .map(|app| {
+                    let star = if favorites.contains(&app.id) {
+                        "★ "
+                    } else {
+                        "  "
+                    };
+                    ListItem::new(format!("{}{}", star, app.name))
+                })
+                .collect();
+            let title = if self.loading_apps {
+                "Channels (loading…)"
+            } else {
+                "Channels"
+            };
+            let list = List::new(items)
+                .block(self.block(title, Pane::Channels))
+                .highlight_style(highlight(self.pane == Pane::Channels));
+            frame.render_stateful_widget(list, area, &mut self.app_list);
+        }
+    }
+
+    // reversed where the keys go, dimmer in the other panes
+    fn highlight(focused: bool) -> Style {
+        if focused {
+            Style::new().add_modifier(Modifier::REVERSED)
+        } else {
+            Style::new().add_modifier(Modifier::UNDERLINED)
+        }
+    }
+
+    fn step_list(list: &mut ListState, len: usize, by: isize) {
+        if len == 0 {
+            return;
+        }
+        let at = list.selected().unwrap_or(0) as isize + by;
+        list.select(Some(at.clamp(0, len as isize - 1) as usize));
+    }
+}
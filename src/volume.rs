@@ -0,0 +1,73 @@
+// the volume control: a rocker that springs back to the middle and buttons either side of it,
+// all of which keep pressing VolumeUp or VolumeDown for as long as they're held. ECP has no
+// way to read or set the volume outright, so repeated presses are all there is
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VolumeSettings {
+    // presses a second while a button is held, or the rocker is pushed all the way
+    pub repeat_per_sec: f32,
+}
+
+impl Default for VolumeSettings {
+    fn default() -> Self {
+        Self {
+            repeat_per_sec: 6.0,
+        }
+    }
+}
+
+// slower than this and a held rocker would look like it had stopped working
+const MIN_RATE: f32 = 1.0;
+
+struct Held {
+    key: &'static str,
+    last: Instant,
+    every: Duration,
+}
+
+#[derive(Default)]
+pub struct VolumeRepeat {
+    held: Option<Held>,
+}
+
+impl VolumeRepeat {
+    // takes the key held this frame, if any, with how many presses a second it wants, and
+    // returns the key when a press is due; the first comes as soon as it's pressed
+    pub fn update(&mut self, pressed: Option<(&'static str, f32)>) -> Option<&'static str> {
+        let Some((key, rate)) = pressed else {
+            self.held = None;
+            return None;
+        };
+        let every = Duration::from_secs_f32(1.0 / rate.max(MIN_RATE));
+        match &mut self.held {
+            Some(held) if held.key == key => {
+                held.every = every;
+                if held.last.elapsed() < every {
+                    return None;
+                }
+                held.last = Instant::now();
+            }
+            _ => {
+                self.held = Some(Held {
+                    key,
+                    last: Instant::now(),
+                    every,
+                })
+            }
+        }
+        Some(key)
+    }
+
+    pub fn holding(&self) -> bool {
+        self.held.is_some()
+    }
+
+    // how long until the next press is due, so a frame comes in time to send it
+    pub fn waiting(&self) -> Option<Duration> {
+        let held = self.held.as_ref()?;
+        Some(held.every.saturating_sub(held.last.elapsed()))
+    }
+}
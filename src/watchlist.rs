@@ -0,0 +1,115 @@
+// named deep links, TV bookmarks for a single show or film. they live in the config, so they
+// sync and merge like macros do
+
+use roku_remote::{ecp, RokuError};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeepLink {
+    pub app_id: String,
+    pub content_id: String,
+    // "movie", "episode", "series", ...; some channels need it to find the content
+    #[serde(default)]
+    pub media_type: Option<String>,
+    // "" keeps it at the top level
+    #[serde(default)]
+    pub folder: String,
+}
+
+impl DeepLink {
+    pub fn launch(&self, ip: &str) -> Result<(), RokuError> {
+        ecp::launch_content(
+            ip,
+            &self.app_id,
+            &self.content_id,
+            self.media_type.as_deref(),
+        )
+    }
+}
+
+// folder -> (name, link), folders and names in order with the top level first
+pub fn by_folder(watchlist: &BTreeMap<String, DeepLink>) -> BTreeMap<&str, Vec<(&str, &DeepLink)>> {
+    let mut folders: BTreeMap<&str, Vec<(&str, &DeepLink)>> = BTreeMap::new();
+    for (name, link) in watchlist {
+        folders
+            .entry(link.folder.trim())
+            .or_default()
+            .push((name, link));
+    }
+    folders
+}
+
+// channels whose content ids can be read off the address of a title in a browser, so a link can
+// be pasted straight in
+pub struct Preset {
+    pub name: &'static str,
+    pub app_id: &'static str,
+    pub media_type: Option<&'static str>,
+    pub hint: &'static str,
+    content_id: fn(&url::Url) -> Option<String>,
+}
+
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "YouTube video",
+        app_id: "837",
+        media_type: None,
+        hint: "video id or youtube.com/watch?v=… link",
+        content_id: youtube_id,
+    },
+    Preset {
+        name: "Netflix title",
+        app_id: "12",
+        media_type: Some("movie"),
+        hint: "title id or netflix.com/title/… link",
+        content_id: netflix_id,
+    },
+];
+
+impl Preset {
+    // the content id in a pasted link, or the input itself when it's already an id
+    pub fn content_id(&self, input: &str) -> String {
+        let input = input.trim();
+        let link = if input.contains('/') && !input.contains("://") {
+            format!("https://{}", input)
+        } else {
+            input.to_string()
+        };
+        url::Url::parse(&link)
+            .ok()
+            .and_then(|url| (self.content_id)(&url))
+            .unwrap_or_else(|| input.to_string())
+    }
+}
+
+// youtube.com/watch?v=ID, youtu.be/ID, and youtube.com/shorts/ID or /embed/ID
+fn youtube_id(url: &url::Url) -> Option<String> {
+    let host = url
+        .host_str()?
+        .trim_start_matches("www.")
+        .trim_start_matches("m.");
+    let mut path = url.path_segments()?;
+    match (host, path.next()?) {
+        ("youtu.be", id) => Some(id.to_string()),
+        ("youtube.com", "watch") => url
+            .query_pairs()
+            .find(|(key, _)| key == "v")
+            .map(|(_, id)| id.into_owned()),
+        ("youtube.com", "shorts" | "embed" | "live") => path.next().map(str::to_string),
+        _ => None,
+    }
+    .filter(|id| !id.is_empty())
+}
+
+// netflix.com/title/ID or /watch/ID, with or without a country in front
+fn netflix_id(url: &url::Url) -> Option<String> {
+    if !url.host_str()?.ends_with("netflix.com") {
+        return None;
+    }
+    let path: Vec<&str> = url.path_segments()?.collect();
+    path.windows(2)
+        .find(|pair| pair[0] == "title" || pair[0] == "watch")
+        .map(|pair| pair[1].to_string())
+        .filter(|id| id.chars().all(|c| c.is_ascii_digit()) && !id.is_empty())
+}
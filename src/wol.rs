@@ -0,0 +1,64 @@
+// Roku TVs in deep standby stop answering ECP altogether, but keep their network card
+// listening for a Wake-on-LAN magic packet: six 0xff bytes and then the MAC sixteen times,
+// broadcast over UDP
+
+use crate::http::dry_run;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddrV4, UdpSocket};
+
+// the discard port, which is what most senders use; 7 works too
+const WOL_PORT: u16 = 9;
+
+/// Reads a MAC such as "a8:b5:7c:12:34:56", with colons, dashes or nothing between the bytes.
+pub fn parse_mac(mac: &str) -> Option<[u8; 6]> {
+    let hex: String = mac
+        .trim()
+        .chars()
+        .filter(|c| !matches!(c, ':' | '-' | '.'))
+        .collect();
+    if hex.len() != 12 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let mut bytes = [0; 6];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(bytes)
+}
+
+fn format_mac(mac: [u8; 6]) -> String {
+    mac.iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+pub fn magic_packet(mac: [u8; 6]) -> Vec<u8> {
+    let mut packet = vec![0xff; 6];
+    for _ in 0..16 {
+        packet.extend_from_slice(&mac);
+    }
+    packet
+}
+
+/// Broadcasts a magic packet for the MAC on the local network. Sent a few times, since
+/// nothing answers a magic packet and UDP may drop one.
+pub fn wake(mac: &str) -> io::Result<()> {
+    let mac = parse_mac(mac).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("not a MAC address: {}", mac),
+        )
+    })?;
+    if dry_run::enabled() {
+        dry_run::record(&format!("WAKE {}", format_mac(mac)));
+        return Ok(());
+    }
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    socket.set_broadcast(true)?;
+    let packet = magic_packet(mac);
+    for _ in 0..3 {
+        socket.send_to(&packet, SocketAddrV4::new(Ipv4Addr::BROADCAST, WOL_PORT))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,100 @@
+// the GUI's network calls, run on a few background threads so a slow or missing device never
+// stalls a frame. jobs that produce something send a Reply back and wake the GUI to take it
+
+use crate::ecp::{AppEntry, DeviceInfo, Discovered, TvActiveChannel, TvChannel};
+use eframe::egui;
+use roku_remote::RokuError;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+// enough that a slow launch doesn't hold up discovery, few enough not to swamp a device
+const THREADS: usize = 4;
+
+pub enum Reply {
+    Discovered(Vec<Discovered>),
+    // what a discovery run in the background found, merged in quietly
+    Rediscovered(Vec<Discovered>),
+    Apps(String, Result<Vec<AppEntry>, RokuError>),
+    DeviceInfo(String, DeviceInfo),
+    // a device added by hand and whether it answered device-info
+    Added(String, Result<DeviceInfo, RokuError>),
+    // source and target device with their channels, for comparing lineups
+    Lineups(String, Vec<AppEntry>, String, Vec<AppEntry>),
+    // a Roku TV's channel lineup, and the channel its tuner is on
+    TvChannels(
+        String,
+        Result<Vec<TvChannel>, RokuError>,
+        Option<TvActiveChannel>,
+    ),
+    TvActive(String, Option<TvActiveChannel>),
+    // a device sent a magic packet, with its device-info if it came back up in time
+    Woke(String, Option<DeviceInfo>),
+    // a command run for nobody in particular, named for the status line and the history
+    Ran(String, Result<(), RokuError>),
+    // a command that didn't go through, ready for the status line
+    Failed(String),
+    // what the developer installer said to a sideload or a delete
+    Sideloaded(String, Result<String, String>),
+    // a developer-mode screenshot, with its extension
+    Screenshot(String, Result<(Vec<u8>, String), String>),
+}
+
+type Job = Box<dyn FnOnce() -> Option<Reply> + Send>;
+
+pub struct Worker {
+    jobs: Sender<Job>,
+    replies: Receiver<Reply>,
+    // woken when a reply comes in; set on the first frame
+    ctx: Arc<Mutex<Option<egui::Context>>>,
+}
+
+impl Default for Worker {
+    fn default() -> Self {
+        let (jobs, queue) = mpsc::channel::<Job>();
+        let (reply_tx, replies) = mpsc::channel();
+        let queue = Arc::new(Mutex::new(queue));
+        let ctx: Arc<Mutex<Option<egui::Context>>> = Arc::default();
+        for _ in 0..THREADS {
+            let (queue, reply_tx, ctx) = (Arc::clone(&queue), reply_tx.clone(), Arc::clone(&ctx));
+            thread::spawn(move || loop {
+                // the lock is only held while waiting for the next job, not while running it
+                let next = queue.lock().unwrap().recv();
+                let Ok(job) = next else {
+                    return;
+                };
+                if let Some(reply) = job() {
+                    if reply_tx.send(reply).is_err() {
+                        return;
+                    }
+                    if let Some(ctx) = &*ctx.lock().unwrap() {
+                        ctx.request_repaint();
+                    }
+                }
+            });
+        }
+        Self { jobs, replies, ctx }
+    }
+}
+
+impl Worker {
+    // runs a command nobody waits for, such as launching a channel; what names it
+    pub fn run(
+        &self,
+        what: impl Into<String>,
+        job: impl FnOnce() -> Result<(), RokuError> + Send + 'static,
+    ) {
+        let what = what.into();
+        self.ask(move || Some(Reply::Ran(what, job())));
+    }
+
+    pub fn ask(&self, job: impl FnOnce() -> Option<Reply> + Send + 'static) {
+        let _ = self.jobs.send(Box::new(job));
+    }
+
+    // the replies that came in since the last frame
+    pub fn replies(&self, ctx: &egui::Context) -> Vec<Reply> {
+        self.ctx.lock().unwrap().get_or_insert_with(|| ctx.clone());
+        self.replies.try_iter().collect()
+    }
+}
@@ -0,0 +1,171 @@
+// discovery and the ECP calls against the pretend Roku in mock_roku, end to end over real
+// sockets
+
+mod mock_roku;
+
+use mock_roku::MockRoku;
+use roku_remote::ecp::{self, KeyAction, SsdpSettings};
+use roku_remote::{Pacer, RokuError, RokuKey};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[test]
+fn discovery_finds_the_mock() {
+    let roku = MockRoku::start();
+    roku.answer_ssdp();
+    let found = ecp::discover_devices(&SsdpSettings::default());
+    let device = found
+        .iter()
+        .find(|device| device.address == roku.addr)
+        .unwrap_or_else(|| panic!("{} not among {:?}", roku.addr, found));
+    assert_eq!(device.serial.as_deref(), Some(roku.serial.as_str()));
+}
+
+#[test]
+fn discovery_with_ssdp_all_keeps_the_mock() {
+    let roku = MockRoku::start();
+    roku.answer_ssdp();
+    let settings = SsdpSettings {
+        search_target: "urn:dial-multiscreen-org:service:dial:1".into(),
+        search_all: true,
+        ..SsdpSettings::default()
+    };
+    let found = ecp::discover_devices(&settings);
+    assert!(
+        found.iter().any(|device| device.address == roku.addr),
+        "{} not among {:?}",
+        roku.addr,
+        found
+    );
+}
+
+#[test]
+fn keys_arrive_in_order() {
+    let roku = MockRoku::start();
+    ecp::send_command(&roku.addr, "Home").unwrap();
+    ecp::press(&roku.addr, RokuKey::VolumeUp).unwrap();
+    ecp::press(&roku.addr, RokuKey::Lit('&')).unwrap();
+    ecp::send_key_action(&roku.addr, "Right", KeyAction::Down).unwrap();
+    ecp::send_key_action(&roku.addr, "Right", KeyAction::Up).unwrap();
+    assert_eq!(
+        roku.keys(),
+        [
+            "keypress/Home",
+            "keypress/VolumeUp",
+            "keypress/Lit_%26",
+            "keydown/Right",
+            "keyup/Right",
+        ]
+    );
+}
+
+#[test]
+fn text_goes_one_character_at_a_time() {
+    let roku = MockRoku::start();
+    ecp::send_key(&roku.addr, "a b").unwrap();
+    assert_eq!(
+        roku.keys(),
+        ["keypress/Lit_a", "keypress/Lit_%20", "keypress/Lit_b"]
+    );
+}
+
+#[test]
+fn unknown_key_is_turned_down() {
+    let roku = MockRoku::start();
+    assert_eq!(
+        ecp::send_command(&roku.addr, "Teleport"),
+        Err(RokuError::Status(400))
+    );
+    assert!(roku.keys().is_empty());
+}
+
+#[test]
+fn nothing_listening_is_a_connection_error() {
+    // a port that was free a moment ago
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .to_string();
+    assert!(matches!(
+        ecp::send_command(&addr, "Home"),
+        Err(RokuError::Connection(_))
+    ));
+}
+
+#[test]
+fn device_info_and_apps() {
+    let roku = MockRoku::start();
+    let info = ecp::get_device_info(&roku.addr).unwrap();
+    assert_eq!(info.serial_number, roku.serial);
+    assert_eq!(info.name, "Test Bench");
+    assert!(!info.is_tv);
+    assert_eq!(ecp::get_power_mode(&roku.addr).as_deref(), Some("PowerOn"));
+    assert_eq!(ecp::get_private_listening(&roku.addr), Some(false));
+
+    let apps: Vec<(String, String)> = ecp::get_apps(&roku.addr)
+        .unwrap()
+        .into_iter()
+        .map(|app| (app.id, app.name))
+        .collect();
+    assert_eq!(
+        apps,
+        [
+            ("12".to_string(), "Netflix".to_string()),
+            ("837".to_string(), "YouTube".to_string()),
+            ("2285".to_string(), "Hulu".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn launching_changes_the_active_app() {
+    let roku = MockRoku::start();
+    assert_eq!(
+        ecp::get_playback_state(&roku.addr).as_deref(),
+        Some("close")
+    );
+    ecp::launch_app(&roku.addr, "837").unwrap();
+    assert_eq!(roku.launched(), ["837"]);
+    let active = ecp::get_active_app(&roku.addr).unwrap();
+    assert_eq!(
+        (active.id.as_str(), active.name.as_str()),
+        ("837", "YouTube")
+    );
+    assert_eq!(ecp::get_playback_state(&roku.addr).as_deref(), Some("play"));
+
+    ecp::press(&roku.addr, RokuKey::Home).unwrap();
+    assert_eq!(ecp::get_active_app(&roku.addr).unwrap().id, "");
+    assert_eq!(
+        ecp::launch_app(&roku.addr, "999"),
+        Err(RokuError::Status(404))
+    );
+
+    // a launch the device turns down is an error, not a channel that was slow to come up
+    let wait = Duration::from_secs(2);
+    assert_eq!(ecp::launch_and_wait(&roku.addr, "12", wait), Ok(true));
+    assert_eq!(
+        ecp::launch_and_wait(&roku.addr, "999", wait),
+        Err(RokuError::Status(404))
+    );
+}
+
+#[test]
+fn pacer_delivers_every_press() {
+    let roku = MockRoku::start();
+    let pacer = Pacer::new();
+    let keys = ["Up", "Up", "Right", "Select", "Back"];
+    for key in keys {
+        pacer.send(&roku.addr, key);
+    }
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let mut sent = Vec::new();
+    while sent.len() < keys.len() && Instant::now() < deadline {
+        sent.extend(pacer.sent());
+        thread::sleep(Duration::from_millis(20));
+    }
+    assert!(sent.iter().all(|press| press.result.is_ok()), "{:?}", sent);
+    let expected: Vec<String> = keys.iter().map(|key| format!("keypress/{}", key)).collect();
+    assert_eq!(roku.keys(), expected);
+    assert!(pacer.failures().is_empty());
+}
@@ -0,0 +1,216 @@
+// a pretend Roku for the integration tests: ECP over http on a free localhost port, and an SSDP
+// responder that answers M-SEARCHes for every mock started, the way a real device would. each
+// mock keeps what it was sent, so a test can check its keypresses and launches arrived
+
+// each test binary uses its own part of this
+#![allow(dead_code)]
+
+use roku_remote::RokuKey;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::io::Cursor;
+use std::net::{Ipv4Addr, SocketAddrV4, UdpSocket};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use tiny_http::{Header, Method, Response, Server};
+
+const SSDP_ADDR: Ipv4Addr = Ipv4Addr::new(239, 255, 255, 250);
+const SSDP_PORT: u16 = 1900;
+
+// id, name
+const APPS: &[(&str, &str)] = &[("12", "Netflix"), ("837", "YouTube"), ("2285", "Hulu")];
+
+#[derive(Default)]
+struct State {
+    // "keypress/Home", "keydown/Up", ... in the order they came in
+    keys: Vec<String>,
+    launched: Vec<String>,
+    // index into APPS; None on the home screen
+    app: Option<usize>,
+}
+
+pub struct MockRoku {
+    // "127.0.0.1:port", what the lib's calls take as the device
+    pub addr: String,
+    pub serial: String,
+    state: Arc<Mutex<State>>,
+}
+
+impl MockRoku {
+    pub fn start() -> MockRoku {
+        static STARTED: AtomicUsize = AtomicUsize::new(0);
+        let serial = format!("MOCK{:08}", STARTED.fetch_add(1, Ordering::Relaxed));
+        let server = Server::http("127.0.0.1:0").expect("mock ECP server");
+        let addr = server
+            .server_addr()
+            .to_ip()
+            .expect("an ip address")
+            .to_string();
+        let state: Arc<Mutex<State>> = Arc::default();
+        {
+            let (state, serial) = (Arc::clone(&state), serial.clone());
+            thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    let path = request.url().trim_start_matches('/').to_string();
+                    let response = answer(&state, &serial, request.method(), &path);
+                    request.respond(response).ok();
+                }
+            });
+        }
+        MockRoku {
+            addr,
+            serial,
+            state,
+        }
+    }
+
+    // from now on SSDP searches find this mock too
+    pub fn answer_ssdp(&self) {
+        ssdp_responder()
+            .lock()
+            .unwrap()
+            .push((self.addr.clone(), self.serial.clone()));
+    }
+
+    pub fn keys(&self) -> Vec<String> {
+        self.state.lock().unwrap().keys.clone()
+    }
+
+    pub fn launched(&self) -> Vec<String> {
+        self.state.lock().unwrap().launched.clone()
+    }
+}
+
+fn answer(
+    state: &Mutex<State>,
+    serial: &str,
+    method: &Method,
+    path: &str,
+) -> Response<Cursor<Vec<u8>>> {
+    let mut state = state.lock().unwrap();
+    match (method, path) {
+        (Method::Get, "query/device-info") => xml(format!(
+            "<device-info>\
+             <serial-number>{}</serial-number>\
+             <model-name>Mock Roku</model-name>\
+             <model-number>0000M</model-number>\
+             <user-device-name>Test Bench</user-device-name>\
+             <software-version>12.5.0</software-version>\
+             <is-tv>false</is-tv>\
+             <power-mode>PowerOn</power-mode>\
+             <supports-private-listening>true</supports-private-listening>\
+             <headphones-connected>false</headphones-connected>\
+             </device-info>",
+            serial
+        )),
+        (Method::Get, "query/apps") => {
+            let apps: String = (0..APPS.len()).map(app_xml).collect();
+            xml(format!("<apps>{}</apps>", apps))
+        }
+        (Method::Get, "query/active-app") => xml(match state.app {
+            Some(i) => format!("<active-app>{}</active-app>", app_xml(i)),
+            None => "<active-app><app>Roku</app></active-app>".to_string(),
+        }),
+        (Method::Get, "query/media-player") => xml(format!(
+            "<player error=\"false\" state=\"{}\"></player>",
+            if state.app.is_some() { "play" } else { "close" }
+        )),
+        (Method::Post, path) if path.starts_with("launch/") => {
+            let id = path["launch/".len()..]
+                .split('?')
+                .next()
+                .unwrap_or_default();
+            match APPS.iter().position(|app| app.0 == id) {
+                Some(i) => {
+                    state.app = Some(i);
+                    state.launched.push(id.to_string());
+                    empty(200)
+                }
+                None => empty(404),
+            }
+        }
+        (Method::Post, path) => {
+            let Some((action, key)) = path.split_once('/') else {
+                return empty(404);
+            };
+            if !["keypress", "keydown", "keyup"].contains(&action) {
+                return empty(404);
+            }
+            // a real device turns down keys it doesn't know
+            if key.parse::<RokuKey>().is_err() {
+                return empty(400);
+            }
+            if key.eq_ignore_ascii_case("Home") {
+                state.app = None;
+            }
+            state.keys.push(path.to_string());
+            empty(200)
+        }
+        _ => empty(404),
+    }
+}
+
+fn app_xml(i: usize) -> String {
+    let (id, name) = APPS[i];
+    format!(
+        "<app id=\"{}\" type=\"appl\" version=\"1.0.0\">{}</app>",
+        id, name
+    )
+}
+
+fn xml(body: String) -> Response<Cursor<Vec<u8>>> {
+    Response::from_string(body)
+        .with_header(Header::from_bytes("Content-Type", "text/xml; charset=utf-8").unwrap())
+}
+
+fn empty(status: u16) -> Response<Cursor<Vec<u8>>> {
+    Response::from_string("").with_status_code(status)
+}
+
+// address, serial
+type Answering = Mutex<Vec<(String, String)>>;
+
+// the devices the responder answers for, started on first use and shared by every mock
+fn ssdp_responder() -> &'static Answering {
+    static DEVICES: OnceLock<Arc<Answering>> = OnceLock::new();
+    DEVICES.get_or_init(|| {
+        let devices: Arc<Answering> = Arc::default();
+        let socket = ssdp_socket().expect("SSDP responder on port 1900");
+        let answering = Arc::clone(&devices);
+        thread::spawn(move || {
+            let mut buf = [0u8; 2048];
+            while let Ok((amt, from)) = socket.recv_from(&mut buf) {
+                let search = String::from_utf8_lossy(&buf[..amt]).to_ascii_lowercase();
+                if !search.starts_with("m-search") {
+                    continue;
+                }
+                if !search.contains("st: roku:ecp") && !search.contains("st: ssdp:all") {
+                    continue;
+                }
+                for (addr, serial) in answering.lock().unwrap().iter() {
+                    let reply = format!(
+                        "HTTP/1.1 200 OK\r\n\
+                         Cache-Control: max-age=3600\r\n\
+                         ST: roku:ecp\r\n\
+                         USN: uuid:roku:ecp:{}\r\n\
+                         Ext: \r\n\
+                         Server: Roku/12.5.0 UPnP/1.0 Roku/12.5.0\r\n\
+                         LOCATION: http://{}/\r\n\r\n",
+                        serial, addr
+                    );
+                    socket.send_to(reply.as_bytes(), from).ok();
+                }
+            }
+        });
+        devices
+    })
+}
+
+// listening where M-SEARCHes go, next to anything else on the machine that does
+fn ssdp_socket() -> std::io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    socket.bind(&SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, SSDP_PORT).into())?;
+    socket.join_multicast_v4(&SSDP_ADDR, &Ipv4Addr::UNSPECIFIED)?;
+    Ok(socket.into())
+}